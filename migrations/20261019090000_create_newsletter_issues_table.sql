@@ -0,0 +1,12 @@
+CREATE TABLE newsletter_issues
+(
+    id           uuid        NOT NULL,
+    PRIMARY KEY (id),
+    title        TEXT        NOT NULL,
+    text_content TEXT        NOT NULL,
+    html_content TEXT        NOT NULL,
+    status       TEXT        NOT NULL,
+    created_at   timestamptz NOT NULL,
+    updated_at   timestamptz NOT NULL,
+    published_at timestamptz NULL
+);
@@ -0,0 +1,10 @@
+ALTER TABLE newsletter_issues
+    ADD COLUMN scheduled_for timestamptz NULL;
+
+CREATE TABLE issue_delivery_queue
+(
+    newsletter_issue_id uuid NOT NULL
+        REFERENCES newsletter_issues (id),
+    subscriber_email    TEXT NOT NULL,
+    PRIMARY KEY (newsletter_issue_id, subscriber_email)
+);
@@ -0,0 +1,6 @@
+ALTER TABLE newsletter_issues
+    ADD COLUMN slug TEXT NULL UNIQUE;
+
+CREATE INDEX newsletter_issues_published_at_idx
+    ON newsletter_issues (published_at DESC)
+    WHERE slug IS NOT NULL;
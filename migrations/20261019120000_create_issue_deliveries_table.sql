@@ -0,0 +1,17 @@
+ALTER TABLE newsletter_issues
+    ADD COLUMN sent_at timestamptz NULL;
+
+CREATE TABLE issue_deliveries
+(
+    newsletter_issue_id uuid        NOT NULL
+        REFERENCES newsletter_issues (id),
+    subscriber_email    TEXT        NOT NULL,
+    status              TEXT        NOT NULL,
+    error               TEXT        NULL,
+    queued_at           timestamptz NOT NULL,
+    updated_at          timestamptz NOT NULL,
+    PRIMARY KEY (newsletter_issue_id, subscriber_email)
+);
+
+CREATE INDEX issue_deliveries_subscriber_email_idx
+    ON issue_deliveries (subscriber_email, updated_at DESC);
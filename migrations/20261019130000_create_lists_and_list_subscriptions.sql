@@ -0,0 +1,62 @@
+CREATE TABLE lists
+(
+    id         uuid        NOT NULL,
+    PRIMARY KEY (id),
+    slug       TEXT        NOT NULL UNIQUE,
+    name       TEXT        NOT NULL,
+    created_at timestamptz NOT NULL
+);
+
+INSERT INTO lists (id, slug, name, created_at)
+VALUES (gen_random_uuid(), 'default', 'Newsletter', now());
+
+CREATE TABLE list_subscriptions
+(
+    list_id           uuid        NOT NULL
+        REFERENCES lists (id),
+    subscriber_id     uuid        NOT NULL
+        REFERENCES subscriptions (id),
+    PRIMARY KEY (list_id, subscriber_id),
+    status            TEXT        NOT NULL,
+    unsubscribe_token TEXT        NOT NULL UNIQUE,
+    subscribed_at     timestamptz NOT NULL,
+    updated_at        timestamptz NOT NULL
+);
+
+CREATE INDEX list_subscriptions_subscriber_id_idx ON list_subscriptions (subscriber_id);
+
+-- Every existing subscriber keeps their status, now on the default list.
+INSERT INTO list_subscriptions (
+    list_id, subscriber_id, status, unsubscribe_token, subscribed_at, updated_at
+)
+SELECT lists.id,
+       subscriptions.id,
+       subscriptions.status,
+       replace(gen_random_uuid()::text, '-', ''),
+       subscriptions.subscribed_at,
+       subscriptions.subscribed_at
+FROM subscriptions
+         CROSS JOIN lists
+WHERE lists.slug = 'default';
+
+ALTER TABLE subscriptions
+    DROP COLUMN status;
+
+ALTER TABLE subscription_tokens
+    ADD COLUMN list_id uuid NULL
+        REFERENCES lists (id);
+UPDATE subscription_tokens
+SET list_id = (SELECT id FROM lists WHERE slug = 'default');
+ALTER TABLE subscription_tokens
+    ALTER COLUMN list_id SET NOT NULL;
+-- A confirmation link works once; following it again changes nothing.
+ALTER TABLE subscription_tokens
+    ADD COLUMN used_at timestamptz NULL;
+
+ALTER TABLE newsletter_issues
+    ADD COLUMN list_id uuid NULL
+        REFERENCES lists (id);
+UPDATE newsletter_issues
+SET list_id = (SELECT id FROM lists WHERE slug = 'default');
+ALTER TABLE newsletter_issues
+    ALTER COLUMN list_id SET NOT NULL;
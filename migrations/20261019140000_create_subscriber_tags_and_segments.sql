@@ -0,0 +1,18 @@
+CREATE TABLE subscriber_tags
+(
+    subscriber_id uuid        NOT NULL
+        REFERENCES subscriptions (id),
+    tag           TEXT        NOT NULL,
+    PRIMARY KEY (subscriber_id, tag),
+    created_at    timestamptz NOT NULL
+);
+
+CREATE INDEX subscriber_tags_tag_idx ON subscriber_tags (tag);
+
+-- Free-form subscriber details that segments can filter on.
+ALTER TABLE subscriptions
+    ADD COLUMN attributes JSONB NOT NULL DEFAULT '{}';
+
+-- Issues without a segment go to every confirmed subscriber of their list.
+ALTER TABLE newsletter_issues
+    ADD COLUMN segment TEXT NULL;
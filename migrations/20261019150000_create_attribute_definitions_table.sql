@@ -0,0 +1,15 @@
+CREATE TABLE attribute_definitions
+(
+    name           TEXT        NOT NULL,
+    PRIMARY KEY (name),
+    attribute_type TEXT        NOT NULL,
+    options        TEXT[]      NOT NULL DEFAULT '{}',
+    created_at     timestamptz NOT NULL
+);
+
+
+-- Attributes submitted for a subscriber that already exists are only merged
+-- into their profile once they confirm, so that anyone knowing an address
+-- cannot rewrite its attributes.
+ALTER TABLE subscription_tokens
+    ADD COLUMN attributes JSONB NOT NULL DEFAULT '{}'::jsonb;
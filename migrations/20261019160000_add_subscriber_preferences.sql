@@ -0,0 +1,26 @@
+ALTER TABLE subscriptions
+    ADD COLUMN preferences_token TEXT        NULL UNIQUE,
+    ADD COLUMN digest_frequency  TEXT        NOT NULL DEFAULT 'immediate',
+    ADD COLUMN paused_until      timestamptz NULL;
+
+UPDATE subscriptions
+SET preferences_token = replace(gen_random_uuid()::text, '-', '');
+
+ALTER TABLE subscriptions
+    ALTER COLUMN preferences_token SET NOT NULL;
+
+CREATE TABLE subscriber_preference_changes
+(
+    id            uuid        NOT NULL,
+    PRIMARY KEY (id),
+    subscriber_id uuid        NOT NULL
+        REFERENCES subscriptions (id),
+    field         TEXT        NOT NULL,
+    old_value     TEXT        NULL,
+    new_value     TEXT        NULL,
+    source        TEXT        NOT NULL,
+    changed_at    timestamptz NOT NULL
+);
+
+CREATE INDEX subscriber_preference_changes_subscriber_id_idx
+    ON subscriber_preference_changes (subscriber_id, changed_at);
@@ -0,0 +1,9 @@
+-- Subscribers who exercised their right to erasure. Only a hash of the address
+-- is kept, so that it can be recognised without being stored.
+CREATE TABLE erased_subscribers
+(
+    email_hash TEXT        NOT NULL,
+    PRIMARY KEY (email_hash),
+    source     TEXT        NOT NULL,
+    erased_at  timestamptz NOT NULL
+);
@@ -0,0 +1,19 @@
+-- Proof of the double opt-in: one row per subscription and confirmation.
+CREATE TABLE consent_records
+(
+    id                   uuid        NOT NULL,
+    PRIMARY KEY (id),
+    subscriber_id        uuid        NOT NULL
+        REFERENCES subscriptions (id),
+    list_id              uuid        NOT NULL
+        REFERENCES lists (id),
+    event                TEXT        NOT NULL,
+    ip_address           TEXT        NULL,
+    user_agent           TEXT        NULL,
+    form_source          TEXT        NOT NULL,
+    consent_text_version TEXT        NOT NULL,
+    recorded_at          timestamptz NOT NULL
+);
+
+CREATE INDEX consent_records_subscriber_id_idx
+    ON consent_records (subscriber_id, recorded_at);
@@ -0,0 +1,39 @@
+CREATE TABLE import_jobs
+(
+    id             uuid        NOT NULL,
+    PRIMARY KEY (id),
+    list_id        uuid        NOT NULL
+        REFERENCES lists (id),
+    mode           TEXT        NOT NULL,
+    attestation    TEXT        NULL,
+    status         TEXT        NOT NULL,
+    error          TEXT        NULL,
+    total_rows     INT         NOT NULL DEFAULT 0,
+    imported_rows  INT         NOT NULL DEFAULT 0,
+    duplicate_rows INT         NOT NULL DEFAULT 0,
+    failed_rows    INT         NOT NULL DEFAULT 0,
+    created_at     timestamptz NOT NULL,
+    completed_at   timestamptz NULL
+);
+
+-- Rows that were not imported, and why.
+CREATE TABLE import_row_errors
+(
+    import_job_id uuid NOT NULL
+        REFERENCES import_jobs (id),
+    row_number    INT  NOT NULL,
+    PRIMARY KEY (import_job_id, row_number),
+    email         TEXT NULL,
+    error         TEXT NOT NULL
+);
+
+-- Confirmation emails owed to contacts imported in opt-in mode.
+CREATE TABLE opt_in_email_queue
+(
+    subscription_token TEXT        NOT NULL
+        REFERENCES subscription_tokens (subscription_token),
+    PRIMARY KEY (subscription_token),
+    subscriber_id      uuid        NOT NULL
+        REFERENCES subscriptions (id),
+    enqueued_at        timestamptz NOT NULL
+);
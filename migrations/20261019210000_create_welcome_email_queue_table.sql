@@ -0,0 +1,10 @@
+-- Welcome emails owed to subscribers who just confirmed.
+CREATE TABLE welcome_email_queue
+(
+    subscriber_id uuid        NOT NULL
+        REFERENCES subscriptions (id),
+    list_id       uuid        NOT NULL
+        REFERENCES lists (id),
+    PRIMARY KEY (subscriber_id, list_id),
+    enqueued_at   timestamptz NOT NULL
+);
@@ -0,0 +1,11 @@
+-- Hits on rate limited endpoints, counted per key and fixed window so that
+-- every instance sees the same counts.
+CREATE TABLE rate_limit_counters
+(
+    key          text        NOT NULL,
+    window_start timestamptz NOT NULL,
+    expires_at   timestamptz NOT NULL,
+    hits         integer     NOT NULL,
+    PRIMARY KEY (key, window_start)
+);
+CREATE INDEX rate_limit_counters_expires_at_idx ON rate_limit_counters (expires_at);
@@ -0,0 +1,27 @@
+-- Subscribers are told apart by their normalized address, so that addresses
+-- that only differ in case are the same subscriber. `email` keeps the form
+-- the subscriber wrote.
+ALTER TABLE subscriptions ADD COLUMN normalized_email TEXT;
+
+-- Existing addresses that only differ in case keep distinct keys, marked
+-- with the id of the later subscribers, until they are merged by hand.
+UPDATE subscriptions
+SET normalized_email = CASE
+    WHEN duplicates.rank = 1 THEN lower(subscriptions.email)
+    ELSE lower(subscriptions.email) || '#duplicate:' || subscriptions.id
+END
+FROM (
+    SELECT id,
+           row_number() OVER (PARTITION BY lower(email) ORDER BY subscribed_at, id) AS rank
+    FROM subscriptions
+) AS duplicates
+WHERE duplicates.id = subscriptions.id;
+
+-- Domains used to be stored as written and are now stored in punycode, which
+-- SQL cannot compute. Existing addresses with internationalized domains keep
+-- their Unicode key, so a new subscription with the same address is kept
+-- apart from them as well, until they are merged by hand.
+
+ALTER TABLE subscriptions ALTER COLUMN normalized_email SET NOT NULL;
+CREATE UNIQUE INDEX subscriptions_normalized_email_idx ON subscriptions (normalized_email);
+ALTER TABLE subscriptions DROP CONSTRAINT subscriptions_email_key;
@@ -0,0 +1,2 @@
+-- The language a subscriber reads in. NULL stands for the default locale.
+ALTER TABLE subscriptions ADD COLUMN locale TEXT NULL;
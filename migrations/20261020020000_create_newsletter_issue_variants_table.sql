@@ -0,0 +1,12 @@
+-- Translations of an issue. Subscribers whose locale has no variant get the
+-- issue itself.
+CREATE TABLE newsletter_issue_variants
+(
+    newsletter_issue_id uuid NOT NULL
+        REFERENCES newsletter_issues (id),
+    locale              TEXT NOT NULL,
+    title               TEXT NOT NULL,
+    text_content        TEXT NOT NULL,
+    html_content        TEXT NOT NULL,
+    PRIMARY KEY (newsletter_issue_id, locale)
+);
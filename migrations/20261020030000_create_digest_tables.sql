@@ -0,0 +1,34 @@
+-- Issues waiting to go out in the next digest of a daily or weekly subscriber.
+CREATE TABLE digest_queue
+(
+    subscriber_id       uuid        NOT NULL
+        REFERENCES subscriptions (id),
+    newsletter_issue_id uuid        NOT NULL
+        REFERENCES newsletter_issues (id),
+    PRIMARY KEY (subscriber_id, newsletter_issue_id),
+    queued_at           timestamptz NOT NULL
+);
+
+-- Every digest sent, and the issues it covered.
+CREATE TABLE digests
+(
+    id            uuid        NOT NULL,
+    PRIMARY KEY (id),
+    subscriber_id uuid        NOT NULL
+        REFERENCES subscriptions (id),
+    frequency     TEXT        NOT NULL,
+    status        TEXT        NOT NULL,
+    error         TEXT        NULL,
+    created_at    timestamptz NOT NULL
+);
+
+CREATE INDEX digests_subscriber_id_idx ON digests (subscriber_id, created_at);
+
+CREATE TABLE digest_issues
+(
+    digest_id           uuid NOT NULL
+        REFERENCES digests (id),
+    newsletter_issue_id uuid NOT NULL
+        REFERENCES newsletter_issues (id),
+    PRIMARY KEY (digest_id, newsletter_issue_id)
+);
@@ -0,0 +1,28 @@
+-- Opens and clicks, tracked through a token in every delivered issue.
+ALTER TABLE issue_deliveries
+    ADD COLUMN tracking_token TEXT        NOT NULL UNIQUE
+        DEFAULT replace(gen_random_uuid()::text, '-', ''),
+    ADD COLUMN opened_at      timestamptz NULL,
+    ADD COLUMN clicked_at     timestamptz NULL;
+
+-- The links of delivered issues. Tracked clicks can only lead to them.
+CREATE TABLE issue_links
+(
+    id                  uuid NOT NULL,
+    PRIMARY KEY (id),
+    newsletter_issue_id uuid NOT NULL
+        REFERENCES newsletter_issues (id),
+    url                 TEXT NOT NULL,
+    UNIQUE (newsletter_issue_id, url)
+);
+
+CREATE TABLE link_clicks
+(
+    tracking_token TEXT        NOT NULL
+        REFERENCES issue_deliveries (tracking_token),
+    link_id        uuid        NOT NULL
+        REFERENCES issue_links (id),
+    clicked_at     timestamptz NOT NULL
+);
+
+CREATE INDEX link_clicks_tracking_token_idx ON link_clicks (tracking_token);
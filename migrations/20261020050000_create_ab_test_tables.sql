@@ -0,0 +1,39 @@
+-- Subjects and contents tried on a sample of an issue's recipients before
+-- the winner goes out to the rest.
+CREATE TABLE ab_tests
+(
+    newsletter_issue_id uuid        NOT NULL
+        REFERENCES newsletter_issues (id),
+    PRIMARY KEY (newsletter_issue_id),
+    sample_percent      INT         NOT NULL,
+    wait_minutes        INT         NOT NULL,
+    metric              TEXT        NOT NULL,
+    decide_at           timestamptz NULL,
+    winner              TEXT        NULL,
+    decided_at          timestamptz NULL
+);
+
+CREATE TABLE ab_test_variants
+(
+    newsletter_issue_id uuid NOT NULL
+        REFERENCES ab_tests (newsletter_issue_id),
+    label               TEXT NOT NULL,
+    PRIMARY KEY (newsletter_issue_id, label),
+    title               TEXT NOT NULL,
+    text_content        TEXT NOT NULL,
+    html_content        TEXT NOT NULL
+);
+
+-- The variant each recipient of the sample got. The rest of the recipients
+-- get the winner.
+ALTER TABLE issue_deliveries
+    ADD COLUMN ab_variant TEXT NULL;
+
+-- Recipients held back until the winner is picked.
+CREATE TABLE ab_test_holdouts
+(
+    newsletter_issue_id uuid NOT NULL
+        REFERENCES ab_tests (newsletter_issue_id),
+    subscriber_email    TEXT NOT NULL,
+    PRIMARY KEY (newsletter_issue_id, subscriber_email)
+);
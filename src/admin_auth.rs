@@ -0,0 +1,105 @@
+use crate::routes::{ErrorCode, Problem};
+use actix_web::body::EitherBody;
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::header::{self, HeaderValue};
+use actix_web::http::StatusCode;
+use futures_util::future::{ready, LocalBoxFuture, Ready};
+use secrecy::{ExposeSecret, Secret};
+use std::rc::Rc;
+use subtle::ConstantTimeEq;
+
+/// Only lets through requests bearing the configured admin token in their
+/// `Authorization` header. Every request is turned away with
+/// `401 Unauthorized` when no token is configured.
+pub struct AdminAuth {
+    token: Rc<Option<Secret<String>>>,
+}
+
+impl AdminAuth {
+    pub fn new(token: Option<Secret<String>>) -> Self {
+        Self {
+            token: Rc::new(token),
+        }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for AdminAuth
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = actix_web::Error;
+    type Transform = AdminAuthMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(AdminAuthMiddleware {
+            service: Rc::new(service),
+            token: self.token.clone(),
+        }))
+    }
+}
+
+pub struct AdminAuthMiddleware<S> {
+    service: Rc<S>,
+    token: Rc<Option<Secret<String>>>,
+}
+
+impl<S> AdminAuthMiddleware<S> {
+    fn is_authorized(&self, request: &ServiceRequest) -> bool {
+        let Some(expected) = self.token.as_ref() else {
+            return false;
+        };
+        request
+            .headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .is_some_and(|given| {
+                given
+                    .as_bytes()
+                    .ct_eq(expected.expose_secret().as_bytes())
+                    .into()
+            })
+    }
+}
+
+impl<S, B> Service<ServiceRequest> for AdminAuthMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, request: ServiceRequest) -> Self::Future {
+        if !self.is_authorized(&request) {
+            tracing::warn!(path = %request.path(), "Rejected an unauthenticated admin request");
+            let mut response = Problem::new(
+                StatusCode::UNAUTHORIZED,
+                ErrorCode::Unauthorized.as_str(),
+                "A valid admin token is required.".to_owned(),
+            )
+            .into_response();
+            response
+                .headers_mut()
+                .insert(header::WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"));
+            return Box::pin(ready(Ok(request
+                .into_response(response)
+                .map_into_right_body())));
+        }
+
+        let service = self.service.clone();
+        Box::pin(async move {
+            service
+                .call(request)
+                .await
+                .map(ServiceResponse::map_into_left_body)
+        })
+    }
+}
@@ -1,10 +1,13 @@
+use crate::bot_protection::{CaptchaVerifier, HttpCaptchaVerifier};
 use crate::configuration::Settings;
 use crate::email::aws_email_client::SesClientFactory;
 use crate::email::email_client::{EmailClient, EmailClientProvider};
 use std::sync::Arc;
 
+#[derive(Clone)]
 pub struct Dependencies {
     pub email_client: Arc<dyn EmailClient>,
+    pub captcha_verifier: Option<Arc<dyn CaptchaVerifier>>,
 }
 
 pub async fn build_dependencies(configuration: &Settings) -> Dependencies {
@@ -13,5 +16,13 @@ pub async fn build_dependencies(configuration: &Settings) -> Dependencies {
             .email_client()
             .await,
     );
-    Dependencies { email_client }
+    let captcha_verifier = configuration
+        .bot_protection
+        .captcha
+        .as_ref()
+        .map(|settings| Arc::new(HttpCaptchaVerifier::new(settings)) as Arc<dyn CaptchaVerifier>);
+    Dependencies {
+        email_client,
+        captcha_verifier,
+    }
 }
@@ -0,0 +1,73 @@
+use crate::configuration::CaptchaSettings;
+use anyhow::Context;
+use secrecy::{ExposeSecret, Secret};
+use std::net::IpAddr;
+use std::time::Duration;
+
+#[async_trait::async_trait]
+pub trait CaptchaVerifier: Send + Sync {
+    /// Whether the provider confirms that `response` was produced by a person
+    /// solving the challenge.
+    async fn verify(
+        &self,
+        response: &str,
+        client_ip: Option<IpAddr>,
+    ) -> Result<bool, anyhow::Error>;
+}
+
+/// Verifies responses against a reCAPTCHA-style `siteverify` endpoint.
+pub struct HttpCaptchaVerifier {
+    http_client: reqwest::Client,
+    verify_url: String,
+    secret: Secret<String>,
+}
+
+impl HttpCaptchaVerifier {
+    pub fn new(settings: &CaptchaSettings) -> Self {
+        let http_client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(10))
+            .build()
+            .expect("Failed to build the CAPTCHA HTTP client.");
+        Self {
+            http_client,
+            verify_url: settings.verify_url.clone(),
+            secret: settings.secret.clone(),
+        }
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct VerifyResponse {
+    success: bool,
+}
+
+#[async_trait::async_trait]
+impl CaptchaVerifier for HttpCaptchaVerifier {
+    #[tracing::instrument(name = "Verify a CAPTCHA response", skip(self, response))]
+    async fn verify(
+        &self,
+        response: &str,
+        client_ip: Option<IpAddr>,
+    ) -> Result<bool, anyhow::Error> {
+        let mut form = vec![
+            ("secret", self.secret.expose_secret().clone()),
+            ("response", response.to_owned()),
+        ];
+        if let Some(ip) = client_ip {
+            form.push(("remoteip", ip.to_string()));
+        }
+        let verification: VerifyResponse = self
+            .http_client
+            .post(&self.verify_url)
+            .form(&form)
+            .send()
+            .await
+            .context("Failed to reach the CAPTCHA provider.")?
+            .error_for_status()
+            .context("The CAPTCHA provider returned an error.")?
+            .json()
+            .await
+            .context("Failed to read the CAPTCHA verification.")?;
+        Ok(verification.success)
+    }
+}
@@ -0,0 +1,112 @@
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use secrecy::{ExposeSecret, Secret};
+use sha2::Sha256;
+
+/// Tokens older than this are rejected, so that one cannot be reused forever.
+const MAX_AGE_SECONDS: i64 = 24 * 60 * 60;
+
+#[derive(Debug, PartialEq)]
+pub enum FormTokenError {
+    Invalid,
+    TooFast,
+    Expired,
+}
+
+/// `<unix timestamp>.<hex HMAC-SHA256 of the timestamp>`
+pub fn issue_form_token(secret: &Secret<String>, issued_at: DateTime<Utc>) -> String {
+    let timestamp = issued_at.timestamp().to_string();
+    let signature = hex::encode(mac(secret, &timestamp).finalize().into_bytes());
+    format!("{}.{}", timestamp, signature)
+}
+
+pub fn verify_form_token(
+    secret: &Secret<String>,
+    token: &str,
+    min_fill_seconds: u64,
+    now: DateTime<Utc>,
+) -> Result<(), FormTokenError> {
+    let (timestamp, signature) = token.split_once('.').ok_or(FormTokenError::Invalid)?;
+    let signature = hex::decode(signature).map_err(|_| FormTokenError::Invalid)?;
+    mac(secret, timestamp)
+        .verify_slice(&signature)
+        .map_err(|_| FormTokenError::Invalid)?;
+    let issued_at: i64 = timestamp.parse().map_err(|_| FormTokenError::Invalid)?;
+    let age = now.timestamp() - issued_at;
+    if age < min_fill_seconds as i64 {
+        return Err(FormTokenError::TooFast);
+    }
+    if age > MAX_AGE_SECONDS {
+        return Err(FormTokenError::Expired);
+    }
+    Ok(())
+}
+
+fn mac(secret: &Secret<String>, timestamp: &str) -> Hmac<Sha256> {
+    // HMAC accepts keys of any length.
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.expose_secret().as_bytes()).unwrap();
+    mac.update(timestamp.as_bytes());
+    mac
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{issue_form_token, verify_form_token, FormTokenError};
+    use chrono::{Duration, Utc};
+    use claims::{assert_err_eq, assert_ok};
+    use secrecy::Secret;
+
+    fn secret() -> Secret<String> {
+        Secret::new("a-very-secret-key".to_string())
+    }
+
+    #[test]
+    fn a_token_is_accepted_after_the_minimum_fill_time() {
+        // Arrange
+        let issued_at = Utc::now();
+        let token = issue_form_token(&secret(), issued_at);
+
+        // Act
+        let result = verify_form_token(&secret(), &token, 3, issued_at + Duration::seconds(5));
+
+        // Assert
+        assert_ok!(result);
+    }
+
+    #[test]
+    fn a_token_submitted_too_soon_or_too_late_is_rejected() {
+        // Arrange
+        let issued_at = Utc::now();
+        let token = issue_form_token(&secret(), issued_at);
+
+        // Act
+        let too_fast = verify_form_token(&secret(), &token, 3, issued_at + Duration::seconds(1));
+        let expired = verify_form_token(&secret(), &token, 3, issued_at + Duration::days(2));
+
+        // Assert
+        assert_err_eq!(too_fast, FormTokenError::TooFast);
+        assert_err_eq!(expired, FormTokenError::Expired);
+    }
+
+    #[test]
+    fn a_tampered_token_is_rejected() {
+        // Arrange
+        let issued_at = Utc::now() - Duration::minutes(5);
+        let token = issue_form_token(&secret(), issued_at);
+        let (_, signature) = token.split_once('.').unwrap();
+        let backdated = format!("{}.{}", issued_at.timestamp() - 60, signature);
+        let other_secret = Secret::new("another-key".to_string());
+
+        // Act
+        let results = [
+            verify_form_token(&secret(), &backdated, 3, Utc::now()),
+            verify_form_token(&other_secret, &token, 3, Utc::now()),
+            verify_form_token(&secret(), "garbage", 3, Utc::now()),
+        ];
+
+        // Assert
+        for result in results {
+            assert_err_eq!(result, FormTokenError::Invalid);
+        }
+    }
+}
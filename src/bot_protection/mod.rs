@@ -0,0 +1,121 @@
+pub use captcha::*;
+pub use form_token::*;
+
+mod captcha;
+mod form_token;
+
+use crate::configuration::BotProtectionSettings;
+use chrono::Utc;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::net::IpAddr;
+use std::sync::Arc;
+
+/// The form field the signed form token is submitted in.
+pub const FORM_TOKEN_FIELD: &str = "form_token";
+
+/// Why a submission was taken for a bot's.
+#[derive(Debug, PartialEq)]
+pub enum Rejection {
+    HoneypotFilled,
+    MissingFormToken,
+    InvalidFormToken,
+    SubmittedTooFast,
+    ExpiredFormToken,
+    MissingCaptcha,
+    FailedCaptcha,
+}
+
+impl Display for Rejection {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let reason = match self {
+            Rejection::HoneypotFilled => "the honeypot field was filled in",
+            Rejection::MissingFormToken => "the form token is missing",
+            Rejection::InvalidFormToken => "the form token is invalid",
+            Rejection::SubmittedTooFast => "the form was submitted too fast",
+            Rejection::ExpiredFormToken => "the form token has expired",
+            Rejection::MissingCaptcha => "the CAPTCHA response is missing",
+            Rejection::FailedCaptcha => "the CAPTCHA was not solved",
+        };
+        f.write_str(reason)
+    }
+}
+
+/// Tells people from bots on the subscription form. Every check is optional
+/// and only runs once configured.
+#[derive(Clone)]
+pub struct BotProtection {
+    settings: Arc<BotProtectionSettings>,
+    captcha_verifier: Option<Arc<dyn CaptchaVerifier>>,
+}
+
+impl BotProtection {
+    pub fn new(
+        settings: BotProtectionSettings,
+        captcha_verifier: Option<Arc<dyn CaptchaVerifier>>,
+    ) -> Self {
+        Self {
+            settings: Arc::new(settings),
+            captcha_verifier,
+        }
+    }
+
+    /// A fresh token to embed in the form, if form tokens are enabled.
+    pub fn issue_form_token(&self) -> Option<String> {
+        let settings = self.settings.form_token.as_ref()?;
+        Some(issue_form_token(&settings.secret, Utc::now()))
+    }
+
+    /// Checks a submitted form. The fields used by the checks are removed
+    /// from `fields`, so that they are not taken for subscriber attributes.
+    ///
+    /// The CAPTCHA provider failing lets the submission through: it is not
+    /// worth losing genuine subscribers over.
+    pub async fn check(
+        &self,
+        fields: &mut HashMap<String, String>,
+        client_ip: Option<IpAddr>,
+    ) -> Result<(), Rejection> {
+        if let Some(honeypot_field) = &self.settings.honeypot_field {
+            if fields
+                .remove(honeypot_field)
+                .is_some_and(|value| !value.is_empty())
+            {
+                return Err(Rejection::HoneypotFilled);
+            }
+        }
+
+        let form_token = fields.remove(FORM_TOKEN_FIELD);
+        if let Some(settings) = &self.settings.form_token {
+            let form_token = form_token.ok_or(Rejection::MissingFormToken)?;
+            verify_form_token(
+                &settings.secret,
+                &form_token,
+                settings.min_fill_seconds,
+                Utc::now(),
+            )
+            .map_err(|e| match e {
+                FormTokenError::Invalid => Rejection::InvalidFormToken,
+                FormTokenError::TooFast => Rejection::SubmittedTooFast,
+                FormTokenError::Expired => Rejection::ExpiredFormToken,
+            })?;
+        }
+
+        if let (Some(settings), Some(verifier)) = (&self.settings.captcha, &self.captcha_verifier) {
+            let response = fields
+                .remove(&settings.response_field)
+                .filter(|response| !response.is_empty())
+                .ok_or(Rejection::MissingCaptcha)?;
+            match verifier.verify(&response, client_ip).await {
+                Ok(true) => {}
+                Ok(false) => return Err(Rejection::FailedCaptcha),
+                Err(e) => tracing::error!(
+                    error.cause_chain = ?e,
+                    error.message = %e,
+                    "Failed to verify a CAPTCHA response. Letting the submission through."
+                ),
+            }
+        }
+        Ok(())
+    }
+}
@@ -1,4 +1,4 @@
-use crate::domain::SubscriberEmail;
+use crate::domain::SenderAddress;
 use crate::environment::ENVIRONMENT;
 use dotenvy::dotenv;
 use http::Uri;
@@ -7,6 +7,9 @@ use serde::Deserialize;
 use serde_aux::field_attributes::deserialize_number_from_string;
 use sqlx::postgres::{PgConnectOptions, PgSslMode};
 use sqlx::ConnectOptions;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::num::NonZeroU16;
 
 #[derive(serde::Deserialize, Clone)]
 pub struct Settings {
@@ -14,6 +17,16 @@ pub struct Settings {
     pub database: DatabaseSettings,
     pub aws: AwsSettings,
     pub email_client: EmailClientSettings,
+    pub feed: FeedSettings,
+    pub subscriptions: SubscriptionSettings,
+    pub branding: BrandingSettings,
+    pub api: ApiSettings,
+    pub rate_limit: RateLimitSettings,
+    pub bot_protection: BotProtectionSettings,
+    pub email_policy: EmailPolicySettings,
+    pub i18n: I18nSettings,
+    #[serde(default)]
+    pub admin: AdminSettings,
 }
 
 #[derive(serde::Deserialize, Clone)]
@@ -33,6 +46,195 @@ where
     s.parse::<Uri>().map_err(serde::de::Error::custom)
 }
 
+#[derive(serde::Deserialize, Clone)]
+pub struct FeedSettings {
+    pub title: String,
+    pub description: String,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub max_entries: NonZeroU16,
+}
+
+#[derive(serde::Deserialize, Clone)]
+pub struct SubscriptionSettings {
+    /// Version of the consent wording shown next to the subscription form.
+    /// It is stored with every consent record.
+    pub consent_text_version: String,
+    /// How long a confirmation link stays valid.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub confirmation_token_ttl_hours: i64,
+    /// Treats Gmail addresses that only differ in dots and `+tags` as the
+    /// same subscriber. Subscribers stored before it changes keep the key
+    /// they were stored with.
+    #[serde(default)]
+    pub fold_email_aliases: bool,
+    /// Sent once a subscriber confirms. No welcome email is sent when unset.
+    #[serde(default)]
+    pub welcome_email: Option<WelcomeEmailSettings>,
+}
+
+#[derive(serde::Deserialize, Clone)]
+pub struct WelcomeEmailSettings {
+    pub subject: String,
+    /// HTML and plain-text templates, with the same merge tags as issues plus
+    /// `{{list_name}}`.
+    pub html_template: String,
+    pub text_template: String,
+    pub attachment: Option<AttachmentSettings>,
+    /// The email in other languages, by locale. Subscribers whose language
+    /// has no translation get the email above.
+    #[serde(default)]
+    pub translations: HashMap<String, WelcomeEmailTranslation>,
+}
+
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct WelcomeEmailTranslation {
+    pub subject: String,
+    pub html_template: String,
+    pub text_template: String,
+}
+
+#[derive(serde::Deserialize, Clone)]
+pub struct AttachmentSettings {
+    pub path: String,
+    pub content_type: String,
+}
+
+/// Access to the `/admin` endpoints.
+#[derive(serde::Deserialize, Clone, Default)]
+pub struct AdminSettings {
+    /// Sent by admin clients as `Authorization: Bearer <token>`. The admin
+    /// endpoints reject every request when it is not set.
+    pub api_token: Option<Secret<String>>,
+    /// The only addresses test copies of an issue may be sent to.
+    #[serde(default)]
+    pub test_recipients: Vec<String>,
+}
+
+/// The JSON API used by our own web and mobile clients.
+#[derive(serde::Deserialize, Clone)]
+pub struct ApiSettings {
+    /// Origins whose pages may call the API from a browser. `*` allows any.
+    #[serde(default)]
+    pub allowed_origins: Vec<String>,
+}
+
+/// Limits on the public endpoints that send emails to the address they are
+/// given. A limit that is not set is not enforced.
+#[derive(serde::Deserialize, Clone)]
+pub struct RateLimitSettings {
+    pub store: RateLimitStoreKind,
+    /// Proxies whose `X-Forwarded-For` header is trusted to name the client.
+    #[serde(default)]
+    pub trusted_proxies: Vec<IpAddr>,
+    pub per_ip: Option<Limit>,
+    pub per_email: Option<Limit>,
+    pub per_endpoint: Option<Limit>,
+    pub global: Option<Limit>,
+}
+
+/// Where hits are counted. Deployments running several instances must use
+/// Postgres, so that every instance sees the same counts.
+#[derive(serde::Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum RateLimitStoreKind {
+    Memory,
+    Postgres,
+}
+
+#[derive(serde::Deserialize, Clone, Copy, Debug)]
+pub struct Limit {
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub max_requests: u32,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub window_seconds: u64,
+}
+
+/// Which addresses may subscribe through the form and the API.
+#[derive(serde::Deserialize, Clone)]
+pub struct EmailPolicySettings {
+    /// Rejects domains on the bundled list of throwaway providers.
+    pub block_disposable: bool,
+    /// More disposable domains, one per line, on top of the bundled list.
+    #[serde(default)]
+    pub disposable_domains_file: Option<String>,
+    /// Local parts of shared mailboxes, such as `noreply`.
+    #[serde(default)]
+    pub role_accounts: Vec<String>,
+    /// Always accepted, even when disposable or denied.
+    #[serde(default)]
+    pub allowed_domains: Vec<String>,
+    #[serde(default)]
+    pub denied_domains: Vec<String>,
+}
+
+/// Languages of the pages and emails subscribers see.
+#[derive(serde::Deserialize, Clone)]
+pub struct I18nSettings {
+    /// Used for subscribers whose language we do not speak.
+    pub default_locale: String,
+    /// More catalogs, named `<locale>.toml`, on top of the bundled ones.
+    #[serde(default)]
+    pub catalogs_dir: Option<String>,
+}
+
+/// Checks that turn away automated submissions of the subscription form.
+/// Each check is disabled while unset.
+#[derive(serde::Deserialize, Clone)]
+pub struct BotProtectionSettings {
+    /// A field hidden from people, so that only bots fill it in.
+    #[serde(default)]
+    pub honeypot_field: Option<String>,
+    #[serde(default)]
+    pub form_token: Option<FormTokenSettings>,
+    #[serde(default)]
+    pub captcha: Option<CaptchaSettings>,
+}
+
+/// Signed timestamps, embedded in the form when it is shown, that prove the
+/// form was not submitted faster than a person could fill it in.
+#[derive(serde::Deserialize, Clone)]
+pub struct FormTokenSettings {
+    pub secret: Secret<String>,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub min_fill_seconds: u64,
+}
+
+/// A CAPTCHA provider with a reCAPTCHA-style verification endpoint, such as
+/// hCaptcha or Turnstile.
+#[derive(serde::Deserialize, Clone)]
+pub struct CaptchaSettings {
+    pub verify_url: String,
+    pub secret: Secret<String>,
+    /// The form field the widget puts its response in.
+    pub response_field: String,
+}
+
+/// Look of the pages shown to subscribers.
+#[derive(serde::Deserialize, Clone)]
+pub struct BrandingSettings {
+    pub name: String,
+    pub logo_url: Option<String>,
+    #[serde(deserialize_with = "deserialize_color")]
+    pub accent_color: String,
+}
+
+fn deserialize_color<'de, D>(deserializer: D) -> Result<String, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let s: String = Deserialize::deserialize(deserializer)?;
+    let is_hex_color =
+        s.len() == 7 && s.starts_with('#') && s[1..].chars().all(|c| c.is_ascii_hexdigit());
+    if is_hex_color {
+        Ok(s)
+    } else {
+        Err(serde::de::Error::custom(format!(
+            "{} is not a colour of the form #rrggbb.",
+            s
+        )))
+    }
+}
+
 #[derive(serde::Deserialize, Clone)]
 pub struct DatabaseSettings {
     pub username: String,
@@ -79,11 +281,24 @@ pub struct AwsSettings {
 #[derive(serde::Deserialize, Clone)]
 pub struct EmailClientSettings {
     pub sender_email: String,
+    /// The name recipients see next to the sender address.
+    #[serde(default)]
+    pub sender_name: Option<String>,
+    /// Where replies go. They go to the sender address when unset.
+    #[serde(default)]
+    pub reply_to: Option<String>,
+    /// Secret part of the URL SNS posts SES notifications to.
+    /// Notifications are rejected when it is not set.
+    pub notifications_token: Option<Secret<String>>,
 }
 
 impl EmailClientSettings {
-    pub fn sender(&self) -> Result<SubscriberEmail, String> {
-        SubscriberEmail::parse(self.sender_email.clone())
+    pub fn sender(&self) -> Result<SenderAddress, String> {
+        SenderAddress::parse(
+            self.sender_email.clone(),
+            self.sender_name.clone(),
+            self.reply_to.clone(),
+        )
     }
 }
 
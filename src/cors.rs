@@ -0,0 +1,101 @@
+use actix_web::body::EitherBody;
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::header::{self, HeaderValue};
+use actix_web::http::Method;
+use actix_web::HttpResponse;
+use futures_util::future::{ready, LocalBoxFuture, Ready};
+use std::rc::Rc;
+
+/// Lets browsers on the configured origins call the wrapped endpoints.
+/// Requests from other origins are served without CORS headers, so browsers
+/// refuse to hand the response to the calling page.
+pub struct Cors {
+    allowed_origins: Rc<Vec<String>>,
+}
+
+impl Cors {
+    /// `*` allows every origin.
+    pub fn new(allowed_origins: Vec<String>) -> Self {
+        Self {
+            allowed_origins: Rc::new(allowed_origins),
+        }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for Cors
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = actix_web::Error;
+    type Transform = CorsMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(CorsMiddleware {
+            service: Rc::new(service),
+            allowed_origins: self.allowed_origins.clone(),
+        }))
+    }
+}
+
+pub struct CorsMiddleware<S> {
+    service: Rc<S>,
+    allowed_origins: Rc<Vec<String>>,
+}
+
+impl<S> CorsMiddleware<S> {
+    fn allowed_origin(&self, request: &ServiceRequest) -> Option<HeaderValue> {
+        let origin = request.headers().get(header::ORIGIN)?;
+        self.allowed_origins
+            .iter()
+            .any(|allowed| allowed == "*" || origin.to_str().is_ok_and(|o| o == allowed))
+            .then(|| origin.clone())
+    }
+}
+
+impl<S, B> Service<ServiceRequest> for CorsMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, request: ServiceRequest) -> Self::Future {
+        let origin = self.allowed_origin(&request);
+        let is_preflight = request.method() == Method::OPTIONS
+            && request
+                .headers()
+                .contains_key(header::ACCESS_CONTROL_REQUEST_METHOD);
+
+        if let (true, Some(origin)) = (is_preflight, &origin) {
+            let response = HttpResponse::NoContent()
+                .insert_header((header::ACCESS_CONTROL_ALLOW_ORIGIN, origin.clone()))
+                .insert_header((header::ACCESS_CONTROL_ALLOW_METHODS, "POST, OPTIONS"))
+                .insert_header((header::ACCESS_CONTROL_ALLOW_HEADERS, "Accept, Content-Type"))
+                .insert_header((header::ACCESS_CONTROL_MAX_AGE, "3600"))
+                .insert_header((header::VARY, "Origin"))
+                .finish();
+            return Box::pin(ready(Ok(request
+                .into_response(response)
+                .map_into_right_body())));
+        }
+
+        let service = self.service.clone();
+        Box::pin(async move {
+            let mut response = service.call(request).await?;
+            if let Some(origin) = origin {
+                let headers = response.headers_mut();
+                headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, origin);
+                headers.insert(header::VARY, HeaderValue::from_static("Origin"));
+            }
+            Ok(response.map_into_left_body())
+        })
+    }
+}
@@ -0,0 +1,203 @@
+use csv_core::{ReadRecordResult, Reader};
+use std::borrow::Cow;
+
+/// Upper bound on the size of a single record, so that a malformed upload
+/// cannot grow the buffers without limit.
+const MAX_RECORD_SIZE: usize = 64 * 1024;
+
+/// The fields of a CSV record, or why they could not be read.
+pub type CsvRecord = Result<Vec<String>, String>;
+
+/// Splits a CSV body into records as its chunks arrive, without ever holding
+/// more than the record being read in memory.
+pub struct CsvRecords {
+    reader: Reader,
+    output: Vec<u8>,
+    output_len: usize,
+    ends: Vec<usize>,
+    ends_len: usize,
+}
+
+impl Default for CsvRecords {
+    fn default() -> Self {
+        Self {
+            reader: Reader::new(),
+            output: vec![0; 1024],
+            output_len: 0,
+            ends: vec![0; 16],
+            ends_len: 0,
+        }
+    }
+}
+
+impl CsvRecords {
+    /// Parses the next chunk of the body and returns the records it completed.
+    /// Fails when a record is larger than the supported maximum.
+    pub fn feed(&mut self, mut input: &[u8]) -> Result<Vec<CsvRecord>, String> {
+        let mut records = Vec::new();
+        loop {
+            let (result, read, written, ended) = self.reader.read_record(
+                input,
+                &mut self.output[self.output_len..],
+                &mut self.ends[self.ends_len..],
+            );
+            input = &input[read..];
+            self.output_len += written;
+            self.ends_len += ended;
+            match result {
+                ReadRecordResult::InputEmpty | ReadRecordResult::End => return Ok(records),
+                ReadRecordResult::OutputFull => {
+                    if self.output.len() >= MAX_RECORD_SIZE {
+                        return Err(format!(
+                            "Records cannot be larger than {} bytes.",
+                            MAX_RECORD_SIZE
+                        ));
+                    }
+                    self.output.resize(self.output.len() * 2, 0);
+                }
+                ReadRecordResult::OutputEndsFull => {
+                    if self.ends.len() >= MAX_RECORD_SIZE {
+                        return Err(format!(
+                            "Records cannot have more than {} fields.",
+                            MAX_RECORD_SIZE
+                        ));
+                    }
+                    self.ends.resize(self.ends.len() * 2, 0);
+                }
+                ReadRecordResult::Record => {
+                    let record = self.take_record();
+                    // Blank lines carry no data.
+                    if record.as_ref().map_or(true, |f| f != &[""]) {
+                        records.push(record);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Signals the end of the body and returns the last record, if it was not
+    /// terminated by a newline.
+    pub fn finish(&mut self) -> Result<Vec<CsvRecord>, String> {
+        self.feed(&[])
+    }
+
+    fn take_record(&mut self) -> CsvRecord {
+        let mut fields = Vec::with_capacity(self.ends_len);
+        let mut start = 0;
+        for &end in &self.ends[..self.ends_len] {
+            fields.push(
+                String::from_utf8(self.output[start..end].to_vec())
+                    .map_err(|_| "The record is not valid UTF-8.".to_string()),
+            );
+            start = end;
+        }
+        self.output_len = 0;
+        self.ends_len = 0;
+        fields.into_iter().collect()
+    }
+}
+
+/// Escapes a field we export so that spreadsheets show it as text: cells that
+/// start like a formula are prefixed with `'` instead of being evaluated.
+pub fn spreadsheet_safe(field: &str) -> Cow<'_, str> {
+    if field.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+        Cow::Owned(format!("'{}", field))
+    } else {
+        Cow::Borrowed(field)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{spreadsheet_safe, CsvRecords};
+    use claims::assert_err;
+
+    fn fields(record: &[&str]) -> Result<Vec<String>, String> {
+        Ok(record.iter().map(|f| f.to_string()).collect())
+    }
+
+    #[test]
+    fn records_split_across_chunks_are_reassembled() {
+        // Arrange
+        let body = "email,name\r\nursula@example.com,\"Le Guin, Ursula\"\nbob@example.com,Bob";
+        let mut reader = CsvRecords::default();
+        let mut records = Vec::new();
+
+        // Act
+        for chunk in body.as_bytes().chunks(3) {
+            records.extend(reader.feed(chunk).unwrap());
+        }
+        records.extend(reader.finish().unwrap());
+
+        // Assert
+        assert_eq!(
+            records,
+            vec![
+                fields(&["email", "name"]),
+                fields(&["ursula@example.com", "Le Guin, Ursula"]),
+                fields(&["bob@example.com", "Bob"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn blank_lines_and_byte_order_marks_are_skipped() {
+        // Arrange
+        let mut reader = CsvRecords::default();
+
+        // Act
+        let mut records = reader
+            .feed(b"\xef\xbb\xbfemail\n\na@example.com\n")
+            .unwrap();
+        records.extend(reader.finish().unwrap());
+
+        // Assert
+        assert_eq!(
+            records,
+            vec![fields(&["email"]), fields(&["a@example.com"])]
+        );
+    }
+
+    #[test]
+    fn records_that_are_not_utf8_are_reported_individually() {
+        // Arrange
+        let mut reader = CsvRecords::default();
+
+        // Act
+        let records = reader
+            .feed(b"a@example.com,\xff\nb@example.com,Bob\n")
+            .unwrap();
+
+        // Assert
+        assert_eq!(records.len(), 2);
+        assert_err!(&records[0]);
+        assert_eq!(records[1], fields(&["b@example.com", "Bob"]));
+    }
+
+    #[test]
+    fn oversized_records_are_rejected() {
+        // Arrange
+        let mut reader = CsvRecords::default();
+        let field = "a".repeat(100 * 1024);
+
+        // Act
+        let result = reader.feed(field.as_bytes());
+
+        // Assert
+        assert_err!(result);
+    }
+
+    #[test]
+    fn fields_that_look_like_formulas_are_escaped() {
+        for field in ["=1+1", "+1", "-1", "@SUM(A1)", "\t=1", "\r=1"] {
+            assert_eq!(spreadsheet_safe(field), format!("'{}", field));
+        }
+    }
+
+    #[test]
+    fn other_fields_are_left_alone() {
+        for field in ["", "ursula@example.com", "Le Guin, Ursula", "1+1="] {
+            assert_eq!(spreadsheet_safe(field), field);
+        }
+    }
+}
@@ -0,0 +1,325 @@
+use crate::bootstrap::Dependencies;
+use crate::configuration::Settings;
+use crate::domain::{DeliveryStatus, DigestFrequency, Locale, SubscriberEmail, SubscriptionStatus};
+use crate::email::email_client::{EmailClient, EmailService, SendEmailRequest};
+use crate::email::merge_tags::MergeTags;
+use crate::i18n::{Catalogs, Translator};
+use crate::issue_delivery_worker::{
+    get_issue, record_delivery, subscriber_merge_tags, ExecutionOutcome,
+};
+use crate::startup::get_connection_pool;
+use chrono::Utc;
+use http::Uri;
+use sqlx::{Executor, PgPool};
+use std::time::Duration;
+use tracing::{field::display, Span};
+use uuid::Uuid;
+
+pub async fn run_digest_worker_until_stopped(
+    configuration: Settings,
+    dependencies: Dependencies,
+) -> Result<(), anyhow::Error> {
+    let connection_pool = get_connection_pool(&configuration.database);
+    let sender = configuration
+        .email_client
+        .sender()
+        .map_err(anyhow::Error::msg)?;
+    let email_service = EmailService::new(sender);
+    let catalogs = Catalogs::new(&configuration.i18n)?;
+    worker_loop(
+        connection_pool,
+        email_service,
+        dependencies.email_client.as_ref(),
+        configuration.application.base_url,
+        catalogs,
+    )
+    .await
+}
+
+async fn worker_loop(
+    pool: PgPool,
+    email_service: EmailService,
+    email_client: &dyn EmailClient,
+    base_url: Uri,
+    catalogs: Catalogs,
+) -> Result<(), anyhow::Error> {
+    loop {
+        match try_send_due_digest(&pool, &email_service, email_client, &base_url, &catalogs).await {
+            Ok(ExecutionOutcome::EmptyQueue) => {
+                tokio::time::sleep(Duration::from_secs(60)).await;
+            }
+            Err(_) => {
+                tokio::time::sleep(Duration::from_secs(1)).await;
+            }
+            Ok(ExecutionOutcome::TaskCompleted) => {}
+        }
+    }
+}
+
+struct DigestRecipient {
+    id: Uuid,
+    email: String,
+    name: String,
+    attributes: serde_json::Value,
+    preferences_token: String,
+    locale: Option<String>,
+    digest_frequency: String,
+}
+
+struct QueuedIssue {
+    newsletter_issue_id: Uuid,
+    status: Option<String>,
+    unsubscribe_token: Option<String>,
+}
+
+struct RenderedDigest {
+    subject: String,
+    html: String,
+    text: String,
+}
+
+/// Sends the digest of one subscriber whose digest is due.
+///
+/// A digest is due once a period of the subscriber's frequency has passed
+/// since their previous digest or, for their first one, since its oldest
+/// issue was queued. It covers every issue queued for them so far, except
+/// those of lists they have left since. The issues covered are recorded
+/// with the digest and removed from the queue in the same transaction, so
+/// no issue is ever sent twice.
+#[tracing::instrument(skip_all, fields(subscriber_email=tracing::field::Empty), err)]
+pub async fn try_send_due_digest(
+    pool: &PgPool,
+    email_service: &EmailService,
+    email_client: &dyn EmailClient,
+    base_url: &Uri,
+    catalogs: &Catalogs,
+) -> Result<ExecutionOutcome, anyhow::Error> {
+    let now = Utc::now();
+    let mut transaction = pool.begin().await?;
+    let recipient = sqlx::query_as!(
+        DigestRecipient,
+        r#"
+    SELECT id, email, name, attributes, preferences_token, locale, digest_frequency
+    FROM subscriptions
+    WHERE EXISTS (SELECT 1 FROM digest_queue WHERE subscriber_id = subscriptions.id)
+      AND (paused_until IS NULL OR paused_until <= $1)
+      AND COALESCE(
+            (SELECT MAX(created_at) FROM digests WHERE subscriber_id = subscriptions.id),
+            (SELECT MIN(queued_at) FROM digest_queue WHERE subscriber_id = subscriptions.id)
+          ) <= CASE digest_frequency WHEN $2 THEN $3 WHEN $4 THEN $5 ELSE $1 END
+    FOR UPDATE
+    SKIP LOCKED
+    LIMIT 1
+            "#,
+        now,
+        DigestFrequency::Daily.as_str(),
+        now - DigestFrequency::Daily.period(),
+        DigestFrequency::Weekly.as_str(),
+        now - DigestFrequency::Weekly.period(),
+    )
+    .fetch_optional(&mut *transaction)
+    .await?;
+    let Some(recipient) = recipient else {
+        return Ok(ExecutionOutcome::EmptyQueue);
+    };
+    Span::current().record("subscriber_email", display(&recipient.email));
+
+    let queued = sqlx::query_as!(
+        QueuedIssue,
+        r#"
+    SELECT digest_queue.newsletter_issue_id,
+           list_subscriptions.status as "status?",
+           list_subscriptions.unsubscribe_token as "unsubscribe_token?"
+    FROM digest_queue
+    JOIN newsletter_issues ON newsletter_issues.id = digest_queue.newsletter_issue_id
+    LEFT JOIN list_subscriptions
+      ON list_subscriptions.subscriber_id = digest_queue.subscriber_id
+     AND list_subscriptions.list_id = newsletter_issues.list_id
+    WHERE digest_queue.subscriber_id = $1
+    ORDER BY newsletter_issues.published_at, digest_queue.queued_at
+            "#,
+        recipient.id
+    )
+    .fetch_all(&mut *transaction)
+    .await?;
+    let (issues, left): (Vec<_>, Vec<_>) = queued
+        .iter()
+        .partition(|q| q.status.as_deref() != Some(SubscriptionStatus::Unsubscribed.as_str()));
+    for issue in left {
+        tracing::info!("Skipping an issue of a list the subscriber left after it was queued");
+        record_delivery(
+            &mut transaction,
+            issue.newsletter_issue_id,
+            &recipient.email,
+            DeliveryStatus::Skipped,
+            Some("unsubscribed".to_string()),
+        )
+        .await?;
+    }
+
+    if !issues.is_empty() {
+        let (status, error) = match SubscriberEmail::parse(recipient.email.clone()) {
+            Ok(email) => {
+                let locale = recipient
+                    .locale
+                    .as_deref()
+                    .and_then(|locale| Locale::parse(locale).ok());
+                let t = catalogs.translator(locale.as_ref());
+                let digest = render_digest(
+                    pool,
+                    &issues,
+                    &email,
+                    &recipient,
+                    locale.as_ref(),
+                    base_url,
+                    &t,
+                )
+                .await?;
+                let send_email_request = SendEmailRequest {
+                    to: &email,
+                    subject: &digest.subject,
+                    html_content: &digest.html,
+                    text_content: &digest.text,
+                    attachments: &[],
+                };
+                match email_service
+                    .send_email(email_client, send_email_request)
+                    .await
+                {
+                    Ok(()) => (DeliveryStatus::Sent, None),
+                    Err(e) => {
+                        tracing::error!(
+                            error.cause_chain = ?e,
+                            error.message = %e,
+                            "Failed to deliver a digest to a confirmed subscriber. \
+                            Skipping.",
+                        );
+                        (DeliveryStatus::Failed, Some(e.to_string()))
+                    }
+                }
+            }
+            Err(error) => {
+                tracing::warn!(
+                    error.cause_chain = ?error,
+                    "Skipping a confirmed subscriber. \
+                    Their stored contact details are invalid",
+                );
+                (DeliveryStatus::Skipped, Some(error))
+            }
+        };
+
+        let digest_id = Uuid::new_v4();
+        let query = sqlx::query!(
+            r#"
+    INSERT INTO digests (id, subscriber_id, frequency, status, error, created_at)
+    VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+            digest_id,
+            recipient.id,
+            recipient.digest_frequency,
+            status.as_str(),
+            error,
+            now
+        );
+        transaction.execute(query).await?;
+        for issue in &issues {
+            let query = sqlx::query!(
+                r#"
+    INSERT INTO digest_issues (digest_id, newsletter_issue_id)
+    VALUES ($1, $2)
+            "#,
+                digest_id,
+                issue.newsletter_issue_id
+            );
+            transaction.execute(query).await?;
+            record_delivery(
+                &mut transaction,
+                issue.newsletter_issue_id,
+                &recipient.email,
+                status,
+                error.clone(),
+            )
+            .await?;
+        }
+    }
+
+    let issue_ids: Vec<Uuid> = queued.iter().map(|q| q.newsletter_issue_id).collect();
+    let query = sqlx::query!(
+        r#"
+    DELETE FROM digest_queue
+    WHERE subscriber_id = $1 AND newsletter_issue_id = ANY($2)
+            "#,
+        recipient.id,
+        &issue_ids
+    );
+    transaction.execute(query).await?;
+    transaction.commit().await?;
+    Ok(ExecutionOutcome::TaskCompleted)
+}
+
+/// Renders the digest from the templates of the subscriber's catalog. Each
+/// issue is taken in the subscriber's locale and personalized with the same
+/// merge tags as when it is sent on its own.
+async fn render_digest(
+    pool: &PgPool,
+    issues: &[&QueuedIssue],
+    email: &SubscriberEmail,
+    recipient: &DigestRecipient,
+    locale: Option<&Locale>,
+    base_url: &Uri,
+    t: &Translator<'_>,
+) -> Result<RenderedDigest, anyhow::Error> {
+    let preferences_url = format!("{}preferences/{}", base_url, recipient.preferences_token);
+    let merge_tags = || {
+        subscriber_merge_tags(&recipient.name, email, &recipient.attributes)
+            .with("preferences_url", &preferences_url)
+    };
+    let html_issue = t.text("email.digest.html_issue");
+    let text_issue = t.text("email.digest.text_issue");
+    let mut html_issues = Vec::with_capacity(issues.len());
+    let mut text_issues = Vec::with_capacity(issues.len());
+    for queued in issues {
+        let issue = get_issue(pool, queued.newsletter_issue_id, locale, None).await?;
+        let archive_url = issue
+            .slug
+            .as_ref()
+            .map(|slug| format!("{}archive/{}", base_url, slug))
+            .unwrap_or_default();
+        let unsubscribe_url = queued
+            .unsubscribe_token
+            .as_ref()
+            .map(|token| format!("{}subscriptions/unsubscribe?token={}", base_url, token))
+            .unwrap_or_default();
+        let issue_tags = merge_tags()
+            .with("archive_url", &archive_url)
+            .with("unsubscribe_url", &unsubscribe_url);
+        let item_tags = MergeTags::new()
+            .with("title", &issue.title)
+            .with("archive_url", &archive_url)
+            .with("content", issue_tags.render_text(&issue.text_content))
+            .with_html("content", issue_tags.render_html(&issue.html_content));
+        html_issues.push(item_tags.render_html(&html_issue));
+        text_issues.push(item_tags.render_text(&text_issue));
+    }
+
+    let digest_tags = merge_tags()
+        .with("issue_count", issues.len().to_string())
+        .with("issues", text_issues.join("\n\n"))
+        .with_html("issues", html_issues.join("\n"));
+    let preferences_label = t.text("email.footer.manage_preferences");
+    Ok(RenderedDigest {
+        subject: digest_tags.render_text(&t.text("email.digest.subject")),
+        html: format!(
+            "{}\n<p><a href=\"{}\">{}</a></p>",
+            digest_tags.render_html(&t.text("email.digest.html")),
+            preferences_url,
+            htmlescape::encode_minimal(&preferences_label)
+        ),
+        text: format!(
+            "{}\n\n{}: {}",
+            digest_tags.render_text(&t.text("email.digest.text")),
+            preferences_label,
+            preferences_url
+        ),
+    })
+}
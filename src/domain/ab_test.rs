@@ -0,0 +1,203 @@
+/// What picks the winner of an A/B test.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AbTestMetric {
+    OpenRate,
+    ClickRate,
+}
+
+impl AbTestMetric {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AbTestMetric::OpenRate => "open_rate",
+            AbTestMetric::ClickRate => "click_rate",
+        }
+    }
+}
+
+impl TryFrom<String> for AbTestMetric {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        match s.as_str() {
+            "open_rate" => Ok(Self::OpenRate),
+            "click_rate" => Ok(Self::ClickRate),
+            other => Err(format!(
+                "{} is not a valid A/B test metric. Use either `open_rate` or `click_rate`.",
+                other
+            )),
+        }
+    }
+}
+
+/// Subjects and contents tried on a sample of an issue's recipients. Once
+/// the wait is over, the variant with the best rate goes out to the rest.
+#[derive(Debug, Clone)]
+pub struct AbTest {
+    pub sample_percent: i32,
+    pub wait_minutes: i32,
+    pub metric: AbTestMetric,
+    pub variants: Vec<AbTestVariant>,
+}
+
+/// A variant of an A/B test, labelled `A`, `B`, ... in the order given.
+#[derive(Debug, Clone)]
+pub struct AbTestVariant {
+    pub label: String,
+    pub title: String,
+    pub html_content: String,
+    pub text_content: String,
+}
+
+impl AbTest {
+    const MAX_VARIANTS: usize = 26;
+    const MAX_WAIT_MINUTES: i32 = 7 * 24 * 60;
+
+    /// Checks the sample and the wait and labels the variants, each given as
+    /// its title, HTML content and text content.
+    pub fn parse(
+        sample_percent: i32,
+        wait_minutes: i32,
+        metric: AbTestMetric,
+        variants: Vec<(String, String, String)>,
+    ) -> Result<AbTest, String> {
+        if !(2..=Self::MAX_VARIANTS).contains(&variants.len()) {
+            return Err(format!(
+                "An A/B test needs between 2 and {} variants.",
+                Self::MAX_VARIANTS
+            ));
+        }
+        if !(1..=99).contains(&sample_percent) {
+            return Err("The A/B test sample must be between 1 and 99 percent.".into());
+        }
+        if !(1..=Self::MAX_WAIT_MINUTES).contains(&wait_minutes) {
+            return Err(format!(
+                "The A/B test wait must be between 1 and {} minutes.",
+                Self::MAX_WAIT_MINUTES
+            ));
+        }
+        let variants = variants
+            .into_iter()
+            .zip(b'A'..)
+            .map(
+                |((title, html_content, text_content), label)| AbTestVariant {
+                    label: char::from(label).to_string(),
+                    title,
+                    html_content,
+                    text_content,
+                },
+            )
+            .collect();
+        Ok(Self {
+            sample_percent,
+            wait_minutes,
+            metric,
+            variants,
+        })
+    }
+}
+
+/// How the recipients of one variant responded to it.
+#[derive(Debug, Clone)]
+pub struct AbTestResult {
+    pub label: String,
+    pub sent: i64,
+    pub opened: i64,
+    pub clicked: i64,
+}
+
+impl AbTestResult {
+    /// The share of the emails sent that were opened or clicked.
+    pub fn rate(&self, metric: AbTestMetric) -> f64 {
+        if self.sent == 0 {
+            return 0.0;
+        }
+        let count = match metric {
+            AbTestMetric::OpenRate => self.opened,
+            AbTestMetric::ClickRate => self.clicked,
+        };
+        count as f64 / self.sent as f64
+    }
+}
+
+/// The label of the variant with the best rate. Ties go to the variant
+/// listed first.
+pub fn pick_winner(results: &[AbTestResult], metric: AbTestMetric) -> Option<&str> {
+    results
+        .iter()
+        .rev()
+        .max_by(|a, b| a.rate(metric).total_cmp(&b.rate(metric)))
+        .map(|result| result.label.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{pick_winner, AbTest, AbTestMetric, AbTestResult};
+    use claims::assert_err;
+
+    fn variants(count: usize) -> Vec<(String, String, String)> {
+        (0..count)
+            .map(|i| (format!("Title {}", i), "<p>Hi</p>".into(), "Hi".into()))
+            .collect()
+    }
+
+    fn result(label: &str, sent: i64, opened: i64, clicked: i64) -> AbTestResult {
+        AbTestResult {
+            label: label.into(),
+            sent,
+            opened,
+            clicked,
+        }
+    }
+
+    #[test]
+    fn variants_are_labelled_in_order() {
+        // Act
+        let test = AbTest::parse(20, 60, AbTestMetric::OpenRate, variants(3)).unwrap();
+
+        // Assert
+        let labels: Vec<_> = test.variants.iter().map(|v| v.label.as_str()).collect();
+        assert_eq!(labels, ["A", "B", "C"]);
+    }
+
+    #[test]
+    fn invalid_tests_are_rejected() {
+        for (sample_percent, wait_minutes, count) in [
+            (20, 60, 1),
+            (20, 60, 27),
+            (0, 60, 2),
+            (100, 60, 2),
+            (20, 0, 2),
+        ] {
+            assert_err!(AbTest::parse(
+                sample_percent,
+                wait_minutes,
+                AbTestMetric::OpenRate,
+                variants(count)
+            ));
+        }
+    }
+
+    #[test]
+    fn the_variant_with_the_best_rate_wins() {
+        // Arrange
+        let results = [
+            result("A", 100, 30, 2),
+            result("B", 50, 20, 1),
+            result("C", 0, 0, 0),
+        ];
+
+        // Act & Assert
+        assert_eq!(pick_winner(&results, AbTestMetric::OpenRate), Some("B"));
+        assert_eq!(pick_winner(&results, AbTestMetric::ClickRate), Some("A"));
+    }
+
+    #[test]
+    fn ties_go_to_the_first_variant() {
+        // Arrange
+        let results = [result("A", 10, 1, 0), result("B", 20, 2, 0)];
+
+        // Act & Assert
+        assert_eq!(pick_winner(&results, AbTestMetric::OpenRate), Some("A"));
+        assert_eq!(pick_winner(&results, AbTestMetric::ClickRate), Some("A"));
+    }
+}
@@ -0,0 +1,95 @@
+/// Where a subscriber gave their consent, e.g. the `homepage-footer` form.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FormSource(String);
+
+impl FormSource {
+    pub fn parse(s: String) -> Result<FormSource, String> {
+        let is_too_long = s.chars().count() > 60;
+        let has_invalid_characters = !s
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_');
+        if s.is_empty() || is_too_long || has_invalid_characters {
+            Err(format!("{} is not a valid form source.", s))
+        } else {
+            Ok(Self(s))
+        }
+    }
+
+    /// The link in the confirmation email, which completes the double opt-in.
+    pub fn confirmation_email() -> Self {
+        Self("confirmation_email".to_string())
+    }
+
+    /// A list picked in the preference center, reached through an emailed
+    /// link, which stands in for the double opt-in.
+    pub fn preference_center() -> Self {
+        Self("preference_center".to_string())
+    }
+}
+
+/// Submissions that do not name their form come from the subscription form.
+impl Default for FormSource {
+    fn default() -> Self {
+        Self("subscription_form".to_string())
+    }
+}
+
+impl AsRef<str> for FormSource {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConsentEvent {
+    Subscribe,
+    Confirm,
+    /// Consent collected elsewhere and attested by an admin on import.
+    Import,
+}
+
+impl ConsentEvent {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ConsentEvent::Subscribe => "subscribe",
+            ConsentEvent::Confirm => "confirm",
+            ConsentEvent::Import => "import",
+        }
+    }
+}
+
+/// Proof of how a subscriber consented, kept for every subscription and
+/// confirmation.
+#[derive(Debug, Clone)]
+pub struct ConsentEvidence {
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub form_source: FormSource,
+    pub consent_text_version: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FormSource;
+    use claims::{assert_err, assert_ok};
+
+    #[test]
+    fn lowercase_identifiers_are_valid_form_sources() {
+        for source in ["homepage-footer", "blog_sidebar_2"] {
+            assert_ok!(FormSource::parse(source.to_string()));
+        }
+    }
+
+    #[test]
+    fn empty_or_long_form_sources_are_rejected() {
+        assert_err!(FormSource::parse("".to_string()));
+        assert_err!(FormSource::parse("a".repeat(61)));
+    }
+
+    #[test]
+    fn form_sources_with_spaces_or_markup_are_rejected() {
+        for source in ["Homepage", "home page", "<script>"] {
+            assert_err!(FormSource::parse(source.to_string()));
+        }
+    }
+}
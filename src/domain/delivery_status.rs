@@ -0,0 +1,67 @@
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DeliveryStatus {
+    Queued,
+    Sent,
+    Failed,
+    Skipped,
+    Bounced,
+}
+
+impl DeliveryStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DeliveryStatus::Queued => "queued",
+            DeliveryStatus::Sent => "sent",
+            DeliveryStatus::Failed => "failed",
+            DeliveryStatus::Skipped => "skipped",
+            DeliveryStatus::Bounced => "bounced",
+        }
+    }
+}
+
+impl TryFrom<String> for DeliveryStatus {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        match s.as_str() {
+            "queued" => Ok(Self::Queued),
+            "sent" => Ok(Self::Sent),
+            "failed" => Ok(Self::Failed),
+            "skipped" => Ok(Self::Skipped),
+            "bounced" => Ok(Self::Bounced),
+            other => Err(format!("{} is not a valid delivery status.", other)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DeliveryStatus;
+    use claims::{assert_err, assert_ok_eq};
+
+    #[test]
+    fn every_status_round_trips_through_its_string_form() {
+        for status in [
+            DeliveryStatus::Queued,
+            DeliveryStatus::Sent,
+            DeliveryStatus::Failed,
+            DeliveryStatus::Skipped,
+            DeliveryStatus::Bounced,
+        ] {
+            // Act
+            let result = DeliveryStatus::try_from(status.as_str().to_string());
+
+            // Assert
+            assert_ok_eq!(result, status);
+        }
+    }
+
+    #[test]
+    fn unknown_status_is_rejected() {
+        // Act
+        let result = DeliveryStatus::try_from("delivered".to_string());
+
+        // Assert
+        assert_err!(result);
+    }
+}
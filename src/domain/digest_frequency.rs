@@ -0,0 +1,72 @@
+use chrono::Duration;
+
+/// How often a subscriber wants to receive issues.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DigestFrequency {
+    Immediate,
+    Daily,
+    Weekly,
+}
+
+impl DigestFrequency {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DigestFrequency::Immediate => "immediate",
+            DigestFrequency::Daily => "daily",
+            DigestFrequency::Weekly => "weekly",
+        }
+    }
+
+    /// The time between two digests. Immediate subscribers have nothing to
+    /// wait for, so whatever is left of their digest goes out at once.
+    pub fn period(&self) -> Duration {
+        match self {
+            DigestFrequency::Immediate => Duration::zero(),
+            DigestFrequency::Daily => Duration::days(1),
+            DigestFrequency::Weekly => Duration::weeks(1),
+        }
+    }
+}
+
+impl TryFrom<String> for DigestFrequency {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        match s.as_str() {
+            "immediate" => Ok(Self::Immediate),
+            "daily" => Ok(Self::Daily),
+            "weekly" => Ok(Self::Weekly),
+            other => Err(format!("{} is not a valid digest frequency.", other)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DigestFrequency;
+    use claims::{assert_err, assert_ok_eq};
+
+    #[test]
+    fn every_frequency_round_trips_through_its_string_form() {
+        for frequency in [
+            DigestFrequency::Immediate,
+            DigestFrequency::Daily,
+            DigestFrequency::Weekly,
+        ] {
+            // Act
+            let result = DigestFrequency::try_from(frequency.as_str().to_string());
+
+            // Assert
+            assert_ok_eq!(result, frequency);
+        }
+    }
+
+    #[test]
+    fn unknown_frequency_is_rejected() {
+        // Act
+        let result = DigestFrequency::try_from("hourly".to_string());
+
+        // Assert
+        assert_err!(result);
+    }
+}
@@ -1,17 +1,36 @@
 use std::fmt::{Display, Formatter};
 use validator::ValidateEmail;
 
+/// An address as it was written, except for the domain, which is
+/// lowercased and stored in its ASCII (punycode) form so that every provider
+/// can deliver to it.
 #[derive(Debug, Clone, PartialEq)]
 pub struct Email(String);
 
 impl Email {
     pub fn parse(s: String) -> Result<Email, String> {
-        if s.validate_email() {
-            Ok(Self(s))
+        let invalid = || format!("{} is not a valid email.", s);
+        let (local_part, domain) = s.trim().rsplit_once('@').ok_or_else(invalid)?;
+        let domain = idna::domain_to_ascii(domain).map_err(|_| invalid())?;
+        let email = format!("{}@{}", local_part, domain);
+        if email.validate_email() {
+            Ok(Self(email))
         } else {
-            Err(format!("{} is not a valid email.", s))
+            Err(invalid())
         }
     }
+
+    /// Everything before the last `@`.
+    pub fn local_part(&self) -> &str {
+        self.0
+            .rsplit_once('@')
+            .map_or("", |(local_part, _)| local_part)
+    }
+
+    /// Everything after the last `@`.
+    pub fn domain(&self) -> &str {
+        self.0.rsplit_once('@').map_or("", |(_, domain)| domain)
+    }
 }
 
 impl AsRef<str> for Email {
@@ -28,7 +47,7 @@ impl Display for Email {
 
 #[cfg(test)]
 mod tests {
-    use claims::assert_err;
+    use claims::{assert_err, assert_ok};
     use fake::faker::internet::en::SafeEmail;
     use fake::Fake;
     use proptest::prelude::{any, Strategy};
@@ -72,6 +91,24 @@ mod tests {
         assert_err!(result);
     }
 
+    #[test]
+    fn the_domain_is_lowercased_and_encoded_in_ascii() {
+        // Arrange
+        let emails = [
+            (" Ursula@Example.COM ", "Ursula@example.com"),
+            ("ursula@Bücher.example", "ursula@xn--bcher-kva.example"),
+        ];
+
+        for (email, expected) in emails {
+            // Act
+            let result = Email::parse(email.to_string());
+
+            // Assert
+            assert_ok!(&result);
+            assert_eq!(result.unwrap().as_ref(), expected);
+        }
+    }
+
     fn valid_email_strategy() -> impl Strategy<Value = String> {
         any::<String>().prop_map(|_| SafeEmail().fake())
     }
@@ -0,0 +1,205 @@
+use crate::configuration::EmailPolicySettings;
+use crate::domain::SubscriberEmail;
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
+
+const BUNDLED_DISPOSABLE_DOMAINS: &str = include_str!("disposable_domains.txt");
+
+/// Which addresses may subscribe, on top of being valid.
+///
+/// Domains match their subdomains too. Allowed domains are exempt from the
+/// disposable and denied lists, so that a false positive can be let through.
+#[derive(Debug, Default)]
+pub struct EmailPolicy {
+    disposable_domains: HashSet<String>,
+    role_accounts: HashSet<String>,
+    allowed_domains: HashSet<String>,
+    denied_domains: HashSet<String>,
+}
+
+impl EmailPolicy {
+    pub fn new(settings: &EmailPolicySettings) -> Result<Self, std::io::Error> {
+        let mut disposable_domains = HashSet::new();
+        if settings.block_disposable {
+            disposable_domains.extend(domain_list(BUNDLED_DISPOSABLE_DOMAINS));
+            if let Some(path) = &settings.disposable_domains_file {
+                disposable_domains.extend(domain_list(&std::fs::read_to_string(path)?));
+            }
+        }
+        Ok(Self {
+            disposable_domains,
+            role_accounts: lowercase(&settings.role_accounts),
+            allowed_domains: lowercase(&settings.allowed_domains),
+            denied_domains: lowercase(&settings.denied_domains),
+        })
+    }
+
+    pub fn check(&self, email: &SubscriberEmail) -> Result<(), PolicyViolation> {
+        let domain = email.domain().to_lowercase();
+        if !matches_domain(&self.allowed_domains, &domain) {
+            if matches_domain(&self.denied_domains, &domain) {
+                return Err(PolicyViolation::DeniedDomain(domain));
+            }
+            if matches_domain(&self.disposable_domains, &domain) {
+                return Err(PolicyViolation::DisposableDomain(domain));
+            }
+        }
+        // `noreply+news@` is as shared as `noreply@`.
+        let local_part = email.local_part().to_lowercase();
+        let mailbox = local_part.split('+').next().unwrap_or_default();
+        if self.role_accounts.contains(mailbox) {
+            return Err(PolicyViolation::RoleAccount(mailbox.to_owned()));
+        }
+        Ok(())
+    }
+}
+
+/// Why an address may not subscribe, with the domain or mailbox at fault.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PolicyViolation {
+    DeniedDomain(String),
+    DisposableDomain(String),
+    RoleAccount(String),
+}
+
+impl Display for PolicyViolation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PolicyViolation::DeniedDomain(domain) => {
+                write!(f, "Addresses at {} cannot subscribe.", domain)
+            }
+            PolicyViolation::DisposableDomain(domain) => write!(
+                f,
+                "{} is a disposable email provider. Please use a permanent address.",
+                domain
+            ),
+            PolicyViolation::RoleAccount(mailbox) => write!(
+                f,
+                "{}@ is a role address. Please use a personal address.",
+                mailbox
+            ),
+        }
+    }
+}
+
+/// One domain per line. Blank lines and `#` comments are skipped.
+fn domain_list(contents: &str) -> impl Iterator<Item = String> + '_ {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_lowercase)
+}
+
+fn lowercase(values: &[String]) -> HashSet<String> {
+    values.iter().map(|value| value.to_lowercase()).collect()
+}
+
+fn matches_domain(domains: &HashSet<String>, domain: &str) -> bool {
+    let mut candidate = domain;
+    loop {
+        if domains.contains(candidate) {
+            return true;
+        }
+        match candidate.split_once('.') {
+            Some((_, parent)) => candidate = parent,
+            None => return false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EmailPolicy, PolicyViolation};
+    use crate::configuration::EmailPolicySettings;
+    use crate::domain::SubscriberEmail;
+    use claims::{assert_err, assert_ok};
+
+    fn policy() -> EmailPolicy {
+        EmailPolicy::new(&EmailPolicySettings {
+            block_disposable: true,
+            disposable_domains_file: None,
+            role_accounts: vec!["noreply".into(), "abuse".into()],
+            allowed_domains: vec!["trashmail.de".into()],
+            denied_domains: vec!["competitor.example".into()],
+        })
+        .unwrap()
+    }
+
+    fn check(policy: &EmailPolicy, email: &str) -> Result<(), PolicyViolation> {
+        policy.check(&SubscriberEmail::parse(email.to_string()).unwrap())
+    }
+
+    #[test]
+    fn personal_addresses_are_accepted() {
+        // Arrange
+        let policy = policy();
+
+        // Act
+        let result = check(&policy, "ursula@example.com");
+
+        // Assert
+        assert_ok!(result);
+    }
+
+    #[test]
+    fn disposable_domains_and_their_subdomains_are_rejected() {
+        // Arrange
+        let policy = policy();
+
+        for email in ["bot@mailinator.com", "bot@eu.YOPMAIL.com"] {
+            // Act
+            let result = check(&policy, email);
+
+            // Assert
+            assert_err!(result, "{} was accepted", email);
+        }
+    }
+
+    #[test]
+    fn role_addresses_are_rejected_whatever_their_case_or_tag() {
+        // Arrange
+        let policy = policy();
+
+        for email in ["noreply@example.com", "Abuse+news@example.com"] {
+            // Act
+            let result = check(&policy, email);
+
+            // Assert
+            assert_err!(result, "{} was accepted", email);
+        }
+    }
+
+    #[test]
+    fn denied_domains_are_rejected_and_allowed_domains_are_exempt() {
+        // Arrange
+        let policy = policy();
+
+        // Act
+        let denied = check(&policy, "ursula@mail.competitor.example");
+        let allowed = check(&policy, "ursula@trashmail.de");
+
+        // Assert
+        assert_err!(denied);
+        assert_ok!(allowed);
+    }
+
+    #[test]
+    fn disposable_domains_are_accepted_when_not_blocked() {
+        // Arrange
+        let policy = EmailPolicy::new(&EmailPolicySettings {
+            block_disposable: false,
+            disposable_domains_file: None,
+            role_accounts: vec![],
+            allowed_domains: vec![],
+            denied_domains: vec![],
+        })
+        .unwrap();
+
+        // Act
+        let result = check(&policy, "ursula@mailinator.com");
+
+        // Assert
+        assert_ok!(result);
+    }
+}
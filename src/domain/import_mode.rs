@@ -0,0 +1,82 @@
+/// How imported contacts join a list.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ImportMode {
+    /// The contacts already opted in elsewhere, as the admin attests.
+    Confirmed { attestation: String },
+    /// Every contact is sent a confirmation email first.
+    OptIn,
+}
+
+impl ImportMode {
+    /// Confirmed imports need an attestation of how consent was collected.
+    pub fn parse(mode: &str, attestation: Option<String>) -> Result<ImportMode, String> {
+        let attestation = attestation
+            .map(|a| a.trim().to_owned())
+            .filter(|a| !a.is_empty());
+        match (mode, attestation) {
+            ("confirmed", Some(attestation)) if attestation.chars().count() <= 1000 => {
+                Ok(Self::Confirmed { attestation })
+            }
+            ("confirmed", Some(_)) => {
+                Err("The attestation cannot be longer than 1000 characters.".into())
+            }
+            ("confirmed", None) => Err(
+                "Importing contacts as confirmed requires an attestation of their consent.".into(),
+            ),
+            ("opt_in", None) => Ok(Self::OptIn),
+            ("opt_in", Some(_)) => Err("Only confirmed imports take an attestation.".into()),
+            (other, _) => Err(format!(
+                "{} is not a valid import mode. Use either `confirmed` or `opt_in`.",
+                other
+            )),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ImportMode::Confirmed { .. } => "confirmed",
+            ImportMode::OptIn => "opt_in",
+        }
+    }
+
+    pub fn attestation(&self) -> Option<&str> {
+        match self {
+            ImportMode::Confirmed { attestation } => Some(attestation),
+            ImportMode::OptIn => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ImportMode;
+    use claims::{assert_err, assert_ok_eq};
+
+    #[test]
+    fn confirmed_imports_keep_their_attestation() {
+        assert_ok_eq!(
+            ImportMode::parse("confirmed", Some(" Opted in on the old site ".into())),
+            ImportMode::Confirmed {
+                attestation: "Opted in on the old site".into()
+            }
+        );
+    }
+
+    #[test]
+    fn confirmed_imports_without_an_attestation_are_rejected() {
+        for attestation in [None, Some("".to_string()), Some("  ".to_string())] {
+            assert_err!(ImportMode::parse("confirmed", attestation));
+        }
+    }
+
+    #[test]
+    fn opt_in_imports_take_no_attestation() {
+        assert_ok_eq!(ImportMode::parse("opt_in", None), ImportMode::OptIn);
+        assert_err!(ImportMode::parse("opt_in", Some("Opted in".into())));
+    }
+
+    #[test]
+    fn unknown_modes_are_rejected() {
+        assert_err!(ImportMode::parse("silent", None));
+    }
+}
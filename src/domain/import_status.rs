@@ -0,0 +1,59 @@
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ImportStatus {
+    Processing,
+    Completed,
+    Failed,
+}
+
+impl ImportStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ImportStatus::Processing => "processing",
+            ImportStatus::Completed => "completed",
+            ImportStatus::Failed => "failed",
+        }
+    }
+}
+
+impl TryFrom<String> for ImportStatus {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        match s.as_str() {
+            "processing" => Ok(Self::Processing),
+            "completed" => Ok(Self::Completed),
+            "failed" => Ok(Self::Failed),
+            other => Err(format!("{} is not a valid import status.", other)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ImportStatus;
+    use claims::{assert_err, assert_ok_eq};
+
+    #[test]
+    fn every_status_round_trips_through_its_string_form() {
+        for status in [
+            ImportStatus::Processing,
+            ImportStatus::Completed,
+            ImportStatus::Failed,
+        ] {
+            // Act
+            let result = ImportStatus::try_from(status.as_str().to_string());
+
+            // Assert
+            assert_ok_eq!(result, status);
+        }
+    }
+
+    #[test]
+    fn unknown_status_is_rejected() {
+        // Act
+        let result = ImportStatus::try_from("queued".to_string());
+
+        // Assert
+        assert_err!(result);
+    }
+}
@@ -0,0 +1,122 @@
+use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+
+/// The instant at which a newsletter issue should go out.
+///
+/// Editors either provide an RFC 3339 timestamp with an explicit offset or a
+/// wall-clock time together with the IANA timezone of the audience
+/// (e.g. `2026-10-26T08:00:00` in `Europe/Lisbon`).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IssueSchedule(DateTime<Utc>);
+
+impl IssueSchedule {
+    pub fn parse(
+        scheduled_for: &str,
+        timezone: Option<&str>,
+        now: DateTime<Utc>,
+    ) -> Result<IssueSchedule, String> {
+        let scheduled_for = match timezone {
+            Some(timezone) => {
+                let tz: Tz = timezone
+                    .parse()
+                    .map_err(|_| format!("{} is not a valid timezone.", timezone))?;
+                let local = NaiveDateTime::parse_from_str(scheduled_for, "%Y-%m-%dT%H:%M:%S")
+                    .or_else(|_| NaiveDateTime::parse_from_str(scheduled_for, "%Y-%m-%dT%H:%M"))
+                    .map_err(|_| {
+                        format!("{} is not a valid local date and time.", scheduled_for)
+                    })?;
+                tz.from_local_datetime(&local)
+                    .earliest()
+                    .ok_or_else(|| {
+                        format!("{} does not exist in the {} timezone.", scheduled_for, tz)
+                    })?
+                    .with_timezone(&Utc)
+            }
+            None => DateTime::parse_from_rfc3339(scheduled_for)
+                .map_err(|_| {
+                    format!(
+                        "{} is not a valid RFC 3339 timestamp. \
+                        Provide a timezone to schedule in local time.",
+                        scheduled_for
+                    )
+                })?
+                .with_timezone(&Utc),
+        };
+
+        if scheduled_for <= now {
+            return Err(format!("{} is not in the future.", scheduled_for));
+        }
+        Ok(Self(scheduled_for))
+    }
+
+    pub fn inner(&self) -> DateTime<Utc> {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::IssueSchedule;
+    use chrono::{TimeZone, Utc};
+    use claims::{assert_err, assert_ok};
+
+    fn now() -> chrono::DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 10, 23, 17, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn local_time_is_converted_using_the_timezone() {
+        // Act
+        let result = IssueSchedule::parse("2026-10-26T08:00:00", Some("America/New_York"), now());
+
+        // Assert
+        let expected = Utc.with_ymd_and_hms(2026, 10, 26, 12, 0, 0).unwrap();
+        assert_eq!(assert_ok!(result).inner(), expected);
+    }
+
+    #[test]
+    fn rfc3339_timestamps_keep_their_offset() {
+        // Act
+        let result = IssueSchedule::parse("2026-10-26T08:00:00+01:00", None, now());
+
+        // Assert
+        let expected = Utc.with_ymd_and_hms(2026, 10, 26, 7, 0, 0).unwrap();
+        assert_eq!(assert_ok!(result).inner(), expected);
+    }
+
+    #[test]
+    fn timestamps_without_offset_or_timezone_are_rejected() {
+        // Act
+        let result = IssueSchedule::parse("2026-10-26T08:00:00", None, now());
+
+        // Assert
+        assert_err!(result);
+    }
+
+    #[test]
+    fn unknown_timezones_are_rejected() {
+        // Act
+        let result = IssueSchedule::parse("2026-10-26T08:00:00", Some("Mars/Olympus"), now());
+
+        // Assert
+        assert_err!(result);
+    }
+
+    #[test]
+    fn times_skipped_by_a_dst_transition_are_rejected() {
+        // Act
+        let result = IssueSchedule::parse("2027-03-28T01:30:00", Some("Europe/Lisbon"), now());
+
+        // Assert
+        assert_err!(result);
+    }
+
+    #[test]
+    fn past_times_are_rejected() {
+        // Act
+        let result = IssueSchedule::parse("2026-10-23T16:59:59Z", None, now());
+
+        // Assert
+        assert_err!(result);
+    }
+}
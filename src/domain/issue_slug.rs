@@ -0,0 +1,70 @@
+use uuid::Uuid;
+
+/// URL-safe identifier of a published issue in the public archive.
+///
+/// The slug is derived from the title and suffixed with the beginning of the
+/// issue id, so two issues sharing a title still get distinct pages.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IssueSlug(String);
+
+impl IssueSlug {
+    pub fn new(title: &str, issue_id: Uuid) -> IssueSlug {
+        let mut slug = String::new();
+        for c in title.chars().flat_map(char::to_lowercase) {
+            if c.is_ascii_alphanumeric() {
+                slug.push(c);
+            } else if !slug.is_empty() && !slug.ends_with('-') {
+                slug.push('-');
+            }
+        }
+        let mut slug: String = slug.trim_end_matches('-').chars().take(60).collect();
+        if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+        slug.push_str(&issue_id.simple().to_string()[..8]);
+        Self(slug)
+    }
+}
+
+impl AsRef<str> for IssueSlug {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::IssueSlug;
+    use uuid::Uuid;
+
+    fn issue_id() -> Uuid {
+        Uuid::parse_str("0a1b2c3d-0000-0000-0000-000000000000").unwrap()
+    }
+
+    #[test]
+    fn titles_are_lowercased_and_hyphenated() {
+        // Act
+        let slug = IssueSlug::new("  Weekly Digest: October, 2026! ", issue_id());
+
+        // Assert
+        assert_eq!(slug.as_ref(), "weekly-digest-october-2026-0a1b2c3d");
+    }
+
+    #[test]
+    fn titles_without_ascii_characters_fall_back_to_the_id() {
+        // Act
+        let slug = IssueSlug::new("Ёжик", issue_id());
+
+        // Assert
+        assert_eq!(slug.as_ref(), "0a1b2c3d");
+    }
+
+    #[test]
+    fn long_titles_are_truncated() {
+        // Act
+        let slug = IssueSlug::new(&"a".repeat(200), issue_id());
+
+        // Assert
+        assert_eq!(slug.as_ref().len(), 60 + 1 + 8);
+    }
+}
@@ -0,0 +1,63 @@
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IssueStatus {
+    Draft,
+    Scheduled,
+    Sending,
+    Sent,
+}
+
+impl IssueStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IssueStatus::Draft => "draft",
+            IssueStatus::Scheduled => "scheduled",
+            IssueStatus::Sending => "sending",
+            IssueStatus::Sent => "sent",
+        }
+    }
+}
+
+impl TryFrom<String> for IssueStatus {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        match s.as_str() {
+            "draft" => Ok(Self::Draft),
+            "scheduled" => Ok(Self::Scheduled),
+            "sending" => Ok(Self::Sending),
+            "sent" => Ok(Self::Sent),
+            other => Err(format!("{} is not a valid issue status.", other)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::IssueStatus;
+    use claims::{assert_err, assert_ok_eq};
+
+    #[test]
+    fn every_status_round_trips_through_its_string_form() {
+        for status in [
+            IssueStatus::Draft,
+            IssueStatus::Scheduled,
+            IssueStatus::Sending,
+            IssueStatus::Sent,
+        ] {
+            // Act
+            let result = IssueStatus::try_from(status.as_str().to_string());
+
+            // Assert
+            assert_ok_eq!(result, status);
+        }
+    }
+
+    #[test]
+    fn unknown_status_is_rejected() {
+        // Act
+        let result = IssueStatus::try_from("published".to_string());
+
+        // Assert
+        assert_err!(result);
+    }
+}
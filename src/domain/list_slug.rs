@@ -0,0 +1,66 @@
+/// URL-safe identifier of a mailing list, e.g. `weekly-digest`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ListSlug(String);
+
+impl ListSlug {
+    pub fn parse(s: String) -> Result<ListSlug, String> {
+        let is_too_long = s.chars().count() > 60;
+        let has_invalid_characters = !s
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+        let has_dangling_hyphen = s.starts_with('-') || s.ends_with('-');
+        if s.is_empty() || is_too_long || has_invalid_characters || has_dangling_hyphen {
+            Err(format!("{} is not a valid list slug.", s))
+        } else {
+            Ok(Self(s))
+        }
+    }
+}
+
+/// The list every subscriber and issue belongs to unless told otherwise.
+impl Default for ListSlug {
+    fn default() -> Self {
+        Self("default".to_string())
+    }
+}
+
+impl AsRef<str> for ListSlug {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ListSlug;
+    use claims::{assert_err, assert_ok};
+
+    #[test]
+    fn lowercase_words_joined_by_hyphens_are_valid() {
+        assert_ok!(ListSlug::parse("weekly-digest-2".to_string()));
+    }
+
+    #[test]
+    fn empty_slug_is_rejected() {
+        assert_err!(ListSlug::parse("".to_string()));
+    }
+
+    #[test]
+    fn a_61_character_slug_is_rejected() {
+        assert_err!(ListSlug::parse("a".repeat(61)));
+    }
+
+    #[test]
+    fn uppercase_and_punctuation_are_rejected() {
+        for slug in ["Weekly", "weekly_digest", "weekly digest", "wöchentlich"] {
+            assert_err!(ListSlug::parse(slug.to_string()));
+        }
+    }
+
+    #[test]
+    fn leading_or_trailing_hyphens_are_rejected() {
+        for slug in ["-weekly", "weekly-"] {
+            assert_err!(ListSlug::parse(slug.to_string()));
+        }
+    }
+}
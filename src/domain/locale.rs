@@ -0,0 +1,106 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+
+/// A BCP 47 language tag such as `pt` or `pt-BR`, in its canonical case:
+/// lowercase language, titlecase script and uppercase region.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Locale(String);
+
+impl Locale {
+    pub fn parse(s: &str) -> Result<Locale, String> {
+        let invalid = || format!("{} is not a valid language tag.", s);
+        let trimmed = s.trim();
+        if trimmed.is_empty() || trimmed.len() > 35 {
+            return Err(invalid());
+        }
+        let mut subtags = Vec::new();
+        for (i, subtag) in trimmed.split(['-', '_']).enumerate() {
+            let is_valid = if i == 0 {
+                (2..=3).contains(&subtag.len()) && subtag.chars().all(|c| c.is_ascii_alphabetic())
+            } else {
+                (1..=8).contains(&subtag.len()) && subtag.chars().all(|c| c.is_ascii_alphanumeric())
+            };
+            if !is_valid {
+                return Err(invalid());
+            }
+            let subtag = match subtag.len() {
+                _ if i == 0 => subtag.to_ascii_lowercase(),
+                2 => subtag.to_ascii_uppercase(),
+                4 if subtag.chars().all(|c| c.is_ascii_alphabetic()) => {
+                    let lower = subtag.to_ascii_lowercase();
+                    lower[..1].to_ascii_uppercase() + &lower[1..]
+                }
+                _ => subtag.to_ascii_lowercase(),
+            };
+            subtags.push(subtag);
+        }
+        Ok(Self(subtags.join("-")))
+    }
+
+    /// The primary language subtag, e.g. `pt` for `pt-BR`.
+    pub fn language(&self) -> &str {
+        self.0.split('-').next().unwrap_or_default()
+    }
+
+    /// The value for this locale, falling back to the one for its language.
+    pub fn lookup<'a, V>(&self, values: &'a HashMap<Locale, V>) -> Option<&'a V> {
+        values
+            .get(self)
+            .or_else(|| values.get(&Locale(self.language().to_owned())))
+    }
+}
+
+impl AsRef<str> for Locale {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Display for Locale {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Locale;
+    use claims::assert_err;
+    use std::collections::HashMap;
+
+    #[test]
+    fn tags_are_stored_in_their_canonical_case() {
+        for (tag, canonical) in [
+            ("PT", "pt"),
+            ("pt_br", "pt-BR"),
+            ("zh-hant-tw", "zh-Hant-TW"),
+            ("es-419", "es-419"),
+        ] {
+            assert_eq!(Locale::parse(tag).unwrap().as_ref(), canonical);
+        }
+    }
+
+    #[test]
+    fn malformed_tags_are_rejected() {
+        for tag in ["", "p", "english", "pt-", "pt BR", "*"] {
+            assert_err!(Locale::parse(tag));
+        }
+    }
+
+    #[test]
+    fn lookup_falls_back_to_the_language() {
+        // Arrange
+        let values = HashMap::from([
+            (Locale::parse("pt").unwrap(), "pt"),
+            (Locale::parse("en-GB").unwrap(), "en-GB"),
+        ]);
+
+        // Act
+        let regional = Locale::parse("pt-BR").unwrap().lookup(&values);
+        let unmatched = Locale::parse("en").unwrap().lookup(&values);
+
+        // Assert
+        assert_eq!(regional, Some(&"pt"));
+        assert_eq!(unmatched, None);
+    }
+}
@@ -1,9 +1,45 @@
+pub use ab_test::{pick_winner, AbTest, AbTestMetric, AbTestResult, AbTestVariant};
+pub use consent::{ConsentEvent, ConsentEvidence, FormSource};
+pub use delivery_status::DeliveryStatus;
+pub use digest_frequency::DigestFrequency;
 pub use email::Email;
+pub use email_policy::{EmailPolicy, PolicyViolation};
+pub use import_mode::ImportMode;
+pub use import_status::ImportStatus;
+pub use issue_schedule::IssueSchedule;
+pub use issue_slug::IssueSlug;
+pub use issue_status::IssueStatus;
+pub use list_slug::ListSlug;
+pub use locale::Locale;
 pub use new_subscriber::NewSubscriber;
+pub use newsletter_issue::{IssueVariant, NewsletterIssue};
+pub use sender_address::SenderAddress;
+pub use subscriber_attributes::{
+    AttributeDefinition, AttributeError, AttributeType, SubscriberAttributes,
+};
 pub use subscriber_email::SubscriberEmail;
 pub use subscriber_name::SubscriberName;
+pub use subscriber_tag::SubscriberTag;
+pub use subscription_status::SubscriptionStatus;
 
+mod ab_test;
+mod consent;
+mod delivery_status;
+mod digest_frequency;
 mod email;
+mod email_policy;
+mod import_mode;
+mod import_status;
+mod issue_schedule;
+mod issue_slug;
+mod issue_status;
+mod list_slug;
+mod locale;
 mod new_subscriber;
+mod newsletter_issue;
+mod sender_address;
+mod subscriber_attributes;
 mod subscriber_email;
 mod subscriber_name;
+mod subscriber_tag;
+mod subscription_status;
@@ -0,0 +1,206 @@
+use crate::domain::{AbTest, AbTestMetric, Locale};
+
+#[derive(Debug, Clone)]
+pub struct NewsletterIssue {
+    pub title: String,
+    pub html_content: String,
+    pub text_content: String,
+    /// Translations, sent to the subscribers who read their locale.
+    pub variants: Vec<IssueVariant>,
+    pub ab_test: Option<AbTest>,
+}
+
+#[derive(Debug, Clone)]
+pub struct IssueVariant {
+    pub locale: Locale,
+    pub title: String,
+    pub html_content: String,
+    pub text_content: String,
+}
+
+impl NewsletterIssue {
+    pub fn parse(
+        title: String,
+        html_content: String,
+        text_content: String,
+    ) -> Result<NewsletterIssue, String> {
+        check_content(&title, &html_content, &text_content)?;
+        Ok(Self {
+            title,
+            html_content,
+            text_content,
+            variants: vec![],
+            ab_test: None,
+        })
+    }
+
+    /// Adds a translation, which must be as complete as the issue itself.
+    pub fn with_variant(
+        mut self,
+        locale: &str,
+        title: String,
+        html_content: String,
+        text_content: String,
+    ) -> Result<NewsletterIssue, String> {
+        let locale = Locale::parse(locale)?;
+        if self.ab_test.is_some() {
+            return Err(TRANSLATED_AB_TEST.into());
+        }
+        if self.variants.iter().any(|v| v.locale == locale) {
+            return Err(format!("The issue has more than one {} variant.", locale));
+        }
+        check_content(&title, &html_content, &text_content)
+            .map_err(|e| format!("{} variant: {}", locale, e))?;
+        self.variants.push(IssueVariant {
+            locale,
+            title,
+            html_content,
+            text_content,
+        });
+        Ok(self)
+    }
+
+    /// Adds an A/B test. Variants without content of their own only try out
+    /// a different subject.
+    pub fn with_ab_test(
+        mut self,
+        sample_percent: i32,
+        wait_minutes: i32,
+        metric: AbTestMetric,
+        variants: Vec<(String, Option<(String, String)>)>,
+    ) -> Result<NewsletterIssue, String> {
+        if !self.variants.is_empty() {
+            return Err(TRANSLATED_AB_TEST.into());
+        }
+        let variants = variants
+            .into_iter()
+            .map(|(title, content)| {
+                let (html_content, text_content) = content
+                    .unwrap_or_else(|| (self.html_content.clone(), self.text_content.clone()));
+                check_content(&title, &html_content, &text_content)?;
+                Ok((title, html_content, text_content))
+            })
+            .collect::<Result<_, String>>()?;
+        self.ab_test = Some(AbTest::parse(
+            sample_percent,
+            wait_minutes,
+            metric,
+            variants,
+        )?);
+        Ok(self)
+    }
+}
+
+const TRANSLATED_AB_TEST: &str = "Translated issues cannot be A/B tested.";
+
+fn check_content(title: &str, html_content: &str, text_content: &str) -> Result<(), String> {
+    if title.trim().is_empty() {
+        return Err("The issue title cannot be empty.".into());
+    }
+    if html_content.trim().is_empty() || text_content.trim().is_empty() {
+        return Err("The issue content cannot be empty.".into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::NewsletterIssue;
+    use crate::domain::AbTestMetric;
+    use claims::{assert_err, assert_ok};
+
+    #[test]
+    fn whitespace_only_title_is_rejected() {
+        // Act
+        let result = NewsletterIssue::parse(" ".into(), "<p>Hi</p>".into(), "Hi".into());
+
+        // Assert
+        assert_err!(result);
+    }
+
+    #[test]
+    fn empty_content_is_rejected() {
+        // Act
+        let result = NewsletterIssue::parse("Title".into(), "".into(), "Hi".into());
+
+        // Assert
+        assert_err!(result);
+    }
+
+    #[test]
+    fn variants_must_be_complete_and_have_distinct_locales() {
+        // Arrange
+        let issue =
+            NewsletterIssue::parse("Title".into(), "<p>Hi</p>".into(), "Hi".into()).unwrap();
+        let variant = |issue: NewsletterIssue, locale: &str, title: &str| {
+            issue.with_variant(locale, title.into(), "<p>Olá</p>".into(), "Olá".into())
+        };
+
+        // Act
+        let empty_title = variant(issue.clone(), "pt", " ");
+        let malformed_locale = variant(issue.clone(), "portuguese", "Título");
+        let duplicate = variant(variant(issue, "pt", "Título").unwrap(), "PT", "Título");
+
+        // Assert
+        assert_err!(empty_title);
+        assert_err!(malformed_locale);
+        assert_err!(duplicate);
+    }
+
+    #[test]
+    fn ab_test_variants_default_to_the_issue_content() {
+        // Arrange
+        let issue =
+            NewsletterIssue::parse("Title".into(), "<p>Hi</p>".into(), "Hi".into()).unwrap();
+
+        // Act
+        let issue = issue
+            .with_ab_test(
+                20,
+                60,
+                AbTestMetric::OpenRate,
+                vec![
+                    ("Subject A".into(), None),
+                    (
+                        "Subject B".into(),
+                        Some(("<p>Hey</p>".into(), "Hey".into())),
+                    ),
+                ],
+            )
+            .unwrap();
+
+        // Assert
+        let variants = &issue.ab_test.unwrap().variants;
+        assert_eq!(variants[0].text_content, "Hi");
+        assert_eq!(variants[1].text_content, "Hey");
+    }
+
+    #[test]
+    fn translated_issues_cannot_be_ab_tested() {
+        // Arrange
+        let issue = NewsletterIssue::parse("Title".into(), "<p>Hi</p>".into(), "Hi".into())
+            .unwrap()
+            .with_variant("pt", "Título".into(), "<p>Olá</p>".into(), "Olá".into())
+            .unwrap();
+
+        // Act
+        let result = issue.with_ab_test(
+            20,
+            60,
+            AbTestMetric::OpenRate,
+            vec![("Subject A".into(), None), ("Subject B".into(), None)],
+        );
+
+        // Assert
+        assert_err!(result);
+    }
+
+    #[test]
+    fn a_valid_issue_is_parsed_successfully() {
+        // Act
+        let result = NewsletterIssue::parse("Title".into(), "<p>Hi</p>".into(), "Hi".into());
+
+        // Assert
+        assert_ok!(result);
+    }
+}
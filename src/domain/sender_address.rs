@@ -0,0 +1,112 @@
+use crate::domain::Email;
+
+/// Longest display name we accept, in characters.
+const MAX_DISPLAY_NAME_LENGTH: usize = 64;
+
+/// The address emails are sent from, with the name recipients see and the
+/// address their replies go to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SenderAddress {
+    email: Email,
+    display_name: Option<String>,
+    reply_to: Option<Email>,
+}
+
+impl SenderAddress {
+    /// A blank display name is the same as none.
+    pub fn parse(
+        email: String,
+        display_name: Option<String>,
+        reply_to: Option<String>,
+    ) -> Result<SenderAddress, String> {
+        let email = Email::parse(email)?;
+        let display_name = display_name
+            .map(|name| name.trim().to_owned())
+            .filter(|name| !name.is_empty());
+        if let Some(name) = &display_name {
+            if name.chars().any(char::is_control) {
+                return Err(format!("{:?} is not a valid sender name.", name));
+            }
+            if name.chars().count() > MAX_DISPLAY_NAME_LENGTH {
+                return Err(format!(
+                    "The sender name cannot be longer than {} characters.",
+                    MAX_DISPLAY_NAME_LENGTH
+                ));
+            }
+        }
+        let reply_to = reply_to.map(Email::parse).transpose()?;
+        Ok(Self {
+            email,
+            display_name,
+            reply_to,
+        })
+    }
+
+    pub fn email(&self) -> &Email {
+        &self.email
+    }
+
+    pub fn display_name(&self) -> Option<&str> {
+        self.display_name.as_deref()
+    }
+
+    pub fn reply_to(&self) -> Option<&Email> {
+        self.reply_to.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SenderAddress;
+    use claims::{assert_err, assert_none, assert_ok};
+
+    #[test]
+    fn a_sender_with_a_name_and_a_reply_to_address_is_parsed() {
+        // Act
+        let result = SenderAddress::parse(
+            "news@example.com".into(),
+            Some(" Newsletter ".into()),
+            Some("editor@example.com".into()),
+        );
+
+        // Assert
+        assert_ok!(&result);
+        let sender = result.unwrap();
+        assert_eq!(sender.email().as_ref(), "news@example.com");
+        assert_eq!(sender.display_name(), Some("Newsletter"));
+        assert_eq!(sender.reply_to().unwrap().as_ref(), "editor@example.com");
+    }
+
+    #[test]
+    fn a_blank_name_is_left_out() {
+        // Act
+        let sender = SenderAddress::parse("news@example.com".into(), Some("  ".into()), None);
+
+        // Assert
+        assert_none!(sender.unwrap().display_name());
+    }
+
+    #[test]
+    fn names_that_could_inject_headers_are_rejected() {
+        // Act
+        let result = SenderAddress::parse(
+            "news@example.com".into(),
+            Some("News\r\nBcc: someone@example.com".into()),
+            None,
+        );
+
+        // Assert
+        assert_err!(result);
+    }
+
+    #[test]
+    fn invalid_addresses_are_rejected() {
+        // Act
+        let sender = SenderAddress::parse("news".into(), None, None);
+        let reply_to = SenderAddress::parse("news@example.com".into(), None, Some("x".into()));
+
+        // Assert
+        assert_err!(sender);
+        assert_err!(reply_to);
+    }
+}
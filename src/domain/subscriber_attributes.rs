@@ -0,0 +1,303 @@
+use chrono::NaiveDate;
+use serde_json::{Map, Number, Value};
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+
+/// Form fields that cannot be used as attribute names.
+const RESERVED_NAMES: [&str; 5] = ["email", "name", "list", "source", "locale"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AttributeType {
+    String,
+    Number,
+    Bool,
+    Date,
+    Enum(Vec<String>),
+}
+
+impl AttributeType {
+    pub fn parse(attribute_type: &str, options: Vec<String>) -> Result<AttributeType, String> {
+        let attribute_type = match attribute_type {
+            "string" => AttributeType::String,
+            "number" => AttributeType::Number,
+            "bool" => AttributeType::Bool,
+            "date" => AttributeType::Date,
+            "enum" => {
+                let has_blank_option = options.iter().any(|o| o.trim().is_empty());
+                let has_duplicates = options
+                    .iter()
+                    .enumerate()
+                    .any(|(i, o)| options[..i].contains(o));
+                if options.is_empty() || has_blank_option || has_duplicates {
+                    return Err(
+                        "Enum attributes need a list of distinct, non-empty options.".into(),
+                    );
+                }
+                return Ok(AttributeType::Enum(options));
+            }
+            other => return Err(format!("{} is not a valid attribute type.", other)),
+        };
+        if !options.is_empty() {
+            return Err("Only enum attributes can have options.".into());
+        }
+        Ok(attribute_type)
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AttributeType::String => "string",
+            AttributeType::Number => "number",
+            AttributeType::Bool => "bool",
+            AttributeType::Date => "date",
+            AttributeType::Enum(_) => "enum",
+        }
+    }
+
+    pub fn options(&self) -> &[String] {
+        match self {
+            AttributeType::Enum(options) => options,
+            _ => &[],
+        }
+    }
+}
+
+/// A custom subscriber attribute defined by an admin, e.g. a `country` enum.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttributeDefinition {
+    pub name: String,
+    pub attribute_type: AttributeType,
+}
+
+impl AttributeDefinition {
+    pub fn parse(name: String, attribute_type: AttributeType) -> Result<Self, String> {
+        let is_too_long = name.chars().count() > 50;
+        let has_invalid_characters = !name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+        if name.is_empty()
+            || is_too_long
+            || has_invalid_characters
+            || RESERVED_NAMES.contains(&name.as_str())
+        {
+            return Err(format!("{} is not a valid attribute name.", name));
+        }
+        Ok(Self {
+            name,
+            attribute_type,
+        })
+    }
+
+    /// Converts a submitted form value into its JSON representation. Dates are
+    /// stored as `YYYY-MM-DD` strings so that they sort correctly.
+    pub fn parse_value(&self, raw: &str) -> Result<Value, AttributeError> {
+        let invalid = || AttributeError::InvalidValue {
+            name: self.name.clone(),
+            value: raw.to_owned(),
+        };
+        let raw = raw.trim();
+        match &self.attribute_type {
+            AttributeType::String => Ok(Value::String(raw.to_owned())),
+            AttributeType::Number => {
+                let number = match raw.parse::<i64>() {
+                    Ok(n) => Number::from(n),
+                    Err(_) => raw
+                        .parse::<f64>()
+                        .ok()
+                        .and_then(Number::from_f64)
+                        .ok_or_else(invalid)?,
+                };
+                Ok(Value::Number(number))
+            }
+            AttributeType::Bool => match raw {
+                "true" | "on" => Ok(Value::Bool(true)),
+                "false" | "off" => Ok(Value::Bool(false)),
+                _ => Err(invalid()),
+            },
+            AttributeType::Date => NaiveDate::parse_from_str(raw, "%Y-%m-%d")
+                .map(|date| Value::String(date.format("%Y-%m-%d").to_string()))
+                .map_err(|_| invalid()),
+            AttributeType::Enum(options) => {
+                if options.iter().any(|o| o == raw) {
+                    Ok(Value::String(raw.to_owned()))
+                } else {
+                    Err(invalid())
+                }
+            }
+        }
+    }
+}
+
+/// Validated custom attributes of a subscriber, stored as a JSON object.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SubscriberAttributes(Map<String, Value>);
+
+impl SubscriberAttributes {
+    /// Every field must match a definition. Blank fields are left out, as
+    /// forms submit them for inputs the subscriber skipped.
+    pub fn parse(
+        raw: HashMap<String, String>,
+        definitions: &[AttributeDefinition],
+    ) -> Result<SubscriberAttributes, AttributeError> {
+        let mut attributes = Map::new();
+        for (name, value) in raw {
+            let definition = definitions
+                .iter()
+                .find(|d| d.name == name)
+                .ok_or_else(|| AttributeError::Unknown(name.clone()))?;
+            if value.trim().is_empty() {
+                continue;
+            }
+            attributes.insert(name, definition.parse_value(&value)?);
+        }
+        Ok(Self(attributes))
+    }
+
+    pub fn as_json(&self) -> Value {
+        Value::Object(self.0.clone())
+    }
+}
+
+/// Why a custom attribute was rejected.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AttributeError {
+    Unknown(String),
+    InvalidValue { name: String, value: String },
+}
+
+impl Display for AttributeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AttributeError::Unknown(name) => {
+                write!(f, "{} is not a known subscriber attribute.", name)
+            }
+            AttributeError::InvalidValue { name, value } => {
+                write!(f, "{} is not a valid value for {}.", value, name)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AttributeDefinition, AttributeType, SubscriberAttributes};
+    use claims::{assert_err, assert_ok, assert_ok_eq};
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    fn definitions() -> Vec<AttributeDefinition> {
+        vec![
+            AttributeDefinition::parse("company".into(), AttributeType::String).unwrap(),
+            AttributeDefinition::parse("seats".into(), AttributeType::Number).unwrap(),
+            AttributeDefinition::parse("beta".into(), AttributeType::Bool).unwrap(),
+            AttributeDefinition::parse("birthday".into(), AttributeType::Date).unwrap(),
+            AttributeDefinition::parse(
+                "country".into(),
+                AttributeType::Enum(vec!["PT".into(), "FR".into()]),
+            )
+            .unwrap(),
+        ]
+    }
+
+    fn form(fields: &[(&str, &str)]) -> HashMap<String, String> {
+        fields
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn values_are_converted_to_their_json_type() {
+        // Arrange
+        let fields = form(&[
+            ("company", "ACME"),
+            ("seats", "12"),
+            ("beta", "on"),
+            ("birthday", "1990-02-03"),
+            ("country", "PT"),
+        ]);
+
+        // Act
+        let result = SubscriberAttributes::parse(fields, &definitions());
+
+        // Assert
+        assert_eq!(
+            result.unwrap().as_json(),
+            json!({
+                "company": "ACME",
+                "seats": 12,
+                "beta": true,
+                "birthday": "1990-02-03",
+                "country": "PT"
+            })
+        );
+    }
+
+    #[test]
+    fn blank_fields_are_left_out() {
+        // Act
+        let result = SubscriberAttributes::parse(form(&[("seats", " ")]), &definitions());
+
+        // Assert
+        assert_eq!(result.unwrap().as_json(), json!({}));
+    }
+
+    #[test]
+    fn invalid_values_are_rejected() {
+        for (name, value) in [
+            ("seats", "many"),
+            ("beta", "yes please"),
+            ("birthday", "03/02/1990"),
+            ("country", "ES"),
+        ] {
+            // Act
+            let result = SubscriberAttributes::parse(form(&[(name, value)]), &definitions());
+
+            // Assert
+            assert_err!(result, "{}={} was accepted", name, value);
+        }
+    }
+
+    #[test]
+    fn undefined_attributes_are_rejected() {
+        // Act
+        let result = SubscriberAttributes::parse(form(&[("shoe_size", "42")]), &definitions());
+
+        // Assert
+        assert_err!(result);
+    }
+
+    #[test]
+    fn attribute_names_must_be_lowercase_identifiers_that_are_not_reserved() {
+        assert_ok!(AttributeDefinition::parse(
+            "plan_2".into(),
+            AttributeType::String
+        ));
+        for name in [
+            "",
+            "Plan",
+            "plan-type",
+            "email",
+            "name",
+            "list",
+            "source",
+            "locale",
+        ] {
+            assert_err!(AttributeDefinition::parse(
+                name.into(),
+                AttributeType::String
+            ));
+        }
+    }
+
+    #[test]
+    fn only_enums_take_options() {
+        assert_ok_eq!(
+            AttributeType::parse("enum", vec!["a".into(), "b".into()]),
+            AttributeType::Enum(vec!["a".into(), "b".into()])
+        );
+        assert_err!(AttributeType::parse("enum", vec![]));
+        assert_err!(AttributeType::parse("enum", vec!["a".into(), "a".into()]));
+        assert_err!(AttributeType::parse("string", vec!["a".into()]));
+        assert_err!(AttributeType::parse("text", vec![]));
+    }
+}
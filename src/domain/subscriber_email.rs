@@ -1,3 +1,84 @@
-use crate::domain::email::Email;
+use crate::domain::Email;
+use std::fmt::{Display, Formatter};
 
-pub type SubscriberEmail = Email;
+/// Providers whose addresses ignore dots and `+tags` in the local part.
+const GMAIL_DOMAINS: [&str; 2] = ["gmail.com", "googlemail.com"];
+
+/// The address of a subscriber, as opposed to the one we send from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SubscriberEmail(Email);
+
+impl SubscriberEmail {
+    pub fn parse(s: String) -> Result<SubscriberEmail, String> {
+        Email::parse(s).map(Self)
+    }
+
+    /// The key subscribers are told apart by: addresses that only differ in
+    /// case are the same subscriber. With `fold_aliases`, so are Gmail
+    /// addresses that only differ in dots and `+tags`.
+    pub fn normalized(&self, fold_aliases: bool) -> String {
+        let local_part = self.local_part().to_lowercase();
+        let domain = self.domain();
+        if fold_aliases && GMAIL_DOMAINS.contains(&domain) {
+            let mailbox = local_part.split('+').next().unwrap_or_default();
+            return format!("{}@gmail.com", mailbox.replace('.', ""));
+        }
+        format!("{}@{}", local_part, domain)
+    }
+
+    pub fn local_part(&self) -> &str {
+        self.0.local_part()
+    }
+
+    pub fn domain(&self) -> &str {
+        self.0.domain()
+    }
+}
+
+impl AsRef<str> for SubscriberEmail {
+    fn as_ref(&self) -> &str {
+        self.0.as_ref()
+    }
+}
+
+impl Display for SubscriberEmail {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SubscriberEmail;
+
+    #[test]
+    fn addresses_differing_in_case_have_the_same_normalized_form() {
+        // Arrange
+        let a = SubscriberEmail::parse("Ursula@Example.com".to_string()).unwrap();
+        let b = SubscriberEmail::parse("ursula@example.COM".to_string()).unwrap();
+
+        // Act
+        let keys = (a.normalized(false), b.normalized(false));
+
+        // Assert
+        assert_eq!(keys.0, "ursula@example.com");
+        assert_eq!(keys.0, keys.1);
+    }
+
+    #[test]
+    fn gmail_aliases_are_folded_only_when_asked() {
+        // Arrange
+        let email = SubscriberEmail::parse("U.rsula+news@googlemail.com".to_string()).unwrap();
+        let other_provider =
+            SubscriberEmail::parse("u.rsula+news@example.com".to_string()).unwrap();
+
+        // Act
+        let folded = email.normalized(true);
+        let unfolded = email.normalized(false);
+
+        // Assert
+        assert_eq!(folded, "ursula@gmail.com");
+        assert_eq!(unfolded, "u.rsula+news@googlemail.com");
+        assert_eq!(other_provider.normalized(true), "u.rsula+news@example.com");
+    }
+}
@@ -0,0 +1,51 @@
+/// Label attached to subscribers to target them with segments, e.g. `beta`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SubscriberTag(String);
+
+impl SubscriberTag {
+    pub fn parse(s: String) -> Result<SubscriberTag, String> {
+        let is_too_long = s.chars().count() > 50;
+        let has_invalid_characters = !s
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_');
+        if s.is_empty() || is_too_long || has_invalid_characters {
+            Err(format!("{} is not a valid tag.", s))
+        } else {
+            Ok(Self(s))
+        }
+    }
+}
+
+impl AsRef<str> for SubscriberTag {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SubscriberTag;
+    use claims::{assert_err, assert_ok};
+
+    #[test]
+    fn lowercase_tags_with_separators_are_valid() {
+        assert_ok!(SubscriberTag::parse("early_adopter-2".to_string()));
+    }
+
+    #[test]
+    fn empty_tag_is_rejected() {
+        assert_err!(SubscriberTag::parse("".to_string()));
+    }
+
+    #[test]
+    fn a_51_character_tag_is_rejected() {
+        assert_err!(SubscriberTag::parse("a".repeat(51)));
+    }
+
+    #[test]
+    fn uppercase_whitespace_and_quotes_are_rejected() {
+        for tag in ["Beta", "beta testers", "beta\"", "bêta"] {
+            assert_err!(SubscriberTag::parse(tag.to_string()));
+        }
+    }
+}
@@ -1,11 +1,13 @@
 use crate::configuration::AwsSettings;
-use crate::domain::Email;
+use crate::domain::SenderAddress;
 use crate::email::email_client::{EmailClient, EmailClientProvider, SendEmailRequest};
+use crate::email::mime::{mailbox, raw_message};
 use anyhow::Context;
 use aws_config::timeout::TimeoutConfig;
 use aws_config::{BehaviorVersion, Region};
 use aws_sdk_sesv2::config::Credentials;
-use aws_sdk_sesv2::types::{Body, Content, Destination, EmailContent, Message};
+use aws_sdk_sesv2::primitives::Blob;
+use aws_sdk_sesv2::types::{Body, Content, Destination, EmailContent, Message, RawMessage};
 use aws_sdk_sesv2::Client;
 use secrecy::{ExposeSecret, Secret};
 use std::time::Duration;
@@ -16,28 +18,41 @@ pub type SesClient = Client;
 impl EmailClient for SesClient {
     async fn send_email(
         &self,
-        sender_email: &Email,
+        sender: &SenderAddress,
         send_email_request: SendEmailRequest<'_>,
     ) -> Result<(), anyhow::Error> {
         let destination = Destination::builder()
             .to_addresses(send_email_request.to.as_ref())
             .build();
 
-        let message = Message::builder()
-            .subject(build_content(send_email_request.subject))
-            .body(
-                Body::builder()
-                    .text(build_content(send_email_request.text_content))
-                    .html(build_content(send_email_request.html_content))
-                    .build(),
-            )
-            .build();
-
-        let email_content = EmailContent::builder().simple(message).build();
+        // SES only accepts attachments as part of a raw MIME message.
+        let email_content = if send_email_request.attachments.is_empty() {
+            let message = Message::builder()
+                .subject(build_content(send_email_request.subject))
+                .body(
+                    Body::builder()
+                        .text(build_content(send_email_request.text_content))
+                        .html(build_content(send_email_request.html_content))
+                        .build(),
+                )
+                .build();
+            EmailContent::builder().simple(message).build()
+        } else {
+            let message = RawMessage::builder()
+                .data(Blob::new(raw_message(sender, &send_email_request)))
+                .build()
+                .context("Failed to build the raw email.")?;
+            EmailContent::builder().raw(message).build()
+        };
 
         let result = self
             .send_email()
-            .from_email_address(sender_email.as_ref())
+            .from_email_address(mailbox(sender))
+            .set_reply_to_addresses(
+                sender
+                    .reply_to()
+                    .map(|reply_to| vec![reply_to.as_ref().to_owned()]),
+            )
             .destination(destination)
             .content(email_content)
             .send()
@@ -121,7 +136,7 @@ mod tests {
     #[tokio::test]
     async fn sends_email_with_correct_arguments() {
         // Arrange
-        let sender_email = Email::parse(SafeEmail().fake::<String>()).unwrap();
+        let sender = SenderAddress::parse(SafeEmail().fake::<String>(), None, None).unwrap();
         let recipient_email = SubscriberEmail::parse(SafeEmail().fake::<String>()).unwrap();
         let subject = Paragraph(1..10).fake::<String>();
         let html_content = format!("<p>{}</p>", Paragraph(1..10).fake::<String>());
@@ -132,6 +147,7 @@ mod tests {
             subject: &subject,
             html_content: &html_content,
             text_content: &text_content,
+            attachments: &[],
         };
 
         let recipient_email_string = recipient_email.as_ref().to_string();
@@ -149,7 +165,7 @@ mod tests {
             &mock_client!(aws_sdk_sesv2, RuleMode::Sequential, &[&mock_send_email]);
 
         // Act
-        let result = aws_email_client.send_email(&sender_email, request).await;
+        let result = aws_email_client.send_email(&sender, request).await;
 
         // Assert
         assert_ok!(result);
@@ -158,7 +174,7 @@ mod tests {
     #[tokio::test]
     async fn send_email_fails_if_client_returns_err() {
         // Arrange
-        let sender_email = Email::parse(SafeEmail().fake::<String>()).unwrap();
+        let sender = SenderAddress::parse(SafeEmail().fake::<String>(), None, None).unwrap();
         let recipient_email = SubscriberEmail::parse(SafeEmail().fake::<String>()).unwrap();
         let subject = Paragraph(1..10).fake::<String>();
         let html_content = format!("<p>{}</p>", Paragraph(1..10).fake::<String>());
@@ -169,6 +185,7 @@ mod tests {
             subject: &subject,
             html_content: &html_content,
             text_content: &text_content,
+            attachments: &[],
         };
 
         let recipient_email_string = recipient_email.as_ref().to_string();
@@ -188,7 +205,7 @@ mod tests {
             &mock_client!(aws_sdk_sesv2, RuleMode::Sequential, &[&mock_send_email]);
 
         // Act
-        let result = aws_email_client.send_email(&sender_email, request).await;
+        let result = aws_email_client.send_email(&sender, request).await;
 
         // Assert
         assert_err!(result);
@@ -1,6 +1,4 @@
-use crate::domain::{Email, SubscriberEmail};
-use crate::routes::error_chain_fmt;
-use std::fmt::{Debug, Formatter};
+use crate::domain::{SenderAddress, SubscriberEmail};
 
 #[derive(Debug, PartialEq, Clone)]
 pub struct SendEmailRequest<'a> {
@@ -8,28 +6,34 @@ pub struct SendEmailRequest<'a> {
     pub subject: &'a str,
     pub html_content: &'a str,
     pub text_content: &'a str,
+    pub attachments: &'a [Attachment],
+}
+
+/// A file sent along with an email.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Attachment {
+    pub filename: String,
+    pub content_type: String,
+    pub content: Vec<u8>,
 }
 
 pub struct EmailService {
-    sender_email: Email,
+    sender: SenderAddress,
 }
 
 impl EmailService {
-    pub fn new(sender: Email) -> Self {
-        EmailService {
-            sender_email: sender,
-        }
+    pub fn new(sender: SenderAddress) -> Self {
+        EmailService { sender }
     }
 
     pub async fn send_email(
         &self,
         email_client: &dyn EmailClient,
         send_email_request: SendEmailRequest<'_>,
-    ) -> Result<(), EmailClientError> {
+    ) -> Result<(), anyhow::Error> {
         email_client
-            .send_email(&self.sender_email, send_email_request)
+            .send_email(&self.sender, send_email_request)
             .await
-            .map_err(EmailClientError::SendEmailError)
     }
 }
 
@@ -37,7 +41,7 @@ impl EmailService {
 pub trait EmailClient: Sync + Send {
     async fn send_email(
         &self,
-        sender_email: &Email,
+        sender: &SenderAddress,
         request: SendEmailRequest<'_>,
     ) -> Result<(), anyhow::Error>;
 }
@@ -47,18 +51,6 @@ pub trait EmailClientProvider<T: EmailClient> {
     async fn email_client(&self) -> T;
 }
 
-#[derive(thiserror::Error)]
-pub enum EmailClientError {
-    #[error(transparent)]
-    SendEmailError(#[from] anyhow::Error),
-}
-
-impl Debug for EmailClientError {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        error_chain_fmt(self, f)
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -68,7 +60,7 @@ mod tests {
     use fake::Fake;
 
     struct MockEmailClient<'a> {
-        expected_sender_email: Email,
+        expected_sender: SenderAddress,
         expected_send_email_request: SendEmailRequest<'a>,
     }
 
@@ -76,10 +68,10 @@ mod tests {
     impl<'a> EmailClient for MockEmailClient<'a> {
         async fn send_email(
             &self,
-            sender_email: &Email,
+            sender: &SenderAddress,
             send_email_request: SendEmailRequest<'_>,
         ) -> Result<(), anyhow::Error> {
-            assert_eq!(*sender_email, self.expected_sender_email);
+            assert_eq!(*sender, self.expected_sender);
             assert_eq!(send_email_request, self.expected_send_email_request);
             Ok(())
         }
@@ -88,7 +80,12 @@ mod tests {
     #[tokio::test]
     async fn calls_email_client_with_correct_arguments() {
         // Arrange
-        let sender_email = Email::parse(SafeEmail().fake::<String>()).unwrap();
+        let sender = SenderAddress::parse(
+            SafeEmail().fake::<String>(),
+            Some("Newsletter".into()),
+            None,
+        )
+        .unwrap();
         let recipient_email = SubscriberEmail::parse(SafeEmail().fake::<String>()).unwrap();
         let subject = Paragraph(1..10).fake::<String>();
         let html_content = format!("<p>{}</p>", Paragraph(1..10).fake::<String>());
@@ -99,14 +96,15 @@ mod tests {
             subject: &subject,
             html_content: &html_content,
             text_content: &text_content,
+            attachments: &[],
         };
 
         let mock_email_client = MockEmailClient {
-            expected_sender_email: sender_email.clone(),
+            expected_sender: sender.clone(),
             expected_send_email_request: send_email_request.clone(),
         };
 
-        let email_service = EmailService::new(sender_email.clone());
+        let email_service = EmailService::new(sender);
 
         // Act
         let result = email_service
@@ -0,0 +1,88 @@
+/// Replaces the trackable links in the `href` attributes of `html` with the
+/// URL `tracked_url` returns for them, if any. Only absolute HTTP(S) links
+/// are trackable: links built from merge tags, such as
+/// `{{unsubscribe_url}}`, are personal and left as they are.
+pub fn rewrite_links(html: &str, mut tracked_url: impl FnMut(&str) -> Option<String>) -> String {
+    let mut rewritten = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = rest.find("href=") {
+        let value_start = start + "href=".len();
+        let Some(quote) = rest[value_start..]
+            .chars()
+            .next()
+            .filter(|c| *c == '"' || *c == '\'')
+        else {
+            rewritten.push_str(&rest[..value_start]);
+            rest = &rest[value_start..];
+            continue;
+        };
+        let Some(length) = rest[value_start + 1..].find(quote) else {
+            break;
+        };
+        let href = &rest[value_start + 1..value_start + 1 + length];
+        rewritten.push_str(&rest[..value_start + 1]);
+        match trackable(href).and_then(|url| tracked_url(&url)) {
+            Some(url) => rewritten.push_str(&htmlescape::encode_minimal(&url)),
+            None => rewritten.push_str(href),
+        }
+        rest = &rest[value_start + 1 + length..];
+    }
+    rewritten.push_str(rest);
+    rewritten
+}
+
+/// The distinct trackable links of `html`, in order of appearance.
+pub fn trackable_links(html: &str) -> Vec<String> {
+    let mut links: Vec<String> = vec![];
+    rewrite_links(html, |url| {
+        if !links.iter().any(|link| link == url) {
+            links.push(url.to_owned());
+        }
+        None
+    });
+    links
+}
+
+fn trackable(href: &str) -> Option<String> {
+    let url = htmlescape::decode_html(href).ok()?;
+    let is_absolute = url.starts_with("https://") || url.starts_with("http://");
+    (is_absolute && !url.contains("{{")).then_some(url)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{rewrite_links, trackable_links};
+
+    #[test]
+    fn only_absolute_links_without_merge_tags_are_tracked() {
+        // Arrange
+        let html = r#"<a href="https://example.com/?a=1&amp;b=2">Read</a>
+<a href='http://example.com'>Home</a> <a href="{{unsubscribe_url}}">Leave</a>
+<a href="mailto:editor@example.com">Write</a> <a href="https://example.com/?a=1&amp;b=2">Again</a>"#;
+
+        // Act
+        let links = trackable_links(html);
+
+        // Assert
+        assert_eq!(
+            links,
+            ["https://example.com/?a=1&b=2", "http://example.com"]
+        );
+    }
+
+    #[test]
+    fn tracked_links_are_replaced_in_place() {
+        // Arrange
+        let html =
+            r#"<p><a href="https://example.com">Read</a> <a href="{{archive_url}}">View</a></p>"#;
+
+        // Act
+        let rewritten = rewrite_links(html, |url| Some(format!("https://t.test/?u={}", url)));
+
+        // Assert
+        assert_eq!(
+            rewritten,
+            r#"<p><a href="https://t.test/?u=https://example.com">Read</a> <a href="{{archive_url}}">View</a></p>"#
+        );
+    }
+}
@@ -0,0 +1,136 @@
+use std::collections::HashMap;
+
+/// Values substituted for the `{{ tag }}` placeholders found in issue content.
+///
+/// Tags without a value are replaced with an empty string, so rendering with
+/// an empty set strips every personal detail from the content.
+#[derive(Default)]
+pub struct MergeTags {
+    values: HashMap<String, String>,
+    html_values: HashMap<String, String>,
+}
+
+impl MergeTags {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with(mut self, tag: &str, value: impl Into<String>) -> Self {
+        self.values.insert(tag.to_owned(), value.into());
+        self
+    }
+
+    /// A value that is already HTML, such as rendered content. It is only
+    /// used by `render_html`, which inserts it as is.
+    pub fn with_html(mut self, tag: &str, value: impl Into<String>) -> Self {
+        self.html_values.insert(tag.to_owned(), value.into());
+        self
+    }
+
+    pub fn render_text(&self, template: &str) -> String {
+        self.render(template, |tag| self.values.get(tag).cloned())
+    }
+
+    pub fn render_html(&self, template: &str) -> String {
+        self.render(template, |tag| {
+            self.html_values
+                .get(tag)
+                .cloned()
+                .or_else(|| self.values.get(tag).map(|v| htmlescape::encode_minimal(v)))
+        })
+    }
+
+    fn render(&self, template: &str, value: impl Fn(&str) -> Option<String>) -> String {
+        let mut rendered = String::with_capacity(template.len());
+        let mut rest = template;
+        while let Some(start) = rest.find("{{") {
+            let Some(length) = rest[start + 2..].find("}}") else {
+                break;
+            };
+            let tag = rest[start + 2..start + 2 + length].trim();
+            rendered.push_str(&rest[..start]);
+            if is_tag_name(tag) {
+                if let Some(value) = value(tag) {
+                    rendered.push_str(&value);
+                }
+            } else {
+                rendered.push_str(&rest[start..start + 2 + length + 2]);
+            }
+            rest = &rest[start + 2 + length + 2..];
+        }
+        rendered.push_str(rest);
+        rendered
+    }
+}
+
+pub fn strip_merge_tags(template: &str) -> String {
+    MergeTags::new().render_text(template)
+}
+
+fn is_tag_name(tag: &str) -> bool {
+    !tag.is_empty()
+        && tag
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{strip_merge_tags, MergeTags};
+
+    #[test]
+    fn known_tags_are_replaced() {
+        // Arrange
+        let tags = MergeTags::new().with("name", "Ursula");
+
+        // Act
+        let rendered = tags.render_text("Hi {{name}}, hi {{ name }}!");
+
+        // Assert
+        assert_eq!(rendered, "Hi Ursula, hi Ursula!");
+    }
+
+    #[test]
+    fn unknown_tags_are_removed() {
+        // Act
+        let rendered = MergeTags::new().render_text("Hi {{name}}!");
+
+        // Assert
+        assert_eq!(rendered, "Hi !");
+    }
+
+    #[test]
+    fn html_values_are_escaped() {
+        // Arrange
+        let tags = MergeTags::new().with("name", "<script>");
+
+        // Act
+        let rendered = tags.render_html("<p>Hi {{name}}</p>");
+
+        // Assert
+        assert_eq!(rendered, "<p>Hi &lt;script&gt;</p>");
+    }
+
+    #[test]
+    fn html_values_are_inserted_as_is_and_only_into_html() {
+        // Arrange
+        let tags = MergeTags::new().with_html("content", "<p>Issue</p>");
+
+        // Act
+        let html = tags.render_html("<div>{{content}}</div>");
+        let text = tags.render_text("{{content}}");
+
+        // Assert
+        assert_eq!(html, "<div><p>Issue</p></div>");
+        assert_eq!(text, "");
+    }
+
+    #[test]
+    fn text_that_is_not_a_tag_is_left_untouched() {
+        // Act
+        let rendered = strip_merge_tags("{{ not a tag }} and {{unterminated");
+
+        // Assert
+        assert_eq!(rendered, "{{ not a tag }} and {{unterminated");
+    }
+}
@@ -0,0 +1,228 @@
+use crate::domain::SenderAddress;
+use crate::email::email_client::SendEmailRequest;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use uuid::Uuid;
+
+/// Longest encoded line allowed by RFC 2045.
+const LINE_LENGTH: usize = 76;
+
+/// Builds the MIME message for an email that carries attachments: the text
+/// and HTML bodies as alternatives, followed by one part per attachment.
+pub fn raw_message(sender: &SenderAddress, request: &SendEmailRequest<'_>) -> Vec<u8> {
+    let mixed_boundary = format!("mixed-{}", Uuid::new_v4().simple());
+    let alternative_boundary = format!("alternative-{}", Uuid::new_v4().simple());
+
+    let mut message = String::new();
+    message.push_str(&format!("From: {}\r\n", mailbox(sender)));
+    if let Some(reply_to) = sender.reply_to() {
+        message.push_str(&format!("Reply-To: {}\r\n", reply_to.as_ref()));
+    }
+    message.push_str(&format!("To: {}\r\n", request.to.as_ref()));
+    message.push_str(&format!("Subject: {}\r\n", encode_header(request.subject)));
+    message.push_str("MIME-Version: 1.0\r\n");
+    message.push_str(&format!(
+        "Content-Type: multipart/mixed;\r\n boundary=\"{}\"\r\n\r\n",
+        mixed_boundary
+    ));
+
+    message.push_str(&format!("--{}\r\n", mixed_boundary));
+    message.push_str(&format!(
+        "Content-Type: multipart/alternative;\r\n boundary=\"{}\"\r\n\r\n",
+        alternative_boundary
+    ));
+    for (content_type, content) in [
+        ("text/plain", request.text_content),
+        ("text/html", request.html_content),
+    ] {
+        message.push_str(&format!("--{}\r\n", alternative_boundary));
+        message.push_str(&format!(
+            "Content-Type: {}; charset=utf-8\r\n",
+            content_type
+        ));
+        message.push_str("Content-Transfer-Encoding: base64\r\n\r\n");
+        message.push_str(&encode_body(content.as_bytes()));
+    }
+    message.push_str(&format!("--{}--\r\n", alternative_boundary));
+
+    for attachment in request.attachments {
+        message.push_str(&format!("--{}\r\n", mixed_boundary));
+        message.push_str(&format!(
+            "Content-Type: {}; name=\"{}\"\r\n",
+            attachment.content_type, attachment.filename
+        ));
+        message.push_str(&format!(
+            "Content-Disposition: attachment; filename=\"{}\"\r\n",
+            attachment.filename
+        ));
+        message.push_str("Content-Transfer-Encoding: base64\r\n\r\n");
+        message.push_str(&encode_body(&attachment.content));
+    }
+    message.push_str(&format!("--{}--\r\n", mixed_boundary));
+    message.into_bytes()
+}
+
+/// The sender as a `From` mailbox, such as `"Newsletter" <news@example.com>`.
+/// Names that are not plain ASCII are sent as RFC 2047 encoded words.
+pub fn mailbox(sender: &SenderAddress) -> String {
+    let email = sender.email().as_ref();
+    match sender.display_name() {
+        None => email.to_owned(),
+        Some(name) if name.is_ascii() => format!(
+            "\"{}\" <{}>",
+            name.replace('\\', "\\\\").replace('"', "\\\""),
+            email
+        ),
+        Some(name) => format!("{} <{}>", encoded_words(name).join(" "), email),
+    }
+}
+
+/// Encodes a header value as RFC 2047 encoded words, short enough to keep
+/// every header line within the length limit.
+fn encode_header(value: &str) -> String {
+    encoded_words(value).join("\r\n ")
+}
+
+fn encoded_words(value: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    for c in value.chars() {
+        if word.len() + c.len_utf8() > 45 {
+            words.push(std::mem::take(&mut word));
+        }
+        word.push(c);
+    }
+    words.push(word);
+    words
+        .iter()
+        .map(|w| format!("=?utf-8?B?{}?=", STANDARD.encode(w)))
+        .collect()
+}
+
+fn encode_body(content: &[u8]) -> String {
+    let encoded = STANDARD.encode(content);
+    let mut body = String::with_capacity(encoded.len() + encoded.len() / LINE_LENGTH * 2 + 2);
+    for line in encoded.as_bytes().chunks(LINE_LENGTH) {
+        // Base64 output is ASCII.
+        body.push_str(std::str::from_utf8(line).unwrap());
+        body.push_str("\r\n");
+    }
+    body
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{encode_header, mailbox, raw_message};
+    use crate::domain::{SenderAddress, SubscriberEmail};
+    use crate::email::email_client::{Attachment, SendEmailRequest};
+    use base64::engine::general_purpose::STANDARD;
+    use base64::Engine;
+
+    #[test]
+    fn attachments_are_sent_as_base64_parts_after_the_bodies() {
+        // Arrange
+        let sender = SenderAddress::parse("newsletter@example.com".into(), None, None).unwrap();
+        let recipient = SubscriberEmail::parse("ursula@example.com".into()).unwrap();
+        let attachments = [Attachment {
+            filename: "guide.pdf".into(),
+            content_type: "application/pdf".into(),
+            content: b"%PDF-1.4".to_vec(),
+        }];
+        let request = SendEmailRequest {
+            to: &recipient,
+            subject: "Welcome",
+            html_content: "<p>Hello</p>",
+            text_content: "Hello",
+            attachments: &attachments,
+        };
+
+        // Act
+        let message = String::from_utf8(raw_message(&sender, &request)).unwrap();
+
+        // Assert
+        assert!(message.starts_with("From: newsletter@example.com\r\nTo: ursula@example.com\r\n"));
+        let text = message.find(&STANDARD.encode("Hello")).unwrap();
+        let html = message.find(&STANDARD.encode("<p>Hello</p>")).unwrap();
+        let attachment = message
+            .find("Content-Disposition: attachment; filename=\"guide.pdf\"")
+            .unwrap();
+        assert!(text < html && html < attachment);
+        assert!(message.contains(&STANDARD.encode("%PDF-1.4")));
+        assert!(message.lines().all(|line| line.len() <= 78));
+    }
+
+    #[test]
+    fn long_non_ascii_subjects_are_split_into_encoded_words() {
+        // Arrange
+        let subject = "Bienvenue à notre lettre d'information, où l'on parle de café".repeat(2);
+
+        // Act
+        let header = encode_header(&subject);
+
+        // Assert
+        assert!(header.split("\r\n ").count() > 1);
+        assert!(header.split("\r\n ").all(|word| word.len() <= 75));
+        let decoded: String = header
+            .split("\r\n ")
+            .map(|word| {
+                let encoded = word.trim_start_matches("=?utf-8?B?").trim_end_matches("?=");
+                String::from_utf8(STANDARD.decode(encoded).unwrap()).unwrap()
+            })
+            .collect();
+        assert_eq!(decoded, subject);
+    }
+
+    #[test]
+    fn the_sender_name_is_quoted_or_encoded() {
+        // Arrange
+        let plain = SenderAddress::parse(
+            "news@example.com".into(),
+            Some(r#"The "Weekly" News"#.into()),
+            None,
+        )
+        .unwrap();
+        let accented =
+            SenderAddress::parse("news@example.com".into(), Some("Notícias".into()), None).unwrap();
+
+        // Act
+        let plain = mailbox(&plain);
+        let accented = mailbox(&accented);
+
+        // Assert
+        assert_eq!(plain, r#""The \"Weekly\" News" <news@example.com>"#);
+        assert_eq!(
+            accented,
+            format!(
+                "=?utf-8?B?{}?= <news@example.com>",
+                STANDARD.encode("Notícias")
+            )
+        );
+    }
+
+    #[test]
+    fn the_reply_to_address_is_set_in_the_headers() {
+        // Arrange
+        let sender = SenderAddress::parse(
+            "news@example.com".into(),
+            Some("Newsletter".into()),
+            Some("editor@example.com".into()),
+        )
+        .unwrap();
+        let recipient = SubscriberEmail::parse("ursula@example.com".into()).unwrap();
+        let request = SendEmailRequest {
+            to: &recipient,
+            subject: "Welcome",
+            html_content: "<p>Hello</p>",
+            text_content: "Hello",
+            attachments: &[],
+        };
+
+        // Act
+        let message = String::from_utf8(raw_message(&sender, &request)).unwrap();
+
+        // Assert
+        assert!(message.starts_with(
+            "From: \"Newsletter\" <news@example.com>\r\nReply-To: editor@example.com\r\n"
+        ));
+    }
+}
@@ -1,2 +1,6 @@
 pub mod aws_email_client;
 pub mod email_client;
+pub mod link_tracking;
+pub mod merge_tags;
+pub mod mime;
+pub mod welcome_email;
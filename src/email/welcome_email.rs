@@ -0,0 +1,198 @@
+use crate::configuration::{WelcomeEmailSettings, WelcomeEmailTranslation};
+use crate::domain::Locale;
+use crate::email::email_client::Attachment;
+use anyhow::Context;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// The welcome email, with its attachment read into memory once so that
+/// sending it never touches the filesystem.
+#[derive(Debug)]
+pub struct WelcomeEmail {
+    pub subject: String,
+    pub html_template: String,
+    pub text_template: String,
+    pub attachments: Vec<Attachment>,
+    pub translations: HashMap<Locale, WelcomeEmailTranslation>,
+}
+
+/// The subject and templates of the welcome email in one language.
+pub struct WelcomeEmailTemplates<'a> {
+    pub subject: &'a str,
+    pub html_template: &'a str,
+    pub text_template: &'a str,
+}
+
+impl WelcomeEmail {
+    /// The email in the subscriber's locale, falling back to its language and
+    /// then to the untranslated email.
+    pub fn templates(&self, locale: Option<&Locale>) -> WelcomeEmailTemplates<'_> {
+        match locale.and_then(|locale| locale.lookup(&self.translations)) {
+            Some(translation) => WelcomeEmailTemplates {
+                subject: &translation.subject,
+                html_template: &translation.html_template,
+                text_template: &translation.text_template,
+            },
+            None => WelcomeEmailTemplates {
+                subject: &self.subject,
+                html_template: &self.html_template,
+                text_template: &self.text_template,
+            },
+        }
+    }
+
+    pub fn load(settings: &WelcomeEmailSettings) -> Result<Self, anyhow::Error> {
+        let attachments = match &settings.attachment {
+            None => vec![],
+            Some(attachment) => {
+                let path = Path::new(&attachment.path);
+                let filename = path
+                    .file_name()
+                    .and_then(|f| f.to_str())
+                    .filter(|f| {
+                        f.chars()
+                            .all(|c| (c.is_ascii_graphic() || c == ' ') && c != '"' && c != '\\')
+                    })
+                    .with_context(|| {
+                        format!(
+                            "{} does not end with a plain ASCII file name.",
+                            attachment.path
+                        )
+                    })?
+                    .to_owned();
+                let content_type = attachment.content_type.trim().to_owned();
+                if !is_media_type(&content_type) {
+                    anyhow::bail!("{} is not a valid content type.", content_type);
+                }
+                let content = std::fs::read(path).with_context(|| {
+                    format!(
+                        "Failed to read the welcome email attachment {}.",
+                        path.display()
+                    )
+                })?;
+                vec![Attachment {
+                    filename,
+                    content_type,
+                    content,
+                }]
+            }
+        };
+        let translations = settings
+            .translations
+            .iter()
+            .map(|(locale, translation)| {
+                let locale = Locale::parse(locale).map_err(anyhow::Error::msg)?;
+                Ok((locale, translation.clone()))
+            })
+            .collect::<Result<_, anyhow::Error>>()?;
+        Ok(Self {
+            subject: settings.subject.clone(),
+            html_template: settings.html_template.clone(),
+            text_template: settings.text_template.clone(),
+            attachments,
+            translations,
+        })
+    }
+}
+
+/// Whether `s` is a bare `type/subtype` media type, without parameters.
+fn is_media_type(s: &str) -> bool {
+    let is_token = |part: &str| {
+        !part.is_empty()
+            && part
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "!#$&-^_.+".contains(c))
+    };
+    matches!(s.split_once('/'), Some((type_, subtype)) if is_token(type_) && is_token(subtype))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::WelcomeEmail;
+    use crate::configuration::{AttachmentSettings, WelcomeEmailSettings, WelcomeEmailTranslation};
+    use crate::domain::Locale;
+    use claims::assert_err;
+    use std::collections::HashMap;
+    use uuid::Uuid;
+
+    fn settings(attachment: Option<AttachmentSettings>) -> WelcomeEmailSettings {
+        WelcomeEmailSettings {
+            subject: "Welcome".into(),
+            html_template: "<p>Hello {{name}}</p>".into(),
+            text_template: "Hello {{name}}".into(),
+            attachment,
+            translations: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn the_attachment_is_read_with_its_file_name() {
+        // Arrange
+        let path = std::env::temp_dir().join(format!("{}.pdf", Uuid::new_v4()));
+        std::fs::write(&path, b"%PDF-1.4").unwrap();
+        let settings = settings(Some(AttachmentSettings {
+            path: path.to_str().unwrap().into(),
+            content_type: "application/pdf".into(),
+        }));
+
+        // Act
+        let welcome_email = WelcomeEmail::load(&settings).unwrap();
+
+        // Assert
+        let attachment = &welcome_email.attachments[0];
+        assert_eq!(
+            attachment.filename,
+            path.file_name().unwrap().to_str().unwrap()
+        );
+        assert_eq!(attachment.content_type, "application/pdf");
+        assert_eq!(attachment.content, b"%PDF-1.4");
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn a_missing_or_mistyped_attachment_is_rejected() {
+        // Arrange
+        let path = std::env::temp_dir().join(format!("{}.pdf", Uuid::new_v4()));
+        std::fs::write(&path, b"%PDF-1.4").unwrap();
+        let missing = settings(Some(AttachmentSettings {
+            path: "/does/not/exist.pdf".into(),
+            content_type: "application/pdf".into(),
+        }));
+        let mistyped = settings(Some(AttachmentSettings {
+            path: path.to_str().unwrap().into(),
+            content_type: "a pdf".into(),
+        }));
+
+        // Act
+        let results = [WelcomeEmail::load(&missing), WelcomeEmail::load(&mistyped)];
+
+        // Assert
+        for result in results {
+            assert_err!(result);
+        }
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn subscribers_get_the_translation_for_their_language() {
+        // Arrange
+        let mut settings = settings(None);
+        settings.translations.insert(
+            "pt".into(),
+            WelcomeEmailTranslation {
+                subject: "Bem-vindo".into(),
+                html_template: "<p>Olá {{name}}</p>".into(),
+                text_template: "Olá {{name}}".into(),
+            },
+        );
+        let welcome_email = WelcomeEmail::load(&settings).unwrap();
+
+        // Act
+        let brazilian = welcome_email.templates(Some(&Locale::parse("pt-BR").unwrap()));
+        let german = welcome_email.templates(Some(&Locale::parse("de").unwrap()));
+
+        // Assert
+        assert_eq!(brazilian.subject, "Bem-vindo");
+        assert_eq!(german.subject, "Welcome");
+    }
+}
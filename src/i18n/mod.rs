@@ -0,0 +1,303 @@
+use crate::configuration::I18nSettings;
+use crate::domain::Locale;
+use anyhow::Context;
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Catalogs shipped with the application, by locale.
+const BUNDLED_CATALOGS: [(&str, &str); 2] = [
+    ("en", include_str!("en.toml")),
+    ("pt", include_str!("pt.toml")),
+];
+
+type Catalog = HashMap<String, String>;
+
+/// The texts subscribers see, in every language we speak.
+///
+/// Catalogs are TOML files whose nested tables are flattened into dotted
+/// keys, such as `pages.unsubscribed.title`. Texts missing from a catalog
+/// are taken from the catalog of the default locale.
+#[derive(Debug)]
+pub struct Catalogs {
+    default_locale: Locale,
+    catalogs: HashMap<Locale, Catalog>,
+}
+
+impl Catalogs {
+    /// Loads the bundled catalogs, then every `<locale>.toml` file in the
+    /// configured directory on top of them, so that deployments can reword
+    /// texts or add languages.
+    pub fn new(settings: &I18nSettings) -> Result<Self, anyhow::Error> {
+        let mut catalogs = HashMap::new();
+        for (locale, contents) in BUNDLED_CATALOGS {
+            let locale = Locale::parse(locale).map_err(anyhow::Error::msg)?;
+            catalogs.insert(locale, parse_catalog(contents)?);
+        }
+        if let Some(directory) = &settings.catalogs_dir {
+            for entry in std::fs::read_dir(directory)
+                .with_context(|| format!("Failed to read the catalogs in {}.", directory))?
+            {
+                let path = entry?.path();
+                if path.extension().and_then(|e| e.to_str()) != Some("toml") {
+                    continue;
+                }
+                let (locale, catalog) = read_catalog(&path)?;
+                catalogs.entry(locale).or_default().extend(catalog);
+            }
+        }
+        let default_locale = Locale::parse(&settings.default_locale).map_err(anyhow::Error::msg)?;
+        if !catalogs.contains_key(&default_locale) {
+            anyhow::bail!(
+                "There is no catalog for the default locale {}.",
+                default_locale
+            );
+        }
+        Ok(Self {
+            default_locale,
+            catalogs,
+        })
+    }
+
+    pub fn default_locale(&self) -> &Locale {
+        &self.default_locale
+    }
+
+    /// Whether subscribers with this locale get texts in their language.
+    pub fn supports(&self, locale: &Locale) -> bool {
+        locale.lookup(&self.catalogs).is_some()
+    }
+
+    /// Every locale with a catalog, with the name of its language in that
+    /// language, sorted by locale.
+    pub fn languages(&self) -> Vec<(&Locale, String)> {
+        let mut languages: Vec<_> = self
+            .catalogs
+            .keys()
+            .map(|locale| (locale, self.translator(Some(locale)).text("language.name")))
+            .collect();
+        languages.sort_by(|a, b| a.0.as_ref().cmp(b.0.as_ref()));
+        languages
+    }
+
+    /// The subscriber's texts. Subscribers without a supported locale get
+    /// the default one.
+    pub fn translator(&self, locale: Option<&Locale>) -> Translator<'_> {
+        let (locale, catalog) = locale
+            .and_then(|locale| {
+                self.catalogs.get_key_value(locale).or_else(|| {
+                    self.catalogs
+                        .get_key_value(&Locale::parse(locale.language()).ok()?)
+                })
+            })
+            .unwrap_or_else(|| (&self.default_locale, &self.catalogs[&self.default_locale]));
+        Translator {
+            locale,
+            catalog,
+            fallback: &self.catalogs[&self.default_locale],
+        }
+    }
+
+    /// The supported locale ranked highest in an `Accept-Language` header,
+    /// as the client wrote it.
+    pub fn negotiate(&self, accept_language: &str) -> Option<Locale> {
+        let mut ranges: Vec<(f32, Locale)> = accept_language
+            .split(',')
+            .filter_map(|range| {
+                let mut parts = range.split(';');
+                let locale = Locale::parse(parts.next()?).ok()?;
+                let quality = parts
+                    .find_map(|p| p.trim().strip_prefix("q="))
+                    .map_or(Some(1.0), |q| q.trim().parse::<f32>().ok())?;
+                (quality > 0.0).then_some((quality, locale))
+            })
+            .collect();
+        // Stable, so that ranges of equal quality keep the client's order.
+        ranges.sort_by(|a, b| b.0.total_cmp(&a.0));
+        ranges
+            .into_iter()
+            .map(|(_, locale)| locale)
+            .find(|locale| self.supports(locale))
+    }
+}
+
+/// Texts in one locale.
+pub struct Translator<'a> {
+    locale: &'a Locale,
+    catalog: &'a Catalog,
+    fallback: &'a Catalog,
+}
+
+impl Translator<'_> {
+    /// The locale of the catalog the texts come from.
+    pub fn locale(&self) -> &Locale {
+        self.locale
+    }
+
+    pub fn text(&self, key: &str) -> String {
+        self.text_with(key, &[])
+    }
+
+    /// Formats `date` with the strftime pattern at `key`. Patterns chrono
+    /// cannot read fall back to ISO 8601 dates.
+    pub fn date(&self, key: &str, date: DateTime<Utc>) -> String {
+        let format = self.text(key);
+        if StrftimeItems::new(&format).any(|i| i == Item::Error) {
+            return date.format("%Y-%m-%d").to_string();
+        }
+        date.format(&format).to_string()
+    }
+
+    /// Fills `{name}` placeholders in with the given values. Unknown
+    /// placeholders are left as they are.
+    pub fn text_with(&self, key: &str, values: &[(&str, &str)]) -> String {
+        let Some(template) = self.catalog.get(key).or_else(|| self.fallback.get(key)) else {
+            tracing::warn!(key, "A text is missing from every catalog.");
+            return key.to_owned();
+        };
+        let mut text = String::with_capacity(template.len());
+        let mut rest = template.as_str();
+        while let Some(start) = rest.find('{') {
+            text.push_str(&rest[..start]);
+            let after = &rest[start + 1..];
+            let value = after.find('}').and_then(|end| {
+                let name = &after[..end];
+                values
+                    .iter()
+                    .find(|(n, _)| *n == name)
+                    .map(|(_, value)| (*value, end))
+            });
+            match value {
+                Some((value, end)) => {
+                    text.push_str(value);
+                    rest = &after[end + 1..];
+                }
+                None => {
+                    text.push('{');
+                    rest = after;
+                }
+            }
+        }
+        text.push_str(rest);
+        text
+    }
+}
+
+fn read_catalog(path: &Path) -> Result<(Locale, Catalog), anyhow::Error> {
+    let locale = path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .and_then(|stem| Locale::parse(stem).ok())
+        .with_context(|| format!("{} is not named after a locale.", path.display()))?;
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read the catalog {}.", path.display()))?;
+    let catalog =
+        parse_catalog(&contents).with_context(|| format!("{} is not valid.", path.display()))?;
+    Ok((locale, catalog))
+}
+
+fn parse_catalog(contents: &str) -> Result<Catalog, anyhow::Error> {
+    let table: toml::Table = contents.parse()?;
+    let mut catalog = HashMap::new();
+    flatten("", &table, &mut catalog)?;
+    Ok(catalog)
+}
+
+fn flatten(prefix: &str, table: &toml::Table, catalog: &mut Catalog) -> Result<(), anyhow::Error> {
+    for (key, value) in table {
+        let key = format!("{}{}", prefix, key);
+        match value {
+            toml::Value::String(text) => {
+                catalog.insert(key, text.clone());
+            }
+            toml::Value::Table(table) => flatten(&format!("{}.", key), table, catalog)?,
+            _ => anyhow::bail!("{} is not a text.", key),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_catalog, Catalogs, BUNDLED_CATALOGS};
+    use crate::configuration::I18nSettings;
+    use crate::domain::Locale;
+    use std::collections::BTreeSet;
+
+    fn catalogs() -> Catalogs {
+        Catalogs::new(&I18nSettings {
+            default_locale: "en".into(),
+            catalogs_dir: None,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn every_bundled_catalog_has_every_text() {
+        // Arrange
+        let keys = |contents| {
+            parse_catalog(contents)
+                .unwrap()
+                .into_keys()
+                .collect::<BTreeSet<_>>()
+        };
+        let expected = keys(BUNDLED_CATALOGS[0].1);
+
+        // Act & Assert
+        for (locale, contents) in BUNDLED_CATALOGS {
+            assert_eq!(keys(contents), expected, "{} differs", locale);
+        }
+    }
+
+    #[test]
+    fn the_best_supported_language_is_negotiated() {
+        let catalogs = catalogs();
+        for (header, expected) in [
+            ("pt-BR,pt;q=0.9,en;q=0.8", Some("pt-BR")),
+            ("de-DE, en-US;q=0.5, pt;q=0.7", Some("pt")),
+            ("de, fr;q=0.5", None),
+            ("pt;q=0, en", Some("en")),
+            ("*", None),
+        ] {
+            assert_eq!(
+                catalogs.negotiate(header).as_ref().map(Locale::as_ref),
+                expected,
+                "{}",
+                header
+            );
+        }
+    }
+
+    #[test]
+    fn texts_fall_back_to_the_language_and_then_to_the_default_locale() {
+        // Arrange
+        let catalogs = catalogs();
+        let brazilian = Locale::parse("pt-BR").unwrap();
+        let german = Locale::parse("de").unwrap();
+
+        // Act
+        let brazilian = catalogs.translator(Some(&brazilian));
+        let german = catalogs.translator(Some(&german));
+
+        // Assert
+        assert_eq!(brazilian.locale().as_ref(), "pt");
+        assert_eq!(german.locale().as_ref(), "en");
+    }
+
+    #[test]
+    fn placeholders_are_filled_in_once() {
+        // Arrange
+        let catalogs = catalogs();
+        let translator = catalogs.translator(None);
+
+        // Act
+        let text = translator.text_with(
+            "pages.unsubscribed.message",
+            &[("list", "{list} & {other}")],
+        );
+
+        // Assert
+        assert_eq!(text, "You will no longer receive {list} & {other}.");
+    }
+}
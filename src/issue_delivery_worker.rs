@@ -0,0 +1,541 @@
+use crate::bootstrap::Dependencies;
+use crate::configuration::Settings;
+use crate::domain::{DeliveryStatus, IssueStatus, Locale, SubscriberEmail, SubscriptionStatus};
+use crate::email::email_client::{EmailClient, EmailService, SendEmailRequest};
+use crate::email::link_tracking::{rewrite_links, trackable_links};
+use crate::email::merge_tags::MergeTags;
+use crate::i18n::{Catalogs, Translator};
+use crate::startup::get_connection_pool;
+use chrono::Utc;
+use http::Uri;
+use sqlx::{Executor, PgPool, Postgres, Transaction};
+use std::collections::HashMap;
+use std::time::Duration;
+use tracing::{field::display, Span};
+use uuid::Uuid;
+
+pub enum ExecutionOutcome {
+    TaskCompleted,
+    EmptyQueue,
+}
+
+pub async fn run_worker_until_stopped(
+    configuration: Settings,
+    dependencies: Dependencies,
+) -> Result<(), anyhow::Error> {
+    let connection_pool = get_connection_pool(&configuration.database);
+    let sender = configuration
+        .email_client
+        .sender()
+        .map_err(anyhow::Error::msg)?;
+    let email_service = EmailService::new(sender);
+    let catalogs = Catalogs::new(&configuration.i18n)?;
+    worker_loop(
+        connection_pool,
+        email_service,
+        dependencies.email_client.as_ref(),
+        configuration.application.base_url,
+        catalogs,
+    )
+    .await
+}
+
+async fn worker_loop(
+    pool: PgPool,
+    email_service: EmailService,
+    email_client: &dyn EmailClient,
+    base_url: Uri,
+    catalogs: Catalogs,
+) -> Result<(), anyhow::Error> {
+    loop {
+        match try_execute_task(&pool, &email_service, email_client, &base_url, &catalogs).await {
+            Ok(ExecutionOutcome::EmptyQueue) => {
+                tokio::time::sleep(Duration::from_secs(10)).await;
+            }
+            Err(_) => {
+                tokio::time::sleep(Duration::from_secs(1)).await;
+            }
+            Ok(ExecutionOutcome::TaskCompleted) => {}
+        }
+    }
+}
+
+#[tracing::instrument(
+    skip_all,
+    fields(
+        newsletter_issue_id=tracing::field::Empty,
+        subscriber_email=tracing::field::Empty
+    ),
+    err
+)]
+pub async fn try_execute_task(
+    pool: &PgPool,
+    email_service: &EmailService,
+    email_client: &dyn EmailClient,
+    base_url: &Uri,
+    catalogs: &Catalogs,
+) -> Result<ExecutionOutcome, anyhow::Error> {
+    let task = dequeue_task(pool).await?;
+    let Some((mut transaction, issue_id, email)) = task else {
+        mark_drained_issues_as_sent(pool).await?;
+        return Ok(ExecutionOutcome::EmptyQueue);
+    };
+    Span::current()
+        .record("newsletter_issue_id", display(issue_id))
+        .record("subscriber_email", display(&email));
+
+    let recipient = get_recipient(pool, issue_id, &email).await?;
+    let (status, error) = match SubscriberEmail::parse(email.clone()) {
+        Ok(_) if !recipient.is_subscribed() => {
+            tracing::info!("Skipping a subscriber who left the list after the issue was queued");
+            (DeliveryStatus::Skipped, Some("unsubscribed".to_string()))
+        }
+        Ok(email) => {
+            let locale = recipient
+                .locale
+                .as_deref()
+                .and_then(|locale| Locale::parse(locale).ok());
+            let delivery = get_delivery(pool, issue_id, email.as_ref()).await?;
+            let mut issue = get_issue(
+                pool,
+                issue_id,
+                locale.as_ref(),
+                delivery.ab_variant.as_deref(),
+            )
+            .await?;
+            add_tracking(
+                pool,
+                issue_id,
+                &mut issue,
+                &delivery.tracking_token,
+                base_url,
+            )
+            .await?;
+            let t = catalogs.translator(locale.as_ref());
+            let content = personalize(&issue, &email, &recipient, base_url, &t);
+            let send_email_request = SendEmailRequest {
+                to: &email,
+                subject: &issue.title,
+                html_content: &content.html,
+                text_content: &content.text,
+                attachments: &[],
+            };
+            match email_service
+                .send_email(email_client, send_email_request)
+                .await
+            {
+                Ok(()) => (DeliveryStatus::Sent, None),
+                Err(e) => {
+                    tracing::error!(
+                        error.cause_chain = ?e,
+                        error.message = %e,
+                        "Failed to deliver issue to a confirmed subscriber. \
+                        Skipping.",
+                    );
+                    (DeliveryStatus::Failed, Some(e.to_string()))
+                }
+            }
+        }
+        Err(error) => {
+            tracing::warn!(
+                error.cause_chain = ?error,
+                "Skipping a confirmed subscriber. \
+                Their stored contact details are invalid",
+            );
+            (DeliveryStatus::Skipped, Some(error))
+        }
+    };
+
+    record_delivery(&mut transaction, issue_id, &email, status, error).await?;
+    delete_task(&mut transaction, issue_id, &email).await?;
+    transaction.commit().await?;
+    Ok(ExecutionOutcome::TaskCompleted)
+}
+
+pub(crate) type PgTransaction = Transaction<'static, Postgres>;
+
+#[tracing::instrument(skip_all)]
+async fn dequeue_task(
+    pool: &PgPool,
+) -> Result<Option<(PgTransaction, Uuid, String)>, anyhow::Error> {
+    let mut transaction = pool.begin().await?;
+    let r = sqlx::query!(
+        r#"
+    SELECT newsletter_issue_id, subscriber_email
+    FROM issue_delivery_queue
+    FOR UPDATE
+    SKIP LOCKED
+    LIMIT 1
+            "#,
+    )
+    .fetch_optional(&mut *transaction)
+    .await?;
+    if let Some(r) = r {
+        Ok(Some((
+            transaction,
+            r.newsletter_issue_id,
+            r.subscriber_email,
+        )))
+    } else {
+        Ok(None)
+    }
+}
+
+#[tracing::instrument(skip_all)]
+async fn delete_task(
+    transaction: &mut PgTransaction,
+    issue_id: Uuid,
+    email: &str,
+) -> Result<(), anyhow::Error> {
+    let query = sqlx::query!(
+        r#"
+    DELETE FROM issue_delivery_queue
+    WHERE newsletter_issue_id = $1 AND subscriber_email = $2
+            "#,
+        issue_id,
+        email
+    );
+    transaction.execute(query).await?;
+    Ok(())
+}
+
+#[tracing::instrument(skip(transaction, email, error))]
+pub(crate) async fn record_delivery(
+    transaction: &mut PgTransaction,
+    issue_id: Uuid,
+    email: &str,
+    status: DeliveryStatus,
+    error: Option<String>,
+) -> Result<(), anyhow::Error> {
+    let query = sqlx::query!(
+        r#"
+    UPDATE issue_deliveries
+    SET status = $3, error = $4, updated_at = $5
+    WHERE newsletter_issue_id = $1 AND subscriber_email = $2
+            "#,
+        issue_id,
+        email,
+        status.as_str(),
+        error,
+        Utc::now()
+    );
+    transaction.execute(query).await?;
+    Ok(())
+}
+
+/// Issues stay in the `sending` state until every delivery task has been
+/// processed and the winner of their A/B test, if any, has been sent to the
+/// rest of the recipients, at which point they become `sent`.
+#[tracing::instrument(skip_all)]
+async fn mark_drained_issues_as_sent(pool: &PgPool) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"
+    UPDATE newsletter_issues
+    SET status = $1, updated_at = $3, sent_at = $3
+    WHERE status = $2
+      AND NOT EXISTS (
+        SELECT 1 FROM issue_delivery_queue
+        WHERE newsletter_issue_id = newsletter_issues.id
+      )
+      AND NOT EXISTS (
+        SELECT 1 FROM ab_tests
+        WHERE newsletter_issue_id = newsletter_issues.id AND decided_at IS NULL
+      )
+            "#,
+        IssueStatus::Sent.as_str(),
+        IssueStatus::Sending.as_str(),
+        Utc::now()
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub(crate) struct NewsletterIssue {
+    pub(crate) title: String,
+    pub(crate) text_content: String,
+    pub(crate) html_content: String,
+    pub(crate) slug: Option<String>,
+}
+
+struct Delivery {
+    tracking_token: String,
+    ab_variant: Option<String>,
+}
+
+struct PersonalizedContent {
+    html: String,
+    text: String,
+}
+
+struct Recipient {
+    name: String,
+    attributes: serde_json::Value,
+    preferences_token: Option<String>,
+    status: Option<String>,
+    unsubscribe_token: Option<String>,
+    locale: Option<String>,
+}
+
+impl Recipient {
+    fn is_subscribed(&self) -> bool {
+        self.status.as_deref() != Some(SubscriptionStatus::Unsubscribed.as_str())
+    }
+}
+
+/// Fills in the subscriber's merge tags, including `{{attributes.<name>}}` for
+/// their custom attributes, prepends a link to the issue's
+/// public archive page and appends links to leave the issue's list and to
+/// manage the subscriber's preferences, labelled in the subscriber's language.
+fn personalize(
+    issue: &NewsletterIssue,
+    email: &SubscriberEmail,
+    recipient: &Recipient,
+    base_url: &Uri,
+    t: &Translator,
+) -> PersonalizedContent {
+    let archive_url = issue
+        .slug
+        .as_ref()
+        .map(|slug| format!("{}archive/{}", base_url, slug));
+    let unsubscribe_url = recipient
+        .unsubscribe_token
+        .as_ref()
+        .map(|token| format!("{}subscriptions/unsubscribe?token={}", base_url, token));
+    let preferences_url = recipient
+        .preferences_token
+        .as_ref()
+        .map(|token| format!("{}preferences/{}", base_url, token));
+    let merge_tags = subscriber_merge_tags(&recipient.name, email, &recipient.attributes)
+        .with("archive_url", archive_url.clone().unwrap_or_default())
+        .with(
+            "unsubscribe_url",
+            unsubscribe_url.clone().unwrap_or_default(),
+        )
+        .with(
+            "preferences_url",
+            preferences_url.clone().unwrap_or_default(),
+        );
+
+    let mut html = merge_tags.render_html(&issue.html_content);
+    let mut text = merge_tags.render_text(&issue.text_content);
+    if let Some(archive_url) = archive_url {
+        let label = t.text("email.footer.view_in_browser");
+        html = format!(
+            "<p><a href=\"{}\">{}</a></p>\n{}",
+            archive_url,
+            htmlescape::encode_minimal(&label),
+            html
+        );
+        text = format!("{}: {}\n\n{}", label, archive_url, text);
+    }
+    if let Some(unsubscribe_url) = unsubscribe_url {
+        let label = t.text("email.footer.unsubscribe");
+        html = format!(
+            "{}\n<p><a href=\"{}\">{}</a></p>",
+            html,
+            unsubscribe_url,
+            htmlescape::encode_minimal(&label)
+        );
+        text = format!("{}\n\n{}: {}", text, label, unsubscribe_url);
+    }
+    if let Some(preferences_url) = preferences_url {
+        let label = t.text("email.footer.manage_preferences");
+        html = format!(
+            "{}\n<p><a href=\"{}\">{}</a></p>",
+            html,
+            preferences_url,
+            htmlescape::encode_minimal(&label)
+        );
+        text = format!("{}\n{}: {}", text, label, preferences_url);
+    }
+    PersonalizedContent { html, text }
+}
+
+/// The subscriber's `{{name}}`, `{{email}}` and `{{attributes.<name>}}`
+/// merge tags.
+pub(crate) fn subscriber_merge_tags(
+    name: &str,
+    email: &SubscriberEmail,
+    attributes: &serde_json::Value,
+) -> MergeTags {
+    let mut merge_tags = MergeTags::new()
+        .with("name", name)
+        .with("email", email.as_ref());
+    if let Some(attributes) = attributes.as_object() {
+        for (name, value) in attributes {
+            let value = match value {
+                serde_json::Value::String(s) => s.clone(),
+                other => other.to_string(),
+            };
+            merge_tags = merge_tags.with(&format!("attributes.{}", name), value);
+        }
+    }
+    merge_tags
+}
+
+/// The issue in the subscriber's locale, falling back to its language and
+/// then to the issue as written, or the given variant of its A/B test.
+#[tracing::instrument(skip_all)]
+pub(crate) async fn get_issue(
+    pool: &PgPool,
+    issue_id: Uuid,
+    locale: Option<&Locale>,
+    ab_variant: Option<&str>,
+) -> Result<NewsletterIssue, anyhow::Error> {
+    let mut issue = sqlx::query_as!(
+        NewsletterIssue,
+        r#"
+    SELECT title, text_content, html_content, slug
+    FROM newsletter_issues
+    WHERE id = $1
+            "#,
+        issue_id
+    )
+    .fetch_one(pool)
+    .await?;
+    if let Some(label) = ab_variant {
+        let variant = sqlx::query!(
+            r#"
+    SELECT title, text_content, html_content
+    FROM ab_test_variants
+    WHERE newsletter_issue_id = $1 AND label = $2
+            "#,
+            issue_id,
+            label
+        )
+        .fetch_one(pool)
+        .await?;
+        issue.title = variant.title;
+        issue.text_content = variant.text_content;
+        issue.html_content = variant.html_content;
+    }
+    let Some(locale) = locale else {
+        return Ok(issue);
+    };
+    let variants: HashMap<Locale, _> = sqlx::query!(
+        r#"
+    SELECT locale, title, text_content, html_content
+    FROM newsletter_issue_variants
+    WHERE newsletter_issue_id = $1
+            "#,
+        issue_id
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .filter_map(|v| Some((Locale::parse(&v.locale).ok()?, v)))
+    .collect();
+    if let Some(variant) = locale.lookup(&variants) {
+        issue.title.clone_from(&variant.title);
+        issue.text_content.clone_from(&variant.text_content);
+        issue.html_content.clone_from(&variant.html_content);
+    }
+    Ok(issue)
+}
+
+/// The delivery's tracking token and the A/B test variant it gets: the one
+/// it was assigned in the sample, or else the winner.
+#[tracing::instrument(skip_all)]
+async fn get_delivery(
+    pool: &PgPool,
+    issue_id: Uuid,
+    email: &str,
+) -> Result<Delivery, anyhow::Error> {
+    let delivery = sqlx::query_as!(
+        Delivery,
+        r#"
+    SELECT issue_deliveries.tracking_token,
+           COALESCE(issue_deliveries.ab_variant, ab_tests.winner) as ab_variant
+    FROM issue_deliveries
+    LEFT JOIN ab_tests ON ab_tests.newsletter_issue_id = issue_deliveries.newsletter_issue_id
+    WHERE issue_deliveries.newsletter_issue_id = $1
+      AND issue_deliveries.subscriber_email = $2
+            "#,
+        issue_id,
+        email
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(delivery)
+}
+
+/// Routes the issue's links through the click tracker, remembering them so
+/// that tracked clicks can only lead there, and appends the open tracking
+/// pixel.
+#[tracing::instrument(skip_all)]
+async fn add_tracking(
+    pool: &PgPool,
+    issue_id: Uuid,
+    issue: &mut NewsletterIssue,
+    tracking_token: &str,
+    base_url: &Uri,
+) -> Result<(), anyhow::Error> {
+    let mut link_ids = HashMap::new();
+    for url in trackable_links(&issue.html_content) {
+        let link_id = sqlx::query_scalar!(
+            r#"
+    INSERT INTO issue_links (id, newsletter_issue_id, url)
+    VALUES ($1, $2, $3)
+    ON CONFLICT (newsletter_issue_id, url) DO UPDATE SET url = EXCLUDED.url
+    RETURNING id
+            "#,
+            Uuid::new_v4(),
+            issue_id,
+            url
+        )
+        .fetch_one(pool)
+        .await?;
+        link_ids.insert(url, link_id);
+    }
+    issue.html_content = rewrite_links(&issue.html_content, |url| {
+        link_ids
+            .get(url)
+            .map(|link_id| format!("{}track/click/{}/{}", base_url, tracking_token, link_id))
+    });
+    issue.html_content = format!(
+        "{}\n<img src=\"{}track/open/{}\" width=\"1\" height=\"1\" alt=\"\">",
+        issue.html_content, base_url, tracking_token
+    );
+    Ok(())
+}
+
+/// Looks the subscriber up together with their membership of the issue's
+/// list, which may have changed since the delivery was queued.
+#[tracing::instrument(skip_all)]
+async fn get_recipient(
+    pool: &PgPool,
+    issue_id: Uuid,
+    email: &str,
+) -> Result<Recipient, anyhow::Error> {
+    let recipient = sqlx::query_as!(
+        Recipient,
+        r#"
+    SELECT subscriptions.name,
+           subscriptions.attributes,
+           subscriptions.preferences_token as "preferences_token?",
+           list_subscriptions.status as "status?",
+           list_subscriptions.unsubscribe_token as "unsubscribe_token?",
+           subscriptions.locale
+    FROM subscriptions
+    JOIN newsletter_issues ON newsletter_issues.id = $1
+    LEFT JOIN list_subscriptions
+      ON list_subscriptions.subscriber_id = subscriptions.id
+     AND list_subscriptions.list_id = newsletter_issues.list_id
+    WHERE subscriptions.email = $2
+            "#,
+        issue_id,
+        email
+    )
+    .fetch_optional(pool)
+    .await?
+    .unwrap_or(Recipient {
+        name: String::new(),
+        attributes: serde_json::Value::Null,
+        preferences_token: None,
+        status: None,
+        unsubscribe_token: None,
+        locale: None,
+    });
+    Ok(recipient)
+}
@@ -1,8 +1,20 @@
+pub mod admin_auth;
 pub mod bootstrap;
+pub mod bot_protection;
 pub mod configuration;
+pub mod cors;
+pub mod csv_records;
+pub mod digest_worker;
 pub mod domain;
 pub mod email;
 pub mod environment;
+pub mod i18n;
+pub mod issue_delivery_worker;
+pub mod opt_in_worker;
+pub mod rate_limit;
 pub mod routes;
+pub mod scheduler;
+pub mod segment;
 pub mod startup;
 pub mod telemetry;
+pub mod welcome_email_worker;
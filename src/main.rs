@@ -1,18 +1,73 @@
+use std::fmt::{Debug, Display};
+use tokio::task::JoinError;
 use zero2prod::bootstrap::build_dependencies;
 use zero2prod::configuration::get_configuration;
+use zero2prod::digest_worker::run_digest_worker_until_stopped;
+use zero2prod::issue_delivery_worker::run_worker_until_stopped;
+use zero2prod::opt_in_worker::run_opt_in_worker_until_stopped;
+use zero2prod::scheduler::run_scheduler_until_stopped;
 use zero2prod::startup::Application;
 use zero2prod::telemetry;
+use zero2prod::welcome_email_worker::run_welcome_email_worker_until_stopped;
 
 #[tokio::main]
-async fn main() -> Result<(), std::io::Error> {
+async fn main() -> Result<(), anyhow::Error> {
     let telemetry_subscriber =
         telemetry::get_subscriber("zero2prod".into(), "info".into(), std::io::stdout);
     telemetry::init_subscriber(telemetry_subscriber);
 
     let configuration = get_configuration().expect("Failed to read configuration.");
     let dependencies = build_dependencies(&configuration).await;
-    let server = Application::build(configuration, dependencies).await?;
+    let application = Application::build(configuration.clone(), dependencies.clone()).await?;
+
+    let application_task = tokio::spawn(application.run_until_stopped());
+    let scheduler_task = tokio::spawn(run_scheduler_until_stopped(configuration.clone()));
+    let opt_in_worker_task = tokio::spawn(run_opt_in_worker_until_stopped(
+        configuration.clone(),
+        dependencies.clone(),
+    ));
+    let welcome_email_worker_task = tokio::spawn(run_welcome_email_worker_until_stopped(
+        configuration.clone(),
+        dependencies.clone(),
+    ));
+    let digest_worker_task = tokio::spawn(run_digest_worker_until_stopped(
+        configuration.clone(),
+        dependencies.clone(),
+    ));
+    let worker_task = tokio::spawn(run_worker_until_stopped(configuration, dependencies));
+
+    tokio::select! {
+        o = application_task => report_exit("API", o),
+        o = scheduler_task => report_exit("Newsletter scheduler", o),
+        o = worker_task => report_exit("Background worker", o),
+        o = opt_in_worker_task => report_exit("Opt-in worker", o),
+        o = welcome_email_worker_task => report_exit("Welcome email worker", o),
+        o = digest_worker_task => report_exit("Digest worker", o),
+    };
 
-    server.run_until_stopped().await?;
     Ok(())
 }
+
+fn report_exit(task_name: &str, outcome: Result<Result<(), impl Debug + Display>, JoinError>) {
+    match outcome {
+        Ok(Ok(())) => {
+            tracing::info!("{} has exited", task_name)
+        }
+        Ok(Err(e)) => {
+            tracing::error!(
+                error.cause_chain = ?e,
+                error.message = %e,
+                "{} failed",
+                task_name
+            )
+        }
+        Err(e) => {
+            tracing::error!(
+                error.cause_chain = ?e,
+                error.message = %e,
+                "{} task failed to complete",
+                task_name
+            )
+        }
+    }
+}
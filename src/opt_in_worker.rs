@@ -0,0 +1,123 @@
+use crate::bootstrap::Dependencies;
+use crate::configuration::Settings;
+use crate::domain::{Locale, SubscriberEmail};
+use crate::email::email_client::{EmailClient, EmailService};
+use crate::i18n::Catalogs;
+use crate::issue_delivery_worker::ExecutionOutcome;
+use crate::routes::send_confirmation_email;
+use crate::startup::get_connection_pool;
+use http::Uri;
+use sqlx::{Executor, PgPool};
+use std::time::Duration;
+use tracing::{field::display, Span};
+
+pub async fn run_opt_in_worker_until_stopped(
+    configuration: Settings,
+    dependencies: Dependencies,
+) -> Result<(), anyhow::Error> {
+    let connection_pool = get_connection_pool(&configuration.database);
+    let sender = configuration
+        .email_client
+        .sender()
+        .map_err(anyhow::Error::msg)?;
+    let email_service = EmailService::new(sender);
+    let catalogs = Catalogs::new(&configuration.i18n)?;
+    worker_loop(
+        connection_pool,
+        email_service,
+        dependencies.email_client.as_ref(),
+        configuration.application.base_url,
+        catalogs,
+    )
+    .await
+}
+
+async fn worker_loop(
+    pool: PgPool,
+    email_service: EmailService,
+    email_client: &dyn EmailClient,
+    base_url: Uri,
+    catalogs: Catalogs,
+) -> Result<(), anyhow::Error> {
+    loop {
+        match try_send_opt_in_email(&pool, &email_service, email_client, &base_url, &catalogs).await
+        {
+            Ok(ExecutionOutcome::EmptyQueue) => {
+                tokio::time::sleep(Duration::from_secs(10)).await;
+            }
+            Err(_) => {
+                tokio::time::sleep(Duration::from_secs(1)).await;
+            }
+            Ok(ExecutionOutcome::TaskCompleted) => {}
+        }
+    }
+}
+
+/// Sends the confirmation email owed to one contact imported in opt-in mode.
+/// Each email is attempted once: failures are logged and dropped, as the
+/// contact can always subscribe again.
+#[tracing::instrument(skip_all, fields(subscriber_email=tracing::field::Empty), err)]
+pub async fn try_send_opt_in_email(
+    pool: &PgPool,
+    email_service: &EmailService,
+    email_client: &dyn EmailClient,
+    base_url: &Uri,
+    catalogs: &Catalogs,
+) -> Result<ExecutionOutcome, anyhow::Error> {
+    let mut transaction = pool.begin().await?;
+    let task = sqlx::query!(
+        r#"
+    SELECT opt_in_email_queue.subscription_token, subscriptions.email, subscriptions.locale
+    FROM opt_in_email_queue
+    JOIN subscriptions ON subscriptions.id = opt_in_email_queue.subscriber_id
+    FOR UPDATE OF opt_in_email_queue
+    SKIP LOCKED
+    LIMIT 1
+            "#,
+    )
+    .fetch_optional(&mut *transaction)
+    .await?;
+    let Some(task) = task else {
+        return Ok(ExecutionOutcome::EmptyQueue);
+    };
+    Span::current().record("subscriber_email", display(&task.email));
+
+    match SubscriberEmail::parse(task.email) {
+        Ok(email) => {
+            let locale = task
+                .locale
+                .as_deref()
+                .and_then(|locale| Locale::parse(locale).ok());
+            if let Err(e) = send_confirmation_email(
+                email_service,
+                &email,
+                email_client,
+                base_url,
+                &task.subscription_token,
+                &catalogs.translator(locale.as_ref()),
+            )
+            .await
+            {
+                tracing::error!(
+                    error.cause_chain = ?e,
+                    error.message = %e,
+                    "Failed to send an opt-in email to an imported contact. Skipping.",
+                );
+            }
+        }
+        Err(error) => {
+            tracing::warn!(
+                error.cause_chain = ?error,
+                "Skipping an imported contact. Their stored contact details are invalid",
+            );
+        }
+    }
+
+    let query = sqlx::query!(
+        r#"DELETE FROM opt_in_email_queue WHERE subscription_token = $1"#,
+        task.subscription_token
+    );
+    transaction.execute(query).await?;
+    transaction.commit().await?;
+    Ok(ExecutionOutcome::TaskCompleted)
+}
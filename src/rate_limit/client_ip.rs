@@ -0,0 +1,97 @@
+use actix_web::dev::ServiceRequest;
+use actix_web::{HttpMessage, HttpRequest};
+use std::net::IpAddr;
+
+/// The address of the client that sent a request, as seen past the trusted
+/// proxies. Stored in the request extensions by the rate limiter.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClientIp(pub IpAddr);
+
+impl ClientIp {
+    pub fn from_request(request: &ServiceRequest, trusted_proxies: &[IpAddr]) -> Option<Self> {
+        let peer = request.peer_addr()?.ip();
+        let forwarded_for = request
+            .headers()
+            .get_all("x-forwarded-for")
+            .filter_map(|h| h.to_str().ok())
+            .collect::<Vec<_>>()
+            .join(",");
+        Some(Self(client_ip(peer, &forwarded_for, trusted_proxies)))
+    }
+
+    /// The address found by the rate limiter, or the peer's on routes it
+    /// does not guard.
+    pub fn of(request: &HttpRequest) -> Option<IpAddr> {
+        request
+            .extensions()
+            .get::<ClientIp>()
+            .map(|client_ip| client_ip.0)
+            .or_else(|| request.peer_addr().map(|addr| addr.ip()))
+    }
+}
+
+/// Walks `X-Forwarded-For` from the closest hop and stops at the first
+/// address that is not a trusted proxy: anything before it could have been
+/// written by the client itself.
+pub fn client_ip(peer: IpAddr, forwarded_for: &str, trusted_proxies: &[IpAddr]) -> IpAddr {
+    let mut client = peer;
+    if forwarded_for.is_empty() {
+        return client;
+    }
+    for hop in forwarded_for.rsplit(',') {
+        if !trusted_proxies.contains(&client) {
+            break;
+        }
+        match hop.trim().parse() {
+            Ok(ip) => client = ip,
+            Err(_) => break,
+        }
+    }
+    client
+}
+
+#[cfg(test)]
+mod tests {
+    use super::client_ip;
+    use std::net::IpAddr;
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn the_header_is_ignored_when_the_peer_is_not_a_trusted_proxy() {
+        // Arrange
+        let trusted = [ip("10.0.0.1")];
+
+        // Act
+        let client = client_ip(ip("198.51.100.4"), "203.0.113.7", &trusted);
+
+        // Assert
+        assert_eq!(client, ip("198.51.100.4"));
+    }
+
+    #[test]
+    fn the_first_untrusted_hop_is_the_client() {
+        // Arrange
+        let trusted = [ip("10.0.0.1"), ip("10.0.0.2")];
+
+        // Act
+        let client = client_ip(ip("10.0.0.1"), "192.0.2.1, 203.0.113.7, 10.0.0.2", &trusted);
+
+        // Assert
+        assert_eq!(client, ip("203.0.113.7"));
+    }
+
+    #[test]
+    fn a_garbled_hop_stops_the_walk() {
+        // Arrange
+        let trusted = [ip("10.0.0.1")];
+
+        // Act
+        let client = client_ip(ip("10.0.0.1"), "203.0.113.7, not-an-ip", &trusted);
+
+        // Assert
+        assert_eq!(client, ip("10.0.0.1"));
+    }
+}
@@ -0,0 +1,92 @@
+use crate::rate_limit::{ClientIp, Decision, RateLimiter};
+use crate::routes::{ErrorCode, Problem};
+use actix_web::body::EitherBody;
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::StatusCode;
+use actix_web::HttpMessage;
+use futures_util::future::{ready, LocalBoxFuture, Ready};
+use std::rc::Rc;
+
+/// Turns away requests over the per-IP, per-endpoint or global limits with
+/// `429 Too Many Requests`.
+pub struct RateLimit {
+    limiter: RateLimiter,
+}
+
+impl RateLimit {
+    pub fn new(limiter: RateLimiter) -> Self {
+        Self { limiter }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for RateLimit
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = actix_web::Error;
+    type Transform = RateLimitMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RateLimitMiddleware {
+            service: Rc::new(service),
+            limiter: self.limiter.clone(),
+        }))
+    }
+}
+
+pub struct RateLimitMiddleware<S> {
+    service: Rc<S>,
+    limiter: RateLimiter,
+}
+
+impl<S, B> Service<ServiceRequest> for RateLimitMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, request: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+        let limiter = self.limiter.clone();
+        Box::pin(async move {
+            let Some(client_ip) = ClientIp::from_request(&request, limiter.trusted_proxies())
+            else {
+                return service
+                    .call(request)
+                    .await
+                    .map(ServiceResponse::map_into_left_body);
+            };
+            request.extensions_mut().insert(client_ip);
+            // Paths that carry a token are counted as one endpoint.
+            let endpoint = request
+                .match_pattern()
+                .unwrap_or_else(|| request.path().to_owned());
+            match limiter.check_request(client_ip.0, &endpoint).await {
+                Decision::Allowed => service
+                    .call(request)
+                    .await
+                    .map(ServiceResponse::map_into_left_body),
+                Decision::Limited { retry_after } => {
+                    tracing::warn!(%endpoint, client_ip = %client_ip.0, "Rate limited a request");
+                    let response = Problem::new(
+                        StatusCode::TOO_MANY_REQUESTS,
+                        ErrorCode::RateLimited.as_str(),
+                        "Too many requests. Please try again later.".to_owned(),
+                    )
+                    .with_retry_after(retry_after)
+                    .into_response();
+                    Ok(request.into_response(response).map_into_right_body())
+                }
+            }
+        })
+    }
+}
@@ -0,0 +1,220 @@
+pub use client_ip::*;
+pub use middleware::*;
+pub use store::*;
+
+mod client_ip;
+mod middleware;
+mod store;
+
+use crate::configuration::{Limit, RateLimitSettings, RateLimitStoreKind};
+use chrono::{DateTime, Utc};
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Whether a request may go ahead.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Decision {
+    Allowed,
+    Limited { retry_after: Duration },
+}
+
+/// Counts hits in fixed windows against the configured limits.
+#[derive(Clone)]
+pub struct RateLimiter {
+    store: Arc<dyn RateLimitStore>,
+    settings: Arc<RateLimitSettings>,
+}
+
+impl RateLimiter {
+    pub fn new(settings: RateLimitSettings, pool: PgPool) -> Self {
+        let store: Arc<dyn RateLimitStore> = match settings.store {
+            RateLimitStoreKind::Memory => Arc::new(MemoryStore::default()),
+            RateLimitStoreKind::Postgres => Arc::new(PostgresStore::new(pool)),
+        };
+        Self {
+            store,
+            settings: Arc::new(settings),
+        }
+    }
+
+    pub fn trusted_proxies(&self) -> &[IpAddr] {
+        &self.settings.trusted_proxies
+    }
+
+    /// Counts a request against the per-IP, per-endpoint and global limits.
+    pub async fn check_request(&self, ip: IpAddr, endpoint: &str) -> Decision {
+        self.check(&[
+            (self.settings.per_ip, format!("ip:{}", ip)),
+            (self.settings.per_endpoint, format!("endpoint:{}", endpoint)),
+            (self.settings.global, "global".to_owned()),
+        ])
+        .await
+    }
+
+    /// Counts an email sent to an address against the per-email limit.
+    /// Takes the normalized address, so that the spellings and aliases of
+    /// one mailbox share a counter.
+    pub async fn check_email(&self, normalized_email: &str) -> Decision {
+        self.check(&[(self.settings.per_email, email_key(normalized_email))])
+            .await
+    }
+
+    /// Every configured limit is counted, so that a request turned away by
+    /// one limit still counts towards the others. Requests are let through
+    /// when the store fails: an outage must not take the endpoints down.
+    async fn check(&self, limits: &[(Option<Limit>, String)]) -> Decision {
+        let now = Utc::now();
+        let mut decision = Decision::Allowed;
+        for (limit, key) in limits {
+            let Some(limit) = limit else {
+                continue;
+            };
+            let (window_start, expires_at) = window(now, limit.window_seconds);
+            let hits = match self.store.hit(key, window_start, expires_at).await {
+                Ok(hits) => hits,
+                Err(e) => {
+                    tracing::error!(
+                        error.cause_chain = ?e,
+                        error.message = %e,
+                        "Failed to count a hit. Letting the request through."
+                    );
+                    continue;
+                }
+            };
+            if hits > limit.max_requests {
+                let retry_after = (expires_at - now).to_std().unwrap_or_default();
+                decision = match decision {
+                    Decision::Limited {
+                        retry_after: longest,
+                    } if longest >= retry_after => decision,
+                    _ => Decision::Limited { retry_after },
+                };
+            }
+        }
+        decision
+    }
+}
+
+/// Counters are keyed by a hash of the address, so that the stores never
+/// hold an address that may have to be erased.
+fn email_key(normalized_email: &str) -> String {
+    let hash = Sha256::digest(normalized_email.as_bytes());
+    format!("email:{}", hex::encode(hash))
+}
+
+/// The fixed window of `window_seconds` containing `now`.
+fn window(now: DateTime<Utc>, window_seconds: u64) -> (DateTime<Utc>, DateTime<Utc>) {
+    let window_seconds = window_seconds.max(1) as i64;
+    let start = now.timestamp() - now.timestamp().rem_euclid(window_seconds);
+    let window_start = DateTime::from_timestamp(start, 0).unwrap_or(now);
+    (
+        window_start,
+        window_start + chrono::Duration::seconds(window_seconds),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{email_key, window, Decision, RateLimiter};
+    use crate::configuration::{Limit, RateLimitSettings, RateLimitStoreKind};
+    use chrono::{DateTime, Utc};
+    use sqlx::postgres::PgPoolOptions;
+    use std::net::IpAddr;
+
+    fn limiter(per_ip: Limit, global: Option<Limit>) -> RateLimiter {
+        let settings = RateLimitSettings {
+            store: RateLimitStoreKind::Memory,
+            trusted_proxies: vec![],
+            per_ip: Some(per_ip),
+            per_email: None,
+            per_endpoint: None,
+            global,
+        };
+        // The memory store never touches the pool.
+        let pool = PgPoolOptions::new()
+            .connect_lazy("postgres://localhost/unused")
+            .unwrap();
+        RateLimiter::new(settings, pool)
+    }
+
+    #[test]
+    fn windows_are_aligned_on_their_length() {
+        // Arrange
+        let now = DateTime::parse_from_rfc3339("2026-10-19T12:34:56Z")
+            .unwrap()
+            .with_timezone(&Utc);
+
+        // Act
+        let (start, end) = window(now, 3600);
+
+        // Assert
+        assert_eq!(start.to_rfc3339(), "2026-10-19T12:00:00+00:00");
+        assert_eq!(end.to_rfc3339(), "2026-10-19T13:00:00+00:00");
+    }
+
+    #[test]
+    fn email_keys_do_not_reveal_the_address() {
+        // Act
+        let key = email_key("ursula@example.com");
+
+        // Assert
+        assert!(key.starts_with("email:"));
+        assert!(!key.contains("ursula"));
+    }
+
+    #[tokio::test]
+    async fn requests_over_the_limit_are_turned_away_per_ip() {
+        // Arrange
+        let limit = Limit {
+            max_requests: 2,
+            window_seconds: 3600,
+        };
+        let limiter = limiter(limit, None);
+        let ip: IpAddr = "203.0.113.7".parse().unwrap();
+        let other_ip: IpAddr = "203.0.113.8".parse().unwrap();
+
+        // Act
+        let mut decisions = Vec::new();
+        for _ in 0..3 {
+            decisions.push(limiter.check_request(ip, "/subscriptions").await);
+        }
+        let other = limiter.check_request(other_ip, "/subscriptions").await;
+
+        // Assert
+        assert_eq!(decisions[..2], [Decision::Allowed, Decision::Allowed]);
+        assert!(matches!(
+            decisions[2],
+            Decision::Limited { retry_after } if retry_after.as_secs() <= 3600
+        ));
+        assert_eq!(other, Decision::Allowed);
+    }
+
+    #[tokio::test]
+    async fn the_global_limit_applies_to_every_client() {
+        // Arrange
+        let per_ip = Limit {
+            max_requests: 10,
+            window_seconds: 60,
+        };
+        let global = Limit {
+            max_requests: 1,
+            window_seconds: 60,
+        };
+        let limiter = limiter(per_ip, Some(global));
+
+        // Act
+        let first = limiter
+            .check_request("203.0.113.7".parse().unwrap(), "/subscriptions")
+            .await;
+        let second = limiter
+            .check_request("203.0.113.8".parse().unwrap(), "/subscriptions")
+            .await;
+
+        // Assert
+        assert_eq!(first, Decision::Allowed);
+        assert_ne!(second, Decision::Allowed);
+    }
+}
@@ -0,0 +1,185 @@
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Mutex;
+
+#[async_trait::async_trait]
+pub trait RateLimitStore: Send + Sync {
+    /// Counts a hit on `key` in the window starting at `window_start`, and
+    /// returns the number of hits that window has seen.
+    async fn hit(
+        &self,
+        key: &str,
+        window_start: DateTime<Utc>,
+        expires_at: DateTime<Utc>,
+    ) -> Result<u32, anyhow::Error>;
+}
+
+/// Counts hits in the memory of this instance.
+#[derive(Default)]
+pub struct MemoryStore {
+    counters: Mutex<Counters>,
+}
+
+/// The counters, also ordered by expiry so that expired ones are dropped
+/// without going through the others.
+#[derive(Default)]
+struct Counters {
+    by_key: HashMap<String, Counter>,
+    by_expiry: BTreeSet<(DateTime<Utc>, String)>,
+}
+
+struct Counter {
+    window_start: DateTime<Utc>,
+    expires_at: DateTime<Utc>,
+    hits: u32,
+}
+
+impl Counters {
+    fn drop_expired(&mut self, now: DateTime<Utc>) {
+        while self
+            .by_expiry
+            .first()
+            .is_some_and(|(expires_at, _)| *expires_at <= now)
+        {
+            if let Some((_, key)) = self.by_expiry.pop_first() {
+                self.by_key.remove(&key);
+            }
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl RateLimitStore for MemoryStore {
+    async fn hit(
+        &self,
+        key: &str,
+        window_start: DateTime<Utc>,
+        expires_at: DateTime<Utc>,
+    ) -> Result<u32, anyhow::Error> {
+        let mut counters = self
+            .counters
+            .lock()
+            .map_err(|_| anyhow::anyhow!("The rate limit counters are poisoned."))?;
+        counters.drop_expired(Utc::now());
+        if let Some(counter) = counters.by_key.get_mut(key) {
+            if counter.window_start == window_start {
+                counter.hits += 1;
+                return Ok(counter.hits);
+            }
+        }
+        let counter = Counter {
+            window_start,
+            expires_at,
+            hits: 1,
+        };
+        if let Some(previous) = counters.by_key.insert(key.to_owned(), counter) {
+            counters
+                .by_expiry
+                .remove(&(previous.expires_at, key.to_owned()));
+        }
+        counters.by_expiry.insert((expires_at, key.to_owned()));
+        Ok(1)
+    }
+}
+
+/// Counts hits in Postgres, so that every instance sees the same counts.
+pub struct PostgresStore {
+    pool: PgPool,
+}
+
+impl PostgresStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl RateLimitStore for PostgresStore {
+    #[tracing::instrument(name = "Count a rate limited hit", skip(self))]
+    async fn hit(
+        &self,
+        key: &str,
+        window_start: DateTime<Utc>,
+        expires_at: DateTime<Utc>,
+    ) -> Result<u32, anyhow::Error> {
+        let hits = sqlx::query!(
+            r#"
+    INSERT INTO rate_limit_counters (key, window_start, expires_at, hits)
+    VALUES ($1, $2, $3, 1)
+    ON CONFLICT (key, window_start) DO UPDATE
+    SET hits = rate_limit_counters.hits + 1
+    RETURNING hits
+            "#,
+            key,
+            window_start,
+            expires_at
+        )
+        .fetch_one(&self.pool)
+        .await
+        .context("Failed to count a hit.")?
+        .hits;
+        // Expired windows are cleared by a small share of the hits, which
+        // keeps the table short without a dedicated worker.
+        if rand::random::<u8>() == 0 {
+            sqlx::query!(r#"DELETE FROM rate_limit_counters WHERE expires_at < now()"#)
+                .execute(&self.pool)
+                .await
+                .context("Failed to clear expired rate limit windows.")?;
+        }
+        Ok(u32::try_from(hits).unwrap_or(u32::MAX))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MemoryStore, RateLimitStore};
+    use chrono::{Duration, Utc};
+
+    #[tokio::test]
+    async fn the_memory_store_drops_expired_counters() {
+        // Arrange
+        let store = MemoryStore::default();
+        let now = Utc::now();
+        let expired = now - Duration::seconds(1);
+        store
+            .hit("expired", expired - Duration::hours(1), expired)
+            .await
+            .unwrap();
+
+        // Act
+        let hits = store
+            .hit("current", now, now + Duration::hours(1))
+            .await
+            .unwrap();
+
+        // Assert
+        assert_eq!(hits, 1);
+        let counters = store.counters.lock().unwrap();
+        assert!(!counters.by_key.contains_key("expired"));
+        assert_eq!(counters.by_key.len(), counters.by_expiry.len());
+    }
+
+    #[tokio::test]
+    async fn the_memory_store_starts_over_in_a_new_window() {
+        // Arrange
+        let store = MemoryStore::default();
+        let now = Utc::now();
+        let next = now + Duration::hours(1);
+        for _ in 0..2 {
+            store.hit("key", now, next).await.unwrap();
+        }
+
+        // Act
+        let hits = store
+            .hit("key", next, next + Duration::hours(1))
+            .await
+            .unwrap();
+
+        // Assert
+        assert_eq!(hits, 1);
+        let counters = store.counters.lock().unwrap();
+        assert_eq!(counters.by_expiry.len(), 1);
+    }
+}
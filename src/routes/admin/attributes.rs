@@ -0,0 +1,98 @@
+use crate::domain::{AttributeDefinition, AttributeType};
+use crate::routes::AppError;
+use actix_web::{web, HttpResponse};
+use anyhow::Context;
+use chrono::Utc;
+use sqlx::{PgExecutor, PgPool};
+use std::fmt::Debug;
+
+#[derive(serde::Deserialize, serde::Serialize)]
+pub struct AttributeData {
+    name: String,
+    #[serde(rename = "type")]
+    attribute_type: String,
+    #[serde(default)]
+    options: Vec<String>,
+}
+
+impl TryFrom<AttributeData> for AttributeDefinition {
+    type Error = String;
+
+    fn try_from(value: AttributeData) -> Result<Self, Self::Error> {
+        let attribute_type = AttributeType::parse(&value.attribute_type, value.options)?;
+        AttributeDefinition::parse(value.name, attribute_type)
+    }
+}
+
+impl From<AttributeDefinition> for AttributeData {
+    fn from(value: AttributeDefinition) -> Self {
+        Self {
+            attribute_type: value.attribute_type.as_str().to_owned(),
+            options: value.attribute_type.options().to_vec(),
+            name: value.name,
+        }
+    }
+}
+
+#[tracing::instrument(name = "Define a subscriber attribute", skip(body, pool))]
+pub async fn create_attribute(
+    body: web::Json<AttributeData>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let definition: AttributeDefinition = body.0.try_into().map_err(AppError::ValidationError)?;
+
+    let inserted = sqlx::query!(
+        r#"
+    INSERT INTO attribute_definitions (name, attribute_type, options, created_at)
+    VALUES ($1, $2, $3, $4)
+    ON CONFLICT (name) DO NOTHING
+            "#,
+        definition.name,
+        definition.attribute_type.as_str(),
+        definition.attribute_type.options(),
+        Utc::now()
+    )
+    .execute(pool.get_ref())
+    .await
+    .context("Failed to store the attribute definition.")?
+    .rows_affected()
+        == 1;
+    if !inserted {
+        return Err(AppError::Conflict(format!(
+            "An attribute called {} already exists.",
+            definition.name
+        )));
+    }
+
+    Ok(HttpResponse::Created().json(AttributeData::from(definition)))
+}
+
+#[tracing::instrument(name = "List subscriber attributes", skip(pool))]
+pub async fn get_attributes(pool: web::Data<PgPool>) -> Result<HttpResponse, AppError> {
+    let definitions: Vec<AttributeData> = get_attribute_definitions(pool.get_ref())
+        .await?
+        .into_iter()
+        .map(AttributeData::from)
+        .collect();
+    Ok(HttpResponse::Ok().json(definitions))
+}
+
+#[tracing::instrument(name = "Get attribute definitions", skip(executor))]
+pub(crate) async fn get_attribute_definitions(
+    executor: impl PgExecutor<'_>,
+) -> Result<Vec<AttributeDefinition>, anyhow::Error> {
+    let rows = sqlx::query!(
+        r#"SELECT name, attribute_type, options FROM attribute_definitions ORDER BY name"#
+    )
+    .fetch_all(executor)
+    .await
+    .context("Failed to retrieve the attribute definitions.")?;
+
+    rows.into_iter()
+        .map(|r| {
+            let attribute_type =
+                AttributeType::parse(&r.attribute_type, r.options).map_err(anyhow::Error::msg)?;
+            AttributeDefinition::parse(r.name, attribute_type).map_err(anyhow::Error::msg)
+        })
+        .collect()
+}
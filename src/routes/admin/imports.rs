@@ -0,0 +1,699 @@
+use crate::csv_records::{spreadsheet_safe, CsvRecord, CsvRecords};
+use crate::domain::{
+    AttributeDefinition, ConsentEvent, FormSource, ImportMode, ImportStatus, ListSlug,
+    NewSubscriber, SubscriberAttributes, SubscriberEmail, SubscriberName, SubscriptionStatus,
+};
+use crate::routes::{
+    erased_email_hash, generate_subscription_token, get_attribute_definitions, get_list_id,
+    unknown_list, AppError,
+};
+use crate::startup::AppState;
+use actix_web::http::header::{ContentDisposition, DispositionParam, DispositionType};
+use actix_web::{web, HttpResponse};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use futures_util::stream::try_unfold;
+use futures_util::StreamExt;
+use sqlx::{Executor, PgExecutor, PgPool};
+use std::collections::{HashMap, HashSet};
+use std::fmt::Debug;
+use uuid::Uuid;
+
+/// Rows are validated as they stream in and written in batches of this size.
+const BATCH_SIZE: usize = 1000;
+const EXPORT_CHUNK_SIZE: i64 = 1000;
+
+#[derive(serde::Deserialize)]
+pub struct ImportParameters {
+    list: Option<String>,
+    mode: String,
+    attestation: Option<String>,
+}
+
+#[derive(serde::Serialize)]
+struct ImportJob {
+    id: Uuid,
+    list: String,
+    mode: String,
+    attestation: Option<String>,
+    status: String,
+    error: Option<String>,
+    rows: RowCounts,
+    created_at: DateTime<Utc>,
+    completed_at: Option<DateTime<Utc>>,
+}
+
+#[derive(serde::Serialize, Default)]
+struct RowCounts {
+    total: i32,
+    imported: i32,
+    duplicates: i32,
+    failed: i32,
+}
+
+/// Where the subscriber fields are found in the uploaded CSV.
+struct Columns {
+    count: usize,
+    email: usize,
+    name: usize,
+    attributes: Vec<(usize, String)>,
+}
+
+impl Columns {
+    /// The header must have `email` and `name` columns. Any other column
+    /// must be a custom subscriber attribute.
+    fn parse(header: Vec<String>, definitions: &[AttributeDefinition]) -> Result<Self, String> {
+        let header: Vec<String> = header.iter().map(|c| c.trim().to_lowercase()).collect();
+        if let Some(duplicate) = header
+            .iter()
+            .enumerate()
+            .find_map(|(i, c)| header[..i].contains(c).then_some(c))
+        {
+            return Err(format!("The {} column appears more than once.", duplicate));
+        }
+        let position = |name: &str| {
+            header
+                .iter()
+                .position(|c| c == name)
+                .ok_or_else(|| format!("The CSV file has no {} column.", name))
+        };
+        let email = position("email")?;
+        let name = position("name")?;
+        let mut attributes = Vec::new();
+        for (i, column) in header.iter().enumerate() {
+            if i == email || i == name {
+                continue;
+            }
+            if !definitions.iter().any(|d| &d.name == column) {
+                return Err(format!("{} is not a known subscriber attribute.", column));
+            }
+            attributes.push((i, column.clone()));
+        }
+        Ok(Self {
+            count: header.len(),
+            email,
+            name,
+            attributes,
+        })
+    }
+
+    fn parse_row(
+        &self,
+        mut fields: Vec<String>,
+        definitions: &[AttributeDefinition],
+    ) -> Result<NewSubscriber, String> {
+        if fields.len() != self.count {
+            return Err(format!(
+                "Expected {} fields, found {}.",
+                self.count,
+                fields.len()
+            ));
+        }
+        let attributes = self
+            .attributes
+            .iter()
+            .map(|(i, name)| (name.clone(), std::mem::take(&mut fields[*i])))
+            .collect();
+        Ok(NewSubscriber {
+            email: SubscriberEmail::parse(std::mem::take(&mut fields[self.email]))?,
+            name: SubscriberName::parse(std::mem::take(&mut fields[self.name]))?,
+            attributes: SubscriberAttributes::parse(attributes, definitions)
+                .map_err(|e| e.to_string())?,
+        })
+    }
+}
+
+struct PendingRow {
+    row_number: i32,
+    subscriber: NewSubscriber,
+}
+
+struct RowError {
+    row_number: i32,
+    email: Option<String>,
+    error: String,
+}
+
+/// The state of an import while its CSV streams in.
+struct Import {
+    id: Uuid,
+    list_id: Uuid,
+    mode: ImportMode,
+    consent_text_version: String,
+    columns: Columns,
+    definitions: Vec<AttributeDefinition>,
+    fold_email_aliases: bool,
+    /// Normalized addresses seen so far, to catch duplicates within the file.
+    seen: HashSet<String>,
+    /// Row 1 is the header.
+    last_row: i32,
+    counts: RowCounts,
+    pending: Vec<PendingRow>,
+    errors: Vec<RowError>,
+}
+
+impl Import {
+    fn add_record(&mut self, record: CsvRecord) {
+        self.last_row += 1;
+        self.counts.total += 1;
+        let row_number = self.last_row;
+        let subscriber = match record.and_then(|f| self.columns.parse_row(f, &self.definitions)) {
+            Ok(subscriber) => subscriber,
+            Err(error) => {
+                self.counts.failed += 1;
+                self.errors.push(RowError {
+                    row_number,
+                    email: None,
+                    error,
+                });
+                return;
+            }
+        };
+        if !self
+            .seen
+            .insert(subscriber.email.normalized(self.fold_email_aliases))
+        {
+            self.counts.duplicates += 1;
+            self.errors.push(RowError {
+                row_number,
+                email: Some(subscriber.email.as_ref().to_owned()),
+                error: "The address appears earlier in the file.".into(),
+            });
+            return;
+        }
+        self.pending.push(PendingRow {
+            row_number,
+            subscriber,
+        });
+    }
+
+    fn is_batch_full(&self) -> bool {
+        self.pending.len() + self.errors.len() >= BATCH_SIZE
+    }
+
+    /// Writes the pending rows and their errors in a single transaction.
+    #[tracing::instrument(name = "Write a batch of imported subscribers", skip_all, fields(import_id = %self.id))]
+    async fn flush(&mut self, pool: &PgPool) -> Result<(), anyhow::Error> {
+        let pending = std::mem::take(&mut self.pending);
+        let mut transaction = pool.begin().await?;
+
+        let normalized_emails: Vec<String> = pending
+            .iter()
+            .map(|r| r.subscriber.email.normalized(self.fold_email_aliases))
+            .collect();
+        let hashes: Vec<String> = pending
+            .iter()
+            .map(|r| erased_email_hash(r.subscriber.email.as_ref(), self.fold_email_aliases))
+            .collect();
+        let erased: HashSet<String> = sqlx::query_scalar!(
+            r#"SELECT email_hash FROM erased_subscribers WHERE email_hash = ANY($1)"#,
+            &hashes
+        )
+        .fetch_all(&mut *transaction)
+        .await?
+        .into_iter()
+        .collect();
+        let existing: HashMap<String, (Uuid, Option<String>)> = sqlx::query!(
+            r#"
+    SELECT subscriptions.normalized_email, subscriptions.id,
+           list_subscriptions.status AS "status?"
+    FROM subscriptions
+    LEFT JOIN list_subscriptions
+        ON list_subscriptions.subscriber_id = subscriptions.id
+        AND list_subscriptions.list_id = $2
+    WHERE subscriptions.normalized_email = ANY($1)
+            "#,
+            &normalized_emails,
+            self.list_id
+        )
+        .fetch_all(&mut *transaction)
+        .await?
+        .into_iter()
+        .map(|r| (r.normalized_email, (r.id, r.status)))
+        .collect();
+
+        let mut members = Vec::new();
+        let mut new_rows = Vec::new();
+        for ((row, normalized_email), hash) in
+            pending.into_iter().zip(normalized_emails).zip(hashes)
+        {
+            if erased.contains(&hash) {
+                // The report must not bring an erased address back.
+                self.counts.failed += 1;
+                self.errors.push(RowError {
+                    row_number: row.row_number,
+                    email: None,
+                    error: "The address was erased at the subscriber's request.".into(),
+                });
+                continue;
+            }
+            match existing.get(&normalized_email) {
+                Some((_, Some(_))) => {
+                    self.counts.duplicates += 1;
+                    self.errors.push(RowError {
+                        row_number: row.row_number,
+                        email: Some(row.subscriber.email.as_ref().to_owned()),
+                        error: "The address is already on the list.".into(),
+                    });
+                }
+                Some((subscriber_id, None)) => members.push(*subscriber_id),
+                None => new_rows.push((Uuid::new_v4(), normalized_email, row)),
+            }
+        }
+
+        let inserted: HashSet<Uuid> = sqlx::query_scalar!(
+            r#"
+    INSERT INTO subscriptions (
+        id, email, normalized_email, name, subscribed_at, attributes, preferences_token
+    )
+    SELECT id, email, normalized_email, name, $7, attributes, preferences_token
+    FROM UNNEST($1::uuid[], $2::text[], $3::text[], $4::text[], $5::jsonb[], $6::text[])
+        AS new (id, email, normalized_email, name, attributes, preferences_token)
+    ON CONFLICT (normalized_email) DO NOTHING
+    RETURNING id
+            "#,
+            &new_rows.iter().map(|(id, _, _)| *id).collect::<Vec<_>>(),
+            &new_rows
+                .iter()
+                .map(|(_, _, r)| r.subscriber.email.as_ref().to_owned())
+                .collect::<Vec<_>>(),
+            &new_rows
+                .iter()
+                .map(|(_, normalized_email, _)| normalized_email.clone())
+                .collect::<Vec<_>>(),
+            &new_rows
+                .iter()
+                .map(|(_, _, r)| r.subscriber.name.as_ref().to_owned())
+                .collect::<Vec<_>>(),
+            &new_rows
+                .iter()
+                .map(|(_, _, r)| r.subscriber.attributes.as_json())
+                .collect::<Vec<_>>(),
+            &new_rows
+                .iter()
+                .map(|_| generate_subscription_token())
+                .collect::<Vec<_>>(),
+            Utc::now()
+        )
+        .fetch_all(&mut *transaction)
+        .await?
+        .into_iter()
+        .collect();
+        for (subscriber_id, _, row) in new_rows {
+            if inserted.contains(&subscriber_id) {
+                members.push(subscriber_id);
+            } else {
+                // Someone subscribed with the same address while we were importing.
+                self.counts.duplicates += 1;
+                self.errors.push(RowError {
+                    row_number: row.row_number,
+                    email: Some(row.subscriber.email.as_ref().to_owned()),
+                    error: "The address is already on the list.".into(),
+                });
+            }
+        }
+
+        let now = Utc::now();
+        let status = match self.mode {
+            ImportMode::Confirmed { .. } => SubscriptionStatus::Confirmed,
+            ImportMode::OptIn => SubscriptionStatus::PendingConfirmation,
+        };
+        let query = sqlx::query!(
+            r#"
+    INSERT INTO list_subscriptions (
+        list_id, subscriber_id, status, unsubscribe_token, subscribed_at, updated_at
+    )
+    SELECT $1, subscriber_id, $3, unsubscribe_token, $5, $5
+    FROM UNNEST($2::uuid[], $4::text[]) AS new (subscriber_id, unsubscribe_token)
+    ON CONFLICT (list_id, subscriber_id) DO NOTHING
+            "#,
+            self.list_id,
+            &members,
+            status.as_str(),
+            &members
+                .iter()
+                .map(|_| generate_subscription_token())
+                .collect::<Vec<_>>(),
+            now
+        );
+        transaction.execute(query).await?;
+        match self.mode {
+            ImportMode::Confirmed { .. } => {
+                let form_source =
+                    FormSource::parse(format!("import-{}", self.id)).map_err(anyhow::Error::msg)?;
+                let query = sqlx::query!(
+                    r#"
+    INSERT INTO consent_records (
+        id, subscriber_id, list_id, event, ip_address, user_agent,
+        form_source, consent_text_version, recorded_at
+    )
+    SELECT gen_random_uuid(), subscriber_id, $2, $3, NULL, NULL, $4, $5, $6
+    FROM UNNEST($1::uuid[]) AS new (subscriber_id)
+            "#,
+                    &members,
+                    self.list_id,
+                    ConsentEvent::Import.as_str(),
+                    form_source.as_ref(),
+                    self.consent_text_version,
+                    now
+                );
+                transaction.execute(query).await?;
+            }
+            ImportMode::OptIn => {
+                let tokens: Vec<String> = members
+                    .iter()
+                    .map(|_| generate_subscription_token())
+                    .collect();
+                let query = sqlx::query!(
+                    r#"
+    INSERT INTO subscription_tokens (subscription_token, subscriber_id, list_id)
+    SELECT subscription_token, subscriber_id, $3
+    FROM UNNEST($1::text[], $2::uuid[]) AS new (subscription_token, subscriber_id)
+            "#,
+                    &tokens,
+                    &members,
+                    self.list_id
+                );
+                transaction.execute(query).await?;
+                let query = sqlx::query!(
+                    r#"
+    INSERT INTO opt_in_email_queue (subscription_token, subscriber_id, enqueued_at)
+    SELECT subscription_token, subscriber_id, $3
+    FROM UNNEST($1::text[], $2::uuid[]) AS new (subscription_token, subscriber_id)
+            "#,
+                    &tokens,
+                    &members,
+                    now
+                );
+                transaction.execute(query).await?;
+            }
+        }
+        self.counts.imported += members.len() as i32;
+
+        let errors = std::mem::take(&mut self.errors);
+        let query = sqlx::query!(
+            r#"
+    INSERT INTO import_row_errors (import_job_id, row_number, email, error)
+    SELECT $1, row_number, NULLIF(email, ''), error
+    FROM UNNEST($2::int4[], $3::text[], $4::text[]) AS new (row_number, email, error)
+            "#,
+            self.id,
+            &errors.iter().map(|e| e.row_number).collect::<Vec<_>>(),
+            &errors
+                .iter()
+                .map(|e| e.email.clone().unwrap_or_default())
+                .collect::<Vec<_>>(),
+            &errors.iter().map(|e| e.error.clone()).collect::<Vec<_>>()
+        );
+        transaction.execute(query).await?;
+        let query = sqlx::query!(
+            r#"
+    UPDATE import_jobs
+    SET total_rows = $2, imported_rows = $3, duplicate_rows = $4, failed_rows = $5
+    WHERE id = $1
+            "#,
+            self.id,
+            self.counts.total,
+            self.counts.imported,
+            self.counts.duplicates,
+            self.counts.failed
+        );
+        transaction.execute(query).await?;
+        transaction.commit().await?;
+        Ok(())
+    }
+}
+
+/// Imports subscribers from a CSV upload into a list.
+///
+/// The body is parsed as it streams in. Rows are validated one by one and
+/// written in batches, so that large files never sit in memory. Rows that
+/// cannot be imported are recorded in the import's error report.
+#[tracing::instrument(name = "Import subscribers", skip(query, body, pool, state))]
+pub async fn import_subscribers(
+    query: web::Query<ImportParameters>,
+    mut body: web::Payload,
+    pool: web::Data<PgPool>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let settings = &state.subscriptions;
+    let ImportParameters {
+        list,
+        mode,
+        attestation,
+    } = query.into_inner();
+    let list = list
+        .map(ListSlug::parse)
+        .transpose()
+        .map_err(AppError::ValidationError)?
+        .unwrap_or_default();
+    let mode = ImportMode::parse(&mode, attestation).map_err(AppError::ValidationError)?;
+    let list_id = get_list_id(pool.get_ref(), &list)
+        .await
+        .context("Failed to retrieve the mailing list.")?
+        .ok_or_else(|| AppError::ValidationError(unknown_list(&list)))?;
+    let definitions = get_attribute_definitions(pool.get_ref()).await?;
+
+    let mut reader = CsvRecords::default();
+    let mut records = Vec::new();
+    while records.is_empty() {
+        records = read_records(&mut body, &mut reader)
+            .await
+            .map_err(AppError::ValidationError)?
+            .ok_or_else(|| AppError::ValidationError("The CSV file is empty.".into()))?;
+    }
+    let mut records = records.into_iter();
+    let header = records
+        .next()
+        .expect("At least one record was read")
+        .map_err(AppError::ValidationError)?;
+    let columns = Columns::parse(header, &definitions).map_err(AppError::ValidationError)?;
+
+    let mut import = Import {
+        id: Uuid::new_v4(),
+        list_id,
+        mode,
+        consent_text_version: settings.consent_text_version.clone(),
+        columns,
+        definitions,
+        fold_email_aliases: settings.fold_email_aliases,
+        seen: HashSet::new(),
+        last_row: 1,
+        counts: RowCounts::default(),
+        pending: Vec::new(),
+        errors: Vec::new(),
+    };
+    insert_import_job(pool.get_ref(), &import)
+        .await
+        .context("Failed to store the import job.")?;
+
+    let outcome = loop {
+        for record in records {
+            import.add_record(record);
+            if import.is_batch_full() {
+                import.flush(&pool).await?;
+            }
+        }
+        match read_records(&mut body, &mut reader).await {
+            Ok(Some(next)) => records = next.into_iter(),
+            Ok(None) => break Ok(()),
+            Err(e) => break Err(e),
+        }
+    };
+    import.flush(&pool).await?;
+    let (status, error) = match outcome {
+        Ok(()) => (ImportStatus::Completed, None),
+        Err(e) => {
+            tracing::warn!(error = %e, "The import was interrupted");
+            (ImportStatus::Failed, Some(e))
+        }
+    };
+    sqlx::query!(
+        r#"
+    UPDATE import_jobs SET status = $2, error = $3, completed_at = $4
+    WHERE id = $1
+            "#,
+        import.id,
+        status.as_str(),
+        error,
+        Utc::now()
+    )
+    .execute(pool.get_ref())
+    .await
+    .context("Failed to complete the import job.")?;
+
+    let job = get_import_job(pool.get_ref(), import.id)
+        .await
+        .context("Failed to retrieve the import job.")?
+        .ok_or_else(import_not_found)?;
+    Ok(HttpResponse::Created().json(job))
+}
+
+/// Reads the next chunk of the upload. Returns `None` once the body is over.
+async fn read_records(
+    body: &mut web::Payload,
+    reader: &mut CsvRecords,
+) -> Result<Option<Vec<CsvRecord>>, String> {
+    match body.next().await {
+        Some(chunk) => {
+            let chunk = chunk.map_err(|e| format!("Failed to read the upload: {}", e))?;
+            reader.feed(&chunk).map(Some)
+        }
+        None => {
+            let records = reader.finish()?;
+            Ok((!records.is_empty()).then_some(records))
+        }
+    }
+}
+
+#[tracing::instrument(name = "Get an import job", skip(pool))]
+pub async fn get_import(
+    import_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let job = get_import_job(pool.get_ref(), import_id.into_inner())
+        .await
+        .context("Failed to retrieve the import job.")?
+        .ok_or_else(import_not_found)?;
+    Ok(HttpResponse::Ok().json(job))
+}
+
+struct ExportState {
+    pool: PgPool,
+    import_id: Uuid,
+    after: i32,
+    header_written: bool,
+}
+
+/// Streams the rows that were not imported as CSV, in chunks.
+#[tracing::instrument(name = "Export an import's error report", skip(pool))]
+pub async fn get_import_errors(
+    import_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let import_id = import_id.into_inner();
+    get_import_job(pool.get_ref(), import_id)
+        .await
+        .context("Failed to retrieve the import job.")?
+        .ok_or_else(import_not_found)?;
+
+    let state = ExportState {
+        pool: pool.get_ref().clone(),
+        import_id,
+        after: 0,
+        header_written: false,
+    };
+    let stream = try_unfold(state, |mut state| async move {
+        let rows = sqlx::query!(
+            r#"
+    SELECT row_number, email, error
+    FROM import_row_errors
+    WHERE import_job_id = $1 AND row_number > $2
+    ORDER BY row_number
+    LIMIT $3
+            "#,
+            state.import_id,
+            state.after,
+            EXPORT_CHUNK_SIZE
+        )
+        .fetch_all(&state.pool)
+        .await
+        .context("Failed to retrieve the import errors.")?;
+
+        if rows.is_empty() && state.header_written {
+            return Ok::<_, anyhow::Error>(None);
+        }
+
+        let mut writer = csv::WriterBuilder::new()
+            .has_headers(false)
+            .from_writer(vec![]);
+        if !state.header_written {
+            writer.write_record(["row", "email", "error"])?;
+            state.header_written = true;
+        }
+        for row in &rows {
+            writer.write_record([
+                row.row_number.to_string().as_str(),
+                &spreadsheet_safe(row.email.as_deref().unwrap_or_default()),
+                &spreadsheet_safe(&row.error),
+            ])?;
+        }
+        state.after = rows.last().map_or(state.after, |r| r.row_number);
+        let chunk = writer.into_inner().context("Failed to write CSV rows.")?;
+        Ok(Some((web::Bytes::from(chunk), state)))
+    });
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/csv; charset=utf-8")
+        .insert_header(ContentDisposition {
+            disposition: DispositionType::Attachment,
+            parameters: vec![DispositionParam::Filename(format!(
+                "import-{}-errors.csv",
+                import_id
+            ))],
+        })
+        .streaming(stream))
+}
+
+#[tracing::instrument(name = "Store an import job", skip_all, fields(import_id = %import.id))]
+async fn insert_import_job(
+    executor: impl PgExecutor<'_>,
+    import: &Import,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+    INSERT INTO import_jobs (id, list_id, mode, attestation, status, created_at)
+    VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+        import.id,
+        import.list_id,
+        import.mode.as_str(),
+        import.mode.attestation(),
+        ImportStatus::Processing.as_str(),
+        Utc::now()
+    )
+    .execute(executor)
+    .await?;
+    Ok(())
+}
+
+async fn get_import_job(
+    executor: impl PgExecutor<'_>,
+    import_id: Uuid,
+) -> Result<Option<ImportJob>, sqlx::Error> {
+    let job = sqlx::query!(
+        r#"
+    SELECT import_jobs.id, lists.slug, import_jobs.mode, import_jobs.attestation,
+           import_jobs.status, import_jobs.error, import_jobs.total_rows,
+           import_jobs.imported_rows, import_jobs.duplicate_rows, import_jobs.failed_rows,
+           import_jobs.created_at, import_jobs.completed_at
+    FROM import_jobs
+    JOIN lists ON lists.id = import_jobs.list_id
+    WHERE import_jobs.id = $1
+            "#,
+        import_id
+    )
+    .fetch_optional(executor)
+    .await?
+    .map(|r| ImportJob {
+        id: r.id,
+        list: r.slug,
+        mode: r.mode,
+        attestation: r.attestation,
+        status: r.status,
+        error: r.error,
+        rows: RowCounts {
+            total: r.total_rows,
+            imported: r.imported_rows,
+            duplicates: r.duplicate_rows,
+            failed: r.failed_rows,
+        },
+        created_at: r.created_at,
+        completed_at: r.completed_at,
+    });
+    Ok(job)
+}
+
+fn import_not_found() -> AppError {
+    AppError::NotFound("There is no import with the given id.".into())
+}
@@ -0,0 +1,437 @@
+use crate::csv_records::spreadsheet_safe;
+use crate::domain::{AbTestMetric, AbTestResult, DeliveryStatus, IssueStatus};
+use crate::routes::{issue_not_found, AppError};
+use actix_web::http::header::{ContentDisposition, DispositionParam, DispositionType};
+use actix_web::{web, HttpResponse};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use futures_util::stream::try_unfold;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+const DEFAULT_PAGE_SIZE: i64 = 50;
+const MAX_PAGE_SIZE: i64 = 500;
+const EXPORT_CHUNK_SIZE: i64 = 1000;
+
+#[derive(serde::Serialize)]
+struct IssueReport {
+    id: Uuid,
+    title: String,
+    status: &'static str,
+    slug: Option<String>,
+    deliveries: DeliveryCounts,
+    engagement: Engagement,
+    ab_test: Option<AbTestReport>,
+    timeline: Vec<TimelineEvent>,
+}
+
+#[derive(serde::Serialize, Default)]
+struct DeliveryCounts {
+    total: i64,
+    queued: i64,
+    sent: i64,
+    failed: i64,
+    skipped: i64,
+    bounced: i64,
+}
+
+#[derive(serde::Serialize)]
+struct Engagement {
+    opened: i64,
+    clicked: i64,
+}
+
+#[derive(serde::Serialize)]
+struct AbTestReport {
+    metric: &'static str,
+    sample_percent: i32,
+    wait_minutes: i32,
+    decide_at: Option<DateTime<Utc>>,
+    winner: Option<String>,
+    decided_at: Option<DateTime<Utc>>,
+    variants: Vec<AbTestVariantReport>,
+}
+
+#[derive(serde::Serialize)]
+struct AbTestVariantReport {
+    label: String,
+    title: String,
+    recipients: i64,
+    sent: i64,
+    opened: i64,
+    clicked: i64,
+    open_rate: f64,
+    click_rate: f64,
+}
+
+#[derive(serde::Serialize)]
+struct TimelineEvent {
+    event: &'static str,
+    at: DateTime<Utc>,
+}
+
+#[derive(serde::Serialize)]
+struct DeliveryPage {
+    page: i64,
+    per_page: i64,
+    total: i64,
+    items: Vec<Delivery>,
+}
+
+#[derive(serde::Serialize)]
+struct Delivery {
+    email: String,
+    status: String,
+    error: Option<String>,
+    queued_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+}
+
+#[derive(serde::Deserialize)]
+pub struct DeliveriesQuery {
+    page: Option<i64>,
+    per_page: Option<i64>,
+    status: Option<String>,
+    format: Option<String>,
+}
+
+#[tracing::instrument(name = "Get a newsletter issue delivery report", skip(pool))]
+pub async fn get_issue_report(
+    issue_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let issue_id = issue_id.into_inner();
+    let issue = sqlx::query!(
+        r#"
+    SELECT title, status, slug, created_at, scheduled_for, published_at, sent_at
+    FROM newsletter_issues
+    WHERE id = $1
+            "#,
+        issue_id
+    )
+    .fetch_optional(pool.get_ref())
+    .await
+    .context("Failed to retrieve the newsletter issue.")?
+    .ok_or_else(issue_not_found)?;
+    let status = IssueStatus::try_from(issue.status).map_err(anyhow::Error::msg)?;
+
+    let mut deliveries = DeliveryCounts::default();
+    let counts = sqlx::query!(
+        r#"
+    SELECT status, COUNT(*) as "count!"
+    FROM issue_deliveries
+    WHERE newsletter_issue_id = $1
+    GROUP BY status
+            "#,
+        issue_id
+    )
+    .fetch_all(pool.get_ref())
+    .await
+    .context("Failed to count the issue deliveries.")?;
+    for row in counts {
+        let count = row.count;
+        deliveries.total += count;
+        match DeliveryStatus::try_from(row.status).map_err(anyhow::Error::msg)? {
+            DeliveryStatus::Queued => deliveries.queued += count,
+            DeliveryStatus::Sent => deliveries.sent += count,
+            DeliveryStatus::Failed => deliveries.failed += count,
+            DeliveryStatus::Skipped => deliveries.skipped += count,
+            DeliveryStatus::Bounced => deliveries.bounced += count,
+        }
+    }
+
+    let engagement = sqlx::query_as!(
+        Engagement,
+        r#"
+    SELECT COUNT(opened_at) as "opened!", COUNT(clicked_at) as "clicked!"
+    FROM issue_deliveries
+    WHERE newsletter_issue_id = $1
+            "#,
+        issue_id
+    )
+    .fetch_one(pool.get_ref())
+    .await
+    .context("Failed to count the issue opens and clicks.")?;
+    let ab_test = get_ab_test_report(pool.get_ref(), issue_id)
+        .await
+        .context("Failed to retrieve the issue's A/B test results.")?;
+
+    let attempts = sqlx::query!(
+        r#"
+    SELECT MIN(updated_at) as first_attempt, MAX(updated_at) as last_attempt
+    FROM issue_deliveries
+    WHERE newsletter_issue_id = $1 AND status <> $2
+            "#,
+        issue_id,
+        DeliveryStatus::Queued.as_str()
+    )
+    .fetch_one(pool.get_ref())
+    .await
+    .context("Failed to retrieve the issue delivery times.")?;
+
+    let mut timeline: Vec<TimelineEvent> = [
+        ("created", Some(issue.created_at)),
+        ("scheduled_for", issue.scheduled_for),
+        ("published", issue.published_at),
+        ("first_delivery_attempt", attempts.first_attempt),
+        ("last_delivery_attempt", attempts.last_attempt),
+        (
+            "ab_test_decided",
+            ab_test.as_ref().and_then(|test| test.decided_at),
+        ),
+        ("sent", issue.sent_at),
+    ]
+    .into_iter()
+    .filter_map(|(event, at)| at.map(|at| TimelineEvent { event, at }))
+    .collect();
+    timeline.sort_by_key(|e| e.at);
+
+    Ok(HttpResponse::Ok().json(IssueReport {
+        id: issue_id,
+        title: issue.title,
+        status: status.as_str(),
+        slug: issue.slug,
+        deliveries,
+        engagement,
+        ab_test,
+        timeline,
+    }))
+}
+
+/// The results of each variant of the issue's A/B test, counting only the
+/// recipients of the sample, not those who got the winner afterwards.
+async fn get_ab_test_report(
+    pool: &PgPool,
+    issue_id: Uuid,
+) -> Result<Option<AbTestReport>, anyhow::Error> {
+    let Some(test) = sqlx::query!(
+        r#"
+    SELECT sample_percent, wait_minutes, metric, decide_at, winner, decided_at
+    FROM ab_tests
+    WHERE newsletter_issue_id = $1
+            "#,
+        issue_id
+    )
+    .fetch_optional(pool)
+    .await?
+    else {
+        return Ok(None);
+    };
+    let metric = AbTestMetric::try_from(test.metric).map_err(anyhow::Error::msg)?;
+
+    let rows = sqlx::query!(
+        r#"
+    SELECT ab_test_variants.label,
+           ab_test_variants.title,
+           COUNT(issue_deliveries.subscriber_email) as "recipients!",
+           COUNT(issue_deliveries.subscriber_email)
+             FILTER (WHERE issue_deliveries.status = $2) as "sent!",
+           COUNT(issue_deliveries.opened_at) as "opened!",
+           COUNT(issue_deliveries.clicked_at) as "clicked!"
+    FROM ab_test_variants
+    LEFT JOIN issue_deliveries
+      ON issue_deliveries.newsletter_issue_id = ab_test_variants.newsletter_issue_id
+     AND issue_deliveries.ab_variant = ab_test_variants.label
+    WHERE ab_test_variants.newsletter_issue_id = $1
+    GROUP BY ab_test_variants.label, ab_test_variants.title
+    ORDER BY ab_test_variants.label
+            "#,
+        issue_id,
+        DeliveryStatus::Sent.as_str()
+    )
+    .fetch_all(pool)
+    .await?;
+    let variants = rows
+        .into_iter()
+        .map(|row| {
+            let result = AbTestResult {
+                label: row.label,
+                sent: row.sent,
+                opened: row.opened,
+                clicked: row.clicked,
+            };
+            AbTestVariantReport {
+                open_rate: result.rate(AbTestMetric::OpenRate),
+                click_rate: result.rate(AbTestMetric::ClickRate),
+                label: result.label,
+                title: row.title,
+                recipients: row.recipients,
+                sent: result.sent,
+                opened: result.opened,
+                clicked: result.clicked,
+            }
+        })
+        .collect();
+
+    Ok(Some(AbTestReport {
+        metric: metric.as_str(),
+        sample_percent: test.sample_percent,
+        wait_minutes: test.wait_minutes,
+        decide_at: test.decide_at,
+        winner: test.winner,
+        decided_at: test.decided_at,
+        variants,
+    }))
+}
+
+/// Lists the delivery status of every recipient of an issue, one page at a
+/// time, or as a streamed CSV export when `format=csv`.
+#[tracing::instrument(name = "List newsletter issue deliveries", skip(query, pool))]
+pub async fn get_issue_deliveries(
+    issue_id: web::Path<Uuid>,
+    query: web::Query<DeliveriesQuery>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let issue_id = issue_id.into_inner();
+    let status = query
+        .status
+        .clone()
+        .map(DeliveryStatus::try_from)
+        .transpose()
+        .map_err(AppError::ValidationError)?
+        .map(|s| s.as_str().to_owned());
+
+    let exists = sqlx::query!(
+        r#"SELECT id FROM newsletter_issues WHERE id = $1"#,
+        issue_id
+    )
+    .fetch_optional(pool.get_ref())
+    .await
+    .context("Failed to retrieve the newsletter issue.")?
+    .is_some();
+    if !exists {
+        return Err(issue_not_found());
+    }
+
+    match query.format.as_deref() {
+        None | Some("json") => {}
+        Some("csv") => return Ok(export_deliveries(pool.get_ref().clone(), issue_id, status)),
+        Some(other) => {
+            return Err(AppError::ValidationError(format!(
+                "{} is not a supported format. Use either `json` or `csv`.",
+                other
+            )))
+        }
+    }
+
+    let page = query.page.unwrap_or(1).max(1);
+    let per_page = query
+        .per_page
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+
+    let total = sqlx::query!(
+        r#"
+    SELECT COUNT(*) as "count!"
+    FROM issue_deliveries
+    WHERE newsletter_issue_id = $1 AND ($2::text IS NULL OR status = $2)
+            "#,
+        issue_id,
+        status
+    )
+    .fetch_one(pool.get_ref())
+    .await
+    .context("Failed to count the issue deliveries.")?
+    .count;
+
+    let items = sqlx::query_as!(
+        Delivery,
+        r#"
+    SELECT subscriber_email as email, status, error, queued_at, updated_at
+    FROM issue_deliveries
+    WHERE newsletter_issue_id = $1 AND ($2::text IS NULL OR status = $2)
+    ORDER BY subscriber_email
+    LIMIT $3 OFFSET $4
+            "#,
+        issue_id,
+        status,
+        per_page,
+        (page - 1) * per_page
+    )
+    .fetch_all(pool.get_ref())
+    .await
+    .context("Failed to retrieve the issue deliveries.")?;
+
+    Ok(HttpResponse::Ok().json(DeliveryPage {
+        page,
+        per_page,
+        total,
+        items,
+    }))
+}
+
+struct ExportState {
+    pool: PgPool,
+    issue_id: Uuid,
+    status: Option<String>,
+    after: Option<String>,
+    header_written: bool,
+}
+
+/// Streams the deliveries as CSV, reading them in keyset-paginated chunks so
+/// large issues are never loaded into memory at once.
+fn export_deliveries(pool: PgPool, issue_id: Uuid, status: Option<String>) -> HttpResponse {
+    let state = ExportState {
+        pool,
+        issue_id,
+        status,
+        after: None,
+        header_written: false,
+    };
+    let stream = try_unfold(state, |mut state| async move {
+        let rows = sqlx::query_as!(
+            Delivery,
+            r#"
+    SELECT subscriber_email as email, status, error, queued_at, updated_at
+    FROM issue_deliveries
+    WHERE newsletter_issue_id = $1
+      AND ($2::text IS NULL OR status = $2)
+      AND ($3::text IS NULL OR subscriber_email > $3)
+    ORDER BY subscriber_email
+    LIMIT $4
+            "#,
+            state.issue_id,
+            state.status,
+            state.after,
+            EXPORT_CHUNK_SIZE
+        )
+        .fetch_all(&state.pool)
+        .await
+        .context("Failed to retrieve the issue deliveries.")?;
+
+        if rows.is_empty() && state.header_written {
+            return Ok::<_, anyhow::Error>(None);
+        }
+
+        let mut writer = csv::WriterBuilder::new()
+            .has_headers(false)
+            .from_writer(vec![]);
+        if !state.header_written {
+            writer.write_record(["email", "status", "error", "queued_at", "updated_at"])?;
+            state.header_written = true;
+        }
+        for row in &rows {
+            writer.write_record([
+                &spreadsheet_safe(&row.email),
+                row.status.as_str(),
+                &spreadsheet_safe(row.error.as_deref().unwrap_or_default()),
+                &row.queued_at.to_rfc3339(),
+                &row.updated_at.to_rfc3339(),
+            ])?;
+        }
+        state.after = rows.last().map(|r| r.email.clone()).or(state.after);
+        let chunk = writer.into_inner().context("Failed to write CSV rows.")?;
+        Ok(Some((web::Bytes::from(chunk), state)))
+    });
+
+    HttpResponse::Ok()
+        .content_type("text/csv; charset=utf-8")
+        .insert_header(ContentDisposition {
+            disposition: DispositionType::Attachment,
+            parameters: vec![DispositionParam::Filename(format!(
+                "issue-{}-deliveries.csv",
+                issue_id
+            ))],
+        })
+        .streaming(stream)
+}
@@ -0,0 +1,425 @@
+use crate::domain::{IssueSchedule, IssueStatus, NewsletterIssue, SubscriberEmail};
+use crate::email::email_client::SendEmailRequest;
+use crate::email::merge_tags::MergeTags;
+use crate::routes::{
+    get_list_id, insert_issue_variants, insert_newsletter_issue, start_issue_delivery,
+    unknown_list, AppError, BodyData,
+};
+use crate::startup::AppState;
+use actix_web::http::header::ContentType;
+use actix_web::{web, HttpResponse};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sqlx::{PgExecutor, PgPool};
+use std::fmt::Debug;
+use uuid::Uuid;
+
+#[derive(serde::Serialize)]
+struct CreatedIssue {
+    id: Uuid,
+}
+
+#[derive(serde::Deserialize)]
+pub struct TestSendData {
+    recipients: Vec<String>,
+}
+
+#[derive(serde::Deserialize)]
+pub struct ScheduleData {
+    scheduled_for: String,
+    timezone: Option<String>,
+}
+
+impl ScheduleData {
+    fn parse(&self) -> Result<IssueSchedule, AppError> {
+        IssueSchedule::parse(&self.scheduled_for, self.timezone.as_deref(), Utc::now())
+            .map_err(AppError::ValidationError)
+    }
+}
+
+#[tracing::instrument(name = "Create a newsletter issue draft", skip(body, pool))]
+pub async fn create_issue(
+    body: web::Json<BodyData>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let list = body
+        .list()
+        .map_err(AppError::ValidationError)?
+        .unwrap_or_default();
+    let segment = body.segment().map_err(AppError::ValidationError)?;
+    let issue: NewsletterIssue = body.0.try_into().map_err(AppError::ValidationError)?;
+    let list_id = get_list_id(pool.get_ref(), &list)
+        .await
+        .context("Failed to retrieve the mailing list.")?
+        .ok_or_else(|| AppError::ValidationError(unknown_list(&list)))?;
+
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool.")?;
+    let id = insert_newsletter_issue(
+        &mut transaction,
+        &issue,
+        list_id,
+        segment.as_deref(),
+        IssueStatus::Draft,
+    )
+    .await
+    .context("Failed to store the newsletter issue draft.")?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to store a newsletter issue draft.")?;
+
+    Ok(HttpResponse::Created().json(CreatedIssue { id }))
+}
+
+#[tracing::instrument(name = "Edit a newsletter issue draft", skip(body, pool))]
+pub async fn update_issue(
+    issue_id: web::Path<Uuid>,
+    body: web::Json<BodyData>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let issue_id = issue_id.into_inner();
+    let list = body.list().map_err(AppError::ValidationError)?;
+    let segment = body.segment().map_err(AppError::ValidationError)?;
+    let issue: NewsletterIssue = body.0.try_into().map_err(AppError::ValidationError)?;
+    let list_id = match list {
+        Some(list) => Some(
+            get_list_id(pool.get_ref(), &list)
+                .await
+                .context("Failed to retrieve the mailing list.")?
+                .ok_or_else(|| AppError::ValidationError(unknown_list(&list)))?,
+        ),
+        None => None,
+    };
+
+    let updated = update_draft(&pool, issue_id, &issue, list_id, segment.as_deref())
+        .await
+        .context("Failed to update the newsletter issue draft.")?;
+    if !updated {
+        return Err(invalid_transition(&pool, issue_id, "edited").await);
+    }
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+#[tracing::instrument(name = "Preview a newsletter issue", skip(pool))]
+pub async fn preview_issue(
+    issue_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let issue = get_issue(&pool, issue_id.into_inner())
+        .await
+        .context("Failed to retrieve the newsletter issue.")?
+        .ok_or_else(issue_not_found)?;
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Preview: {}</title>
+</head>
+<body>
+{}
+</body>
+</html>"#,
+            htmlescape::encode_minimal(&issue.title),
+            issue.html_content
+        )))
+}
+
+#[tracing::instrument(
+    name = "Send a test copy of a newsletter issue",
+    skip(body, pool, state)
+)]
+pub async fn send_test_issue(
+    issue_id: web::Path<Uuid>,
+    body: web::Json<TestSendData>,
+    pool: web::Data<PgPool>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    if body.recipients.is_empty() {
+        return Err(AppError::ValidationError(
+            "At least one test recipient is required.".into(),
+        ));
+    }
+    let recipients = body
+        .0
+        .recipients
+        .into_iter()
+        .map(SubscriberEmail::parse)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(AppError::ValidationError)?;
+    let allowed = &state.admin.test_recipients;
+    if let Some(recipient) = recipients.iter().find(|recipient| {
+        !allowed
+            .iter()
+            .any(|address| address.trim().eq_ignore_ascii_case(recipient.as_ref()))
+    }) {
+        return Err(AppError::ValidationError(format!(
+            "{} is not a configured test recipient.",
+            recipient
+        )));
+    }
+
+    let issue = get_issue(&pool, issue_id.into_inner())
+        .await
+        .context("Failed to retrieve the newsletter issue.")?
+        .ok_or_else(issue_not_found)?;
+
+    let subject = format!("[TEST] {}", issue.title);
+    for recipient in &recipients {
+        let merge_tags = MergeTags::new().with("email", recipient.as_ref());
+        let send_email_request = SendEmailRequest {
+            to: recipient,
+            subject: &subject,
+            html_content: &merge_tags.render_html(&issue.html_content),
+            text_content: &merge_tags.render_text(&issue.text_content),
+            attachments: &[],
+        };
+        state
+            .email_service
+            .send_email(state.email_client.as_ref(), send_email_request)
+            .await
+            .with_context(|| format!("Failed to send a test issue to {}", recipient))?;
+    }
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+#[tracing::instrument(name = "Publish a newsletter issue draft", skip(pool))]
+pub async fn publish_issue(
+    issue_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let issue_id = issue_id.into_inner();
+
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool.")?;
+    let transitioned = transition_issue(
+        &mut *transaction,
+        issue_id,
+        IssueStatus::Draft,
+        IssueStatus::Sending,
+        None,
+    )
+    .await
+    .context("Failed to update the newsletter issue status.")?;
+    if !transitioned {
+        return Err(invalid_transition(&pool, issue_id, "published").await);
+    }
+    start_issue_delivery(&mut transaction, issue_id)
+        .await
+        .context("Failed to start the newsletter issue delivery.")?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to publish a newsletter issue.")?;
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+#[tracing::instrument(name = "Schedule a newsletter issue draft", skip(body, pool))]
+pub async fn schedule_issue(
+    issue_id: web::Path<Uuid>,
+    body: web::Json<ScheduleData>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let issue_id = issue_id.into_inner();
+    let schedule = body.parse()?;
+
+    let transitioned = transition_issue(
+        pool.get_ref(),
+        issue_id,
+        IssueStatus::Draft,
+        IssueStatus::Scheduled,
+        Some(schedule.inner()),
+    )
+    .await
+    .context("Failed to schedule the newsletter issue.")?;
+    if !transitioned {
+        return Err(invalid_transition(&pool, issue_id, "scheduled").await);
+    }
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+#[tracing::instrument(name = "Reschedule a newsletter issue", skip(body, pool))]
+pub async fn reschedule_issue(
+    issue_id: web::Path<Uuid>,
+    body: web::Json<ScheduleData>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let issue_id = issue_id.into_inner();
+    let schedule = body.parse()?;
+
+    let transitioned = transition_issue(
+        pool.get_ref(),
+        issue_id,
+        IssueStatus::Scheduled,
+        IssueStatus::Scheduled,
+        Some(schedule.inner()),
+    )
+    .await
+    .context("Failed to reschedule the newsletter issue.")?;
+    if !transitioned {
+        return Err(invalid_transition(&pool, issue_id, "rescheduled").await);
+    }
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+#[tracing::instrument(name = "Cancel a scheduled newsletter issue", skip(pool))]
+pub async fn cancel_scheduled_issue(
+    issue_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let issue_id = issue_id.into_inner();
+
+    let transitioned = transition_issue(
+        pool.get_ref(),
+        issue_id,
+        IssueStatus::Scheduled,
+        IssueStatus::Draft,
+        None,
+    )
+    .await
+    .context("Failed to cancel the scheduled newsletter issue.")?;
+    if !transitioned {
+        return Err(invalid_transition(&pool, issue_id, "cancelled").await);
+    }
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+async fn invalid_transition(pool: &PgPool, issue_id: Uuid, action: &str) -> AppError {
+    match get_issue(pool, issue_id).await {
+        Ok(Some(issue)) => AppError::Conflict(format!(
+            "The issue is {} and cannot be {}.",
+            issue.status.as_str(),
+            action
+        )),
+        Ok(None) => issue_not_found(),
+        Err(e) => AppError::UnexpectedError(e.context("Failed to retrieve the newsletter issue.")),
+    }
+}
+
+struct StoredIssue {
+    title: String,
+    html_content: String,
+    text_content: String,
+    status: IssueStatus,
+}
+
+#[tracing::instrument(name = "Get newsletter issue", skip(pool))]
+async fn get_issue(pool: &PgPool, issue_id: Uuid) -> Result<Option<StoredIssue>, anyhow::Error> {
+    let row = sqlx::query!(
+        r#"
+    SELECT title, html_content, text_content, status
+    FROM newsletter_issues
+    WHERE id = $1
+            "#,
+        issue_id
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    row.map(|r| {
+        Ok(StoredIssue {
+            title: r.title,
+            html_content: r.html_content,
+            text_content: r.text_content,
+            status: IssueStatus::try_from(r.status).map_err(anyhow::Error::msg)?,
+        })
+    })
+    .transpose()
+}
+
+/// Drafts keep their mailing list unless a new one is given, while the
+/// segment, the variants and the A/B test are replaced like the rest of the
+/// content.
+#[tracing::instrument(name = "Update newsletter issue draft", skip(pool, issue))]
+async fn update_draft(
+    pool: &PgPool,
+    issue_id: Uuid,
+    issue: &NewsletterIssue,
+    list_id: Option<Uuid>,
+    segment: Option<&str>,
+) -> Result<bool, sqlx::Error> {
+    let mut transaction = pool.begin().await?;
+    let result = sqlx::query!(
+        r#"
+    UPDATE newsletter_issues
+    SET title = $2, text_content = $3, html_content = $4, updated_at = $5,
+        list_id = COALESCE($7, list_id), segment = $8
+    WHERE id = $1 AND status = $6
+            "#,
+        issue_id,
+        issue.title,
+        issue.text_content,
+        issue.html_content,
+        Utc::now(),
+        IssueStatus::Draft.as_str(),
+        list_id,
+        segment
+    )
+    .execute(&mut *transaction)
+    .await?;
+    if result.rows_affected() != 1 {
+        return Ok(false);
+    }
+    for query in [
+        sqlx::query!(
+            r#"DELETE FROM newsletter_issue_variants WHERE newsletter_issue_id = $1"#,
+            issue_id
+        ),
+        sqlx::query!(
+            r#"DELETE FROM ab_test_variants WHERE newsletter_issue_id = $1"#,
+            issue_id
+        ),
+        sqlx::query!(
+            r#"DELETE FROM ab_tests WHERE newsletter_issue_id = $1"#,
+            issue_id
+        ),
+    ] {
+        query.execute(&mut *transaction).await?;
+    }
+    insert_issue_variants(&mut transaction, issue_id, issue).await?;
+    transaction.commit().await?;
+    Ok(true)
+}
+
+#[tracing::instrument(name = "Transition newsletter issue status", skip(executor))]
+async fn transition_issue(
+    executor: impl PgExecutor<'_>,
+    issue_id: Uuid,
+    from: IssueStatus,
+    to: IssueStatus,
+    scheduled_for: Option<DateTime<Utc>>,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+    UPDATE newsletter_issues
+    SET status = $2, scheduled_for = $3, updated_at = $4
+    WHERE id = $1 AND status = $5
+            "#,
+        issue_id,
+        to.as_str(),
+        scheduled_for,
+        Utc::now(),
+        from.as_str()
+    )
+    .execute(executor)
+    .await?;
+    Ok(result.rows_affected() == 1)
+}
+
+pub(crate) fn issue_not_found() -> AppError {
+    AppError::NotFound("There is no newsletter issue with the given id.".into())
+}
@@ -0,0 +1,109 @@
+use crate::domain::{ListSlug, SubscriptionStatus};
+use crate::routes::AppError;
+use actix_web::{web, HttpResponse};
+use anyhow::Context;
+use chrono::Utc;
+use sqlx::{PgExecutor, PgPool};
+use std::fmt::Debug;
+use uuid::Uuid;
+
+#[derive(serde::Deserialize)]
+pub struct ListData {
+    slug: String,
+    name: String,
+}
+
+#[derive(serde::Serialize)]
+struct CreatedList {
+    id: Uuid,
+}
+
+#[derive(serde::Serialize)]
+struct ListSummary {
+    id: Uuid,
+    slug: String,
+    name: String,
+    confirmed_subscribers: i64,
+}
+
+#[tracing::instrument(name = "Create a mailing list", skip(body, pool))]
+pub async fn create_list(
+    body: web::Json<ListData>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let ListData { slug, name } = body.0;
+    let slug = ListSlug::parse(slug).map_err(AppError::ValidationError)?;
+    if name.trim().is_empty() {
+        return Err(AppError::ValidationError(
+            "The list name cannot be empty.".into(),
+        ));
+    }
+
+    let id = insert_list(&pool, &slug, name.trim())
+        .await
+        .context("Failed to store the mailing list.")?
+        .ok_or_else(|| {
+            AppError::Conflict(format!("A list called {} already exists.", slug.as_ref()))
+        })?;
+
+    Ok(HttpResponse::Created().json(CreatedList { id }))
+}
+
+#[tracing::instrument(name = "List mailing lists", skip(pool))]
+pub async fn get_lists(pool: web::Data<PgPool>) -> Result<HttpResponse, AppError> {
+    let lists = sqlx::query_as!(
+        ListSummary,
+        r#"
+    SELECT lists.id, lists.slug, lists.name,
+           COUNT(list_subscriptions.subscriber_id) as "confirmed_subscribers!"
+    FROM lists
+    LEFT JOIN list_subscriptions
+      ON list_subscriptions.list_id = lists.id AND list_subscriptions.status = $1
+    GROUP BY lists.id
+    ORDER BY lists.created_at, lists.slug
+            "#,
+        SubscriptionStatus::Confirmed.as_str()
+    )
+    .fetch_all(pool.get_ref())
+    .await
+    .context("Failed to retrieve the mailing lists.")?;
+
+    Ok(HttpResponse::Ok().json(lists))
+}
+
+/// Returns `None` when the slug is already taken.
+#[tracing::instrument(name = "Save mailing list details", skip(pool))]
+async fn insert_list(
+    pool: &PgPool,
+    slug: &ListSlug,
+    name: &str,
+) -> Result<Option<Uuid>, sqlx::Error> {
+    let id = sqlx::query!(
+        r#"
+    INSERT INTO lists (id, slug, name, created_at)
+    VALUES ($1, $2, $3, $4)
+    ON CONFLICT (slug) DO NOTHING
+    RETURNING id
+            "#,
+        Uuid::new_v4(),
+        slug.as_ref(),
+        name,
+        Utc::now()
+    )
+    .fetch_optional(pool)
+    .await?
+    .map(|r| r.id);
+    Ok(id)
+}
+
+#[tracing::instrument(name = "Get mailing list id", skip(executor))]
+pub(crate) async fn get_list_id(
+    executor: impl PgExecutor<'_>,
+    slug: &ListSlug,
+) -> Result<Option<Uuid>, sqlx::Error> {
+    let id = sqlx::query!(r#"SELECT id FROM lists WHERE slug = $1"#, slug.as_ref())
+        .fetch_optional(executor)
+        .await?
+        .map(|r| r.id);
+    Ok(id)
+}
@@ -0,0 +1,15 @@
+pub use attributes::*;
+pub use imports::*;
+pub use issue_reports::*;
+pub use issues::*;
+pub use lists::*;
+pub use segments::*;
+pub use subscribers::*;
+
+mod attributes;
+mod imports;
+mod issue_reports;
+mod issues;
+mod lists;
+mod segments;
+mod subscribers;
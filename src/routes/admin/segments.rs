@@ -0,0 +1,53 @@
+use crate::domain::ListSlug;
+use crate::routes::{get_list_id, push_recipients, unknown_list, AppError};
+use crate::segment::Segment;
+use actix_web::{web, HttpResponse};
+use anyhow::Context;
+use sqlx::{PgPool, QueryBuilder};
+
+#[derive(serde::Deserialize)]
+pub struct SegmentData {
+    list: Option<String>,
+    segment: Option<String>,
+}
+
+#[derive(serde::Serialize)]
+struct RecipientCount {
+    recipients: i64,
+}
+
+/// Dry run of a publication: counts the subscribers an issue sent to the list
+/// and segment would reach right now, without queueing anything.
+#[tracing::instrument(name = "Count the recipients of a segment", skip(body, pool))]
+pub async fn count_segment_recipients(
+    body: web::Json<SegmentData>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let SegmentData { list, segment } = body.0;
+    let list = list
+        .map(ListSlug::parse)
+        .transpose()
+        .map_err(AppError::ValidationError)?
+        .unwrap_or_default();
+    let segment = segment
+        .as_deref()
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(Segment::parse)
+        .transpose()
+        .map_err(AppError::ValidationError)?;
+    let list_id = get_list_id(pool.get_ref(), &list)
+        .await
+        .context("Failed to retrieve the mailing list.")?
+        .ok_or_else(|| AppError::ValidationError(unknown_list(&list)))?;
+
+    let mut query = QueryBuilder::new("SELECT COUNT(*)");
+    push_recipients(&mut query, list_id, segment.as_ref());
+    let recipients: i64 = query
+        .build_query_scalar()
+        .fetch_one(pool.get_ref())
+        .await
+        .context("Failed to count the recipients of the segment.")?;
+
+    Ok(HttpResponse::Ok().json(RecipientCount { recipients }))
+}
@@ -0,0 +1,505 @@
+use crate::configuration::SubscriptionSettings;
+use crate::csv_records::spreadsheet_safe;
+use crate::domain::{SubscriberEmail, SubscriberTag, SubscriptionStatus};
+use crate::routes::{erase_subscriber, get_subscriber_data, is_erased, AppError};
+use crate::startup::AppState;
+use actix_web::http::header::{ContentDisposition, DispositionParam, DispositionType};
+use actix_web::{web, HttpResponse};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use futures_util::stream::try_unfold;
+use sqlx::{PgExecutor, PgPool};
+use std::fmt::Debug;
+use uuid::Uuid;
+
+const DEFAULT_PAGE_SIZE: i64 = 50;
+const MAX_PAGE_SIZE: i64 = 500;
+const EXPORT_CHUNK_SIZE: i64 = 1000;
+
+#[derive(serde::Deserialize)]
+pub struct SubscribersQuery {
+    status: Option<String>,
+    list: Option<String>,
+    subscribed_after: Option<DateTime<Utc>>,
+    subscribed_before: Option<DateTime<Utc>>,
+    search: Option<String>,
+    cursor: Option<String>,
+    limit: Option<i64>,
+    format: Option<String>,
+}
+
+#[derive(serde::Serialize)]
+struct SubscriberPage {
+    items: Vec<SubscriberSummary>,
+    next_cursor: Option<String>,
+}
+
+#[derive(serde::Serialize)]
+struct SubscriberSummary {
+    id: Uuid,
+    email: String,
+    name: String,
+    subscribed_at: DateTime<Utc>,
+    lists: Vec<ListMembership>,
+}
+
+#[derive(serde::Serialize)]
+struct ListMembership {
+    list: String,
+    status: String,
+}
+
+/// The filters of a subscriber listing, shared by the paginated API and the
+/// streamed exports.
+#[derive(Clone)]
+struct SubscriberFilters {
+    status: Option<String>,
+    list: Option<String>,
+    subscribed_after: Option<DateTime<Utc>>,
+    subscribed_before: Option<DateTime<Utc>>,
+    search: Option<String>,
+}
+
+/// The position of the last subscriber of a page. Subscribers are listed in
+/// the order they signed up, so new sign-ups never shift later pages.
+#[derive(Debug, Clone, Copy)]
+struct Cursor {
+    subscribed_at: DateTime<Utc>,
+    id: Uuid,
+}
+
+impl Cursor {
+    fn parse(s: &str) -> Result<Self, String> {
+        let error = || format!("{} is not a valid cursor.", s);
+        let (micros, id) = s.split_once('.').ok_or_else(error)?;
+        let subscribed_at = micros
+            .parse()
+            .ok()
+            .and_then(DateTime::from_timestamp_micros)
+            .ok_or_else(error)?;
+        let id = Uuid::parse_str(id).map_err(|_| error())?;
+        Ok(Self { subscribed_at, id })
+    }
+
+    fn encode(&self) -> String {
+        format!("{}.{}", self.subscribed_at.timestamp_micros(), self.id)
+    }
+}
+
+/// Lists subscribers one page at a time, or streams every match as CSV or
+/// NDJSON when `format` asks for it.
+#[tracing::instrument(name = "List subscribers", skip(query, pool))]
+pub async fn get_subscribers(
+    query: web::Query<SubscribersQuery>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let query = query.into_inner();
+    let status = query
+        .status
+        .map(SubscriptionStatus::try_from)
+        .transpose()
+        .map_err(AppError::ValidationError)?
+        .map(|s| s.as_str().to_owned());
+    let cursor = query
+        .cursor
+        .as_deref()
+        .map(Cursor::parse)
+        .transpose()
+        .map_err(AppError::ValidationError)?;
+    let filters = SubscriberFilters {
+        status,
+        list: query.list,
+        subscribed_after: query.subscribed_after,
+        subscribed_before: query.subscribed_before,
+        search: query
+            .search
+            .map(|s| s.trim().to_owned())
+            .filter(|s| !s.is_empty()),
+    };
+
+    let export_format = match query.format.as_deref() {
+        None | Some("json") => None,
+        Some("csv") => Some(ExportFormat::Csv),
+        Some("ndjson") => Some(ExportFormat::Ndjson),
+        Some(other) => {
+            return Err(AppError::ValidationError(format!(
+                "{} is not a supported format. Use `json`, `csv` or `ndjson`.",
+                other
+            )))
+        }
+    };
+    if let Some(format) = export_format {
+        return Ok(export_subscribers(pool.get_ref().clone(), filters, format));
+    }
+
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+    // One extra row tells us whether there is a next page.
+    let mut items = fetch_subscribers(pool.get_ref(), &filters, cursor, limit + 1)
+        .await
+        .context("Failed to retrieve the subscribers.")?;
+    let next_cursor = if items.len() as i64 > limit {
+        items.truncate(limit as usize);
+        items.last().map(|s| {
+            Cursor {
+                subscribed_at: s.subscribed_at,
+                id: s.id,
+            }
+            .encode()
+        })
+    } else {
+        None
+    };
+
+    Ok(HttpResponse::Ok().json(SubscriberPage { items, next_cursor }))
+}
+
+#[derive(Clone, Copy)]
+enum ExportFormat {
+    Csv,
+    Ndjson,
+}
+
+struct ExportState {
+    pool: PgPool,
+    filters: SubscriberFilters,
+    format: ExportFormat,
+    after: Option<Cursor>,
+    header_written: bool,
+}
+
+/// Streams every matching subscriber, reading them in keyset-paginated chunks
+/// so the table is never loaded into memory at once.
+fn export_subscribers(
+    pool: PgPool,
+    filters: SubscriberFilters,
+    format: ExportFormat,
+) -> HttpResponse {
+    let state = ExportState {
+        pool,
+        filters,
+        format,
+        after: None,
+        header_written: false,
+    };
+    let stream = try_unfold(state, |mut state| async move {
+        let rows = fetch_subscribers(&state.pool, &state.filters, state.after, EXPORT_CHUNK_SIZE)
+            .await
+            .context("Failed to retrieve the subscribers.")?;
+
+        if rows.is_empty() && (state.header_written || matches!(state.format, ExportFormat::Ndjson))
+        {
+            return Ok::<_, anyhow::Error>(None);
+        }
+
+        let chunk = match state.format {
+            ExportFormat::Csv => {
+                let mut writer = csv::WriterBuilder::new()
+                    .has_headers(false)
+                    .from_writer(vec![]);
+                if !state.header_written {
+                    writer.write_record(["id", "email", "name", "subscribed_at", "lists"])?;
+                    state.header_written = true;
+                }
+                for row in &rows {
+                    let lists = row
+                        .lists
+                        .iter()
+                        .map(|m| format!("{}:{}", m.list, m.status))
+                        .collect::<Vec<_>>()
+                        .join(" ");
+                    writer.write_record([
+                        row.id.to_string().as_str(),
+                        &spreadsheet_safe(&row.email),
+                        &spreadsheet_safe(&row.name),
+                        &row.subscribed_at.to_rfc3339(),
+                        &spreadsheet_safe(&lists),
+                    ])?;
+                }
+                writer.into_inner().context("Failed to write CSV rows.")?
+            }
+            ExportFormat::Ndjson => {
+                let mut chunk = Vec::new();
+                for row in &rows {
+                    serde_json::to_writer(&mut chunk, row)?;
+                    chunk.push(b'\n');
+                }
+                chunk
+            }
+        };
+        state.after = rows
+            .last()
+            .map(|s| Cursor {
+                subscribed_at: s.subscribed_at,
+                id: s.id,
+            })
+            .or(state.after);
+        Ok(Some((web::Bytes::from(chunk), state)))
+    });
+
+    let (content_type, extension) = match format {
+        ExportFormat::Csv => ("text/csv; charset=utf-8", "csv"),
+        ExportFormat::Ndjson => ("application/x-ndjson", "ndjson"),
+    };
+    HttpResponse::Ok()
+        .content_type(content_type)
+        .insert_header(ContentDisposition {
+            disposition: DispositionType::Attachment,
+            parameters: vec![DispositionParam::Filename(format!(
+                "subscribers.{}",
+                extension
+            ))],
+        })
+        .streaming(stream)
+}
+
+#[tracing::instrument(name = "Get subscribers", skip(pool, filters))]
+async fn fetch_subscribers(
+    pool: &PgPool,
+    filters: &SubscriberFilters,
+    after: Option<Cursor>,
+    limit: i64,
+) -> Result<Vec<SubscriberSummary>, sqlx::Error> {
+    let search = filters.search.as_deref().map(|s| {
+        let escaped = s
+            .replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_");
+        format!("%{}%", escaped)
+    });
+    let rows = sqlx::query!(
+        r#"
+    SELECT id, email, name, subscribed_at,
+           ARRAY(
+               SELECT lists.slug
+               FROM list_subscriptions
+               JOIN lists ON lists.id = list_subscriptions.list_id
+               WHERE list_subscriptions.subscriber_id = subscriptions.id
+               ORDER BY lists.slug
+           ) AS "lists!",
+           ARRAY(
+               SELECT list_subscriptions.status
+               FROM list_subscriptions
+               JOIN lists ON lists.id = list_subscriptions.list_id
+               WHERE list_subscriptions.subscriber_id = subscriptions.id
+               ORDER BY lists.slug
+           ) AS "statuses!"
+    FROM subscriptions
+    WHERE (($1::text IS NULL AND $2::text IS NULL) OR EXISTS (
+              SELECT 1
+              FROM list_subscriptions
+              JOIN lists ON lists.id = list_subscriptions.list_id
+              WHERE list_subscriptions.subscriber_id = subscriptions.id
+                AND ($1::text IS NULL OR list_subscriptions.status = $1)
+                AND ($2::text IS NULL OR lists.slug = $2)
+          ))
+      AND ($3::timestamptz IS NULL OR subscribed_at >= $3)
+      AND ($4::timestamptz IS NULL OR subscribed_at < $4)
+      AND ($5::text IS NULL OR email ILIKE $5 OR name ILIKE $5)
+      AND ($6::timestamptz IS NULL OR (subscribed_at, id) > ($6, $7::uuid))
+    ORDER BY subscribed_at, id
+    LIMIT $8
+            "#,
+        filters.status,
+        filters.list,
+        filters.subscribed_after,
+        filters.subscribed_before,
+        search,
+        after.map(|c| c.subscribed_at),
+        after.map(|c| c.id),
+        limit
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|r| SubscriberSummary {
+            id: r.id,
+            email: r.email,
+            name: r.name,
+            subscribed_at: r.subscribed_at,
+            lists: r
+                .lists
+                .into_iter()
+                .zip(r.statuses)
+                .map(|(list, status)| ListMembership { list, status })
+                .collect(),
+        })
+        .collect())
+}
+
+#[derive(serde::Deserialize)]
+pub struct TagData {
+    tag: String,
+}
+
+#[tracing::instrument(name = "Tag a subscriber", skip(body, pool))]
+pub async fn add_subscriber_tag(
+    subscriber_id: web::Path<Uuid>,
+    body: web::Json<TagData>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let subscriber_id = subscriber_id.into_inner();
+    let tag = SubscriberTag::parse(body.0.tag).map_err(AppError::ValidationError)?;
+
+    let exists = sqlx::query!(
+        r#"SELECT id FROM subscriptions WHERE id = $1"#,
+        subscriber_id
+    )
+    .fetch_optional(pool.get_ref())
+    .await
+    .context("Failed to retrieve the subscriber.")?
+    .is_some();
+    if !exists {
+        return Err(subscriber_not_found());
+    }
+
+    sqlx::query!(
+        r#"
+    INSERT INTO subscriber_tags (subscriber_id, tag, created_at)
+    VALUES ($1, $2, $3)
+    ON CONFLICT DO NOTHING
+            "#,
+        subscriber_id,
+        tag.as_ref(),
+        Utc::now()
+    )
+    .execute(pool.get_ref())
+    .await
+    .context("Failed to store the subscriber tag.")?;
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+#[tracing::instrument(name = "Untag a subscriber", skip(pool))]
+pub async fn remove_subscriber_tag(
+    path: web::Path<(Uuid, String)>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let (subscriber_id, tag) = path.into_inner();
+
+    sqlx::query!(
+        r#"DELETE FROM subscriber_tags WHERE subscriber_id = $1 AND tag = $2"#,
+        subscriber_id,
+        tag
+    )
+    .execute(pool.get_ref())
+    .await
+    .context("Failed to delete the subscriber tag.")?;
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Everything we hold about the subscriber, including their consent records.
+#[tracing::instrument(name = "Get a subscriber", skip(pool))]
+pub async fn get_subscriber_details(
+    subscriber_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let data = get_subscriber_data(pool.get_ref(), subscriber_id.into_inner())
+        .await
+        .context("Failed to collect the subscriber's data.")?
+        .ok_or_else(subscriber_not_found)?;
+
+    Ok(HttpResponse::Ok().json(data))
+}
+
+#[derive(serde::Deserialize)]
+pub struct SubscriberEmailData {
+    email: String,
+}
+
+/// Exports everything we hold about an address, to answer a data-subject
+/// access request. Erased addresses are reported as such.
+#[tracing::instrument(name = "Export a subscriber's data", skip(query, pool, state))]
+pub async fn export_subscriber_data(
+    query: web::Query<SubscriberEmailData>,
+    pool: web::Data<PgPool>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let Some((subscriber_id, _)) =
+        find_subscriber(pool.get_ref(), &query.email, &state.subscriptions).await?
+    else {
+        return Err(not_found_or_erased(pool.get_ref(), &query.email, &state.subscriptions).await?);
+    };
+    let data = get_subscriber_data(pool.get_ref(), subscriber_id)
+        .await
+        .context("Failed to collect the subscriber's data.")?
+        .ok_or_else(subscriber_not_found)?;
+
+    Ok(HttpResponse::Ok().json(data))
+}
+
+#[tracing::instrument(name = "Erase a subscriber's data", skip(body, pool, state))]
+pub async fn erase_subscriber_data(
+    body: web::Json<SubscriberEmailData>,
+    pool: web::Data<PgPool>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool.")?;
+    let Some((subscriber_id, email)) =
+        find_subscriber(&mut *transaction, &body.email, &state.subscriptions).await?
+    else {
+        return Err(not_found_or_erased(pool.get_ref(), &body.email, &state.subscriptions).await?);
+    };
+    erase_subscriber(
+        &mut transaction,
+        subscriber_id,
+        &email,
+        "admin",
+        state.subscriptions.fold_email_aliases,
+    )
+    .await
+    .context("Failed to erase the subscriber.")?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to erase the subscriber.")?;
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Looks the address up the way subscriptions are deduplicated, and returns
+/// the subscriber's id and address as stored.
+async fn find_subscriber(
+    executor: impl PgExecutor<'_>,
+    email: &str,
+    settings: &SubscriptionSettings,
+) -> Result<Option<(Uuid, String)>, AppError> {
+    let Ok(email) = SubscriberEmail::parse(email.to_owned()) else {
+        return Ok(None);
+    };
+    let subscriber = sqlx::query!(
+        r#"SELECT id, email FROM subscriptions WHERE normalized_email = $1"#,
+        email.normalized(settings.fold_email_aliases)
+    )
+    .fetch_optional(executor)
+    .await
+    .context("Failed to retrieve the subscriber.")?;
+    Ok(subscriber.map(|s| (s.id, s.email)))
+}
+
+async fn not_found_or_erased(
+    pool: &PgPool,
+    email: &str,
+    settings: &SubscriptionSettings,
+) -> Result<AppError, AppError> {
+    let erased = is_erased(pool, email, settings.fold_email_aliases)
+        .await
+        .context("Failed to check whether the subscriber was erased.")?;
+    Ok(if erased {
+        AppError::Gone("The subscriber's data has been erased.".into())
+    } else {
+        subscriber_not_found()
+    })
+}
+
+fn subscriber_not_found() -> AppError {
+    AppError::NotFound("There is no such subscriber.".into())
+}
@@ -0,0 +1,3 @@
+pub use subscriptions::*;
+
+mod subscriptions;
@@ -0,0 +1,217 @@
+use crate::domain::{
+    AttributeDefinition, AttributeError, EmailPolicy, FormSource, ListSlug, Locale, NewSubscriber,
+    SubscriberAttributes, SubscriberEmail, SubscriberName,
+};
+use crate::i18n::Translator;
+use crate::routes::{
+    attribute_error, create_subscription, get_attribute_definitions, invalid, picked_locale,
+    policy_violation, request_locale, AppError, ErrorCode, FieldErrors, SubscriptionRequest,
+};
+use crate::startup::AppState;
+use actix_web::{web, HttpRequest, HttpResponse};
+use serde_json::Value;
+use sqlx::PgPool;
+use std::collections::HashMap;
+
+#[derive(serde::Deserialize)]
+pub struct SubscriptionBody {
+    email: Option<String>,
+    name: Option<String>,
+    list: Option<String>,
+    /// The form or screen the subscriber used, kept as proof of consent.
+    source: Option<String>,
+    /// The subscriber's language. It is negotiated from `Accept-Language`
+    /// when missing.
+    locale: Option<String>,
+    /// Custom subscriber attributes. `null` values are left out.
+    #[serde(default)]
+    attributes: HashMap<String, Value>,
+}
+
+impl SubscriptionBody {
+    /// Checks every field, so that clients can show all the errors at once.
+    /// Messages are in the subscriber's language.
+    fn parse(
+        self,
+        definitions: &[AttributeDefinition],
+        email_policy: &EmailPolicy,
+        t: &Translator,
+        negotiated_locale: Option<Locale>,
+    ) -> Result<SubscriptionRequest, FieldErrors> {
+        let mut errors = FieldErrors::default();
+        let email = required(t, &mut errors, "email", self.email, |email| {
+            let email = SubscriberEmail::parse(email.clone())
+                .map_err(|_| (ErrorCode::InvalidEmail, invalid(t, "email", &email)))?;
+            email_policy
+                .check(&email)
+                .map_err(|v| (ErrorCode::DisallowedEmail, policy_violation(t, &v)))?;
+            Ok(email)
+        });
+        let name = required(t, &mut errors, "name", self.name, |name| {
+            SubscriberName::parse(name.clone())
+                .map_err(|_| (ErrorCode::InvalidName, invalid(t, "name", &name)))
+        });
+        let list = optional(&mut errors, "list", self.list, |list| {
+            ListSlug::parse(list.clone())
+                .map_err(|_| (ErrorCode::InvalidList, invalid(t, "list", &list)))
+        });
+        let form_source = optional(&mut errors, "source", self.source, |source| {
+            FormSource::parse(source.clone())
+                .map_err(|_| (ErrorCode::InvalidSource, invalid(t, "source", &source)))
+        });
+        let locale = picked_locale(self.locale.as_deref())
+            .map_err(|locale| {
+                errors.add(
+                    "locale",
+                    ErrorCode::InvalidLocale,
+                    invalid(t, "locale", locale),
+                )
+            })
+            .ok();
+        let attributes = parse_attributes(t, &mut errors, self.attributes, definitions);
+
+        match (email, name, list, form_source, locale) {
+            (Some(email), Some(name), Some(list), Some(form_source), Some(locale))
+                if errors.is_empty() =>
+            {
+                Ok(SubscriptionRequest {
+                    new_subscriber: NewSubscriber {
+                        email,
+                        name,
+                        attributes,
+                    },
+                    list,
+                    form_source,
+                    locale: locale.or(negotiated_locale),
+                })
+            }
+            _ => Err(errors),
+        }
+    }
+}
+
+fn required<T>(
+    t: &Translator,
+    errors: &mut FieldErrors,
+    field: &str,
+    value: Option<String>,
+    parse: impl FnOnce(String) -> Result<T, (ErrorCode, String)>,
+) -> Option<T> {
+    let Some(value) = value else {
+        errors.add(
+            field,
+            ErrorCode::Required,
+            t.text_with("errors.required", &[("field", field)]),
+        );
+        return None;
+    };
+    parse(value)
+        .map_err(|(code, message)| errors.add(field, code, message))
+        .ok()
+}
+
+/// Missing optional fields take their default value.
+fn optional<T: Default>(
+    errors: &mut FieldErrors,
+    field: &str,
+    value: Option<String>,
+    parse: impl FnOnce(String) -> Result<T, (ErrorCode, String)>,
+) -> Option<T> {
+    match value {
+        None => Some(T::default()),
+        Some(value) => parse(value)
+            .map_err(|(code, message)| errors.add(field, code, message))
+            .ok(),
+    }
+}
+
+/// JSON scalars are accepted for every attribute type and checked the same
+/// way as form values.
+fn parse_attributes(
+    t: &Translator,
+    errors: &mut FieldErrors,
+    raw: HashMap<String, Value>,
+    definitions: &[AttributeDefinition],
+) -> SubscriberAttributes {
+    let mut values = HashMap::new();
+    for (name, value) in raw {
+        let field = format!("attributes.{}", name);
+        let Some(definition) = definitions.iter().find(|d| d.name == name) else {
+            errors.add(
+                &field,
+                ErrorCode::UnknownAttribute,
+                attribute_error(t, &AttributeError::Unknown(name)),
+            );
+            continue;
+        };
+        let value = match value {
+            Value::Null => continue,
+            Value::String(s) => s,
+            Value::Number(n) => n.to_string(),
+            Value::Bool(b) => b.to_string(),
+            Value::Array(_) | Value::Object(_) => {
+                errors.add(
+                    &field,
+                    ErrorCode::InvalidAttribute,
+                    t.text_with("errors.attribute_type", &[("name", &name)]),
+                );
+                continue;
+            }
+        };
+        match definition.parse_value(&value) {
+            Ok(_) => {
+                values.insert(name, value);
+            }
+            Err(e) => errors.add(&field, ErrorCode::InvalidAttribute, attribute_error(t, &e)),
+        }
+    }
+    // Every value has been checked against its definition above.
+    SubscriberAttributes::parse(values, definitions).unwrap_or_default()
+}
+
+#[derive(serde::Serialize)]
+struct SubscriptionCreated<'a> {
+    code: &'a str,
+    message: &'a str,
+}
+
+#[tracing::instrument(
+    name = "Adding a new subscriber through the API",
+    skip(body, request, pool, state)
+)]
+pub async fn api_subscribe(
+    body: web::Json<SubscriptionBody>,
+    request: HttpRequest,
+    pool: web::Data<PgPool>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let body = body.into_inner();
+    let negotiated_locale = request_locale(&request, &state.catalogs);
+    let message_locale = picked_locale(body.locale.as_deref())
+        .ok()
+        .flatten()
+        .or_else(|| negotiated_locale.clone());
+    let t = state.catalogs.translator(message_locale.as_ref());
+    let definitions = get_attribute_definitions(pool.get_ref()).await?;
+    let subscription = body
+        .parse(&definitions, &state.email_policy, &t, negotiated_locale)
+        .map_err(AppError::InvalidFields)?;
+    let email = subscription.new_subscriber.email.as_ref().to_owned();
+    let awaits_confirmation =
+        create_subscription(subscription, &request, &pool, &state, &t).await?;
+    let (code, message) = if awaits_confirmation {
+        (
+            "confirmation_sent",
+            t.text_with("api.confirmation_sent", &[("email", &email)]),
+        )
+    } else {
+        (
+            "already_subscribed",
+            t.text("pages.already_subscribed.message"),
+        )
+    };
+    Ok(HttpResponse::Ok().json(SubscriptionCreated {
+        code,
+        message: &message,
+    }))
+}
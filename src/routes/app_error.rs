@@ -0,0 +1,341 @@
+use actix_web::body::{BoxBody, EitherBody};
+use actix_web::dev::ServiceResponse;
+use actix_web::error::{InternalError, JsonPayloadError, QueryPayloadError, UrlencodedError};
+use actix_web::http::header::{self, HeaderName, HeaderValue};
+use actix_web::http::StatusCode;
+use actix_web::{HttpMessage, HttpRequest, HttpResponse, ResponseError};
+use std::collections::BTreeMap;
+use std::fmt::{Debug, Display, Formatter};
+use std::time::Duration;
+use tracing_actix_web::RequestId;
+
+/// Every code a failed request is answered with. Clients branch on these, so
+/// existing codes must never be renamed or reused.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ErrorCode {
+    /// The body is not valid JSON or form data, or does not have the
+    /// expected shape.
+    MalformedBody,
+    /// The query string does not have the expected shape.
+    MalformedQuery,
+    /// The body is sent with a content type the endpoint does not accept.
+    UnsupportedMediaType,
+    /// The request is invalid. Details may be given per field.
+    ValidationFailed,
+    /// The admin token is missing or wrong.
+    Unauthorized,
+    /// The token of a confirmation or unsubscribe link is unknown.
+    InvalidToken,
+    /// The token of a confirmation link is too old to be used.
+    ExpiredToken,
+    NotFound,
+    Conflict,
+    /// The resource existed but has been removed for good.
+    Gone,
+    /// Too many requests were sent. `Retry-After` says when to try again.
+    RateLimited,
+    /// A required field is missing.
+    Required,
+    InvalidEmail,
+    /// The address is valid but not accepted, such as a disposable or a
+    /// role address.
+    DisallowedEmail,
+    InvalidName,
+    InvalidList,
+    UnknownList,
+    InvalidSource,
+    InvalidLocale,
+    UnknownAttribute,
+    InvalidAttribute,
+    UnexpectedError,
+}
+
+impl ErrorCode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCode::MalformedBody => "malformed_body",
+            ErrorCode::MalformedQuery => "malformed_query",
+            ErrorCode::UnsupportedMediaType => "unsupported_media_type",
+            ErrorCode::ValidationFailed => "validation_failed",
+            ErrorCode::Unauthorized => "unauthorized",
+            ErrorCode::InvalidToken => "invalid_token",
+            ErrorCode::ExpiredToken => "expired_token",
+            ErrorCode::NotFound => "not_found",
+            ErrorCode::Conflict => "conflict",
+            ErrorCode::Gone => "gone",
+            ErrorCode::RateLimited => "rate_limited",
+            ErrorCode::Required => "required",
+            ErrorCode::InvalidEmail => "invalid_email",
+            ErrorCode::DisallowedEmail => "disallowed_email",
+            ErrorCode::InvalidName => "invalid_name",
+            ErrorCode::InvalidList => "invalid_list",
+            ErrorCode::UnknownList => "unknown_list",
+            ErrorCode::InvalidSource => "invalid_source",
+            ErrorCode::InvalidLocale => "invalid_locale",
+            ErrorCode::UnknownAttribute => "unknown_attribute",
+            ErrorCode::InvalidAttribute => "invalid_attribute",
+            ErrorCode::UnexpectedError => "unexpected_error",
+        }
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct FieldError {
+    pub code: &'static str,
+    pub message: String,
+}
+
+/// Validation failures keyed by field name. Custom attributes are reported
+/// as `attributes.<name>`.
+#[derive(Debug, Clone, Default, serde::Serialize)]
+pub struct FieldErrors(BTreeMap<String, FieldError>);
+
+impl FieldErrors {
+    pub fn add(&mut self, field: &str, code: ErrorCode, message: impl Into<String>) {
+        self.0.insert(
+            field.to_owned(),
+            FieldError {
+                code: code.as_str(),
+                message: message.into(),
+            },
+        );
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// Every message, for pages that show the errors in one sentence.
+impl Display for FieldErrors {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let messages: Vec<&str> = self.0.values().map(|e| e.message.as_str()).collect();
+        write!(f, "{}", messages.join(" "))
+    }
+}
+
+/// The error returned by every route that does not render a page. It is sent
+/// as RFC 7807 problem details: internal causes stay out of the response and
+/// reach the logs through `Debug`, which prints the whole cause chain.
+#[derive(thiserror::Error)]
+pub enum AppError {
+    #[error("{0}")]
+    ValidationError(String),
+    #[error("The request is invalid.")]
+    InvalidFields(FieldErrors),
+    #[error("{0}")]
+    InvalidToken(String),
+    #[error("{0}")]
+    ExpiredToken(String),
+    #[error("{0}")]
+    NotFound(String),
+    #[error("{0}")]
+    Conflict(String),
+    #[error("{0}")]
+    Gone(String),
+    #[error("Too many requests. Please try again later.")]
+    RateLimited(Duration),
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl Debug for AppError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl AppError {
+    pub(crate) fn code(&self) -> ErrorCode {
+        match self {
+            AppError::ValidationError(_) | AppError::InvalidFields(_) => {
+                ErrorCode::ValidationFailed
+            }
+            AppError::InvalidToken(_) => ErrorCode::InvalidToken,
+            AppError::ExpiredToken(_) => ErrorCode::ExpiredToken,
+            AppError::NotFound(_) => ErrorCode::NotFound,
+            AppError::Conflict(_) => ErrorCode::Conflict,
+            AppError::Gone(_) => ErrorCode::Gone,
+            AppError::RateLimited(_) => ErrorCode::RateLimited,
+            AppError::UnexpectedError(_) => ErrorCode::UnexpectedError,
+        }
+    }
+}
+
+impl ResponseError for AppError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            AppError::ValidationError(_) | AppError::InvalidFields(_) => StatusCode::BAD_REQUEST,
+            AppError::InvalidToken(_) => StatusCode::UNAUTHORIZED,
+            AppError::ExpiredToken(_) => StatusCode::GONE,
+            AppError::NotFound(_) => StatusCode::NOT_FOUND,
+            AppError::Conflict(_) => StatusCode::CONFLICT,
+            AppError::Gone(_) => StatusCode::GONE,
+            AppError::RateLimited(_) => StatusCode::TOO_MANY_REQUESTS,
+            AppError::UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let detail = match self {
+            AppError::UnexpectedError(_) => {
+                "We could not process your request. Please try again later.".to_owned()
+            }
+            _ => self.to_string(),
+        };
+        let mut problem = Problem::new(self.status_code(), self.code().as_str(), detail);
+        match self {
+            AppError::InvalidFields(fields) => problem.fields = Some(fields.clone()),
+            AppError::RateLimited(retry_after) => problem = problem.with_retry_after(*retry_after),
+            _ => {}
+        }
+        problem.into_response()
+    }
+}
+
+/// RFC 7807 problem details. `code` and `fields` are extension members.
+#[derive(Clone, serde::Serialize)]
+pub(crate) struct Problem {
+    #[serde(rename = "type")]
+    type_: String,
+    title: String,
+    status: u16,
+    detail: String,
+    code: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fields: Option<FieldErrors>,
+    /// Filled in by `with_request_id` once the response leaves the app.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    request_id: Option<String>,
+    /// Sent as the `Retry-After` header.
+    #[serde(skip)]
+    retry_after: Option<Duration>,
+}
+
+impl Problem {
+    pub(crate) fn new(status: StatusCode, code: &'static str, detail: String) -> Self {
+        Self {
+            type_: format!("/problems/{}", code.replace('_', "-")),
+            title: status
+                .canonical_reason()
+                .unwrap_or("Unknown error")
+                .to_owned(),
+            status: status.as_u16(),
+            detail,
+            code,
+            fields: None,
+            request_id: None,
+            retry_after: None,
+        }
+    }
+
+    pub(crate) fn with_title(mut self, title: &str) -> Self {
+        self.title = title.to_owned();
+        self
+    }
+
+    pub(crate) fn with_retry_after(mut self, retry_after: Duration) -> Self {
+        self.retry_after = Some(retry_after);
+        self
+    }
+
+    /// The problem is kept in the response extensions, so that its body can
+    /// be rendered again with the request id.
+    pub(crate) fn into_response(self) -> HttpResponse {
+        let status = StatusCode::from_u16(self.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+        let mut response = HttpResponse::build(status);
+        response.insert_header((
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("application/problem+json"),
+        ));
+        if let Some(retry_after) = self.retry_after {
+            response.insert_header(retry_after_header(retry_after));
+        }
+        let mut response = response.body(self.to_json());
+        response.extensions_mut().insert(self);
+        response
+    }
+
+    fn to_json(&self) -> String {
+        // Serializing strings and numbers cannot fail.
+        serde_json::to_string(self).unwrap()
+    }
+}
+
+/// Adds the id the request is logged with to problem details, so that a
+/// reported error can be traced back to its cause chain in the logs.
+pub(crate) fn with_request_id<B>(response: ServiceResponse<B>) -> ServiceResponse<EitherBody<B>> {
+    let Some(mut problem) = response.response().extensions().get::<Problem>().cloned() else {
+        return response.map_into_left_body();
+    };
+    problem.request_id = response
+        .request()
+        .extensions()
+        .get::<RequestId>()
+        .map(ToString::to_string);
+    response.map_body(|_, _| EitherBody::right(BoxBody::new(problem.to_json())))
+}
+
+pub(crate) fn json_error_handler(
+    error: JsonPayloadError,
+    _request: &HttpRequest,
+) -> actix_web::Error {
+    let code = match error {
+        JsonPayloadError::ContentType => ErrorCode::UnsupportedMediaType,
+        _ => ErrorCode::MalformedBody,
+    };
+    payload_error(error, code)
+}
+
+pub(crate) fn form_error_handler(
+    error: UrlencodedError,
+    _request: &HttpRequest,
+) -> actix_web::Error {
+    let code = match error {
+        UrlencodedError::ContentType => ErrorCode::UnsupportedMediaType,
+        _ => ErrorCode::MalformedBody,
+    };
+    payload_error(error, code)
+}
+
+pub(crate) fn query_error_handler(
+    error: QueryPayloadError,
+    _request: &HttpRequest,
+) -> actix_web::Error {
+    payload_error(error, ErrorCode::MalformedQuery)
+}
+
+/// Requests that do not match any route.
+pub(crate) async fn not_found() -> Result<HttpResponse, AppError> {
+    Err(AppError::NotFound(
+        "There is nothing at this address.".into(),
+    ))
+}
+
+fn payload_error<E>(error: E, code: ErrorCode) -> actix_web::Error
+where
+    E: ResponseError + 'static,
+{
+    let status = match code {
+        ErrorCode::UnsupportedMediaType => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+        _ => StatusCode::BAD_REQUEST,
+    };
+    let response = Problem::new(status, code.as_str(), error.to_string()).into_response();
+    InternalError::from_response(error, response).into()
+}
+
+/// Whole seconds, rounded up so that clients never retry too early.
+pub(crate) fn retry_after_header(retry_after: Duration) -> (HeaderName, String) {
+    let seconds = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+    (header::RETRY_AFTER, seconds.max(1).to_string())
+}
+
+fn error_chain_fmt(e: &impl std::error::Error, f: &mut Formatter<'_>) -> std::fmt::Result {
+    writeln!(f, "{}\n", e)?;
+    let mut current = e.source();
+    while let Some(cause) = current {
+        writeln!(f, "Caused by:\n\t{}", cause)?;
+        current = cause.source();
+    }
+    Ok(())
+}
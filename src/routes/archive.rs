@@ -0,0 +1,204 @@
+use crate::domain::IssueStatus;
+use crate::email::merge_tags::strip_merge_tags;
+use crate::routes::{request_locale, AppError};
+use crate::startup::AppState;
+use actix_web::http::header::ContentType;
+use actix_web::{web, HttpRequest, HttpResponse};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use htmlescape::encode_minimal;
+use sqlx::PgPool;
+use std::fmt::Write;
+
+const ARCHIVE_PAGE_SIZE: i64 = 10;
+
+#[derive(serde::Deserialize)]
+pub struct ArchiveQuery {
+    page: Option<u32>,
+}
+
+/// Lists the issues whose delivery has completed, in the language the
+/// browser asks for.
+#[tracing::instrument(
+    name = "Render the newsletter archive index",
+    skip(query, request, pool, state)
+)]
+pub async fn archive_index(
+    query: web::Query<ArchiveQuery>,
+    request: HttpRequest,
+    pool: web::Data<PgPool>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let t = state
+        .catalogs
+        .translator(request_locale(&request, &state.catalogs).as_ref());
+    let page = query.page.unwrap_or(1).max(1);
+    let offset = i64::from(page - 1) * ARCHIVE_PAGE_SIZE;
+
+    let mut entries = get_archive_entries(&pool, ARCHIVE_PAGE_SIZE + 1, offset)
+        .await
+        .context("Failed to retrieve the archived issues.")?;
+    let has_older = entries.len() as i64 > ARCHIVE_PAGE_SIZE;
+    entries.truncate(ARCHIVE_PAGE_SIZE as usize);
+
+    let mut items = String::new();
+    for entry in &entries {
+        writeln!(
+            items,
+            r#"<li><a href="/archive/{}">{}</a> <time datetime="{}">{}</time></li>"#,
+            encode_minimal(&entry.slug),
+            encode_minimal(&entry.title),
+            entry.published_at.to_rfc3339(),
+            encode_minimal(&t.date("archive.date_format", entry.published_at))
+        )
+        .unwrap();
+    }
+    if entries.is_empty() {
+        writeln!(
+            items,
+            "<li>{}</li>",
+            encode_minimal(&t.text("archive.empty"))
+        )
+        .unwrap();
+    }
+
+    let mut navigation = String::new();
+    if page > 1 {
+        write!(
+            navigation,
+            r#"<a href="/archive?page={}">{}</a> "#,
+            page - 1,
+            encode_minimal(&t.text("archive.newer"))
+        )
+        .unwrap();
+    }
+    if has_older {
+        write!(
+            navigation,
+            r#"<a href="/archive?page={}">{}</a>"#,
+            page + 1,
+            encode_minimal(&t.text("archive.older"))
+        )
+        .unwrap();
+    }
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="{lang}">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>{title}</title>
+</head>
+<body>
+<h1>{title}</h1>
+<ul>
+{items}</ul>
+<nav>{navigation}</nav>
+</body>
+</html>"#,
+            lang = t.locale(),
+            title = encode_minimal(&t.text("archive.title")),
+        )))
+}
+
+/// Issues can be read here as soon as they are published, since the
+/// emails being delivered link to them.
+#[tracing::instrument(
+    name = "Render an archived newsletter issue",
+    skip(request, pool, state)
+)]
+pub async fn archive_issue(
+    slug: web::Path<String>,
+    request: HttpRequest,
+    pool: web::Data<PgPool>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let t = state
+        .catalogs
+        .translator(request_locale(&request, &state.catalogs).as_ref());
+    let issue = get_archived_issue(&pool, &slug)
+        .await
+        .context("Failed to retrieve the archived issue.")?
+        .ok_or_else(|| AppError::NotFound("There is no published issue at this address.".into()))?;
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="{lang}">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>{title}</title>
+</head>
+<body>
+<p><a href="/archive">{archive}</a></p>
+<h1>{title}</h1>
+<p><time datetime="{}">{}</time></p>
+<article>
+{}
+</article>
+</body>
+</html>"#,
+            issue.published_at.to_rfc3339(),
+            encode_minimal(&t.date("archive.date_format", issue.published_at)),
+            strip_merge_tags(&issue.html_content),
+            lang = t.locale(),
+            archive = encode_minimal(&t.text("archive.title")),
+            title = encode_minimal(&issue.title),
+        )))
+}
+
+struct ArchiveEntry {
+    slug: String,
+    title: String,
+    published_at: DateTime<Utc>,
+}
+
+#[tracing::instrument(name = "Get archived issues", skip(pool))]
+async fn get_archive_entries(
+    pool: &PgPool,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<ArchiveEntry>, sqlx::Error> {
+    sqlx::query_as!(
+        ArchiveEntry,
+        r#"
+    SELECT slug as "slug!", title, published_at as "published_at!"
+    FROM newsletter_issues
+    WHERE slug IS NOT NULL AND status = $3
+    ORDER BY published_at DESC
+    LIMIT $1 OFFSET $2
+            "#,
+        limit,
+        offset,
+        IssueStatus::Sent.as_str()
+    )
+    .fetch_all(pool)
+    .await
+}
+
+struct ArchivedIssue {
+    title: String,
+    html_content: String,
+    published_at: DateTime<Utc>,
+}
+
+#[tracing::instrument(name = "Get archived issue", skip(pool))]
+async fn get_archived_issue(
+    pool: &PgPool,
+    slug: &str,
+) -> Result<Option<ArchivedIssue>, sqlx::Error> {
+    sqlx::query_as!(
+        ArchivedIssue,
+        r#"
+    SELECT title, html_content, published_at as "published_at!"
+    FROM newsletter_issues
+    WHERE slug = $1
+            "#,
+        slug
+    )
+    .fetch_optional(pool)
+    .await
+}
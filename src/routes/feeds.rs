@@ -0,0 +1,199 @@
+use crate::email::merge_tags::strip_merge_tags;
+use crate::routes::AppError;
+use crate::startup::AppState;
+use actix_web::http::header::{
+    self, EntityTag, HttpDate, IfModifiedSince, IfNoneMatch, LastModified,
+};
+use actix_web::{web, HttpMessage, HttpRequest, HttpResponse};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use htmlescape::encode_minimal;
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use std::fmt::Write;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[tracing::instrument(name = "Render the Atom feed", skip_all)]
+pub async fn atom_feed(
+    request: HttpRequest,
+    pool: web::Data<PgPool>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let entries = get_feed_entries(&pool, state.feed.max_entries.get().into())
+        .await
+        .context("Failed to retrieve the feed entries.")?;
+    let updated = feed_updated(&entries);
+    let base_url = state.base_url.to_string();
+
+    let mut xml = format!(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom">
+<title>{title}</title>
+<subtitle>{description}</subtitle>
+<link href="{base_url}archive" rel="alternate" type="text/html"/>
+<link href="{base_url}feed.xml" rel="self" type="application/atom+xml"/>
+<id>{base_url}archive</id>
+<author><name>{title}</name></author>
+<updated>{updated}</updated>
+"#,
+        title = encode_minimal(&state.feed.title),
+        description = encode_minimal(&state.feed.description),
+        base_url = encode_minimal(&base_url),
+        updated = updated.to_rfc3339(),
+    );
+    for entry in &entries {
+        let link = encode_minimal(&format!("{}archive/{}", base_url, entry.slug));
+        write!(
+            xml,
+            r#"<entry>
+<title>{}</title>
+<link href="{link}" rel="alternate" type="text/html"/>
+<id>{link}</id>
+<published>{published}</published>
+<updated>{published}</updated>
+<content type="html">{}</content>
+</entry>
+"#,
+            encode_minimal(&entry.title),
+            encode_minimal(&strip_merge_tags(&entry.html_content)),
+            link = link,
+            published = entry.published_at.to_rfc3339(),
+        )
+        .unwrap();
+    }
+    xml.push_str("</feed>\n");
+
+    Ok(cacheable_response(
+        &request,
+        "application/atom+xml; charset=utf-8",
+        updated,
+        xml,
+    ))
+}
+
+#[tracing::instrument(name = "Render the RSS feed", skip_all)]
+pub async fn rss_feed(
+    request: HttpRequest,
+    pool: web::Data<PgPool>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let entries = get_feed_entries(&pool, state.feed.max_entries.get().into())
+        .await
+        .context("Failed to retrieve the feed entries.")?;
+    let updated = feed_updated(&entries);
+    let base_url = state.base_url.to_string();
+
+    let mut xml = format!(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<rss version="2.0" xmlns:atom="http://www.w3.org/2005/Atom">
+<channel>
+<title>{title}</title>
+<link>{base_url}archive</link>
+<description>{description}</description>
+<atom:link href="{base_url}rss.xml" rel="self" type="application/rss+xml"/>
+<lastBuildDate>{updated}</lastBuildDate>
+"#,
+        title = encode_minimal(&state.feed.title),
+        description = encode_minimal(&state.feed.description),
+        base_url = encode_minimal(&base_url),
+        updated = updated.to_rfc2822(),
+    );
+    for entry in &entries {
+        let link = encode_minimal(&format!("{}archive/{}", base_url, entry.slug));
+        write!(
+            xml,
+            r#"<item>
+<title>{}</title>
+<link>{link}</link>
+<guid isPermaLink="true">{link}</guid>
+<pubDate>{}</pubDate>
+<description>{}</description>
+</item>
+"#,
+            encode_minimal(&entry.title),
+            entry.published_at.to_rfc2822(),
+            encode_minimal(&strip_merge_tags(&entry.html_content)),
+            link = link,
+        )
+        .unwrap();
+    }
+    xml.push_str("</channel>\n</rss>\n");
+
+    Ok(cacheable_response(
+        &request,
+        "application/rss+xml; charset=utf-8",
+        updated,
+        xml,
+    ))
+}
+
+/// Answers with `304 Not Modified` when the client already holds the current
+/// version of the feed, based on its `ETag` first and `Last-Modified` second.
+fn cacheable_response(
+    request: &HttpRequest,
+    content_type: &str,
+    updated: DateTime<Utc>,
+    body: String,
+) -> HttpResponse {
+    let etag = EntityTag::new_strong(hex::encode(Sha256::digest(body.as_bytes())));
+    let last_modified =
+        UNIX_EPOCH + Duration::from_secs(updated.timestamp().try_into().unwrap_or_default());
+
+    let not_modified = match request.get_header::<IfNoneMatch>() {
+        Some(IfNoneMatch::Any) => true,
+        Some(IfNoneMatch::Items(tags)) => tags.iter().any(|tag| tag.weak_eq(&etag)),
+        None => request
+            .get_header::<IfModifiedSince>()
+            .map(|since| last_modified <= SystemTime::from(since.0))
+            .unwrap_or(false),
+    };
+
+    let mut response = if not_modified {
+        HttpResponse::NotModified()
+    } else {
+        HttpResponse::Ok()
+    };
+    response
+        .insert_header(header::ETag(etag))
+        .insert_header(LastModified(HttpDate::from(last_modified)));
+
+    if not_modified {
+        response.finish()
+    } else {
+        response.content_type(content_type).body(body)
+    }
+}
+
+/// Issues cannot be edited once published, so the feed was last updated when
+/// its most recent entry was published.
+fn feed_updated(entries: &[FeedEntry]) -> DateTime<Utc> {
+    entries
+        .iter()
+        .map(|e| e.published_at)
+        .max()
+        .unwrap_or(DateTime::UNIX_EPOCH)
+}
+
+struct FeedEntry {
+    slug: String,
+    title: String,
+    html_content: String,
+    published_at: DateTime<Utc>,
+}
+
+#[tracing::instrument(name = "Get feed entries", skip(pool))]
+async fn get_feed_entries(pool: &PgPool, limit: i64) -> Result<Vec<FeedEntry>, sqlx::Error> {
+    sqlx::query_as!(
+        FeedEntry,
+        r#"
+    SELECT slug as "slug!", title, html_content, published_at as "published_at!"
+    FROM newsletter_issues
+    WHERE slug IS NOT NULL
+    ORDER BY published_at DESC
+    LIMIT $1
+            "#,
+        limit
+    )
+    .fetch_all(pool)
+    .await
+}
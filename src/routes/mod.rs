@@ -1,9 +1,31 @@
+pub use admin::*;
+pub use api::*;
+pub use app_error::*;
+pub use archive::*;
+pub use feeds::*;
 pub use health_check::*;
 pub use newsletters::*;
+pub(crate) use pages::*;
+pub use preferences::*;
+pub use ses_notifications::*;
+pub(crate) use subscriber_data::*;
 pub use subscriptions::*;
 pub use subscriptions_confirm::*;
+pub use subscriptions_unsubscribe::*;
+pub use tracking::*;
 
+mod admin;
+mod api;
+mod app_error;
+mod archive;
+mod feeds;
 mod health_check;
 mod newsletters;
+mod pages;
+mod preferences;
+mod ses_notifications;
+mod subscriber_data;
 mod subscriptions;
 mod subscriptions_confirm;
+mod subscriptions_unsubscribe;
+mod tracking;
@@ -1,36 +1,48 @@
-use crate::domain::SubscriberEmail;
-use crate::email::email_client::{EmailClient, EmailService, SendEmailRequest};
-use crate::routes::error_chain_fmt;
-use actix_web::http::StatusCode;
-use actix_web::{web, HttpResponse, ResponseError};
+use crate::domain::{
+    AbTestMetric, DeliveryStatus, DigestFrequency, IssueSlug, IssueStatus, ListSlug,
+    NewsletterIssue, SubscriptionStatus,
+};
+use crate::routes::{get_list_id, AppError};
+use crate::segment::Segment;
+use actix_web::{web, HttpResponse};
 use anyhow::Context;
-use sqlx::PgPool;
-use std::fmt::{Debug, Formatter};
+use chrono::Utc;
+use sqlx::{Executor, PgPool, Postgres, QueryBuilder, Transaction};
+use std::collections::HashMap;
+use std::fmt::Debug;
+use uuid::Uuid;
 
-#[derive(thiserror::Error)]
-pub enum PublishError {
-    #[error(transparent)]
-    UnexpectedError(#[from] anyhow::Error),
+#[derive(serde::Deserialize)]
+pub struct BodyData {
+    title: String,
+    content: Content,
+    list: Option<String>,
+    segment: Option<String>,
+    /// Translations of the issue, by locale.
+    #[serde(default)]
+    variants: HashMap<String, VariantData>,
+    ab_test: Option<AbTestData>,
 }
 
-impl Debug for PublishError {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        error_chain_fmt(self, f)
-    }
+#[derive(serde::Deserialize)]
+pub struct VariantData {
+    title: String,
+    content: Content,
 }
 
-impl ResponseError for PublishError {
-    fn status_code(&self) -> StatusCode {
-        match self {
-            PublishError::UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
-        }
-    }
+#[derive(serde::Deserialize)]
+pub struct AbTestData {
+    sample_percent: i32,
+    wait_minutes: i32,
+    metric: Option<String>,
+    variants: Vec<AbTestVariantData>,
 }
 
+/// A variant of the subject, and optionally of the content.
 #[derive(serde::Deserialize)]
-pub struct BodyData {
+pub struct AbTestVariantData {
     title: String,
-    content: Content,
+    content: Option<Content>,
 }
 
 #[derive(serde::Deserialize)]
@@ -39,74 +51,395 @@ pub struct Content {
     text: String,
 }
 
-pub async fn publish_newsletter(
-    body: web::Json<BodyData>,
-    pool: web::Data<PgPool>,
-    email_service: web::Data<EmailService>,
-    email_client: web::Data<dyn EmailClient>,
-) -> Result<HttpResponse, PublishError> {
-    let confirmed_subscribers = get_confirmed_subscribers(&pool).await?;
-
-    for confirmed_subscriber in confirmed_subscribers {
-        match confirmed_subscriber {
-            Ok(confirmed_subscriber) => {
-                let send_email_request = SendEmailRequest {
-                    to: &confirmed_subscriber.email,
-                    subject: &body.title,
-                    html_content: &body.content.html,
-                    text_content: &body.content.text,
-                };
-
-                email_service
-                    .send_email(email_client.get_ref(), send_email_request)
-                    .await
-                    .with_context(|| {
-                        format!(
-                            "Failed to send newsletter issue to {}",
-                            confirmed_subscriber.email
-                        )
-                    })?
-            }
-            Err(error) => {
-                tracing::warn!(
-                    error.cause_chain = ?error,
-                    "Skipping a confirmed subscriber. \
-                    Their stored contact details are invalid"
-                );
+impl BodyData {
+    /// Slug of the mailing list the issue is addressed to, if one was given.
+    pub(crate) fn list(&self) -> Result<Option<ListSlug>, String> {
+        self.list.clone().map(ListSlug::parse).transpose()
+    }
+
+    /// The issue's segment, checked to be valid. Blank segments target the
+    /// whole list.
+    pub(crate) fn segment(&self) -> Result<Option<String>, String> {
+        match self.segment.as_deref().map(str::trim) {
+            Some(segment) if !segment.is_empty() => {
+                Segment::parse(segment)?;
+                Ok(Some(segment.to_owned()))
             }
+            _ => Ok(None),
         }
     }
+}
+
+impl TryFrom<BodyData> for NewsletterIssue {
+    type Error = String;
+
+    fn try_from(value: BodyData) -> Result<Self, Self::Error> {
+        let issue = NewsletterIssue::parse(value.title, value.content.html, value.content.text)?;
+        let issue = value
+            .variants
+            .into_iter()
+            .try_fold(issue, |issue, (locale, variant)| {
+                issue.with_variant(
+                    &locale,
+                    variant.title,
+                    variant.content.html,
+                    variant.content.text,
+                )
+            })?;
+        let Some(ab_test) = value.ab_test else {
+            return Ok(issue);
+        };
+        let metric = ab_test
+            .metric
+            .map(AbTestMetric::try_from)
+            .transpose()?
+            .unwrap_or(AbTestMetric::OpenRate);
+        let variants = ab_test
+            .variants
+            .into_iter()
+            .map(|v| (v.title, v.content.map(|c| (c.html, c.text))))
+            .collect();
+        issue.with_ab_test(
+            ab_test.sample_percent,
+            ab_test.wait_minutes,
+            metric,
+            variants,
+        )
+    }
+}
+
+#[tracing::instrument(
+    name = "Publish a newsletter issue to the confirmed subscribers of a list",
+    skip(body, pool)
+)]
+pub async fn publish_newsletter(
+    body: web::Json<BodyData>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let list = body
+        .list()
+        .map_err(AppError::ValidationError)?
+        .unwrap_or_default();
+    let segment = body.segment().map_err(AppError::ValidationError)?;
+    let issue: NewsletterIssue = body.0.try_into().map_err(AppError::ValidationError)?;
+    let list_id = get_list_id(pool.get_ref(), &list)
+        .await
+        .context("Failed to retrieve the mailing list.")?
+        .ok_or_else(|| AppError::ValidationError(unknown_list(&list)))?;
+
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool.")?;
+    let issue_id = insert_newsletter_issue(
+        &mut transaction,
+        &issue,
+        list_id,
+        segment.as_deref(),
+        IssueStatus::Sending,
+    )
+    .await
+    .context("Failed to store newsletter issue details.")?;
+    start_issue_delivery(&mut transaction, issue_id)
+        .await
+        .context("Failed to start the newsletter issue delivery.")?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to publish a newsletter issue.")?;
 
     Ok(HttpResponse::Ok().finish())
 }
 
-struct ConfirmedSubscriber {
-    email: SubscriberEmail,
+#[tracing::instrument(name = "Save newsletter issue details", skip(transaction, issue))]
+pub(crate) async fn insert_newsletter_issue(
+    transaction: &mut Transaction<'_, Postgres>,
+    issue: &NewsletterIssue,
+    list_id: Uuid,
+    segment: Option<&str>,
+    status: IssueStatus,
+) -> Result<Uuid, sqlx::Error> {
+    let issue_id = Uuid::new_v4();
+    let now = Utc::now();
+    let query = sqlx::query!(
+        r#"
+    INSERT INTO newsletter_issues (
+        id, title, text_content, html_content, status, created_at, updated_at, list_id, segment
+    )
+    VALUES ($1, $2, $3, $4, $5, $6, $6, $7, $8)
+            "#,
+        issue_id,
+        issue.title,
+        issue.text_content,
+        issue.html_content,
+        status.as_str(),
+        now,
+        list_id,
+        segment
+    );
+    transaction.execute(query).await?;
+    insert_issue_variants(transaction, issue_id, issue).await?;
+    Ok(issue_id)
+}
+
+/// Saves the issue's translations and its A/B test, if any.
+#[tracing::instrument(name = "Save newsletter issue variants", skip(transaction, issue))]
+pub(crate) async fn insert_issue_variants(
+    transaction: &mut Transaction<'_, Postgres>,
+    issue_id: Uuid,
+    issue: &NewsletterIssue,
+) -> Result<(), sqlx::Error> {
+    for variant in &issue.variants {
+        let query = sqlx::query!(
+            r#"
+    INSERT INTO newsletter_issue_variants (
+        newsletter_issue_id, locale, title, text_content, html_content
+    )
+    VALUES ($1, $2, $3, $4, $5)
+            "#,
+            issue_id,
+            variant.locale.as_ref(),
+            variant.title,
+            variant.text_content,
+            variant.html_content
+        );
+        transaction.execute(query).await?;
+    }
+    let Some(ab_test) = &issue.ab_test else {
+        return Ok(());
+    };
+    let query = sqlx::query!(
+        r#"
+    INSERT INTO ab_tests (newsletter_issue_id, sample_percent, wait_minutes, metric)
+    VALUES ($1, $2, $3, $4)
+            "#,
+        issue_id,
+        ab_test.sample_percent,
+        ab_test.wait_minutes,
+        ab_test.metric.as_str()
+    );
+    transaction.execute(query).await?;
+    for variant in &ab_test.variants {
+        let query = sqlx::query!(
+            r#"
+    INSERT INTO ab_test_variants (
+        newsletter_issue_id, label, title, text_content, html_content
+    )
+    VALUES ($1, $2, $3, $4, $5)
+            "#,
+            issue_id,
+            variant.label,
+            variant.title,
+            variant.text_content,
+            variant.html_content
+        );
+        transaction.execute(query).await?;
+    }
+    Ok(())
+}
+
+pub(crate) fn unknown_list(list: &ListSlug) -> String {
+    format!("There is no mailing list called {}.", list.as_ref())
 }
 
-struct Row {
-    email: String,
+/// Appends the `FROM` and `WHERE` clauses selecting the confirmed
+/// subscribers of a list that match the segment, if any. Subscribers who
+/// paused delivery are left out until their pause ends.
+pub(crate) fn push_recipients(
+    query: &mut QueryBuilder<'_, Postgres>,
+    list_id: Uuid,
+    segment: Option<&Segment>,
+) {
+    query
+        .push(
+            " FROM list_subscriptions \
+            JOIN subscriptions ON subscriptions.id = list_subscriptions.subscriber_id \
+            WHERE list_subscriptions.list_id = ",
+        )
+        .push_bind(list_id)
+        .push(" AND list_subscriptions.status = ")
+        .push_bind(SubscriptionStatus::Confirmed.as_str())
+        .push(" AND (subscriptions.paused_until IS NULL OR subscriptions.paused_until <= ")
+        .push_bind(Utc::now())
+        .push(")");
+    if let Some(segment) = segment {
+        query.push(" AND ");
+        segment.push_sql(query, Utc::now());
+    }
 }
 
-async fn get_confirmed_subscribers(
-    pool: &PgPool,
-) -> Result<Vec<Result<ConfirmedSubscriber, anyhow::Error>>, anyhow::Error> {
-    let rows = sqlx::query_as!(
-        Row,
+/// Publishes the issue to the archive and queues one delivery per confirmed
+/// subscriber of the issue's list, narrowed down by the issue's segment. The
+/// emails are sent by the issue delivery worker, which keeps the matching
+/// `issue_deliveries` rows up to date for the delivery report. Subscribers
+/// who asked for digests get the issue with their next digest instead.
+#[tracing::instrument(name = "Start newsletter issue delivery", skip(transaction))]
+pub(crate) async fn start_issue_delivery(
+    transaction: &mut Transaction<'_, Postgres>,
+    issue_id: Uuid,
+) -> Result<(), anyhow::Error> {
+    let issue = sqlx::query!(
+        r#"SELECT title, list_id, segment FROM newsletter_issues WHERE id = $1"#,
+        issue_id
+    )
+    .fetch_one(&mut **transaction)
+    .await?;
+    let slug = IssueSlug::new(&issue.title, issue_id);
+    let segment = issue
+        .segment
+        .as_deref()
+        .map(Segment::parse)
+        .transpose()
+        .map_err(anyhow::Error::msg)
+        .context("The issue's stored segment is invalid.")?;
+
+    let query = sqlx::query!(
         r#"
-        SELECT email
-        FROM subscriptions
-        WHERE status = 'confirmed'
-        "#
+    UPDATE newsletter_issues
+    SET slug = $2, published_at = $3
+    WHERE id = $1
+            "#,
+        issue_id,
+        slug.as_ref(),
+        Utc::now()
+    );
+    transaction.execute(query).await?;
+
+    let mut query = QueryBuilder::new(
+        "INSERT INTO issue_delivery_queue (newsletter_issue_id, subscriber_email) SELECT ",
+    );
+    query.push_bind(issue_id).push(", subscriptions.email");
+    push_recipients(&mut query, issue.list_id, segment.as_ref());
+    query
+        .push(" AND subscriptions.digest_frequency = ")
+        .push_bind(DigestFrequency::Immediate.as_str());
+    query.build().execute(&mut **transaction).await?;
+
+    let mut query = QueryBuilder::new(
+        "INSERT INTO digest_queue (subscriber_id, newsletter_issue_id, queued_at) \
+        SELECT subscriptions.id, ",
+    );
+    query.push_bind(issue_id).push(", ").push_bind(Utc::now());
+    push_recipients(&mut query, issue.list_id, segment.as_ref());
+    query
+        .push(" AND subscriptions.digest_frequency <> ")
+        .push_bind(DigestFrequency::Immediate.as_str());
+    query.build().execute(&mut **transaction).await?;
+
+    let query = sqlx::query!(
+        r#"
+    INSERT INTO issue_deliveries (
+        newsletter_issue_id, subscriber_email, status, queued_at, updated_at
+    )
+    SELECT $1, recipients.email, $2, $3, $3
+    FROM (
+        SELECT subscriber_email AS email
+        FROM issue_delivery_queue
+        WHERE newsletter_issue_id = $1
+        UNION ALL
+        SELECT subscriptions.email
+        FROM digest_queue
+        JOIN subscriptions ON subscriptions.id = digest_queue.subscriber_id
+        WHERE digest_queue.newsletter_issue_id = $1
+    ) AS recipients
+            "#,
+        issue_id,
+        DeliveryStatus::Queued.as_str(),
+        Utc::now()
+    );
+    transaction.execute(query).await?;
+    start_ab_test(transaction, issue_id).await?;
+    Ok(())
+}
+
+/// Splits a sample of the queued recipients between the variants of the
+/// issue's A/B test, if it has one, and holds the others back until the
+/// winner is picked. Digest subscribers are left out of the test and get
+/// the issue as written.
+async fn start_ab_test(
+    transaction: &mut Transaction<'_, Postgres>,
+    issue_id: Uuid,
+) -> Result<(), anyhow::Error> {
+    let Some(ab_test) = sqlx::query!(
+        r#"SELECT sample_percent, wait_minutes FROM ab_tests WHERE newsletter_issue_id = $1"#,
+        issue_id
+    )
+    .fetch_optional(&mut **transaction)
+    .await?
+    else {
+        return Ok(());
+    };
+    let labels = sqlx::query_scalar!(
+        r#"
+    SELECT label FROM ab_test_variants
+    WHERE newsletter_issue_id = $1
+    ORDER BY label
+            "#,
+        issue_id
     )
-    .fetch_all(pool)
+    .fetch_all(&mut **transaction)
     .await?;
-    let confirmed_subscribers = rows
-        .into_iter()
-        .map(|r| match SubscriberEmail::parse(r.email) {
-            Ok(email) => Ok(ConfirmedSubscriber { email }),
-            Err(error) => Err(anyhow::anyhow!(error)),
-        })
-        .collect();
-    Ok(confirmed_subscribers)
+    let queued = sqlx::query_scalar!(
+        r#"
+    SELECT COUNT(*) as "count!"
+    FROM issue_delivery_queue
+    WHERE newsletter_issue_id = $1
+            "#,
+        issue_id
+    )
+    .fetch_one(&mut **transaction)
+    .await?;
+    let sample_size = (queued * i64::from(ab_test.sample_percent) + 99) / 100;
+
+    let query = sqlx::query!(
+        r#"
+    UPDATE issue_deliveries
+    SET ab_variant = sample.label
+    FROM (
+        SELECT subscriber_email,
+               ($3::text[])[(row_number() OVER () - 1) % cardinality($3::text[]) + 1] AS label
+        FROM (
+            SELECT subscriber_email
+            FROM issue_delivery_queue
+            WHERE newsletter_issue_id = $1
+            ORDER BY random()
+            LIMIT $2
+        ) AS shuffled
+    ) AS sample
+    WHERE issue_deliveries.newsletter_issue_id = $1
+      AND issue_deliveries.subscriber_email = sample.subscriber_email
+            "#,
+        issue_id,
+        sample_size,
+        &labels
+    );
+    transaction.execute(query).await?;
+    let query = sqlx::query!(
+        r#"
+    WITH held_back AS (
+        DELETE FROM issue_delivery_queue
+        USING issue_deliveries
+        WHERE issue_delivery_queue.newsletter_issue_id = $1
+          AND issue_deliveries.newsletter_issue_id = $1
+          AND issue_deliveries.subscriber_email = issue_delivery_queue.subscriber_email
+          AND issue_deliveries.ab_variant IS NULL
+        RETURNING issue_delivery_queue.subscriber_email
+    )
+    INSERT INTO ab_test_holdouts (newsletter_issue_id, subscriber_email)
+    SELECT $1, subscriber_email FROM held_back
+            "#,
+        issue_id
+    );
+    transaction.execute(query).await?;
+    let query = sqlx::query!(
+        r#"
+    UPDATE ab_tests
+    SET decide_at = $2
+    WHERE newsletter_issue_id = $1
+            "#,
+        issue_id,
+        Utc::now() + chrono::Duration::minutes(i64::from(ab_test.wait_minutes))
+    );
+    transaction.execute(query).await?;
+    Ok(())
 }
@@ -0,0 +1,214 @@
+use crate::configuration::BrandingSettings;
+use crate::domain::Locale;
+use crate::i18n::{Catalogs, Translator};
+use crate::routes::{retry_after_header, AppError, ErrorCode, Problem};
+use actix_web::error::InternalError;
+use actix_web::http::header::{Accept, ContentType, ACCEPT_LANGUAGE, CONTENT_LANGUAGE};
+use actix_web::http::StatusCode;
+use actix_web::{HttpMessage, HttpRequest, HttpResponse, ResponseError};
+use htmlescape::encode_minimal;
+use std::fmt::{Debug, Display};
+use std::time::Duration;
+
+/// What happened when a subscriber followed a link or submitted a form,
+/// rendered as a page for browsers and as JSON for API clients.
+pub(crate) struct Outcome {
+    status: StatusCode,
+    code: &'static str,
+    lang: String,
+    title: String,
+    message: String,
+    link: Option<(String, String)>,
+    retry_after: Option<Duration>,
+}
+
+impl Outcome {
+    /// Takes its title and message from the `pages.<page>` texts, filling
+    /// the message in with `values`.
+    pub(crate) fn new(
+        t: &Translator,
+        status: StatusCode,
+        code: &'static str,
+        page: &str,
+        values: &[(&str, &str)],
+    ) -> Self {
+        Self {
+            status,
+            code,
+            lang: t.locale().to_string(),
+            title: t.text(&format!("pages.{}.title", page)),
+            message: t.text_with(&format!("pages.{}.message", page), values),
+            link: None,
+            retry_after: None,
+        }
+    }
+
+    /// A follow-up link, only shown on the HTML page.
+    pub(crate) fn with_link(mut self, href: String, label: &str) -> Self {
+        self.link = Some((href, label.to_owned()));
+        self
+    }
+
+    pub(crate) fn with_retry_after(mut self, retry_after: Duration) -> Self {
+        self.retry_after = Some(retry_after);
+        self
+    }
+
+    /// Answers with the status and code of `error`, and the texts of `page`.
+    pub(crate) fn for_error(
+        t: &Translator,
+        error: &AppError,
+        page: &str,
+        values: &[(&str, &str)],
+    ) -> Self {
+        let outcome = Self::new(t, error.status_code(), error.code().as_str(), page, values);
+        match error {
+            AppError::RateLimited(retry_after) => outcome.with_retry_after(*retry_after),
+            _ => outcome,
+        }
+    }
+
+    pub(crate) fn unexpected_error(t: &Translator) -> Self {
+        Self::new(
+            t,
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ErrorCode::UnexpectedError.as_str(),
+            "unexpected_error",
+            &[],
+        )
+    }
+}
+
+/// The supported locale the client ranks highest, if any.
+pub(crate) fn request_locale(request: &HttpRequest, catalogs: &Catalogs) -> Option<Locale> {
+    request
+        .headers()
+        .get(ACCEPT_LANGUAGE)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|accept_language| catalogs.negotiate(accept_language))
+}
+
+/// The locale stored for a subscriber or, when they never named one, the
+/// one negotiated with their browser.
+pub(crate) fn subscriber_locale(
+    stored: Option<&str>,
+    request: &HttpRequest,
+    catalogs: &Catalogs,
+) -> Option<Locale> {
+    stored
+        .and_then(|locale| Locale::parse(locale).ok())
+        .or_else(|| request_locale(request, catalogs))
+}
+
+#[derive(serde::Serialize)]
+struct OutcomeBody<'a> {
+    code: &'a str,
+    message: &'a str,
+}
+
+pub(crate) fn render_outcome(
+    request: &HttpRequest,
+    branding: &BrandingSettings,
+    outcome: &Outcome,
+) -> HttpResponse {
+    let mut response = HttpResponse::build(outcome.status);
+    response.insert_header((CONTENT_LANGUAGE, outcome.lang.as_str()));
+    if let Some(retry_after) = outcome.retry_after {
+        response.insert_header(retry_after_header(retry_after));
+    }
+    if prefers_json(request) {
+        return response.json(OutcomeBody {
+            code: outcome.code,
+            message: &outcome.message,
+        });
+    }
+
+    let logo = branding
+        .logo_url
+        .as_ref()
+        .map(|url| {
+            format!(
+                "<img src=\"{}\" alt=\"{}\" height=\"48\">\n",
+                encode_minimal(url),
+                encode_minimal(&branding.name)
+            )
+        })
+        .unwrap_or_default();
+    let link = outcome
+        .link
+        .as_ref()
+        .map(|(href, label)| {
+            format!(
+                "<p><a href=\"{}\">{}</a></p>\n",
+                encode_minimal(href),
+                encode_minimal(label)
+            )
+        })
+        .unwrap_or_default();
+    response.content_type(ContentType::html()).body(format!(
+        r#"<!DOCTYPE html>
+<html lang="{lang}">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1">
+    <title>{title} - {brand}</title>
+    <style>
+        body {{ font-family: sans-serif; max-width: 36em; margin: 4em auto; padding: 0 1em; color: #222; }}
+        header {{ border-bottom: 4px solid {accent}; margin-bottom: 2em; padding-bottom: 1em; }}
+        a {{ color: {accent}; }}
+    </style>
+</head>
+<body>
+<header>
+{logo}<strong>{brand}</strong>
+</header>
+<h1>{title}</h1>
+<p>{message}</p>
+{link}</body>
+</html>"#,
+        lang = encode_minimal(&outcome.lang),
+        title = encode_minimal(&outcome.title),
+        message = encode_minimal(&outcome.message),
+        brand = encode_minimal(&branding.name),
+        accent = branding.accent_color,
+    ))
+}
+
+/// Turns an error into one that responds with its outcome, while keeping the
+/// error itself for the request logs. JSON clients get problem details.
+pub(crate) fn error_outcome<E>(
+    request: &HttpRequest,
+    branding: &BrandingSettings,
+    error: E,
+    outcome: &Outcome,
+) -> actix_web::Error
+where
+    E: Debug + Display + 'static,
+{
+    let response = if prefers_json(request) {
+        let mut problem = Problem::new(outcome.status, outcome.code, outcome.message.clone())
+            .with_title(&outcome.title);
+        if let Some(retry_after) = outcome.retry_after {
+            problem = problem.with_retry_after(retry_after);
+        }
+        problem.into_response()
+    } else {
+        render_outcome(request, branding, outcome)
+    };
+    InternalError::from_response(error, response).into()
+}
+
+/// Browsers get pages. Clients that rank JSON above HTML get JSON.
+fn prefers_json(request: &HttpRequest) -> bool {
+    let Some(accept) = request.get_header::<Accept>() else {
+        return false;
+    };
+    for mime in accept.ranked() {
+        match (mime.type_().as_str(), mime.subtype().as_str()) {
+            ("application", "json") => return true,
+            ("text", "html") | ("text", "*") | ("*", "*") => return false,
+            _ => {}
+        }
+    }
+    false
+}
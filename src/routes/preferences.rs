@@ -0,0 +1,649 @@
+use crate::domain::{
+    ConsentEvent, ConsentEvidence, DigestFrequency, FormSource, Locale, SubscriberName,
+    SubscriptionStatus,
+};
+use crate::i18n::Catalogs;
+use crate::routes::{
+    consent_evidence, erase_subscriber, generate_subscription_token, get_subscriber_data,
+    record_consent, AppError,
+};
+use crate::startup::AppState;
+use actix_web::http::header::{ContentDisposition, ContentType, DispositionParam, DispositionType};
+use actix_web::{web, HttpRequest, HttpResponse};
+use anyhow::Context;
+use chrono::{DateTime, Duration, Utc};
+use htmlescape::encode_minimal;
+use sqlx::{Executor, PgExecutor, PgPool, Postgres, Transaction};
+use std::fmt::Write;
+use uuid::Uuid;
+
+const MAX_PAUSE_WEEKS: u32 = 52;
+
+/// The submitted preferences form. Lists are repeated `lists` checkboxes, so
+/// the raw form is read as key-value pairs.
+struct PreferencesForm {
+    name: SubscriberName,
+    lists: Vec<String>,
+    digest_frequency: DigestFrequency,
+    /// `None` keeps the current pause, `Some(0)` resumes delivery.
+    pause_weeks: Option<u32>,
+    /// `None` keeps the current language.
+    locale: Option<Locale>,
+}
+
+impl TryFrom<Vec<(String, String)>> for PreferencesForm {
+    type Error = String;
+
+    fn try_from(fields: Vec<(String, String)>) -> Result<Self, Self::Error> {
+        let mut name = None;
+        let mut lists = Vec::new();
+        let mut digest_frequency = None;
+        let mut pause_weeks = None;
+        let mut locale = None;
+        for (key, value) in fields {
+            match key.as_str() {
+                "name" => name = Some(SubscriberName::parse(value)?),
+                "lists" => lists.push(value),
+                "digest_frequency" => digest_frequency = Some(DigestFrequency::try_from(value)?),
+                "pause_weeks" if value.trim().is_empty() => {}
+                "pause_weeks" => {
+                    let weeks = value
+                        .trim()
+                        .parse::<u32>()
+                        .ok()
+                        .filter(|weeks| *weeks <= MAX_PAUSE_WEEKS)
+                        .ok_or_else(|| {
+                            format!(
+                                "Delivery can be paused for up to {} weeks.",
+                                MAX_PAUSE_WEEKS
+                            )
+                        })?;
+                    pause_weeks = Some(weeks);
+                }
+                "locale" if value.trim().is_empty() => {}
+                "locale" => locale = Some(Locale::parse(&value)?),
+                other => return Err(format!("{} is not a known preference.", other)),
+            }
+        }
+        Ok(Self {
+            name: name.ok_or("The name is missing.")?,
+            lists,
+            digest_frequency: digest_frequency.ok_or("The digest frequency is missing.")?,
+            pause_weeks,
+            locale,
+        })
+    }
+}
+
+struct Subscriber {
+    id: Uuid,
+    email: String,
+    name: String,
+    digest_frequency: String,
+    paused_until: Option<DateTime<Utc>>,
+    locale: Option<String>,
+}
+
+impl Subscriber {
+    fn locale(&self) -> Option<Locale> {
+        self.locale
+            .as_deref()
+            .and_then(|locale| Locale::parse(locale).ok())
+    }
+}
+
+struct ListMembership {
+    id: Uuid,
+    slug: String,
+    name: String,
+    status: Option<String>,
+}
+
+impl ListMembership {
+    fn is_confirmed(&self) -> bool {
+        self.status.as_deref() == Some(SubscriptionStatus::Confirmed.as_str())
+    }
+}
+
+#[tracing::instrument(name = "Render the subscriber preferences", skip(token, pool, state))]
+pub async fn preferences_page(
+    token: web::Path<String>,
+    pool: web::Data<PgPool>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let subscriber = get_subscriber(pool.get_ref(), &token)
+        .await
+        .context("Failed to retrieve the subscriber.")?
+        .ok_or_else(unknown_preferences_link)?;
+    let lists = get_list_memberships(pool.get_ref(), subscriber.id)
+        .await
+        .context("Failed to retrieve the subscriber's lists.")?;
+
+    Ok(render_preferences(
+        &state.catalogs,
+        &token,
+        &subscriber,
+        &lists,
+        None,
+    ))
+}
+
+/// Applies the submitted preferences and records every change in the
+/// subscriber's audit trail. Following the emailed link proves ownership of
+/// the address, so lists picked here are confirmed straight away, with the
+/// consent recorded as for a confirmation link.
+#[tracing::instrument(
+    name = "Update the subscriber preferences",
+    skip(token, form, request, pool, state)
+)]
+pub async fn update_preferences(
+    token: web::Path<String>,
+    form: web::Form<Vec<(String, String)>>,
+    request: HttpRequest,
+    pool: web::Data<PgPool>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let mut form =
+        PreferencesForm::try_from(form.into_inner()).map_err(AppError::ValidationError)?;
+
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool.")?;
+    let subscriber = get_subscriber(&mut *transaction, &token)
+        .await
+        .context("Failed to retrieve the subscriber.")?
+        .ok_or_else(unknown_preferences_link)?;
+    let lists = get_list_memberships(&mut *transaction, subscriber.id)
+        .await
+        .context("Failed to retrieve the subscriber's lists.")?;
+    if let Some(unknown) = form
+        .lists
+        .iter()
+        .find(|slug| !lists.iter().any(|l| &l.slug == *slug))
+    {
+        return Err(AppError::ValidationError(format!(
+            "There is no mailing list called {}.",
+            unknown
+        )));
+    }
+    // Keeping the language the page is shown in keeps the stored locale,
+    // which may be more specific, such as `pt-BR` shown in `pt`.
+    let shown_locale = state
+        .catalogs
+        .translator(subscriber.locale().as_ref())
+        .locale()
+        .clone();
+    if form.locale.as_ref() == Some(&shown_locale) {
+        form.locale = None;
+    }
+
+    let consent = consent_evidence(
+        &request,
+        FormSource::preference_center(),
+        &state.subscriptions,
+    );
+    apply_preferences(&mut transaction, &subscriber, &lists, &form, &consent)
+        .await
+        .context("Failed to update the subscriber preferences.")?;
+
+    let subscriber = get_subscriber(&mut *transaction, &token)
+        .await
+        .context("Failed to retrieve the subscriber.")?
+        .ok_or_else(unknown_preferences_link)?;
+    let lists = get_list_memberships(&mut *transaction, subscriber.id)
+        .await
+        .context("Failed to retrieve the subscriber's lists.")?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to update the subscriber preferences.")?;
+
+    Ok(render_preferences(
+        &state.catalogs,
+        &token,
+        &subscriber,
+        &lists,
+        Some("preferences.saved"),
+    ))
+}
+
+#[tracing::instrument(name = "Export the subscriber's own data", skip(token, pool))]
+pub async fn export_own_data(
+    token: web::Path<String>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let subscriber = get_subscriber(pool.get_ref(), &token)
+        .await
+        .context("Failed to retrieve the subscriber.")?
+        .ok_or_else(unknown_preferences_link)?;
+    let data = get_subscriber_data(pool.get_ref(), subscriber.id)
+        .await
+        .context("Failed to collect the subscriber's data.")?
+        .ok_or_else(unknown_preferences_link)?;
+
+    Ok(HttpResponse::Ok()
+        .insert_header(ContentDisposition {
+            disposition: DispositionType::Attachment,
+            parameters: vec![DispositionParam::Filename("my-data.json".into())],
+        })
+        .json(data))
+}
+
+#[derive(serde::Deserialize)]
+pub struct EraseForm {
+    confirm: Option<String>,
+}
+
+/// Erases the subscriber for good. The form asks for an explicit
+/// confirmation, as there is no way back.
+#[tracing::instrument(
+    name = "Erase the subscriber's own data",
+    skip(token, form, pool, state)
+)]
+pub async fn erase_own_data(
+    token: web::Path<String>,
+    form: web::Form<EraseForm>,
+    pool: web::Data<PgPool>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    if form.0.confirm.as_deref() != Some("yes") {
+        return Err(AppError::ValidationError(
+            "Please confirm that your data should be erased.".into(),
+        ));
+    }
+
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool.")?;
+    let subscriber = get_subscriber(&mut *transaction, &token)
+        .await
+        .context("Failed to retrieve the subscriber.")?
+        .ok_or_else(unknown_preferences_link)?;
+    erase_subscriber(
+        &mut transaction,
+        subscriber.id,
+        &subscriber.email,
+        "self_service",
+        state.subscriptions.fold_email_aliases,
+    )
+    .await
+    .context("Failed to erase the subscriber.")?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to erase the subscriber.")?;
+
+    let t = state.catalogs.translator(subscriber.locale().as_ref());
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="{lang}">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>{title}</title>
+</head>
+<body>
+<h1>{title}</h1>
+<p>{message}</p>
+</body>
+</html>"#,
+            lang = t.locale(),
+            title = encode_minimal(&t.text("pages.erased.title")),
+            message = encode_minimal(&t.text("pages.erased.message")),
+        )))
+}
+
+async fn apply_preferences(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber: &Subscriber,
+    lists: &[ListMembership],
+    form: &PreferencesForm,
+    consent: &ConsentEvidence,
+) -> Result<(), sqlx::Error> {
+    let now = Utc::now();
+
+    if form.name.as_ref() != subscriber.name {
+        let query = sqlx::query!(
+            r#"UPDATE subscriptions SET name = $2 WHERE id = $1"#,
+            subscriber.id,
+            form.name.as_ref()
+        );
+        transaction.execute(query).await?;
+        record_preference_change(
+            transaction,
+            subscriber.id,
+            "name",
+            Some(&subscriber.name),
+            Some(form.name.as_ref()),
+            "preference_center",
+        )
+        .await?;
+    }
+
+    if form.digest_frequency.as_str() != subscriber.digest_frequency {
+        let query = sqlx::query!(
+            r#"UPDATE subscriptions SET digest_frequency = $2 WHERE id = $1"#,
+            subscriber.id,
+            form.digest_frequency.as_str()
+        );
+        transaction.execute(query).await?;
+        record_preference_change(
+            transaction,
+            subscriber.id,
+            "digest_frequency",
+            Some(&subscriber.digest_frequency),
+            Some(form.digest_frequency.as_str()),
+            "preference_center",
+        )
+        .await?;
+    }
+
+    if let Some(locale) = &form.locale {
+        if subscriber.locale.as_deref() != Some(locale.as_ref()) {
+            let query = sqlx::query!(
+                r#"UPDATE subscriptions SET locale = $2 WHERE id = $1"#,
+                subscriber.id,
+                locale.as_ref()
+            );
+            transaction.execute(query).await?;
+            record_preference_change(
+                transaction,
+                subscriber.id,
+                "locale",
+                subscriber.locale.as_deref(),
+                Some(locale.as_ref()),
+                "preference_center",
+            )
+            .await?;
+        }
+    }
+
+    if let Some(weeks) = form.pause_weeks {
+        let paused_until = (weeks > 0).then(|| now + Duration::weeks(i64::from(weeks)));
+        if paused_until.is_some() || subscriber.paused_until.is_some() {
+            let query = sqlx::query!(
+                r#"UPDATE subscriptions SET paused_until = $2 WHERE id = $1"#,
+                subscriber.id,
+                paused_until
+            );
+            transaction.execute(query).await?;
+            record_preference_change(
+                transaction,
+                subscriber.id,
+                "paused_until",
+                subscriber.paused_until.map(|t| t.to_rfc3339()).as_deref(),
+                paused_until.map(|t| t.to_rfc3339()).as_deref(),
+                "preference_center",
+            )
+            .await?;
+        }
+    }
+
+    for list in lists {
+        let wanted = form.lists.contains(&list.slug);
+        let status = match (wanted, list.is_confirmed()) {
+            (true, false) => SubscriptionStatus::Confirmed,
+            (false, true) => SubscriptionStatus::Unsubscribed,
+            _ => continue,
+        };
+        let query = sqlx::query!(
+            r#"
+    INSERT INTO list_subscriptions (
+        list_id, subscriber_id, status, unsubscribe_token, subscribed_at, updated_at
+    )
+    VALUES ($1, $2, $3, $4, $5, $5)
+    ON CONFLICT (list_id, subscriber_id) DO UPDATE
+    SET status = EXCLUDED.status, updated_at = EXCLUDED.updated_at
+            "#,
+            list.id,
+            subscriber.id,
+            status.as_str(),
+            generate_subscription_token(),
+            now
+        );
+        transaction.execute(query).await?;
+        record_preference_change(
+            transaction,
+            subscriber.id,
+            &format!("list:{}", list.slug),
+            list.status.as_deref(),
+            Some(status.as_str()),
+            "preference_center",
+        )
+        .await?;
+        if status == SubscriptionStatus::Confirmed {
+            record_consent(
+                transaction,
+                subscriber.id,
+                list.id,
+                ConsentEvent::Confirm,
+                consent,
+            )
+            .await?;
+        }
+    }
+    Ok(())
+}
+
+/// Appends an entry to the subscriber's preference audit trail.
+#[tracing::instrument(
+    name = "Record a preference change",
+    skip(transaction, old_value, new_value)
+)]
+pub(crate) async fn record_preference_change(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_id: Uuid,
+    field: &str,
+    old_value: Option<&str>,
+    new_value: Option<&str>,
+    source: &str,
+) -> Result<(), sqlx::Error> {
+    let query = sqlx::query!(
+        r#"
+    INSERT INTO subscriber_preference_changes (
+        id, subscriber_id, field, old_value, new_value, source, changed_at
+    )
+    VALUES ($1, $2, $3, $4, $5, $6, $7)
+            "#,
+        Uuid::new_v4(),
+        subscriber_id,
+        field,
+        old_value,
+        new_value,
+        source,
+        Utc::now()
+    );
+    transaction.execute(query).await?;
+    Ok(())
+}
+
+#[tracing::instrument(name = "Get subscriber from preferences token", skip_all)]
+async fn get_subscriber(
+    executor: impl PgExecutor<'_>,
+    token: &str,
+) -> Result<Option<Subscriber>, sqlx::Error> {
+    sqlx::query_as!(
+        Subscriber,
+        r#"
+    SELECT id, email, name, digest_frequency, paused_until, locale
+    FROM subscriptions
+    WHERE preferences_token = $1
+            "#,
+        token
+    )
+    .fetch_optional(executor)
+    .await
+}
+
+#[tracing::instrument(name = "Get the subscriber's list memberships", skip(executor))]
+async fn get_list_memberships(
+    executor: impl PgExecutor<'_>,
+    subscriber_id: Uuid,
+) -> Result<Vec<ListMembership>, sqlx::Error> {
+    sqlx::query_as!(
+        ListMembership,
+        r#"
+    SELECT lists.id, lists.slug, lists.name, list_subscriptions.status as "status?"
+    FROM lists
+    LEFT JOIN list_subscriptions
+      ON list_subscriptions.list_id = lists.id AND list_subscriptions.subscriber_id = $1
+    ORDER BY lists.created_at, lists.slug
+            "#,
+        subscriber_id
+    )
+    .fetch_all(executor)
+    .await
+}
+
+fn render_preferences(
+    catalogs: &Catalogs,
+    token: &str,
+    subscriber: &Subscriber,
+    lists: &[ListMembership],
+    notice: Option<&str>,
+) -> HttpResponse {
+    let t = catalogs.translator(subscriber.locale().as_ref());
+    let text = |key: &str| encode_minimal(&t.text(&format!("preferences.{}", key)));
+
+    let mut list_inputs = String::new();
+    for list in lists {
+        writeln!(
+            list_inputs,
+            r#"<label><input type="checkbox" name="lists" value="{}"{}> {}</label><br>"#,
+            encode_minimal(&list.slug),
+            if list.is_confirmed() { " checked" } else { "" },
+            encode_minimal(&list.name)
+        )
+        .unwrap();
+    }
+
+    let mut language_options = String::new();
+    for (locale, name) in catalogs.languages() {
+        writeln!(
+            language_options,
+            r#"<option value="{}"{}>{}</option>"#,
+            locale,
+            if locale == t.locale() {
+                " selected"
+            } else {
+                ""
+            },
+            encode_minimal(&name)
+        )
+        .unwrap();
+    }
+
+    let mut frequency_options = String::new();
+    for frequency in [
+        DigestFrequency::Immediate,
+        DigestFrequency::Daily,
+        DigestFrequency::Weekly,
+    ] {
+        writeln!(
+            frequency_options,
+            r#"<option value="{}"{}>{}</option>"#,
+            frequency.as_str(),
+            if frequency.as_str() == subscriber.digest_frequency {
+                " selected"
+            } else {
+                ""
+            },
+            text(frequency.as_str())
+        )
+        .unwrap();
+    }
+
+    let mut pause_options = String::new();
+    for weeks in [1, 2, 4, 12] {
+        let label = if weeks == 1 {
+            t.text("preferences.pause_one_week")
+        } else {
+            t.text_with("preferences.pause_weeks", &[("weeks", &weeks.to_string())])
+        };
+        writeln!(
+            pause_options,
+            r#"<option value="{}">{}</option>"#,
+            weeks,
+            encode_minimal(&label)
+        )
+        .unwrap();
+    }
+
+    let pause_status = match subscriber.paused_until {
+        Some(until) if until > Utc::now() => {
+            let date = t.date("preferences.date_format", until);
+            format!(
+                "<p><time datetime=\"{}\">{}</time></p>\n",
+                until.to_rfc3339(),
+                encode_minimal(&t.text_with("preferences.paused_until", &[("date", &date)]))
+            )
+        }
+        _ => String::new(),
+    };
+    let notice = notice
+        .map(|n| format!("<p><strong>{}</strong></p>\n", encode_minimal(&t.text(n))))
+        .unwrap_or_default();
+
+    HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="{lang}">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>{title}</title>
+</head>
+<body>
+<h1>{title}</h1>
+{notice}<form method="post" action="/preferences/{token}">
+<p><label>{name_label} <input type="text" name="name" value="{name}" required></label></p>
+<fieldset>
+<legend>{lists_label}</legend>
+{list_inputs}</fieldset>
+<p><label>{language_label}
+<select name="locale">
+{language_options}</select></label></p>
+<p><label>{send_me}
+<select name="digest_frequency">
+{frequency_options}</select></label></p>
+{pause_status}<p><label>{pause}
+<select name="pause_weeks">
+<option value="" selected>{keep_pause}</option>
+<option value="0">{resume}</option>
+{pause_options}</select></label></p>
+<p><button type="submit">{save}</button></p>
+</form>
+<h2>{your_data}</h2>
+<p><a href="/preferences/{token}/export">{export}</a></p>
+<form method="post" action="/preferences/{token}/erase">
+<p><label><input type="checkbox" name="confirm" value="yes" required>
+{erase_confirmation}</label></p>
+<p><button type="submit">{erase}</button></p>
+</form>
+</body>
+</html>"#,
+            lang = t.locale(),
+            title = text("title"),
+            name_label = text("name"),
+            lists_label = text("lists"),
+            language_label = text("language"),
+            send_me = text("send_me"),
+            pause = text("pause"),
+            keep_pause = text("keep_pause"),
+            resume = text("resume"),
+            save = text("save"),
+            your_data = text("your_data"),
+            export = text("export"),
+            erase_confirmation = text("erase_confirmation"),
+            erase = text("erase"),
+            token = encode_minimal(token),
+            name = encode_minimal(&subscriber.name),
+        ))
+}
+
+fn unknown_preferences_link() -> AppError {
+    AppError::NotFound("This preferences link is not valid.".into())
+}
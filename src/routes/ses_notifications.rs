@@ -0,0 +1,122 @@
+use crate::domain::DeliveryStatus;
+use crate::routes::AppError;
+use crate::startup::AppState;
+use actix_web::{web, HttpResponse};
+use anyhow::Context;
+use chrono::Utc;
+use secrecy::ExposeSecret;
+use sqlx::PgPool;
+use subtle::ConstantTimeEq;
+
+/// Envelope of the messages SNS posts to HTTP(S) subscribers.
+#[derive(serde::Deserialize)]
+#[serde(tag = "Type")]
+enum SnsMessage {
+    SubscriptionConfirmation {
+        #[serde(rename = "SubscribeURL")]
+        subscribe_url: String,
+    },
+    Notification {
+        #[serde(rename = "Message")]
+        message: String,
+    },
+    UnsubscribeConfirmation {},
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SesNotification {
+    notification_type: String,
+    bounce: Option<SesBounce>,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SesBounce {
+    bounce_type: String,
+    bounced_recipients: Vec<SesBouncedRecipient>,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SesBouncedRecipient {
+    email_address: String,
+}
+
+/// Receives SES bounce notifications relayed by SNS.
+///
+/// The endpoint is only enabled when a notifications token is configured, and
+/// the token must be part of the URL registered in the SNS subscription.
+#[tracing::instrument(name = "Handle an SES notification", skip_all)]
+pub async fn handle_ses_notification(
+    token: web::Path<String>,
+    body: web::Bytes,
+    pool: web::Data<PgPool>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    match &state.notifications_token {
+        Some(expected)
+            if bool::from(expected.expose_secret().as_bytes().ct_eq(token.as_bytes())) => {}
+        _ => return Err(AppError::NotFound("Unknown notification endpoint.".into())),
+    }
+
+    let message: SnsMessage =
+        serde_json::from_slice(&body).map_err(|e| AppError::ValidationError(e.to_string()))?;
+    match message {
+        SnsMessage::SubscriptionConfirmation { subscribe_url } => {
+            tracing::info!(
+                subscribe_url = %subscribe_url,
+                "Received an SNS subscription confirmation request."
+            );
+        }
+        SnsMessage::Notification { message } => {
+            let notification: SesNotification = serde_json::from_str(&message)
+                .map_err(|e| AppError::ValidationError(e.to_string()))?;
+            if let ("Bounce", Some(bounce)) =
+                (notification.notification_type.as_str(), notification.bounce)
+            {
+                for recipient in bounce.bounced_recipients {
+                    mark_latest_delivery_as_bounced(
+                        &pool,
+                        &recipient.email_address,
+                        &bounce.bounce_type,
+                    )
+                    .await
+                    .context("Failed to record a bounced delivery.")?;
+                }
+            }
+        }
+        SnsMessage::UnsubscribeConfirmation {} => {}
+    }
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+#[tracing::instrument(name = "Mark delivery as bounced", skip(pool, email))]
+async fn mark_latest_delivery_as_bounced(
+    pool: &PgPool,
+    email: &str,
+    bounce_type: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+    UPDATE issue_deliveries
+    SET status = $2, error = $3, updated_at = $4
+    WHERE (newsletter_issue_id, subscriber_email) = (
+        SELECT newsletter_issue_id, subscriber_email
+        FROM issue_deliveries
+        WHERE subscriber_email = $1 AND status = $5
+        ORDER BY updated_at DESC
+        LIMIT 1
+    )
+            "#,
+        email,
+        DeliveryStatus::Bounced.as_str(),
+        format!("{} bounce", bounce_type),
+        Utc::now(),
+        DeliveryStatus::Sent.as_str()
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
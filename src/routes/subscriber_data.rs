@@ -0,0 +1,354 @@
+use crate::domain::SubscriberEmail;
+use chrono::{DateTime, Utc};
+use sha2::{Digest, Sha256};
+use sqlx::{Executor, PgExecutor, PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+/// Everything we hold about a subscriber, as handed out for data-subject
+/// access requests.
+#[derive(serde::Serialize)]
+pub(crate) struct SubscriberData {
+    profile: Profile,
+    lists: Vec<ListMembership>,
+    consents: Vec<ConsentRecord>,
+    preference_changes: Vec<PreferenceChange>,
+    deliveries: Vec<Delivery>,
+    clicks: Vec<LinkClick>,
+    digests: Vec<DigestRecord>,
+}
+
+#[derive(serde::Serialize)]
+struct Profile {
+    id: Uuid,
+    email: String,
+    name: String,
+    subscribed_at: DateTime<Utc>,
+    attributes: serde_json::Value,
+    tags: Vec<String>,
+    digest_frequency: String,
+    paused_until: Option<DateTime<Utc>>,
+    locale: Option<String>,
+}
+
+#[derive(serde::Serialize)]
+struct ListMembership {
+    list: String,
+    status: String,
+    subscribed_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+}
+
+#[derive(serde::Serialize)]
+struct ConsentRecord {
+    list: String,
+    event: String,
+    ip_address: Option<String>,
+    user_agent: Option<String>,
+    form_source: String,
+    consent_text_version: String,
+    recorded_at: DateTime<Utc>,
+}
+
+#[derive(serde::Serialize)]
+struct PreferenceChange {
+    field: String,
+    old_value: Option<String>,
+    new_value: Option<String>,
+    source: String,
+    changed_at: DateTime<Utc>,
+}
+
+#[derive(serde::Serialize)]
+struct Delivery {
+    issue_id: Uuid,
+    issue_title: String,
+    status: String,
+    error: Option<String>,
+    queued_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+    opened_at: Option<DateTime<Utc>>,
+    clicked_at: Option<DateTime<Utc>>,
+}
+
+#[derive(serde::Serialize)]
+struct LinkClick {
+    issue_id: Uuid,
+    url: String,
+    clicked_at: DateTime<Utc>,
+}
+
+#[derive(serde::Serialize)]
+struct DigestRecord {
+    frequency: String,
+    status: String,
+    error: Option<String>,
+    issue_ids: Vec<Uuid>,
+    created_at: DateTime<Utc>,
+}
+
+#[tracing::instrument(name = "Collect the subscriber's data", skip(pool))]
+pub(crate) async fn get_subscriber_data(
+    pool: &PgPool,
+    subscriber_id: Uuid,
+) -> Result<Option<SubscriberData>, sqlx::Error> {
+    let Some(subscriber) = sqlx::query!(
+        r#"
+    SELECT id, email, name, subscribed_at, attributes, digest_frequency, paused_until,
+           locale
+    FROM subscriptions
+    WHERE id = $1
+            "#,
+        subscriber_id
+    )
+    .fetch_optional(pool)
+    .await?
+    else {
+        return Ok(None);
+    };
+    let tags = sqlx::query_scalar!(
+        r#"SELECT tag FROM subscriber_tags WHERE subscriber_id = $1 ORDER BY tag"#,
+        subscriber_id
+    )
+    .fetch_all(pool)
+    .await?;
+    let lists = sqlx::query_as!(
+        ListMembership,
+        r#"
+    SELECT lists.slug AS list, list_subscriptions.status,
+           list_subscriptions.subscribed_at, list_subscriptions.updated_at
+    FROM list_subscriptions
+    JOIN lists ON lists.id = list_subscriptions.list_id
+    WHERE list_subscriptions.subscriber_id = $1
+    ORDER BY lists.slug
+            "#,
+        subscriber_id
+    )
+    .fetch_all(pool)
+    .await?;
+    let consents = sqlx::query_as!(
+        ConsentRecord,
+        r#"
+    SELECT lists.slug AS list, consent_records.event, consent_records.ip_address,
+           consent_records.user_agent, consent_records.form_source,
+           consent_records.consent_text_version, consent_records.recorded_at
+    FROM consent_records
+    JOIN lists ON lists.id = consent_records.list_id
+    WHERE consent_records.subscriber_id = $1
+    ORDER BY consent_records.recorded_at
+            "#,
+        subscriber_id
+    )
+    .fetch_all(pool)
+    .await?;
+    let preference_changes = sqlx::query_as!(
+        PreferenceChange,
+        r#"
+    SELECT field, old_value, new_value, source, changed_at
+    FROM subscriber_preference_changes
+    WHERE subscriber_id = $1
+    ORDER BY changed_at
+            "#,
+        subscriber_id
+    )
+    .fetch_all(pool)
+    .await?;
+    let deliveries = sqlx::query_as!(
+        Delivery,
+        r#"
+    SELECT issue_deliveries.newsletter_issue_id AS issue_id,
+           newsletter_issues.title AS issue_title,
+           issue_deliveries.status, issue_deliveries.error,
+           issue_deliveries.queued_at, issue_deliveries.updated_at,
+           issue_deliveries.opened_at, issue_deliveries.clicked_at
+    FROM issue_deliveries
+    JOIN newsletter_issues ON newsletter_issues.id = issue_deliveries.newsletter_issue_id
+    WHERE issue_deliveries.subscriber_email = $1
+    ORDER BY issue_deliveries.queued_at
+            "#,
+        subscriber.email
+    )
+    .fetch_all(pool)
+    .await?;
+    let clicks = sqlx::query_as!(
+        LinkClick,
+        r#"
+    SELECT issue_links.newsletter_issue_id AS issue_id, issue_links.url,
+           link_clicks.clicked_at
+    FROM link_clicks
+    JOIN issue_deliveries ON issue_deliveries.tracking_token = link_clicks.tracking_token
+    JOIN issue_links ON issue_links.id = link_clicks.link_id
+    WHERE issue_deliveries.subscriber_email = $1
+    ORDER BY link_clicks.clicked_at
+            "#,
+        subscriber.email
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let digests = sqlx::query_as!(
+        DigestRecord,
+        r#"
+    SELECT digests.frequency, digests.status, digests.error, digests.created_at,
+           ARRAY_AGG(digest_issues.newsletter_issue_id) AS "issue_ids!"
+    FROM digests
+    JOIN digest_issues ON digest_issues.digest_id = digests.id
+    WHERE digests.subscriber_id = $1
+    GROUP BY digests.id
+    ORDER BY digests.created_at
+            "#,
+        subscriber_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(Some(SubscriberData {
+        profile: Profile {
+            id: subscriber.id,
+            email: subscriber.email,
+            name: subscriber.name,
+            subscribed_at: subscriber.subscribed_at,
+            attributes: subscriber.attributes,
+            tags,
+            digest_frequency: subscriber.digest_frequency,
+            paused_until: subscriber.paused_until,
+            locale: subscriber.locale,
+        },
+        lists,
+        consents,
+        preference_changes,
+        deliveries,
+        clicks,
+        digests,
+    }))
+}
+
+/// The tombstone of an erased address. It is taken over the normalized form
+/// subscribers are told apart by, so that the aliases of an erased address
+/// are recognised too.
+pub(crate) fn erased_email_hash(email: &str, fold_aliases: bool) -> String {
+    let normalized = SubscriberEmail::parse(email.to_owned())
+        .map(|email| email.normalized(fold_aliases))
+        .unwrap_or_else(|_| email.trim().to_lowercase());
+    hex::encode(Sha256::digest(normalized.as_bytes()))
+}
+
+/// Deletes the subscriber and everything linked to them, and leaves a
+/// tombstone behind. Delivery records are kept for issue reports, with the
+/// address replaced by a random id that cannot be traced back to it.
+#[tracing::instrument(name = "Erase a subscriber", skip(transaction, email))]
+pub(crate) async fn erase_subscriber(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_id: Uuid,
+    email: &str,
+    source: &str,
+    fold_aliases: bool,
+) -> Result<(), sqlx::Error> {
+    let email_hash = erased_email_hash(email, fold_aliases);
+
+    for query in [
+        sqlx::query!(
+            r#"DELETE FROM opt_in_email_queue WHERE subscriber_id = $1"#,
+            subscriber_id
+        ),
+        sqlx::query!(
+            r#"DELETE FROM welcome_email_queue WHERE subscriber_id = $1"#,
+            subscriber_id
+        ),
+        sqlx::query!(
+            r#"DELETE FROM digest_queue WHERE subscriber_id = $1"#,
+            subscriber_id
+        ),
+        sqlx::query!(
+            r#"
+    DELETE FROM digest_issues
+    USING digests
+    WHERE digests.id = digest_issues.digest_id AND digests.subscriber_id = $1
+            "#,
+            subscriber_id
+        ),
+        sqlx::query!(
+            r#"DELETE FROM digests WHERE subscriber_id = $1"#,
+            subscriber_id
+        ),
+        sqlx::query!(
+            r#"DELETE FROM subscription_tokens WHERE subscriber_id = $1"#,
+            subscriber_id
+        ),
+        sqlx::query!(
+            r#"DELETE FROM list_subscriptions WHERE subscriber_id = $1"#,
+            subscriber_id
+        ),
+        sqlx::query!(
+            r#"DELETE FROM subscriber_tags WHERE subscriber_id = $1"#,
+            subscriber_id
+        ),
+        sqlx::query!(
+            r#"DELETE FROM subscriber_preference_changes WHERE subscriber_id = $1"#,
+            subscriber_id
+        ),
+        sqlx::query!(
+            r#"DELETE FROM consent_records WHERE subscriber_id = $1"#,
+            subscriber_id
+        ),
+    ] {
+        transaction.execute(query).await?;
+    }
+    for query in [
+        sqlx::query!(
+            r#"DELETE FROM issue_delivery_queue WHERE subscriber_email = $1"#,
+            email
+        ),
+        sqlx::query!(
+            r#"DELETE FROM ab_test_holdouts WHERE subscriber_email = $1"#,
+            email
+        ),
+    ] {
+        transaction.execute(query).await?;
+    }
+    let query = sqlx::query!(
+        r#"
+    UPDATE issue_deliveries
+    SET subscriber_email = 'erased:' || $2, error = NULL
+    WHERE subscriber_email = $1
+            "#,
+        email,
+        Uuid::new_v4().to_string()
+    );
+    transaction.execute(query).await?;
+    let query = sqlx::query!(
+        r#"UPDATE import_row_errors SET email = NULL WHERE lower(email) = lower($1)"#,
+        email
+    );
+    transaction.execute(query).await?;
+    let query = sqlx::query!(r#"DELETE FROM subscriptions WHERE id = $1"#, subscriber_id);
+    transaction.execute(query).await?;
+    let query = sqlx::query!(
+        r#"
+    INSERT INTO erased_subscribers (email_hash, source, erased_at)
+    VALUES ($1, $2, $3)
+    ON CONFLICT (email_hash) DO UPDATE
+    SET source = EXCLUDED.source, erased_at = EXCLUDED.erased_at
+            "#,
+        email_hash,
+        source,
+        Utc::now()
+    );
+    transaction.execute(query).await?;
+    Ok(())
+}
+
+#[tracing::instrument(name = "Check for an erased address", skip(executor, email))]
+pub(crate) async fn is_erased(
+    executor: impl PgExecutor<'_>,
+    email: &str,
+    fold_aliases: bool,
+) -> Result<bool, sqlx::Error> {
+    let erased = sqlx::query!(
+        r#"SELECT email_hash FROM erased_subscribers WHERE email_hash = $1"#,
+        erased_email_hash(email, fold_aliases)
+    )
+    .fetch_optional(executor)
+    .await?
+    .is_some();
+    Ok(erased)
+}
@@ -1,60 +1,156 @@
+use actix_web::http::header::{CACHE_CONTROL, USER_AGENT};
 use actix_web::http::StatusCode;
-use actix_web::{web, HttpResponse, ResponseError};
+use actix_web::{web, HttpRequest, HttpResponse};
 use anyhow::Context;
 use chrono::Utc;
 use http::Uri;
 use rand::distributions::Alphanumeric;
 use rand::{thread_rng, Rng};
 use sqlx::{Executor, PgPool, Postgres, Transaction};
-use std::fmt::{Debug, Display, Formatter};
+use std::collections::HashMap;
 use uuid::Uuid;
 
-use crate::domain::{NewSubscriber, SubscriberEmail, SubscriberName};
-use crate::email::email_client::{EmailClient, EmailClientError, EmailService, SendEmailRequest};
-use crate::startup::ApplicationBaseUrl;
+use crate::configuration::SubscriptionSettings;
+use crate::domain::{
+    AttributeDefinition, AttributeError, ConsentEvent, ConsentEvidence, EmailPolicy, FormSource,
+    ListSlug, Locale, NewSubscriber, PolicyViolation, SubscriberAttributes, SubscriberEmail,
+    SubscriberName, SubscriptionStatus,
+};
+use crate::email::email_client::{EmailClient, EmailService, SendEmailRequest};
+use crate::i18n::Translator;
+use crate::rate_limit::{ClientIp, Decision};
+use crate::routes::{
+    error_outcome, get_attribute_definitions, get_list_id, render_outcome, request_locale,
+    AppError, ErrorCode, FieldErrors, Outcome,
+};
+use crate::startup::AppState;
 
 #[derive(serde::Deserialize)]
 pub struct FormData {
     email: String,
     name: String,
+    list: Option<String>,
+    /// The form the subscriber used, kept as proof of consent.
+    source: Option<String>,
+    /// The language the subscriber picked, if the form offers a choice.
+    locale: Option<String>,
+    /// Every other field is a custom subscriber attribute.
+    #[serde(flatten)]
+    attributes: HashMap<String, String>,
 }
 
-impl TryFrom<FormData> for NewSubscriber {
-    type Error = String;
+impl FormData {
+    /// Fails on the first invalid field, with a message in the subscriber's
+    /// language. Subscribers who did not pick a language get the negotiated
+    /// one.
+    fn parse(
+        self,
+        definitions: &[AttributeDefinition],
+        email_policy: &EmailPolicy,
+        t: &Translator,
+        negotiated_locale: Option<Locale>,
+    ) -> Result<SubscriptionRequest, String> {
+        let list = self
+            .list
+            .map(|list| ListSlug::parse(list.clone()).map_err(|_| invalid(t, "list", &list)))
+            .transpose()?;
+        let form_source = self
+            .source
+            .map(|source| {
+                FormSource::parse(source.clone()).map_err(|_| invalid(t, "source", &source))
+            })
+            .transpose()?;
+        let locale =
+            picked_locale(self.locale.as_deref()).map_err(|locale| invalid(t, "locale", locale))?;
+        let name =
+            SubscriberName::parse(self.name.clone()).map_err(|_| invalid(t, "name", &self.name))?;
+        let email = SubscriberEmail::parse(self.email.clone())
+            .map_err(|_| invalid(t, "email", &self.email))?;
+        email_policy
+            .check(&email)
+            .map_err(|violation| policy_violation(t, &violation))?;
+        let attributes = SubscriberAttributes::parse(self.attributes, definitions)
+            .map_err(|error| attribute_error(t, &error))?;
+        Ok(SubscriptionRequest {
+            new_subscriber: NewSubscriber {
+                email,
+                name,
+                attributes,
+            },
+            list: list.unwrap_or_default(),
+            form_source: form_source.unwrap_or_default(),
+            locale: locale.or(negotiated_locale),
+        })
+    }
+}
+
+/// A validated subscription, whether it came from the form or the API.
+pub(crate) struct SubscriptionRequest {
+    pub(crate) new_subscriber: NewSubscriber,
+    pub(crate) list: ListSlug,
+    pub(crate) form_source: FormSource,
+    /// `None` when neither the subscriber nor their browser named a language
+    /// we speak.
+    pub(crate) locale: Option<Locale>,
+}
 
-    fn try_from(value: FormData) -> Result<Self, Self::Error> {
-        let name = SubscriberName::parse(value.name)?;
-        let email = SubscriberEmail::parse(value.email)?;
-        Ok(NewSubscriber { email, name })
+/// The language the subscriber picked, if any. Fails with the submitted
+/// value when it is not a language tag.
+pub(crate) fn picked_locale(locale: Option<&str>) -> Result<Option<Locale>, &str> {
+    match locale.map(str::trim) {
+        Some(locale) if !locale.is_empty() => Locale::parse(locale).map(Some).map_err(|_| locale),
+        _ => Ok(None),
     }
 }
 
-#[derive(thiserror::Error)]
-pub enum SubscribeError {
-    #[error("{0}")]
-    ValidationError(String),
-    #[error(transparent)]
-    UnexpectedError(#[from] anyhow::Error),
+/// The message for a field whose value could not be parsed.
+pub(crate) fn invalid(t: &Translator, field: &str, value: &str) -> String {
+    t.text_with(&format!("errors.invalid_{}", field), &[("value", value)])
 }
 
-impl Debug for SubscribeError {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        error_chain_fmt(self, f)
+pub(crate) fn policy_violation(t: &Translator, violation: &PolicyViolation) -> String {
+    match violation {
+        PolicyViolation::DeniedDomain(domain) => {
+            t.text_with("errors.denied_domain", &[("domain", domain)])
+        }
+        PolicyViolation::DisposableDomain(domain) => {
+            t.text_with("errors.disposable_domain", &[("domain", domain)])
+        }
+        PolicyViolation::RoleAccount(mailbox) => {
+            t.text_with("errors.role_account", &[("mailbox", mailbox)])
+        }
     }
 }
 
-impl ResponseError for SubscribeError {
-    fn status_code(&self) -> StatusCode {
-        match self {
-            SubscribeError::ValidationError(_) => StatusCode::BAD_REQUEST,
-            SubscribeError::UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+pub(crate) fn attribute_error(t: &Translator, error: &AttributeError) -> String {
+    match error {
+        AttributeError::Unknown(name) => t.text_with("errors.unknown_attribute", &[("name", name)]),
+        AttributeError::InvalidValue { name, value } => t.text_with(
+            "errors.invalid_attribute",
+            &[("name", name), ("value", value)],
+        ),
+    }
+}
+
+fn error_page(error: &AppError, t: &Translator) -> Outcome {
+    match error {
+        AppError::ValidationError(reason) => {
+            Outcome::for_error(t, error, "invalid_subscription", &[("reason", reason)])
         }
+        AppError::InvalidFields(fields) => Outcome::for_error(
+            t,
+            error,
+            "invalid_subscription",
+            &[("reason", &fields.to_string())],
+        ),
+        AppError::RateLimited(_) => Outcome::for_error(t, error, "rate_limited", &[]),
+        _ => Outcome::unexpected_error(t),
     }
 }
 
 #[tracing::instrument(
     name = "Adding a new subscriber",
-    skip(form, pool, email_service, email_client, base_url),
+    skip(form, request, pool, state),
     fields(
         subscriber_email = %form.email,
         subscriber_name = %form.name
@@ -62,12 +158,125 @@ impl ResponseError for SubscribeError {
 )]
 pub async fn subscribe(
     form: web::Form<FormData>,
+    request: HttpRequest,
     pool: web::Data<PgPool>,
-    email_service: web::Data<EmailService>,
-    email_client: web::Data<dyn EmailClient>,
-    base_url: web::Data<ApplicationBaseUrl>,
-) -> Result<HttpResponse, SubscribeError> {
-    let new_subscriber = form.0.try_into().map_err(SubscribeError::ValidationError)?;
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut form = form.0;
+    let email = form.email.clone();
+    let negotiated_locale = request_locale(&request, &state.catalogs);
+    let page_locale = picked_locale(form.locale.as_deref())
+        .ok()
+        .flatten()
+        .or_else(|| negotiated_locale.clone());
+    let t = state.catalogs.translator(page_locale.as_ref());
+    if let Err(rejection) = state
+        .bot_protection
+        .check(&mut form.attributes, ClientIp::of(&request))
+        .await
+    {
+        // Bots are told the same as people, so that they cannot learn what
+        // gave them away.
+        tracing::warn!(%rejection, "Dropped a subscription that looks automated.");
+        return Ok(render_outcome(
+            &request,
+            &state.branding,
+            &confirmation_sent(&t, &email),
+        ));
+    }
+    match add_subscriber(form, &request, &pool, &state, &t, negotiated_locale).await {
+        Ok(awaits_confirmation) => {
+            let outcome = if awaits_confirmation {
+                confirmation_sent(&t, &email)
+            } else {
+                Outcome::new(
+                    &t,
+                    StatusCode::OK,
+                    "already_subscribed",
+                    "already_subscribed",
+                    &[],
+                )
+            };
+            Ok(render_outcome(&request, &state.branding, &outcome))
+        }
+        Err(e) => {
+            let outcome = error_page(&e, &t);
+            Err(error_outcome(&request, &state.branding, e, &outcome))
+        }
+    }
+}
+
+fn unknown_list_error(t: &Translator, list: &ListSlug) -> AppError {
+    let mut errors = FieldErrors::default();
+    errors.add(
+        "list",
+        ErrorCode::UnknownList,
+        t.text_with("errors.unknown_list", &[("list", list.as_ref())]),
+    );
+    AppError::InvalidFields(errors)
+}
+
+fn confirmation_sent(t: &Translator, email: &str) -> Outcome {
+    Outcome::new(
+        t,
+        StatusCode::OK,
+        "confirmation_sent",
+        "confirmation_sent",
+        &[("email", email.trim())],
+    )
+}
+
+/// A token to submit with the subscription form, proving it was not filled
+/// in faster than a person could.
+pub async fn subscription_form_token(state: web::Data<AppState>) -> Result<HttpResponse, AppError> {
+    let token = state
+        .bot_protection
+        .issue_form_token()
+        .ok_or_else(|| AppError::NotFound("Form tokens are not enabled.".into()))?;
+    Ok(HttpResponse::Ok()
+        .insert_header((CACHE_CONTROL, "no-store"))
+        .json(serde_json::json!({ "token": token })))
+}
+
+async fn add_subscriber(
+    form: FormData,
+    request: &HttpRequest,
+    pool: &PgPool,
+    state: &AppState,
+    t: &Translator<'_>,
+    negotiated_locale: Option<Locale>,
+) -> Result<bool, AppError> {
+    let definitions = get_attribute_definitions(pool).await?;
+    let subscription = form
+        .parse(&definitions, &state.email_policy, t, negotiated_locale)
+        .map_err(AppError::ValidationError)?;
+    create_subscription(subscription, request, pool, state, t).await
+}
+
+/// Returns whether the subscriber has been sent a confirmation email, which
+/// is not needed when they are already confirmed on the list. Errors are
+/// worded with `t`.
+pub(crate) async fn create_subscription(
+    subscription: SubscriptionRequest,
+    request: &HttpRequest,
+    pool: &PgPool,
+    state: &AppState,
+    t: &Translator<'_>,
+) -> Result<bool, AppError> {
+    let settings = &state.subscriptions;
+    let SubscriptionRequest {
+        new_subscriber,
+        list,
+        form_source,
+        locale,
+    } = subscription;
+    let normalized_email = new_subscriber.email.normalized(settings.fold_email_aliases);
+    if let Decision::Limited { retry_after } =
+        state.rate_limiter.check_email(&normalized_email).await
+    {
+        return Err(AppError::RateLimited(retry_after));
+    }
+    let consent = consent_evidence(request, form_source, settings);
     let subscription_token = generate_subscription_token();
 
     let mut transaction = pool
@@ -75,12 +284,40 @@ pub async fn subscribe(
         .await
         .context("Failed to acquire a Postgres connection from the pool.")?;
 
-    let subscriber_id = insert_subscriber(&mut transaction, &new_subscriber)
+    let list_id = get_list_id(&mut *transaction, &list)
         .await
-        .context("Failed to insert new subscriber in the database.")?;
-    store_token(&mut transaction, &subscription_token, subscriber_id)
+        .context("Failed to retrieve the mailing list.")?
+        .ok_or_else(|| unknown_list_error(t, &list))?;
+    let subscriber_id = insert_subscriber(
+        &mut transaction,
+        &new_subscriber,
+        &normalized_email,
+        locale.as_ref(),
+        list_id,
+        &consent,
+    )
+    .await
+    .context("Failed to insert new subscriber in the database.")?;
+    let awaits_confirmation = insert_list_subscription(&mut transaction, list_id, subscriber_id)
         .await
-        .context("Failed to store the confirmation token for a new subscriber.")?;
+        .context("Failed to subscribe the subscriber to the mailing list.")?;
+    if !awaits_confirmation {
+        // Already confirmed on this list: there is nothing left to confirm.
+        transaction
+            .commit()
+            .await
+            .context("Failed to commit SQL transaction to store a new subscriber.")?;
+        return Ok(false);
+    }
+    store_token(
+        &mut transaction,
+        &subscription_token,
+        subscriber_id,
+        list_id,
+        &new_subscriber.attributes.as_json(),
+    )
+    .await
+    .context("Failed to store the confirmation token for a new subscriber.")?;
 
     transaction
         .commit()
@@ -88,93 +325,196 @@ pub async fn subscribe(
         .context("Failed to commit SQL transaction to store a new subscriber.")?;
 
     send_confirmation_email(
-        &email_service,
-        new_subscriber,
-        email_client.get_ref(),
-        &base_url.0,
+        &state.email_service,
+        &new_subscriber.email,
+        state.email_client.as_ref(),
+        &state.base_url,
         &subscription_token,
+        &state.catalogs.translator(locale.as_ref()),
     )
     .await
     .context("Failed to send a confirmation email.")?;
-    Ok(HttpResponse::Ok().finish())
+    Ok(true)
 }
 
+/// Subscribers are shared across lists: an email address that is already
+/// known, compared by its normalized form, keeps its id, name, spelling,
+/// language and attributes. The submitted attributes travel with the
+/// confirmation token instead and are only merged in once it is followed.
+/// Every submission is kept as a consent record.
 #[tracing::instrument(
     name = "Saving new subscriber details in the database",
-    skip(new_subscriber, transaction)
+    skip(new_subscriber, normalized_email, transaction, consent)
 )]
 async fn insert_subscriber(
     transaction: &mut Transaction<'_, Postgres>,
     new_subscriber: &NewSubscriber,
+    normalized_email: &str,
+    locale: Option<&Locale>,
+    list_id: Uuid,
+    consent: &ConsentEvidence,
 ) -> Result<Uuid, sqlx::Error> {
-    let subscriber_id = Uuid::new_v4();
-    let query = sqlx::query!(
+    let subscriber_id = sqlx::query!(
         r#"
-    INSERT INTO subscriptions (id, email, name, subscribed_at, status)
-    VALUES ($1, $2, $3, $4, 'pending_confirmation')
+    INSERT INTO subscriptions (
+        id, email, normalized_email, name, subscribed_at, attributes, preferences_token, locale
+    )
+    VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+    ON CONFLICT (normalized_email) DO UPDATE
+    SET locale = COALESCE(subscriptions.locale, EXCLUDED.locale)
+    RETURNING id
             "#,
-        subscriber_id,
+        Uuid::new_v4(),
         new_subscriber.email.as_ref(),
+        normalized_email,
         new_subscriber.name.as_ref(),
+        Utc::now(),
+        new_subscriber.attributes.as_json(),
+        generate_subscription_token(),
+        locale.map(Locale::as_ref)
+    )
+    .fetch_one(&mut **transaction)
+    .await?
+    .id;
+    record_consent(
+        transaction,
+        subscriber_id,
+        list_id,
+        ConsentEvent::Subscribe,
+        consent,
+    )
+    .await?;
+    Ok(subscriber_id)
+}
+
+/// Collects the evidence of consent that comes with a request.
+pub(crate) fn consent_evidence(
+    request: &HttpRequest,
+    form_source: FormSource,
+    settings: &SubscriptionSettings,
+) -> ConsentEvidence {
+    ConsentEvidence {
+        ip_address: ClientIp::of(request).map(|ip| ip.to_string()),
+        user_agent: request
+            .headers()
+            .get(USER_AGENT)
+            .and_then(|h| h.to_str().ok())
+            .map(str::to_owned),
+        form_source,
+        consent_text_version: settings.consent_text_version.clone(),
+    }
+}
+
+#[tracing::instrument(name = "Record a consent", skip(transaction, consent))]
+pub(crate) async fn record_consent(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_id: Uuid,
+    list_id: Uuid,
+    event: ConsentEvent,
+    consent: &ConsentEvidence,
+) -> Result<(), sqlx::Error> {
+    let query = sqlx::query!(
+        r#"
+    INSERT INTO consent_records (
+        id, subscriber_id, list_id, event, ip_address, user_agent,
+        form_source, consent_text_version, recorded_at
+    )
+    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            "#,
+        Uuid::new_v4(),
+        subscriber_id,
+        list_id,
+        event.as_str(),
+        consent.ip_address,
+        consent.user_agent,
+        consent.form_source.as_ref(),
+        consent.consent_text_version,
         Utc::now()
     );
     transaction.execute(query).await?;
-    Ok(subscriber_id)
+    Ok(())
+}
+
+/// Puts the subscriber's membership of the list in `pending_confirmation`,
+/// unless they already confirmed it. Returns whether a confirmation is due.
+#[tracing::instrument(name = "Saving list subscription in the database", skip(transaction))]
+async fn insert_list_subscription(
+    transaction: &mut Transaction<'_, Postgres>,
+    list_id: Uuid,
+    subscriber_id: Uuid,
+) -> Result<bool, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+    INSERT INTO list_subscriptions (
+        list_id, subscriber_id, status, unsubscribe_token, subscribed_at, updated_at
+    )
+    VALUES ($1, $2, $3, $4, $5, $5)
+    ON CONFLICT (list_id, subscriber_id) DO UPDATE
+    SET status = EXCLUDED.status, updated_at = EXCLUDED.updated_at
+    WHERE list_subscriptions.status <> $6
+    RETURNING subscriber_id
+            "#,
+        list_id,
+        subscriber_id,
+        SubscriptionStatus::PendingConfirmation.as_str(),
+        generate_subscription_token(),
+        Utc::now(),
+        SubscriptionStatus::Confirmed.as_str()
+    )
+    .fetch_optional(&mut **transaction)
+    .await?;
+    Ok(row.is_some())
 }
 
 #[tracing::instrument(
     name = "Store subscription token in the database",
-    skip(subscription_token, transaction)
+    skip(subscription_token, transaction, attributes)
 )]
 async fn store_token(
     transaction: &mut Transaction<'_, Postgres>,
     subscription_token: &str,
     subscriber_id: Uuid,
-) -> Result<(), StoreTokenError> {
+    list_id: Uuid,
+    attributes: &serde_json::Value,
+) -> Result<(), sqlx::Error> {
     let query = sqlx::query!(
         r#"
-    INSERT INTO subscription_tokens (subscription_token, subscriber_id)
-    VALUES ($1, $2)
+    INSERT INTO subscription_tokens (subscription_token, subscriber_id, list_id, attributes)
+    VALUES ($1, $2, $3, $4)
             "#,
         subscription_token,
         subscriber_id,
+        list_id,
+        attributes,
     );
-    transaction.execute(query).await.map_err(StoreTokenError)?;
+    transaction.execute(query).await?;
     Ok(())
 }
 
 #[tracing::instrument(
     name = "Send a confirmation email to a new subscriber",
-    skip(new_subscriber, email_service, email_client, base_url)
+    skip(subscriber_email, email_service, email_client, base_url, t)
 )]
-async fn send_confirmation_email(
+pub(crate) async fn send_confirmation_email(
     email_service: &EmailService,
-    new_subscriber: NewSubscriber,
+    subscriber_email: &SubscriberEmail,
     email_client: &dyn EmailClient,
     base_url: &Uri,
     subscription_token: &str,
-) -> Result<(), EmailClientError> {
+    t: &Translator<'_>,
+) -> Result<(), anyhow::Error> {
     let confirmation_link = format!(
         "{}subscriptions/confirm?subscription_token={}",
         base_url, subscription_token,
     );
-
-    let html_content = &format!(
-        "Welcome to our newsletter!<br />\
-                Click <a href=\"{}\"here</a> to confirm your subscription.",
-        confirmation_link
-    );
-
-    let text_content = &format!(
-        "Welcome to our newsletter!\nVisit {} to confirm your subscription.",
-        confirmation_link
-    );
+    let link = [("link", confirmation_link.as_str())];
 
     let send_email_request = SendEmailRequest {
-        to: &new_subscriber.email,
-        subject: "Welcome",
-        html_content,
-        text_content,
+        to: subscriber_email,
+        subject: &t.text("email.confirmation.subject"),
+        html_content: &t.text_with("email.confirmation.html", &link),
+        text_content: &t.text_with("email.confirmation.text", &link),
+        attachments: &[],
     };
 
     email_service
@@ -182,44 +522,10 @@ async fn send_confirmation_email(
         .await
 }
 
-fn generate_subscription_token() -> String {
+pub(crate) fn generate_subscription_token() -> String {
     let mut rng = thread_rng();
     std::iter::repeat_with(|| rng.sample(Alphanumeric))
         .map(char::from)
         .take(25)
         .collect()
 }
-
-pub struct StoreTokenError(sqlx::Error);
-
-impl Debug for StoreTokenError {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        error_chain_fmt(self, f)
-    }
-}
-
-impl Display for StoreTokenError {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "A database error was encountered while trying to store a subscription token."
-        )
-    }
-}
-
-impl std::error::Error for StoreTokenError {
-    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
-        Some(&self.0)
-    }
-}
-
-// TODO move to common file
-pub fn error_chain_fmt(e: &impl std::error::Error, f: &mut Formatter<'_>) -> std::fmt::Result {
-    writeln!(f, "{}\n", e)?;
-    let mut current = e.source();
-    while let Some(cause) = current {
-        writeln!(f, "Caused by:\n\t{}", cause)?;
-        current = cause.source();
-    }
-    Ok(())
-}
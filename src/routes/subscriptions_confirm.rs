@@ -1,5 +1,16 @@
-use actix_web::{web, HttpResponse};
-use sqlx::PgPool;
+use crate::configuration::SubscriptionSettings;
+use crate::domain::{ConsentEvent, ConsentEvidence, FormSource, SubscriptionStatus};
+use crate::i18n::Translator;
+use crate::routes::{
+    consent_evidence, error_outcome, record_consent, render_outcome, request_locale,
+    subscriber_locale, AppError, Outcome,
+};
+use crate::startup::AppState;
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpRequest, HttpResponse};
+use anyhow::Context;
+use chrono::{DateTime, Duration, Utc};
+use sqlx::{Executor, PgPool};
 use uuid::Uuid;
 
 #[derive(serde::Deserialize)]
@@ -7,33 +18,114 @@ pub struct Parameters {
     subscription_token: String,
 }
 
-#[tracing::instrument(name = "Confirm a pending subscriber", skip(_parameters))]
-pub async fn confirm(_parameters: web::Query<Parameters>, pool: web::Data<PgPool>) -> HttpResponse {
-    let subscriber_id =
-        match get_subscriber_id_from_token(&pool, &_parameters.subscription_token).await {
-            Ok(id) => id,
-            Err(_) => return HttpResponse::InternalServerError().finish(),
-        };
+fn error_page(error: &AppError, t: &Translator) -> Outcome {
+    match error {
+        AppError::InvalidToken(_) => {
+            Outcome::for_error(t, error, "invalid_confirmation_token", &[])
+        }
+        AppError::ExpiredToken(_) => {
+            Outcome::for_error(t, error, "expired_confirmation_token", &[])
+        }
+        _ => Outcome::unexpected_error(t),
+    }
+}
 
-    match subscriber_id {
-        None => HttpResponse::Unauthorized().finish(),
-        Some(subscriber_id) => {
-            if confirm_subscriber(&pool, &subscriber_id).await.is_err() {
-                return HttpResponse::InternalServerError().finish();
-            }
-            HttpResponse::Ok().finish()
+#[tracing::instrument(
+    name = "Confirm a pending subscriber",
+    skip(parameters, request, pool, state)
+)]
+pub async fn confirm(
+    parameters: web::Query<Parameters>,
+    request: HttpRequest,
+    pool: web::Data<PgPool>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, actix_web::Error> {
+    match confirm_token(
+        &parameters.subscription_token,
+        &request,
+        &pool,
+        &state.subscriptions,
+    )
+    .await
+    {
+        Ok(subscription) => {
+            let locale =
+                subscriber_locale(subscription.locale.as_deref(), &request, &state.catalogs);
+            let t = state.catalogs.translator(locale.as_ref());
+            let outcome = Outcome::new(
+                &t,
+                StatusCode::OK,
+                "subscription_confirmed",
+                "subscription_confirmed",
+                &[("list", &subscription.list_name)],
+            )
+            .with_link(
+                format!("/preferences/{}", subscription.preferences_token),
+                &t.text("email.footer.manage_preferences"),
+            );
+            Ok(render_outcome(&request, &state.branding, &outcome))
+        }
+        Err(e) => {
+            let t = state
+                .catalogs
+                .translator(request_locale(&request, &state.catalogs).as_ref());
+            let outcome = error_page(&e, &t);
+            Err(error_outcome(&request, &state.branding, e, &outcome))
         }
     }
 }
 
+async fn confirm_token(
+    subscription_token: &str,
+    request: &HttpRequest,
+    pool: &PgPool,
+    settings: &SubscriptionSettings,
+) -> Result<ConfirmedSubscription, AppError> {
+    let token = get_subscription_from_token(pool, subscription_token)
+        .await
+        .context("Failed to retrieve the confirmation token.")?
+        .ok_or_else(|| AppError::InvalidToken("The confirmation token is not valid.".into()))?;
+    if token.used_at.is_none()
+        && token.created_at + Duration::hours(settings.confirmation_token_ttl_hours) < Utc::now()
+    {
+        return Err(AppError::ExpiredToken(
+            "The confirmation token has expired.".into(),
+        ));
+    }
+    let consent = consent_evidence(request, FormSource::confirmation_email(), settings);
+    let send_welcome_email = settings.welcome_email.is_some();
+    let subscription = confirm_subscriber(
+        pool,
+        subscription_token,
+        &token,
+        &consent,
+        send_welcome_email,
+    )
+    .await
+    .context("Failed to confirm the subscriber.")?;
+    Ok(subscription)
+}
+
+/// The subscription a confirmation token stands for.
+struct PendingSubscription {
+    subscriber_id: Uuid,
+    list_id: Uuid,
+    created_at: DateTime<Utc>,
+    /// Set once the link has been followed.
+    used_at: Option<DateTime<Utc>>,
+    /// Submitted with the subscription, merged in on confirmation.
+    attributes: serde_json::Value,
+}
+
 #[tracing::instrument(name = "Get subscriber_id from token", skip(pool, subscription_token))]
-async fn get_subscriber_id_from_token(
+async fn get_subscription_from_token(
     pool: &PgPool,
     subscription_token: &str,
-) -> Result<Option<Uuid>, sqlx::Error> {
-    let result = sqlx::query!(
+) -> Result<Option<PendingSubscription>, sqlx::Error> {
+    let result = sqlx::query_as!(
+        PendingSubscription,
         r#"
-    SELECT subscriber_id FROM subscription_tokens
+    SELECT subscriber_id, list_id, created_at, used_at, attributes FROM subscription_tokens
     WHERE subscription_token = $1
             "#,
         subscription_token,
@@ -44,22 +136,134 @@ async fn get_subscriber_id_from_token(
         tracing::error!("Failed to execute query: {:?}", e);
         e
     })?;
-    Ok(result.map(|r| r.subscriber_id))
+    Ok(result)
 }
 
-#[tracing::instrument(name = "Get subscriber_id from token", skip(pool, subscriber_id))]
-async fn confirm_subscriber(pool: &PgPool, subscriber_id: &Uuid) -> Result<(), sqlx::Error> {
-    sqlx::query!(
+struct ConfirmedSubscription {
+    list_name: String,
+    preferences_token: String,
+    locale: Option<String>,
+}
+
+/// Uses up the token and, if the subscription is still waiting for it,
+/// confirms the subscriber, merges the attributes they submitted and, when a
+/// welcome email is configured, queues their welcome email. Following a used
+/// link again changes nothing.
+#[tracing::instrument(
+    name = "Mark subscriber as confirmed",
+    skip(pool, subscription_token, pending, consent)
+)]
+async fn confirm_subscriber(
+    pool: &PgPool,
+    subscription_token: &str,
+    pending: &PendingSubscription,
+    consent: &ConsentEvidence,
+    send_welcome_email: bool,
+) -> Result<ConfirmedSubscription, sqlx::Error> {
+    let subscriber_id = &pending.subscriber_id;
+    let list_id = &pending.list_id;
+    let mut transaction = pool.begin().await?;
+    let subscription = sqlx::query!(
         r#"
-    UPDATE subscriptions SET status = 'confirmed' WHERE id = $1
+    SELECT lists.name AS list_name, subscriptions.preferences_token,
+           subscriptions.locale
+    FROM list_subscriptions
+    JOIN lists ON lists.id = list_subscriptions.list_id
+    JOIN subscriptions ON subscriptions.id = list_subscriptions.subscriber_id
+    WHERE list_subscriptions.subscriber_id = $1 AND list_subscriptions.list_id = $2
+    FOR UPDATE OF list_subscriptions
             "#,
         subscriber_id,
+        list_id
     )
-    .execute(pool)
+    .fetch_one(&mut *transaction)
     .await
     .map_err(|e| {
         tracing::error!("Failed to execute query: {:?}", e);
         e
     })?;
-    Ok(())
+    let confirmed = sqlx::query!(
+        r#"
+    UPDATE list_subscriptions SET status = $3, updated_at = $5
+    WHERE subscriber_id = $1 AND list_id = $2 AND status = $4
+      AND EXISTS (
+          SELECT 1 FROM subscription_tokens
+          WHERE subscription_token = $6 AND used_at IS NULL
+      )
+            "#,
+        subscriber_id,
+        list_id,
+        SubscriptionStatus::Confirmed.as_str(),
+        SubscriptionStatus::PendingConfirmation.as_str(),
+        Utc::now(),
+        subscription_token
+    )
+    .execute(&mut *transaction)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to execute query: {:?}", e);
+        e
+    })?
+    .rows_affected()
+        > 0;
+    let query = sqlx::query!(
+        r#"
+    UPDATE subscription_tokens SET used_at = $2
+    WHERE subscription_token = $1 AND used_at IS NULL
+            "#,
+        subscription_token,
+        Utc::now()
+    );
+    transaction.execute(query).await.map_err(|e| {
+        tracing::error!("Failed to execute query: {:?}", e);
+        e
+    })?;
+    if confirmed {
+        let query = sqlx::query!(
+            r#"
+    UPDATE subscriptions SET attributes = attributes || $2
+    WHERE id = $1
+            "#,
+            subscriber_id,
+            pending.attributes
+        );
+        transaction.execute(query).await.map_err(|e| {
+            tracing::error!("Failed to execute query: {:?}", e);
+            e
+        })?;
+        record_consent(
+            &mut transaction,
+            *subscriber_id,
+            *list_id,
+            ConsentEvent::Confirm,
+            consent,
+        )
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to record the consent: {:?}", e);
+            e
+        })?;
+        if send_welcome_email {
+            let query = sqlx::query!(
+                r#"
+    INSERT INTO welcome_email_queue (subscriber_id, list_id, enqueued_at)
+    VALUES ($1, $2, $3)
+    ON CONFLICT DO NOTHING
+            "#,
+                subscriber_id,
+                list_id,
+                Utc::now()
+            );
+            transaction.execute(query).await.map_err(|e| {
+                tracing::error!("Failed to queue the welcome email: {:?}", e);
+                e
+            })?;
+        }
+    }
+    transaction.commit().await?;
+    Ok(ConfirmedSubscription {
+        list_name: subscription.list_name,
+        preferences_token: subscription.preferences_token,
+        locale: subscription.locale,
+    })
 }
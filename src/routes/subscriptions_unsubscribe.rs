@@ -0,0 +1,114 @@
+use crate::domain::SubscriptionStatus;
+use crate::i18n::Translator;
+use crate::routes::{
+    error_outcome, record_preference_change, render_outcome, request_locale, subscriber_locale,
+    AppError, Outcome,
+};
+use crate::startup::AppState;
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpRequest, HttpResponse};
+use anyhow::Context;
+use chrono::Utc;
+use sqlx::PgPool;
+
+fn error_page(error: &AppError, t: &Translator) -> Outcome {
+    match error {
+        AppError::InvalidToken(_) => Outcome::for_error(t, error, "invalid_unsubscribe_token", &[]),
+        _ => Outcome::unexpected_error(t),
+    }
+}
+
+#[derive(serde::Deserialize)]
+pub struct UnsubscribeParameters {
+    token: String,
+}
+
+/// Every delivered issue links here with the token of the subscriber's
+/// membership of the issue's list. Only that list is left.
+#[tracing::instrument(
+    name = "Unsubscribe from a mailing list",
+    skip(parameters, request, pool, state)
+)]
+pub async fn unsubscribe(
+    parameters: web::Query<UnsubscribeParameters>,
+    request: HttpRequest,
+    pool: web::Data<PgPool>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, actix_web::Error> {
+    match leave_list(&parameters.token, &pool).await {
+        Ok((list_name, locale)) => {
+            let locale = subscriber_locale(locale.as_deref(), &request, &state.catalogs);
+            let t = state.catalogs.translator(locale.as_ref());
+            let outcome = Outcome::new(
+                &t,
+                StatusCode::OK,
+                "unsubscribed",
+                "unsubscribed",
+                &[("list", &list_name)],
+            );
+            Ok(render_outcome(&request, &state.branding, &outcome))
+        }
+        Err(e) => {
+            let t = state
+                .catalogs
+                .translator(request_locale(&request, &state.catalogs).as_ref());
+            let outcome = error_page(&e, &t);
+            Err(error_outcome(&request, &state.branding, e, &outcome))
+        }
+    }
+}
+
+/// Returns the name of the list that was left and the subscriber's locale.
+async fn leave_list(token: &str, pool: &PgPool) -> Result<(String, Option<String>), AppError> {
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool.")?;
+    let previous = sqlx::query!(
+        r#"
+    SELECT list_subscriptions.subscriber_id, list_subscriptions.status, lists.slug, lists.name,
+           subscriptions.locale
+    FROM list_subscriptions
+    JOIN lists ON lists.id = list_subscriptions.list_id
+    JOIN subscriptions ON subscriptions.id = list_subscriptions.subscriber_id
+    WHERE list_subscriptions.unsubscribe_token = $1
+    FOR UPDATE OF list_subscriptions
+            "#,
+        token
+    )
+    .fetch_optional(&mut *transaction)
+    .await
+    .context("Failed to retrieve the list subscription.")?
+    .ok_or_else(|| AppError::InvalidToken("The unsubscribe token is not valid.".into()))?;
+
+    if previous.status != SubscriptionStatus::Unsubscribed.as_str() {
+        sqlx::query!(
+            r#"
+    UPDATE list_subscriptions SET status = $2, updated_at = $3
+    WHERE unsubscribe_token = $1
+            "#,
+            token,
+            SubscriptionStatus::Unsubscribed.as_str(),
+            Utc::now()
+        )
+        .execute(&mut *transaction)
+        .await
+        .context("Failed to unsubscribe the subscriber from the mailing list.")?;
+        record_preference_change(
+            &mut transaction,
+            previous.subscriber_id,
+            &format!("list:{}", previous.slug),
+            Some(&previous.status),
+            Some(SubscriptionStatus::Unsubscribed.as_str()),
+            "unsubscribe_link",
+        )
+        .await
+        .context("Failed to record the preference change.")?;
+    }
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to unsubscribe a subscriber.")?;
+
+    Ok((previous.name, previous.locale))
+}
@@ -0,0 +1,98 @@
+use crate::routes::AppError;
+use actix_web::http::header::{self, CacheControl, CacheDirective};
+use actix_web::{web, HttpResponse};
+use anyhow::Context;
+use chrono::Utc;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// A transparent 1x1 GIF.
+const PIXEL: &[u8] = &[
+    0x47, 0x49, 0x46, 0x38, 0x39, 0x61, 0x01, 0x00, 0x01, 0x00, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0xff, 0xff, 0xff, 0x21, 0xf9, 0x04, 0x01, 0x00, 0x00, 0x00, 0x00, 0x2c, 0x00, 0x00, 0x00, 0x00,
+    0x01, 0x00, 0x01, 0x00, 0x00, 0x02, 0x02, 0x44, 0x01, 0x00, 0x3b,
+];
+
+/// Every delivered issue embeds this pixel with the token of its delivery.
+/// Only the first open is recorded, and unknown tokens get the pixel too.
+#[tracing::instrument(name = "Record an issue open", skip(token, pool))]
+pub async fn track_open(
+    token: web::Path<String>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    sqlx::query!(
+        r#"
+    UPDATE issue_deliveries
+    SET opened_at = COALESCE(opened_at, $2)
+    WHERE tracking_token = $1
+        "#,
+        token.as_str(),
+        Utc::now()
+    )
+    .execute(pool.get_ref())
+    .await
+    .context("Failed to record an issue open.")?;
+    Ok(HttpResponse::Ok()
+        .content_type("image/gif")
+        .insert_header(CacheControl(vec![CacheDirective::NoStore]))
+        .body(PIXEL))
+}
+
+/// Links in delivered issues point here. A click also counts as an open,
+/// since images are often blocked.
+#[tracing::instrument(name = "Record a link click", skip(path, pool))]
+pub async fn track_click(
+    path: web::Path<(String, Uuid)>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let (token, link_id) = path.into_inner();
+    let link = sqlx::query!(
+        "SELECT newsletter_issue_id, url FROM issue_links WHERE id = $1",
+        link_id
+    )
+    .fetch_optional(pool.get_ref())
+    .await
+    .context("Failed to retrieve the tracked link.")?
+    .ok_or_else(|| AppError::NotFound("There is no such link.".into()))?;
+
+    let now = Utc::now();
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")?;
+    let recorded = sqlx::query!(
+        r#"
+    UPDATE issue_deliveries
+    SET opened_at = COALESCE(opened_at, $3), clicked_at = COALESCE(clicked_at, $3)
+    WHERE tracking_token = $1 AND newsletter_issue_id = $2
+        "#,
+        token,
+        link.newsletter_issue_id,
+        now
+    )
+    .execute(&mut *transaction)
+    .await
+    .context("Failed to record a link click.")?;
+    if recorded.rows_affected() > 0 {
+        sqlx::query!(
+            r#"
+    INSERT INTO link_clicks (tracking_token, link_id, clicked_at)
+    VALUES ($1, $2, $3)
+            "#,
+            token,
+            link_id,
+            now
+        )
+        .execute(&mut *transaction)
+        .await
+        .context("Failed to record a link click.")?;
+    }
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to record a link click.")?;
+
+    Ok(HttpResponse::Found()
+        .insert_header((header::LOCATION, link.url))
+        .finish())
+}
@@ -0,0 +1,167 @@
+use crate::configuration::Settings;
+use crate::domain::{pick_winner, AbTestMetric, AbTestResult, DeliveryStatus, IssueStatus};
+use crate::routes::start_issue_delivery;
+use crate::startup::get_connection_pool;
+use anyhow::Context;
+use chrono::Utc;
+use sqlx::{Executor, PgPool};
+use std::time::Duration;
+use tracing::{field::display, Span};
+use uuid::Uuid;
+
+pub enum SchedulerOutcome {
+    IssueEnqueued(Uuid),
+    WinnerPicked(Uuid),
+    NoDueIssues,
+}
+
+pub async fn run_scheduler_until_stopped(configuration: Settings) -> Result<(), anyhow::Error> {
+    let connection_pool = get_connection_pool(&configuration.database);
+    scheduler_loop(connection_pool).await
+}
+
+async fn scheduler_loop(pool: PgPool) -> Result<(), anyhow::Error> {
+    loop {
+        let outcome = match try_enqueue_due_issue(&pool).await {
+            Ok(SchedulerOutcome::NoDueIssues) => try_pick_due_ab_test_winner(&pool).await,
+            outcome => outcome,
+        };
+        match outcome {
+            Ok(SchedulerOutcome::NoDueIssues) => {
+                tokio::time::sleep(Duration::from_secs(10)).await;
+            }
+            Err(_) => {
+                tokio::time::sleep(Duration::from_secs(1)).await;
+            }
+            Ok(SchedulerOutcome::IssueEnqueued(_) | SchedulerOutcome::WinnerPicked(_)) => {}
+        }
+    }
+}
+
+/// Claims a single due issue and enqueues its deliveries.
+///
+/// The claim and the enqueueing happen in the same transaction and rows locked
+/// by another instance are skipped, so every issue is enqueued exactly once no
+/// matter how many schedulers are running.
+#[tracing::instrument(skip_all, fields(newsletter_issue_id=tracing::field::Empty), err)]
+pub async fn try_enqueue_due_issue(pool: &PgPool) -> Result<SchedulerOutcome, anyhow::Error> {
+    let mut transaction = pool.begin().await?;
+    let claimed = sqlx::query!(
+        r#"
+    UPDATE newsletter_issues
+    SET status = $1, updated_at = $3
+    WHERE id = (
+        SELECT id FROM newsletter_issues
+        WHERE status = $2 AND scheduled_for <= $3
+        ORDER BY scheduled_for
+        FOR UPDATE
+        SKIP LOCKED
+        LIMIT 1
+    )
+    RETURNING id
+            "#,
+        IssueStatus::Sending.as_str(),
+        IssueStatus::Scheduled.as_str(),
+        Utc::now()
+    )
+    .fetch_optional(&mut *transaction)
+    .await?;
+
+    let Some(claimed) = claimed else {
+        return Ok(SchedulerOutcome::NoDueIssues);
+    };
+    Span::current().record("newsletter_issue_id", display(claimed.id));
+
+    start_issue_delivery(&mut transaction, claimed.id).await?;
+    transaction.commit().await?;
+    Ok(SchedulerOutcome::IssueEnqueued(claimed.id))
+}
+
+/// Picks the winner of a single A/B test whose wait is over and whose sample
+/// has been sent, then queues the winner for the recipients held back.
+///
+/// As with issues, the test is claimed with `SKIP LOCKED` in the same
+/// transaction as the winner is queued, so every winner is sent once.
+#[tracing::instrument(skip_all, fields(newsletter_issue_id=tracing::field::Empty), err)]
+pub async fn try_pick_due_ab_test_winner(pool: &PgPool) -> Result<SchedulerOutcome, anyhow::Error> {
+    let mut transaction = pool.begin().await?;
+    let claimed = sqlx::query!(
+        r#"
+    SELECT newsletter_issue_id, metric
+    FROM ab_tests
+    WHERE decided_at IS NULL
+      AND decide_at <= $1
+      AND NOT EXISTS (
+        SELECT 1 FROM issue_delivery_queue
+        WHERE newsletter_issue_id = ab_tests.newsletter_issue_id
+      )
+    ORDER BY decide_at
+    FOR UPDATE
+    SKIP LOCKED
+    LIMIT 1
+            "#,
+        Utc::now()
+    )
+    .fetch_optional(&mut *transaction)
+    .await?;
+
+    let Some(claimed) = claimed else {
+        return Ok(SchedulerOutcome::NoDueIssues);
+    };
+    let issue_id = claimed.newsletter_issue_id;
+    Span::current().record("newsletter_issue_id", display(issue_id));
+    let metric = AbTestMetric::try_from(claimed.metric).map_err(anyhow::Error::msg)?;
+
+    let results = sqlx::query_as!(
+        AbTestResult,
+        r#"
+    SELECT ab_test_variants.label,
+           COUNT(issue_deliveries.subscriber_email)
+             FILTER (WHERE issue_deliveries.status = $2) as "sent!",
+           COUNT(issue_deliveries.opened_at) as "opened!",
+           COUNT(issue_deliveries.clicked_at) as "clicked!"
+    FROM ab_test_variants
+    LEFT JOIN issue_deliveries
+      ON issue_deliveries.newsletter_issue_id = ab_test_variants.newsletter_issue_id
+     AND issue_deliveries.ab_variant = ab_test_variants.label
+    WHERE ab_test_variants.newsletter_issue_id = $1
+    GROUP BY ab_test_variants.label
+    ORDER BY ab_test_variants.label
+            "#,
+        issue_id,
+        DeliveryStatus::Sent.as_str()
+    )
+    .fetch_all(&mut *transaction)
+    .await?;
+    let winner = pick_winner(&results, metric)
+        .context("The A/B test has no variants.")?
+        .to_owned();
+    tracing::info!(winner, "Picked the winner of an A/B test");
+
+    let query = sqlx::query!(
+        r#"
+    UPDATE ab_tests
+    SET winner = $2, decided_at = $3
+    WHERE newsletter_issue_id = $1
+            "#,
+        issue_id,
+        winner,
+        Utc::now()
+    );
+    transaction.execute(query).await?;
+    let query = sqlx::query!(
+        r#"
+    WITH held_back AS (
+        DELETE FROM ab_test_holdouts
+        WHERE newsletter_issue_id = $1
+        RETURNING newsletter_issue_id, subscriber_email
+    )
+    INSERT INTO issue_delivery_queue (newsletter_issue_id, subscriber_email)
+    SELECT newsletter_issue_id, subscriber_email FROM held_back
+            "#,
+        issue_id
+    );
+    transaction.execute(query).await?;
+    transaction.commit().await?;
+    Ok(SchedulerOutcome::WinnerPicked(issue_id))
+}
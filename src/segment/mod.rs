@@ -0,0 +1,4 @@
+pub use parser::{Comparison, Condition, Moment, Segment, Value};
+
+mod parser;
+mod sql;
@@ -0,0 +1,601 @@
+use crate::domain::SubscriberTag;
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+
+const MAX_LENGTH: usize = 2000;
+const MAX_DEPTH: usize = 32;
+/// Relative times reach back a century at most, which keeps them within the
+/// range of both chrono and Postgres timestamps.
+const MAX_DAYS_AGO: i64 = 36_525;
+
+/// A filter over the subscribers of a list, written in a small expression
+/// language:
+///
+/// ```text
+/// tag = "beta" and not tag = "churned"
+/// (subscribed_at >= "2026-01-01" and subscribed_at < 30 days ago) or tag != "churned"
+/// attributes.plan = "pro" and attributes.seats > 10 and attributes.trial = false
+/// ```
+///
+/// Conditions are combined with `and`, `or`, `not` and parentheses. `tag`
+/// supports `=` and `!=`, while `subscribed_at` and `attributes.<name>`
+/// support every comparison. Dates are either quoted (`"2026-01-01"` or an
+/// RFC 3339 timestamp) or relative (`30 days ago`). Segments only ever pick
+/// among confirmed subscribers, so there is no condition on the status.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Segment {
+    And(Box<Segment>, Box<Segment>),
+    Or(Box<Segment>, Box<Segment>),
+    Not(Box<Segment>),
+    Condition(Condition),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Condition {
+    Tag {
+        op: Comparison,
+        tag: SubscriberTag,
+    },
+    SubscribedAt {
+        op: Comparison,
+        moment: Moment,
+    },
+    Attribute {
+        name: String,
+        op: Comparison,
+        value: Value,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Comparison {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+/// A point in time, either fixed or relative to when the segment is evaluated.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Moment {
+    At(DateTime<Utc>),
+    Ago(Duration),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    String(String),
+    Number(f64),
+    Bool(bool),
+}
+
+impl Comparison {
+    fn is_equality(&self) -> bool {
+        matches!(self, Comparison::Eq | Comparison::Ne)
+    }
+}
+
+impl Moment {
+    pub fn resolve(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        match self {
+            Moment::At(at) => *at,
+            Moment::Ago(duration) => now
+                .checked_sub_signed(*duration)
+                .unwrap_or(DateTime::<Utc>::MIN_UTC),
+        }
+    }
+}
+
+impl Segment {
+    pub fn parse(input: &str) -> Result<Segment, String> {
+        if input.len() > MAX_LENGTH {
+            return Err(format!(
+                "The segment is longer than {} characters.",
+                MAX_LENGTH
+            ));
+        }
+        let tokens = tokenize(input)?;
+        if tokens.is_empty() {
+            return Err("The segment is empty.".into());
+        }
+        let mut parser = Parser {
+            tokens,
+            position: 0,
+            depth: 0,
+        };
+        let segment = parser.parse_or()?;
+        match parser.peek() {
+            None => Ok(segment),
+            Some(token) => Err(format!("Unexpected {} in the segment.", token.describe())),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Str(String),
+    Number(f64),
+    Op(Comparison),
+    LeftParen,
+    RightParen,
+}
+
+impl Token {
+    fn describe(&self) -> String {
+        match self {
+            Token::Word(w) => format!("'{}'", w),
+            Token::Str(s) => format!("\"{}\"", s),
+            Token::Number(n) => format!("'{}'", n),
+            Token::Op(_) => "comparison".into(),
+            Token::LeftParen => "'('".into(),
+            Token::RightParen => "')'".into(),
+        }
+    }
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' => {
+                chars.next();
+                tokens.push(Token::LeftParen);
+            }
+            ')' => {
+                chars.next();
+                tokens.push(Token::RightParen);
+            }
+            '"' => {
+                chars.next();
+                let mut s = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(escaped @ ('"' | '\\')) => s.push(escaped),
+                            _ => return Err("Invalid escape sequence in a string.".into()),
+                        },
+                        Some(c) => s.push(c),
+                        None => return Err("Unterminated string in the segment.".into()),
+                    }
+                }
+                tokens.push(Token::Str(s));
+            }
+            '=' | '!' | '<' | '>' => {
+                chars.next();
+                let or_equal = chars.next_if_eq(&'=').is_some();
+                let op = match (c, or_equal) {
+                    ('=', false) => Comparison::Eq,
+                    ('!', true) => Comparison::Ne,
+                    ('<', false) => Comparison::Lt,
+                    ('<', true) => Comparison::Le,
+                    ('>', false) => Comparison::Gt,
+                    ('>', true) => Comparison::Ge,
+                    _ => return Err("Unknown comparison in the segment.".into()),
+                };
+                tokens.push(Token::Op(op));
+            }
+            c if c.is_ascii_digit() || c == '-' => {
+                let mut number = String::new();
+                while let Some(c) = chars.next_if(|c| c.is_ascii_digit() || *c == '.' || *c == '-')
+                {
+                    number.push(c);
+                }
+                let number = number
+                    .parse()
+                    .map_err(|_| format!("{} is not a valid number.", number))?;
+                tokens.push(Token::Number(number));
+            }
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                let mut word = String::new();
+                while let Some(c) =
+                    chars.next_if(|c| c.is_ascii_alphanumeric() || *c == '_' || *c == '.')
+                {
+                    word.push(c);
+                }
+                tokens.push(Token::Word(word));
+            }
+            other => return Err(format!("Unexpected character '{}' in the segment.", other)),
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        match self.peek() {
+            Some(Token::Word(w)) if w.eq_ignore_ascii_case(keyword) => {
+                self.position += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<(), String> {
+        if self.eat_keyword(keyword) {
+            Ok(())
+        } else {
+            Err(format!("Expected '{}' in the segment.", keyword))
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<Segment, String> {
+        let mut segment = self.parse_and()?;
+        while self.eat_keyword("or") {
+            let right = self.parse_and()?;
+            segment = Segment::Or(Box::new(segment), Box::new(right));
+        }
+        Ok(segment)
+    }
+
+    fn parse_and(&mut self) -> Result<Segment, String> {
+        let mut segment = self.parse_unary()?;
+        while self.eat_keyword("and") {
+            let right = self.parse_unary()?;
+            segment = Segment::And(Box::new(segment), Box::new(right));
+        }
+        Ok(segment)
+    }
+
+    fn parse_unary(&mut self) -> Result<Segment, String> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err("The segment is nested too deeply.".into());
+        }
+        let segment = if self.eat_keyword("not") {
+            Segment::Not(Box::new(self.parse_unary()?))
+        } else if self.peek() == Some(&Token::LeftParen) {
+            self.position += 1;
+            let segment = self.parse_or()?;
+            if self.next() != Some(Token::RightParen) {
+                return Err("Expected ')' in the segment.".into());
+            }
+            segment
+        } else {
+            Segment::Condition(self.parse_condition()?)
+        };
+        self.depth -= 1;
+        Ok(segment)
+    }
+
+    fn parse_condition(&mut self) -> Result<Condition, String> {
+        let field = match self.next() {
+            Some(Token::Word(w)) => w,
+            Some(token) => {
+                return Err(format!(
+                    "Expected a condition but found {}.",
+                    token.describe()
+                ))
+            }
+            None => return Err("Expected a condition at the end of the segment.".into()),
+        };
+        let op = match self.next() {
+            Some(Token::Op(op)) => op,
+            _ => return Err(format!("Expected a comparison after '{}'.", field)),
+        };
+
+        match field.as_str() {
+            "tag" => {
+                require_equality(&field, op)?;
+                let tag = SubscriberTag::parse(self.string(&field)?)?;
+                Ok(Condition::Tag { op, tag })
+            }
+            "status" => Err(
+                "Segments only pick among confirmed subscribers, so 'status' cannot be used."
+                    .into(),
+            ),
+            "subscribed_at" => {
+                let moment = self.moment()?;
+                Ok(Condition::SubscribedAt { op, moment })
+            }
+            _ => {
+                let Some(name) = field.strip_prefix("attributes.") else {
+                    return Err(format!("Unknown field '{}' in the segment.", field));
+                };
+                if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+                    return Err(format!("'{}' is not a valid attribute name.", name));
+                }
+                let value = self.value(&field)?;
+                if matches!(value, Value::Bool(_)) {
+                    require_equality(&field, op)?;
+                }
+                Ok(Condition::Attribute {
+                    name: name.to_owned(),
+                    op,
+                    value,
+                })
+            }
+        }
+    }
+
+    fn string(&mut self, field: &str) -> Result<String, String> {
+        match self.next() {
+            Some(Token::Str(s)) => Ok(s),
+            _ => Err(format!("Expected a quoted value after '{}'.", field)),
+        }
+    }
+
+    fn value(&mut self, field: &str) -> Result<Value, String> {
+        match self.next() {
+            Some(Token::Str(s)) => Ok(Value::String(s)),
+            Some(Token::Number(n)) => Ok(Value::Number(n)),
+            Some(Token::Word(w)) if w.eq_ignore_ascii_case("true") => Ok(Value::Bool(true)),
+            Some(Token::Word(w)) if w.eq_ignore_ascii_case("false") => Ok(Value::Bool(false)),
+            _ => Err(format!("Expected a value after '{}'.", field)),
+        }
+    }
+
+    fn moment(&mut self) -> Result<Moment, String> {
+        match self.next() {
+            Some(Token::Str(s)) => parse_date(&s).map(Moment::At),
+            Some(Token::Number(n)) if n >= 0.0 && n.fract() == 0.0 => {
+                let unit = match self.next() {
+                    Some(Token::Word(w)) => w.to_ascii_lowercase(),
+                    _ => return Err("Expected a time unit after the number.".into()),
+                };
+                let duration = match unit.as_str() {
+                    "hour" | "hours" => Duration::try_hours(n as i64),
+                    "day" | "days" => Duration::try_days(n as i64),
+                    "week" | "weeks" => Duration::try_weeks(n as i64),
+                    other => return Err(format!("'{}' is not a valid time unit.", other)),
+                };
+                let duration = duration
+                    .filter(|duration| *duration <= Duration::days(MAX_DAYS_AGO))
+                    .ok_or_else(|| format!("{} {} ago is too far in the past.", n, unit))?;
+                self.expect_keyword("ago")?;
+                Ok(Moment::Ago(duration))
+            }
+            _ => Err("Expected a date or a relative time after 'subscribed_at'.".into()),
+        }
+    }
+}
+
+fn require_equality(field: &str, op: Comparison) -> Result<(), String> {
+    if op.is_equality() {
+        Ok(())
+    } else {
+        Err(format!("'{}' can only be compared with = or !=.", field))
+    }
+}
+
+fn parse_date(s: &str) -> Result<DateTime<Utc>, String> {
+    if let Ok(at) = DateTime::parse_from_rfc3339(s) {
+        return Ok(at.with_timezone(&Utc));
+    }
+    NaiveDate::parse_from_str(s, "%Y-%m-%d")
+        .map(|date| date.and_hms_opt(0, 0, 0).unwrap().and_utc())
+        .map_err(|_| format!("{} is not a valid date.", s))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Comparison, Condition, Moment, Segment, Value};
+    use crate::domain::SubscriberTag;
+    use chrono::{Duration, TimeZone, Utc};
+    use claims::{assert_err, assert_ok_eq};
+
+    fn tag(op: Comparison, tag: &str) -> Segment {
+        Segment::Condition(Condition::Tag {
+            op,
+            tag: SubscriberTag::parse(tag.to_string()).unwrap(),
+        })
+    }
+
+    #[test]
+    fn a_single_condition_is_parsed() {
+        // Act
+        let result = Segment::parse(r#"tag = "beta""#);
+
+        // Assert
+        assert_ok_eq!(result, tag(Comparison::Eq, "beta"));
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        // Act
+        let result = Segment::parse(r#"tag = "a" or tag = "b" and tag != "c""#);
+
+        // Assert
+        assert_ok_eq!(
+            result,
+            Segment::Or(
+                Box::new(tag(Comparison::Eq, "a")),
+                Box::new(Segment::And(
+                    Box::new(tag(Comparison::Eq, "b")),
+                    Box::new(tag(Comparison::Ne, "c"))
+                ))
+            )
+        );
+    }
+
+    #[test]
+    fn parentheses_and_not_change_the_grouping() {
+        // Act
+        let result = Segment::parse(r#"NOT (tag = "a" OR tag = "b")"#);
+
+        // Assert
+        assert_ok_eq!(
+            result,
+            Segment::Not(Box::new(Segment::Or(
+                Box::new(tag(Comparison::Eq, "a")),
+                Box::new(tag(Comparison::Eq, "b"))
+            )))
+        );
+    }
+
+    #[test]
+    fn subscribed_at_accepts_dates_timestamps_and_relative_times() {
+        let test_cases = [
+            (
+                r#"subscribed_at >= "2026-01-02""#,
+                Comparison::Ge,
+                Moment::At(Utc.with_ymd_and_hms(2026, 1, 2, 0, 0, 0).unwrap()),
+            ),
+            (
+                r#"subscribed_at < "2026-01-02T10:00:00+02:00""#,
+                Comparison::Lt,
+                Moment::At(Utc.with_ymd_and_hms(2026, 1, 2, 8, 0, 0).unwrap()),
+            ),
+            (
+                "subscribed_at > 30 days ago",
+                Comparison::Gt,
+                Moment::Ago(Duration::days(30)),
+            ),
+            (
+                "subscribed_at <= 2 weeks ago",
+                Comparison::Le,
+                Moment::Ago(Duration::weeks(2)),
+            ),
+        ];
+
+        for (input, op, moment) in test_cases {
+            // Act
+            let result = Segment::parse(input);
+
+            // Assert
+            assert_ok_eq!(
+                result,
+                Segment::Condition(Condition::SubscribedAt { op, moment })
+            );
+        }
+    }
+
+    #[test]
+    fn relative_times_too_far_in_the_past_are_rejected() {
+        for input in [
+            "subscribed_at > 100000000 days ago",
+            "subscribed_at > 100000000000000 weeks ago",
+            "subscribed_at > 1000000000000000000000000000000 hours ago",
+            "subscribed_at > 36526 days ago",
+        ] {
+            // Act
+            let result = Segment::parse(input);
+
+            // Assert
+            assert!(
+                assert_err!(result).ends_with("ago is too far in the past."),
+                "{} was not rejected as too far in the past",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn attribute_conditions_accept_strings_numbers_and_booleans() {
+        let test_cases = [
+            (
+                r#"attributes.plan = "pro""#,
+                "plan",
+                Comparison::Eq,
+                Value::String("pro".into()),
+            ),
+            (
+                "attributes.seats >= 2.5",
+                "seats",
+                Comparison::Ge,
+                Value::Number(2.5),
+            ),
+            (
+                "attributes.trial != false",
+                "trial",
+                Comparison::Ne,
+                Value::Bool(false),
+            ),
+        ];
+
+        for (input, name, op, value) in test_cases {
+            // Act
+            let result = Segment::parse(input);
+
+            // Assert
+            assert_ok_eq!(
+                result,
+                Segment::Condition(Condition::Attribute {
+                    name: name.into(),
+                    op,
+                    value
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn strings_support_escaped_quotes() {
+        // Act
+        let result = Segment::parse(r#"attributes.quote = "say \"hi\"""#);
+
+        // Assert
+        assert_ok_eq!(
+            result,
+            Segment::Condition(Condition::Attribute {
+                name: "quote".into(),
+                op: Comparison::Eq,
+                value: Value::String("say \"hi\"".into())
+            })
+        );
+    }
+
+    #[test]
+    fn invalid_segments_are_rejected() {
+        let test_cases = [
+            ("", "empty segment"),
+            ("tag", "missing comparison"),
+            (r#"tag > "beta""#, "ordering on a tag"),
+            (r#"tag = "Not A Tag""#, "invalid tag"),
+            (r#"status = "confirmed""#, "status"),
+            (r#"subscribed_at > "yesterday""#, "invalid date"),
+            ("subscribed_at > 3 fortnights ago", "unknown time unit"),
+            ("subscribed_at > 3 days", "missing 'ago'"),
+            ("attributes.trial < true", "ordering on a boolean"),
+            (r#"attributes. = "x""#, "empty attribute name"),
+            (r#"email = "a@b.c""#, "unknown field"),
+            (r#"tag = "a" tag = "b""#, "missing operator"),
+            (r#"(tag = "a""#, "unbalanced parenthesis"),
+            (r#"tag = "a"#, "unterminated string"),
+            (r#"tag = "a"; DROP TABLE subscriptions"#, "stray characters"),
+        ];
+
+        for (input, description) in test_cases {
+            // Act
+            let result = Segment::parse(input);
+
+            // Assert
+            assert_err!(result, "{} was accepted", description);
+        }
+    }
+
+    #[test]
+    fn deeply_nested_segments_are_rejected() {
+        // Arrange
+        let input = format!("{}tag = \"a\"{}", "(".repeat(40), ")".repeat(40));
+
+        // Act
+        let result = Segment::parse(&input);
+
+        // Assert
+        assert_err!(result);
+    }
+}
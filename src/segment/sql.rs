@@ -0,0 +1,173 @@
+use crate::segment::{Comparison, Condition, Segment, Value};
+use chrono::{DateTime, Utc};
+use sqlx::{Postgres, QueryBuilder};
+
+impl Segment {
+    /// Appends the segment as a boolean SQL expression over the
+    /// `subscriptions` and `list_subscriptions` tables.
+    ///
+    /// Every value coming from the segment is bound as a query parameter, and
+    /// relative times are resolved against `now`.
+    pub fn push_sql(&self, query: &mut QueryBuilder<'_, Postgres>, now: DateTime<Utc>) {
+        match self {
+            Segment::And(left, right) => {
+                query.push("(");
+                left.push_sql(query, now);
+                query.push(" AND ");
+                right.push_sql(query, now);
+                query.push(")");
+            }
+            Segment::Or(left, right) => {
+                query.push("(");
+                left.push_sql(query, now);
+                query.push(" OR ");
+                right.push_sql(query, now);
+                query.push(")");
+            }
+            Segment::Not(segment) => {
+                query.push("NOT (");
+                segment.push_sql(query, now);
+                query.push(")");
+            }
+            Segment::Condition(condition) => condition.push_sql(query, now),
+        }
+    }
+}
+
+impl Condition {
+    fn push_sql(&self, query: &mut QueryBuilder<'_, Postgres>, now: DateTime<Utc>) {
+        match self {
+            Condition::Tag { op, tag } => {
+                if *op == Comparison::Ne {
+                    query.push("NOT ");
+                }
+                query
+                    .push(
+                        "EXISTS (SELECT 1 FROM subscriber_tags \
+                        WHERE subscriber_tags.subscriber_id = subscriptions.id \
+                        AND subscriber_tags.tag = ",
+                    )
+                    .push_bind(tag.as_ref().to_owned())
+                    .push(")");
+            }
+            Condition::SubscribedAt { op, moment } => {
+                query
+                    .push("list_subscriptions.subscribed_at ")
+                    .push(op.as_sql())
+                    .push(" ")
+                    .push_bind(moment.resolve(now));
+            }
+            Condition::Attribute { name, op, value } => {
+                // Subscribers without the attribute never match, except for `!=`.
+                let op = match op {
+                    Comparison::Ne => "IS DISTINCT FROM",
+                    op => op.as_sql(),
+                };
+                match value {
+                    Value::String(s) => {
+                        query
+                            .push("(subscriptions.attributes ->> ")
+                            .push_bind(name.clone())
+                            .push(") ")
+                            .push(op)
+                            .push(" ")
+                            .push_bind(s.clone());
+                    }
+                    Value::Number(n) => {
+                        query
+                            .push("(CASE WHEN jsonb_typeof(subscriptions.attributes -> ")
+                            .push_bind(name.clone())
+                            .push(") = 'number' THEN (subscriptions.attributes ->> ")
+                            .push_bind(name.clone())
+                            .push(")::float8 END) ")
+                            .push(op)
+                            .push(" ")
+                            .push_bind(*n);
+                    }
+                    Value::Bool(b) => {
+                        query
+                            .push("(subscriptions.attributes -> ")
+                            .push_bind(name.clone())
+                            .push(") ")
+                            .push(op)
+                            .push(" to_jsonb(")
+                            .push_bind(*b)
+                            .push("::boolean)");
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Comparison {
+    fn as_sql(&self) -> &'static str {
+        match self {
+            Comparison::Eq => "=",
+            Comparison::Ne => "<>",
+            Comparison::Lt => "<",
+            Comparison::Le => "<=",
+            Comparison::Gt => ">",
+            Comparison::Ge => ">=",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::segment::Segment;
+    use chrono::Utc;
+    use sqlx::{Postgres, QueryBuilder};
+
+    fn compile(input: &str) -> String {
+        let segment = Segment::parse(input).unwrap();
+        let mut query = QueryBuilder::<Postgres>::new("");
+        segment.push_sql(&mut query, Utc::now());
+        query.sql().to_owned()
+    }
+
+    #[test]
+    fn tags_compile_to_an_exists_subquery() {
+        // Act
+        let sql = compile(r#"tag != "beta""#);
+
+        // Assert
+        assert_eq!(
+            sql,
+            "NOT EXISTS (SELECT 1 FROM subscriber_tags \
+            WHERE subscriber_tags.subscriber_id = subscriptions.id \
+            AND subscriber_tags.tag = $1)"
+        );
+    }
+
+    #[test]
+    fn boolean_operators_are_parenthesized() {
+        // Act
+        let sql = compile(
+            r#"tag = "beta" and not (subscribed_at < 1 day ago or subscribed_at >= "2026-01-01")"#,
+        );
+
+        // Assert
+        assert_eq!(
+            sql,
+            "(EXISTS (SELECT 1 FROM subscriber_tags \
+            WHERE subscriber_tags.subscriber_id = subscriptions.id \
+            AND subscriber_tags.tag = $1) AND NOT ((list_subscriptions.subscribed_at < $2 \
+            OR list_subscriptions.subscribed_at >= $3)))"
+        );
+    }
+
+    #[test]
+    fn attribute_names_and_values_are_bound_as_parameters() {
+        // Act
+        let sql = compile(r#"attributes.plan != "pro' OR 1=1 --" or attributes.seats > 3"#);
+
+        // Assert
+        assert_eq!(
+            sql,
+            "((subscriptions.attributes ->> $1) IS DISTINCT FROM $2 OR \
+            (CASE WHEN jsonb_typeof(subscriptions.attributes -> $3) = 'number' \
+            THEN (subscriptions.attributes ->> $4)::float8 END) > $5)"
+        );
+    }
+}
@@ -1,10 +1,31 @@
+use crate::admin_auth::AdminAuth;
 use crate::bootstrap::Dependencies;
-use crate::configuration::{DatabaseSettings, Settings};
+use crate::bot_protection::BotProtection;
+use crate::configuration::{
+    AdminSettings, ApiSettings, BrandingSettings, DatabaseSettings, FeedSettings, Settings,
+    SubscriptionSettings,
+};
+use crate::cors::Cors;
+use crate::domain::EmailPolicy;
 use crate::email::email_client::{EmailClient, EmailService};
-use crate::routes::{confirm, health_check, publish_newsletter, subscribe};
-use actix_web::dev::Server;
+use crate::i18n::Catalogs;
+use crate::rate_limit::{RateLimit, RateLimiter};
+use crate::routes::{
+    add_subscriber_tag, api_subscribe, archive_index, archive_issue, atom_feed,
+    cancel_scheduled_issue, confirm, count_segment_recipients, create_attribute, create_issue,
+    create_list, erase_own_data, erase_subscriber_data, export_own_data, export_subscriber_data,
+    form_error_handler, get_attributes, get_import, get_import_errors, get_issue_deliveries,
+    get_issue_report, get_lists, get_subscriber_details, get_subscribers, handle_ses_notification,
+    health_check, import_subscribers, json_error_handler, not_found, preferences_page,
+    preview_issue, publish_issue, publish_newsletter, query_error_handler, remove_subscriber_tag,
+    reschedule_issue, rss_feed, schedule_issue, send_test_issue, subscribe,
+    subscription_form_token, track_click, track_open, unsubscribe, update_issue,
+    update_preferences, with_request_id,
+};
+use actix_web::dev::{Server, Service};
 use actix_web::{web, App, HttpServer};
 use http::Uri;
+use secrecy::Secret;
 use sqlx::postgres::PgPoolOptions;
 use sqlx::PgPool;
 use std::net::TcpListener;
@@ -28,12 +49,17 @@ impl Application {
             .await
             .expect("Failed to migrate the database");
 
-        let sender_email = configuration
+        let sender = configuration
             .email_client
             .sender()
-            .expect("Invalid sender email address.");
+            .expect("Invalid sender address.");
 
-        let email_service = EmailService::new(sender_email);
+        let email_service = EmailService::new(sender);
+        let rate_limiter = RateLimiter::new(configuration.rate_limit, connection_pool.clone());
+        let email_policy = EmailPolicy::new(&configuration.email_policy)?;
+        let catalogs = Catalogs::new(&configuration.i18n).map_err(std::io::Error::other)?;
+        let bot_protection =
+            BotProtection::new(configuration.bot_protection, dependencies.captcha_verifier);
 
         let address = format!(
             "{}:{}",
@@ -41,13 +67,22 @@ impl Application {
         );
         let listener = TcpListener::bind(address)?;
         let port = listener.local_addr()?.port();
-        let server = run(
-            listener,
-            connection_pool,
+        let state = AppState {
             email_service,
-            dependencies.email_client,
-            configuration.application.base_url,
-        )?;
+            email_client: dependencies.email_client,
+            base_url: configuration.application.base_url,
+            feed: configuration.feed,
+            subscriptions: configuration.subscriptions,
+            branding: configuration.branding,
+            api: configuration.api,
+            admin: configuration.admin,
+            rate_limiter,
+            bot_protection,
+            email_policy,
+            catalogs,
+            notifications_token: configuration.email_client.notifications_token,
+        };
+        let server = run(listener, connection_pool, state)?;
 
         Ok(Self { port, server })
     }
@@ -61,30 +96,152 @@ impl Application {
     }
 }
 
-pub struct ApplicationBaseUrl(pub Uri);
+/// Everything the request handlers share besides the database pool.
+pub struct AppState {
+    pub email_service: EmailService,
+    pub email_client: Arc<dyn EmailClient>,
+    pub base_url: Uri,
+    pub feed: FeedSettings,
+    pub subscriptions: SubscriptionSettings,
+    pub branding: BrandingSettings,
+    pub api: ApiSettings,
+    pub admin: AdminSettings,
+    pub rate_limiter: RateLimiter,
+    pub bot_protection: BotProtection,
+    pub email_policy: EmailPolicy,
+    pub catalogs: Catalogs,
+    /// Part of the URL of the SES notifications webhook, which is disabled
+    /// when unset.
+    pub notifications_token: Option<Secret<String>>,
+}
 
 pub fn run(
     listener: TcpListener,
     db_pool: PgPool,
-    email_service: EmailService,
-    email_client: Arc<dyn EmailClient>,
-    base_url: Uri,
+    state: AppState,
 ) -> Result<Server, std::io::Error> {
     let db_pool = web::Data::new(db_pool);
-    let email_service = web::Data::new(email_service);
-    let email_client: web::Data<dyn EmailClient> = web::Data::from(email_client.clone());
-    let base_url = web::Data::new(ApplicationBaseUrl(base_url));
+    let rate_limiter = state.rate_limiter.clone();
+    let allowed_origins = state.api.allowed_origins.clone();
+    let admin_token = state.admin.api_token.clone();
+    let state = web::Data::new(state);
     let server = HttpServer::new(move || {
         App::new()
             .wrap(TracingLogger::default())
+            .wrap_fn(|request, service| {
+                let response = service.call(request);
+                async move { Ok(with_request_id(response.await?)) }
+            })
             .route("/health_check", web::get().to(health_check))
-            .route("/subscriptions", web::post().to(subscribe))
-            .route("/subscriptions/confirm", web::get().to(confirm))
+            .service(
+                web::resource("/subscriptions")
+                    .wrap(RateLimit::new(rate_limiter.clone()))
+                    .route(web::post().to(subscribe)),
+            )
+            .service(
+                web::scope("/api/v1")
+                    .wrap(RateLimit::new(rate_limiter.clone()))
+                    .wrap(Cors::new(allowed_origins.clone()))
+                    .route("/subscriptions", web::post().to(api_subscribe)),
+            )
+            .service(
+                web::resource("/subscriptions/form-token")
+                    .wrap(Cors::new(allowed_origins.clone()))
+                    .route(web::get().to(subscription_form_token)),
+            )
+            .service(
+                web::resource("/subscriptions/confirm")
+                    .wrap(RateLimit::new(rate_limiter.clone()))
+                    .route(web::get().to(confirm)),
+            )
+            .service(
+                web::resource("/subscriptions/unsubscribe")
+                    .wrap(RateLimit::new(rate_limiter.clone()))
+                    .route(web::get().to(unsubscribe)),
+            )
+            .service(
+                web::resource("/preferences/{token}")
+                    .wrap(RateLimit::new(rate_limiter.clone()))
+                    .route(web::get().to(preferences_page))
+                    .route(web::post().to(update_preferences)),
+            )
+            .route(
+                "/preferences/{token}/export",
+                web::get().to(export_own_data),
+            )
+            .route("/preferences/{token}/erase", web::post().to(erase_own_data))
             .route("/newsletters", web::post().to(publish_newsletter))
+            .route("/archive", web::get().to(archive_index))
+            .route("/archive/{slug}", web::get().to(archive_issue))
+            .route("/feed.xml", web::get().to(atom_feed))
+            .route("/rss.xml", web::get().to(rss_feed))
+            .route("/track/open/{token}", web::get().to(track_open))
+            .route("/track/click/{token}/{link_id}", web::get().to(track_click))
+            .route(
+                "/webhooks/ses/{token}",
+                web::post().to(handle_ses_notification),
+            )
+            .service(
+                web::scope("/admin")
+                    .wrap(AdminAuth::new(admin_token.clone()))
+                    .service(
+                        web::resource("/lists")
+                            .route(web::get().to(get_lists))
+                            .route(web::post().to(create_list)),
+                    )
+                    .service(
+                        web::resource("/attributes")
+                            .route(web::get().to(get_attributes))
+                            .route(web::post().to(create_attribute)),
+                    )
+                    .route("/imports", web::post().to(import_subscribers))
+                    .route("/imports/{import_id}", web::get().to(get_import))
+                    .route(
+                        "/imports/{import_id}/errors",
+                        web::get().to(get_import_errors),
+                    )
+                    .route("/subscribers", web::get().to(get_subscribers))
+                    .route("/subscribers/export", web::get().to(export_subscriber_data))
+                    .route("/subscribers/erase", web::post().to(erase_subscriber_data))
+                    .route(
+                        "/subscribers/{subscriber_id}",
+                        web::get().to(get_subscriber_details),
+                    )
+                    .route(
+                        "/subscribers/{subscriber_id}/tags",
+                        web::post().to(add_subscriber_tag),
+                    )
+                    .route(
+                        "/subscribers/{subscriber_id}/tags/{tag}",
+                        web::delete().to(remove_subscriber_tag),
+                    )
+                    .route("/segments/count", web::post().to(count_segment_recipients))
+                    .route("/issues", web::post().to(create_issue))
+                    .service(
+                        web::resource("/issues/{issue_id}")
+                            .route(web::get().to(get_issue_report))
+                            .route(web::put().to(update_issue)),
+                    )
+                    .route("/issues/{issue_id}/preview", web::get().to(preview_issue))
+                    .route("/issues/{issue_id}/test", web::post().to(send_test_issue))
+                    .route("/issues/{issue_id}/publish", web::post().to(publish_issue))
+                    .route(
+                        "/issues/{issue_id}/deliveries",
+                        web::get().to(get_issue_deliveries),
+                    )
+                    .service(
+                        web::resource("/issues/{issue_id}/schedule")
+                            .route(web::post().to(schedule_issue))
+                            .route(web::put().to(reschedule_issue))
+                            .route(web::delete().to(cancel_scheduled_issue)),
+                    ),
+            )
+            .default_service(web::to(not_found))
+            .app_data(web::JsonConfig::default().error_handler(json_error_handler))
+            .app_data(web::FormConfig::default().error_handler(form_error_handler))
+            .app_data(web::QueryConfig::default().error_handler(query_error_handler))
             .app_data(db_pool.clone())
-            .app_data(email_service.clone())
-            .app_data(email_client.clone())
-            .app_data(base_url.clone())
+            .app_data(state.clone())
     })
     .listen(listener)?
     .run();
@@ -0,0 +1,188 @@
+use crate::bootstrap::Dependencies;
+use crate::configuration::Settings;
+use crate::domain::{Locale, SubscriberEmail, SubscriptionStatus};
+use crate::email::email_client::{EmailClient, EmailService, SendEmailRequest};
+use crate::email::merge_tags::MergeTags;
+use crate::email::welcome_email::WelcomeEmail;
+use crate::i18n::Catalogs;
+use crate::issue_delivery_worker::ExecutionOutcome;
+use crate::startup::get_connection_pool;
+use http::Uri;
+use sqlx::{Executor, PgPool};
+use std::time::Duration;
+use tracing::{field::display, Span};
+
+pub async fn run_welcome_email_worker_until_stopped(
+    configuration: Settings,
+    dependencies: Dependencies,
+) -> Result<(), anyhow::Error> {
+    // Nothing is ever queued when the welcome email is not configured.
+    let Some(settings) = &configuration.subscriptions.welcome_email else {
+        return std::future::pending().await;
+    };
+    let welcome_email = WelcomeEmail::load(settings)?;
+    let connection_pool = get_connection_pool(&configuration.database);
+    let sender = configuration
+        .email_client
+        .sender()
+        .map_err(anyhow::Error::msg)?;
+    let email_service = EmailService::new(sender);
+    let catalogs = Catalogs::new(&configuration.i18n)?;
+    worker_loop(
+        connection_pool,
+        email_service,
+        dependencies.email_client.as_ref(),
+        configuration.application.base_url,
+        welcome_email,
+        catalogs,
+    )
+    .await
+}
+
+async fn worker_loop(
+    pool: PgPool,
+    email_service: EmailService,
+    email_client: &dyn EmailClient,
+    base_url: Uri,
+    welcome_email: WelcomeEmail,
+    catalogs: Catalogs,
+) -> Result<(), anyhow::Error> {
+    loop {
+        match try_send_welcome_email(
+            &pool,
+            &email_service,
+            email_client,
+            &base_url,
+            &welcome_email,
+            &catalogs,
+        )
+        .await
+        {
+            Ok(ExecutionOutcome::EmptyQueue) => {
+                tokio::time::sleep(Duration::from_secs(10)).await;
+            }
+            Err(_) => {
+                tokio::time::sleep(Duration::from_secs(1)).await;
+            }
+            Ok(ExecutionOutcome::TaskCompleted) => {}
+        }
+    }
+}
+
+/// Sends the welcome email owed to one newly confirmed subscriber. Each email
+/// is attempted once: failures are logged and dropped, as a missed welcome
+/// does not affect the subscription.
+#[tracing::instrument(skip_all, fields(subscriber_email=tracing::field::Empty), err)]
+pub async fn try_send_welcome_email(
+    pool: &PgPool,
+    email_service: &EmailService,
+    email_client: &dyn EmailClient,
+    base_url: &Uri,
+    welcome_email: &WelcomeEmail,
+    catalogs: &Catalogs,
+) -> Result<ExecutionOutcome, anyhow::Error> {
+    let mut transaction = pool.begin().await?;
+    let task = sqlx::query!(
+        r#"
+    SELECT welcome_email_queue.subscriber_id,
+           welcome_email_queue.list_id,
+           subscriptions.email,
+           subscriptions.name,
+           subscriptions.preferences_token,
+           subscriptions.locale,
+           list_subscriptions.status,
+           list_subscriptions.unsubscribe_token,
+           lists.name AS list_name
+    FROM welcome_email_queue
+    JOIN subscriptions ON subscriptions.id = welcome_email_queue.subscriber_id
+    JOIN lists ON lists.id = welcome_email_queue.list_id
+    JOIN list_subscriptions
+      ON list_subscriptions.subscriber_id = welcome_email_queue.subscriber_id
+     AND list_subscriptions.list_id = welcome_email_queue.list_id
+    FOR UPDATE OF welcome_email_queue
+    SKIP LOCKED
+    LIMIT 1
+            "#,
+    )
+    .fetch_optional(&mut *transaction)
+    .await?;
+    let Some(task) = task else {
+        return Ok(ExecutionOutcome::EmptyQueue);
+    };
+    Span::current().record("subscriber_email", display(&task.email));
+
+    match SubscriberEmail::parse(task.email) {
+        Ok(_) if task.status != SubscriptionStatus::Confirmed.as_str() => {
+            tracing::info!("Skipping a subscriber who left the list before being welcomed");
+        }
+        Ok(email) => {
+            let locale = task
+                .locale
+                .as_deref()
+                .and_then(|locale| Locale::parse(locale).ok());
+            let templates = welcome_email.templates(locale.as_ref());
+            let t = catalogs.translator(locale.as_ref());
+            let unsubscribe_label = t.text("email.footer.unsubscribe");
+            let preferences_label = t.text("email.footer.manage_preferences");
+            let unsubscribe_url = format!(
+                "{}subscriptions/unsubscribe?token={}",
+                base_url, task.unsubscribe_token
+            );
+            let preferences_url = format!("{}preferences/{}", base_url, task.preferences_token);
+            let merge_tags = MergeTags::new()
+                .with("name", &task.name)
+                .with("email", email.as_ref())
+                .with("list_name", &task.list_name)
+                .with("unsubscribe_url", &unsubscribe_url)
+                .with("preferences_url", &preferences_url);
+            let html_content = format!(
+                "{}\n<p><a href=\"{}\">{}</a></p>\n<p><a href=\"{}\">{}</a></p>",
+                merge_tags.render_html(templates.html_template),
+                unsubscribe_url,
+                htmlescape::encode_minimal(&unsubscribe_label),
+                preferences_url,
+                htmlescape::encode_minimal(&preferences_label)
+            );
+            let text_content = format!(
+                "{}\n\n{}: {}\n{}: {}",
+                merge_tags.render_text(templates.text_template),
+                unsubscribe_label,
+                unsubscribe_url,
+                preferences_label,
+                preferences_url
+            );
+            let send_email_request = SendEmailRequest {
+                to: &email,
+                subject: &merge_tags.render_text(templates.subject),
+                html_content: &html_content,
+                text_content: &text_content,
+                attachments: &welcome_email.attachments,
+            };
+            if let Err(e) = email_service
+                .send_email(email_client, send_email_request)
+                .await
+            {
+                tracing::error!(
+                    error.cause_chain = ?e,
+                    error.message = %e,
+                    "Failed to send a welcome email to a new subscriber. Skipping.",
+                );
+            }
+        }
+        Err(error) => {
+            tracing::warn!(
+                error.cause_chain = ?error,
+                "Skipping a new subscriber. Their stored contact details are invalid",
+            );
+        }
+    }
+
+    let query = sqlx::query!(
+        r#"DELETE FROM welcome_email_queue WHERE subscriber_id = $1 AND list_id = $2"#,
+        task.subscriber_id,
+        task.list_id
+    );
+    transaction.execute(query).await?;
+    transaction.commit().await?;
+    Ok(ExecutionOutcome::TaskCompleted)
+}
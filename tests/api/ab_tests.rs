@@ -0,0 +1,279 @@
+use crate::api::helpers::{spawn_app, subscribe_and_confirm, TestApp};
+use aws_sdk_sesv2::operation::send_email::SendEmailInput;
+
+const SUBSCRIBERS: usize = 10;
+
+fn ab_test_body(metric: &str) -> serde_json::Value {
+    serde_json::json!({
+        "title": "Newsletter title",
+        "content": {
+            "text": "Newsletter body as plain text",
+            "html": "<p>Newsletter body as HTML</p>"
+        },
+        "ab_test": {
+            "sample_percent": 40,
+            "wait_minutes": 60,
+            "metric": metric,
+            "variants": [
+                { "title": "Subject A" },
+                {
+                    "title": "Subject B",
+                    "content": {
+                        "text": "Variant B as plain text",
+                        "html": "<p>Variant B as HTML</p>"
+                    }
+                }
+            ]
+        }
+    })
+}
+
+async fn spawn_app_with_subscribers() -> TestApp {
+    let app = spawn_app().await;
+    for i in 0..SUBSCRIBERS {
+        subscribe_and_confirm(&app, &format!("reader{}@example.com", i), "default").await;
+    }
+    app
+}
+
+async fn issue_id(app: &TestApp) -> String {
+    sqlx::query_scalar!("SELECT id FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .to_string()
+}
+
+fn subjects(requests: &[SendEmailInput]) -> Vec<&str> {
+    let mut subjects: Vec<_> = requests
+        .iter()
+        .map(|r| {
+            r.content()
+                .unwrap()
+                .simple()
+                .unwrap()
+                .subject()
+                .unwrap()
+                .data()
+        })
+        .collect();
+    subjects.sort();
+    subjects
+}
+
+async fn count_holdouts(app: &TestApp) -> i64 {
+    sqlx::query_scalar!(r#"SELECT COUNT(*) AS "count!" FROM ab_test_holdouts"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+}
+
+/// Opens every email sent with `label`, as the open pixel would.
+async fn open_variant(app: &TestApp, label: &str) {
+    let tokens = sqlx::query_scalar!(
+        "SELECT tracking_token FROM issue_deliveries WHERE ab_variant = $1",
+        label
+    )
+    .fetch_all(&app.db_pool)
+    .await
+    .unwrap();
+    for token in tokens {
+        reqwest::get(format!("{}/track/open/{}", app.address, token))
+            .await
+            .unwrap()
+            .error_for_status()
+            .unwrap();
+    }
+}
+
+async fn end_the_wait(app: &TestApp) {
+    sqlx::query!("UPDATE ab_tests SET decide_at = decide_at - interval '1 hour'")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn the_sample_is_split_between_the_variants_and_the_rest_is_held_back() {
+    // Arrange
+    let app = spawn_app_with_subscribers().await;
+
+    // Act
+    app.post_newsletters(&ab_test_body("open_rate"))
+        .await
+        .error_for_status()
+        .unwrap();
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let requests = app.aws_request_wrapper.expect_requests_and_remove(4);
+    assert_eq!(
+        subjects(&requests),
+        ["Subject A", "Subject A", "Subject B", "Subject B"]
+    );
+    let b = requests
+        .iter()
+        .find(|r| {
+            r.content()
+                .unwrap()
+                .simple()
+                .unwrap()
+                .subject()
+                .unwrap()
+                .data()
+                == "Subject B"
+        })
+        .unwrap();
+    let body = b.content().unwrap().simple().unwrap().body().unwrap();
+    assert!(body
+        .text()
+        .unwrap()
+        .data()
+        .contains("Variant B as plain text"));
+    assert_eq!(count_holdouts(&app).await, 6);
+    let status = sqlx::query_scalar!("SELECT status FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_ne!(status, "sent");
+}
+
+#[tokio::test]
+async fn the_winner_is_sent_to_the_rest_once_the_wait_is_over() {
+    // Arrange
+    let app = spawn_app_with_subscribers().await;
+    app.post_newsletters(&ab_test_body("open_rate"))
+        .await
+        .error_for_status()
+        .unwrap();
+    app.dispatch_all_pending_emails().await;
+    app.aws_request_wrapper.expect_requests_and_remove(4);
+    open_variant(&app, "B").await;
+
+    // Act - Part 1 - The wait is not over yet
+    app.pick_all_due_ab_test_winners().await;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert - Part 1
+    app.aws_request_wrapper.expect_zero_requests();
+    assert_eq!(count_holdouts(&app).await, 6);
+
+    // Act - Part 2
+    end_the_wait(&app).await;
+    app.pick_all_due_ab_test_winners().await;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert - Part 2
+    let requests = app.aws_request_wrapper.expect_requests_and_remove(6);
+    assert_eq!(subjects(&requests), ["Subject B"; 6]);
+    assert_eq!(count_holdouts(&app).await, 0);
+    let status = sqlx::query_scalar!("SELECT status FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(status, "sent");
+}
+
+#[tokio::test]
+async fn the_report_shows_the_results_of_each_variant() {
+    // Arrange
+    let app = spawn_app_with_subscribers().await;
+    app.post_newsletters(&ab_test_body("open_rate"))
+        .await
+        .error_for_status()
+        .unwrap();
+    app.dispatch_all_pending_emails().await;
+    open_variant(&app, "A").await;
+    end_the_wait(&app).await;
+    app.pick_all_due_ab_test_winners().await;
+    app.dispatch_all_pending_emails().await;
+
+    // Act
+    let report: serde_json::Value = app
+        .get_issue_report(&issue_id(&app).await)
+        .await
+        .json()
+        .await
+        .unwrap();
+
+    // Assert
+    let test = &report["ab_test"];
+    assert_eq!(test["metric"], "open_rate");
+    assert_eq!(test["sample_percent"], 40);
+    assert_eq!(test["winner"], "A");
+    assert!(test["decided_at"].is_string());
+    let variants = test["variants"].as_array().unwrap();
+    assert_eq!(variants.len(), 2);
+    assert_eq!(variants[0]["label"], "A");
+    assert_eq!(variants[0]["title"], "Subject A");
+    assert_eq!(variants[0]["sent"], 2);
+    assert_eq!(variants[0]["opened"], 2);
+    assert_eq!(variants[0]["open_rate"], 1.0);
+    assert_eq!(variants[1]["label"], "B");
+    assert_eq!(variants[1]["recipients"], 2);
+    assert_eq!(variants[1]["open_rate"], 0.0);
+    assert_eq!(report["deliveries"]["sent"], 10);
+}
+
+#[tokio::test]
+async fn ties_go_to_the_first_variant() {
+    // Arrange
+    let app = spawn_app_with_subscribers().await;
+    app.post_newsletters(&ab_test_body("click_rate"))
+        .await
+        .error_for_status()
+        .unwrap();
+    app.dispatch_all_pending_emails().await;
+    app.aws_request_wrapper.expect_requests_and_remove(4);
+    open_variant(&app, "B").await;
+
+    // Act
+    end_the_wait(&app).await;
+    app.pick_all_due_ab_test_winners().await;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let requests = app.aws_request_wrapper.expect_requests_and_remove(6);
+    assert_eq!(subjects(&requests), ["Subject A"; 6]);
+}
+
+#[tokio::test]
+async fn invalid_ab_tests_are_rejected_with_a_400() {
+    // Arrange
+    let app = spawn_app().await;
+    let with = |field: &str, value: serde_json::Value| {
+        let mut body = ab_test_body("open_rate");
+        body["ab_test"][field] = value;
+        body
+    };
+    let mut translated = ab_test_body("open_rate");
+    translated["variants"] = serde_json::json!({
+        "pt": {
+            "title": "Título",
+            "content": { "text": "Corpo", "html": "<p>Corpo</p>" }
+        }
+    });
+    let test_cases = vec![
+        (
+            with("variants", serde_json::json!([{ "title": "Only one" }])),
+            "a single variant",
+        ),
+        (with("sample_percent", 100.into()), "a sample of everyone"),
+        (with("wait_minutes", 0.into()), "no wait"),
+        (with("metric", "reply_rate".into()), "an unknown metric"),
+        (translated, "a translated issue"),
+    ];
+
+    for (body, description) in test_cases {
+        // Act
+        let response = app.post_newsletters(&body).await;
+
+        // Assert
+        assert_eq!(
+            400,
+            response.status().as_u16(),
+            "The API did not fail with 400 Bad Request when the A/B test had {}.",
+            description
+        );
+    }
+}
@@ -0,0 +1,87 @@
+use crate::api::helpers::{spawn_app, spawn_app_with, ADMIN_TOKEN};
+use reqwest::Method;
+use uuid::Uuid;
+
+fn admin_endpoints() -> Vec<(Method, String)> {
+    let id = Uuid::new_v4();
+    vec![
+        (Method::GET, "/admin/lists".into()),
+        (Method::POST, "/admin/lists".into()),
+        (Method::GET, "/admin/attributes".into()),
+        (Method::POST, "/admin/imports".into()),
+        (Method::GET, format!("/admin/imports/{}", id)),
+        (Method::GET, "/admin/subscribers".into()),
+        (
+            Method::GET,
+            "/admin/subscribers/export?email=a@example.com".into(),
+        ),
+        (Method::POST, "/admin/subscribers/erase".into()),
+        (Method::GET, format!("/admin/subscribers/{}", id)),
+        (Method::POST, "/admin/segments/count".into()),
+        (Method::POST, "/admin/issues".into()),
+        (Method::GET, format!("/admin/issues/{}", id)),
+        (Method::POST, format!("/admin/issues/{}/test", id)),
+        (Method::POST, format!("/admin/issues/{}/publish", id)),
+        (Method::DELETE, format!("/admin/issues/{}/schedule", id)),
+    ]
+}
+
+#[tokio::test]
+async fn admin_endpoints_reject_requests_without_the_admin_token() {
+    // Arrange
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+
+    for (method, path) in admin_endpoints() {
+        for token in [None, Some("not-the-admin-token")] {
+            // Act
+            let mut request = client.request(method.clone(), format!("{}{}", app.address, path));
+            if let Some(token) = token {
+                request = request.bearer_auth(token);
+            }
+            let response = request.send().await.unwrap();
+
+            // Assert
+            assert_eq!(
+                401,
+                response.status().as_u16(),
+                "{} {} was not rejected with token {:?}",
+                method,
+                path,
+                token
+            );
+            assert_eq!(response.headers()["www-authenticate"], "Bearer");
+            let problem: serde_json::Value = response.json().await.unwrap();
+            assert_eq!(problem["code"], "unauthorized");
+        }
+    }
+}
+
+#[tokio::test]
+async fn admin_endpoints_accept_the_admin_token() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = reqwest::Client::new()
+        .get(format!("{}/admin/lists", app.address))
+        .bearer_auth(ADMIN_TOKEN)
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn admin_endpoints_are_closed_when_no_token_is_configured() {
+    // Arrange
+    let app = spawn_app_with(|c| c.admin.api_token = None).await;
+
+    // Act
+    let response = app.get_lists().await;
+
+    // Assert
+    assert_eq!(401, response.status().as_u16());
+}
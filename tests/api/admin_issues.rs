@@ -0,0 +1,267 @@
+use crate::api::helpers::{create_confirmed_subscriber, issue_body, spawn_app};
+use crate::aws_ses_rules::AwsRequestsWrapper;
+use uuid::Uuid;
+
+#[tokio::test]
+async fn creating_an_issue_stores_a_draft() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app.post_issue(&issue_body("Newsletter title")).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 201);
+
+    let saved = sqlx::query!("SELECT title, status, published_at FROM newsletter_issues",)
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved issue.");
+    assert_eq!(saved.title, "Newsletter title");
+    assert_eq!(saved.status, "draft");
+    assert!(saved.published_at.is_none());
+    app.aws_request_wrapper.expect_zero_requests();
+}
+
+#[tokio::test]
+async fn creating_an_issue_returns_400_for_invalid_data() {
+    // Arrange
+    let app = spawn_app().await;
+    let test_cases = vec![
+        (
+            serde_json::json!({"content": {"text": "text", "html": "<p>html</p>"}}),
+            "missing title",
+        ),
+        (
+            serde_json::json!({"title": " ", "content": {"text": "text", "html": "<p>html</p>"}}),
+            "empty title",
+        ),
+        (
+            serde_json::json!({"title": "Newsletter!"}),
+            "missing content",
+        ),
+    ];
+
+    for (invalid_body, error_message) in test_cases {
+        // Act
+        let response = app.post_issue(&invalid_body).await;
+
+        // Assert
+        assert_eq!(
+            400,
+            response.status().as_u16(),
+            "The API did not fail with 400 Bad Request when the payload was {}.",
+            error_message
+        );
+    }
+}
+
+#[tokio::test]
+async fn drafts_can_be_edited() {
+    // Arrange
+    let app = spawn_app().await;
+    let issue_id = app
+        .create_draft_issue(&issue_body("Newsletter title"))
+        .await;
+    let edited = serde_json::json!({
+        "title": "Edited title",
+        "content": {"text": "Edited text", "html": "<p>Edited html</p>"}
+    });
+
+    // Act
+    let response = app.put_issue(&issue_id, &edited).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+
+    let saved = sqlx::query!("SELECT title, text_content, html_content FROM newsletter_issues",)
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved issue.");
+    assert_eq!(saved.title, "Edited title");
+    assert_eq!(saved.text_content, "Edited text");
+    assert_eq!(saved.html_content, "<p>Edited html</p>");
+}
+
+#[tokio::test]
+async fn editing_an_unknown_issue_returns_404() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app
+        .put_issue(&Uuid::new_v4().to_string(), &issue_body("Newsletter title"))
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn published_issues_can_no_longer_be_edited() {
+    // Arrange
+    let app = spawn_app().await;
+    let issue_id = app
+        .create_draft_issue(&issue_body("Newsletter title"))
+        .await;
+    app.post_issue_publish(&issue_id)
+        .await
+        .error_for_status()
+        .unwrap();
+
+    // Act
+    let response = app
+        .put_issue(&issue_id, &issue_body("Newsletter title"))
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 409);
+}
+
+#[tokio::test]
+async fn preview_renders_the_issue_html() {
+    // Arrange
+    let app = spawn_app().await;
+    let issue_id = app
+        .create_draft_issue(&issue_body("Newsletter title"))
+        .await;
+
+    // Act
+    let response = app.get_issue_preview(&issue_id).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(response.headers()["content-type"]
+        .to_str()
+        .unwrap()
+        .starts_with("text/html"));
+    let html = response.text().await.unwrap();
+    assert!(html.contains("<p>Hi {{name}}, this is Newsletter title as HTML</p>"));
+    assert!(html.contains("<title>Preview: Newsletter title</title>"));
+}
+
+#[tokio::test]
+async fn preview_of_an_unknown_issue_returns_404() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app.get_issue_preview(&Uuid::new_v4().to_string()).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn test_sends_only_reach_the_given_addresses() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    let issue_id = app
+        .create_draft_issue(&issue_body("Newsletter title"))
+        .await;
+
+    // Act
+    let response = app
+        .post_issue_test_send(
+            &issue_id,
+            &serde_json::json!({"recipients": ["editor@example.com"]}),
+        )
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let request = app.aws_request_wrapper.expect_one_request();
+    AwsRequestsWrapper::assert_correct_destination(&request, "editor@example.com");
+    AwsRequestsWrapper::assert_correct_subject(&request, "[TEST] Newsletter title");
+
+    let saved = sqlx::query!("SELECT status FROM newsletter_issues",)
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved issue.");
+    assert_eq!(saved.status, "draft");
+}
+
+#[tokio::test]
+async fn test_sends_with_invalid_recipients_are_rejected_with_a_400() {
+    // Arrange
+    let app = spawn_app().await;
+    let issue_id = app
+        .create_draft_issue(&issue_body("Newsletter title"))
+        .await;
+    let test_cases = vec![
+        (serde_json::json!({"recipients": []}), "no recipients"),
+        (
+            serde_json::json!({"recipients": ["editor@example.com", "not-an-email"]}),
+            "an invalid recipient",
+        ),
+        (
+            serde_json::json!({"recipients": ["editor@example.com", "someone@example.org"]}),
+            "a recipient outside the configured test addresses",
+        ),
+    ];
+
+    for (invalid_body, error_message) in test_cases {
+        // Act
+        let response = app.post_issue_test_send(&issue_id, &invalid_body).await;
+
+        // Assert
+        assert_eq!(
+            400,
+            response.status().as_u16(),
+            "The API did not fail with 400 Bad Request when the payload had {}.",
+            error_message
+        );
+    }
+    app.aws_request_wrapper.expect_zero_requests();
+}
+
+#[tokio::test]
+async fn publishing_a_draft_delivers_it_to_confirmed_subscribers() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    let issue_id = app
+        .create_draft_issue(&issue_body("Newsletter title"))
+        .await;
+
+    // Act
+    let response = app.post_issue_publish(&issue_id).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    app.dispatch_all_pending_emails().await;
+    let request = app.aws_request_wrapper.expect_one_request();
+    AwsRequestsWrapper::assert_correct_subject(&request, "Newsletter title");
+
+    let saved = sqlx::query!("SELECT status, published_at FROM newsletter_issues",)
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved issue.");
+    assert_eq!(saved.status, "sent");
+    assert!(saved.published_at.is_some());
+}
+
+#[tokio::test]
+async fn an_issue_cannot_be_published_twice() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    let issue_id = app
+        .create_draft_issue(&issue_body("Newsletter title"))
+        .await;
+    app.post_issue_publish(&issue_id)
+        .await
+        .error_for_status()
+        .unwrap();
+    app.dispatch_all_pending_emails().await;
+    app.aws_request_wrapper.expect_one_request_and_remove();
+
+    // Act
+    let response = app.post_issue_publish(&issue_id).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 409);
+    app.dispatch_all_pending_emails().await;
+    app.aws_request_wrapper.expect_zero_requests();
+}
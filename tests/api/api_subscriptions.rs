@@ -0,0 +1,246 @@
+use crate::api::helpers::{spawn_app, spawn_app_with, TestApp};
+use crate::aws_ses_rules::AwsRequestsWrapper;
+use serde_json::json;
+
+async fn preflight(app: &TestApp, origin: &str) -> reqwest::Response {
+    reqwest::Client::new()
+        .request(
+            reqwest::Method::OPTIONS,
+            format!("{}/api/v1/subscriptions", &app.address),
+        )
+        .header("Origin", origin)
+        .header("Access-Control-Request-Method", "POST")
+        .header("Access-Control-Request-Headers", "content-type")
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+async fn post_from_origin(app: &TestApp, origin: &str) -> reqwest::Response {
+    reqwest::Client::new()
+        .post(format!("{}/api/v1/subscriptions", &app.address))
+        .header("Origin", origin)
+        .json(&json!({"name": "le guin", "email": "ursula_le_guin@gmail.com"}))
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+#[tokio::test]
+async fn a_json_subscription_is_stored_and_confirmed_by_email() {
+    // Arrange
+    let app = spawn_app().await;
+    let email = "ursula_le_guin@gmail.com";
+
+    // Act
+    let response = app
+        .post_api_subscriptions(&json!({
+            "name": "le guin",
+            "email": email,
+            "source": "mobile-app",
+        }))
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["code"], "confirmation_sent");
+
+    let saved = sqlx::query!(
+        r#"
+        SELECT subscriptions.email, list_subscriptions.status, consent_records.form_source
+        FROM subscriptions
+        JOIN list_subscriptions ON list_subscriptions.subscriber_id = subscriptions.id
+        JOIN consent_records ON consent_records.subscriber_id = subscriptions.id
+        "#
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(saved.email, email);
+    assert_eq!(saved.status, "pending_confirmation");
+    assert_eq!(saved.form_source, "mobile-app");
+
+    let request = app.aws_request_wrapper.expect_one_request();
+    AwsRequestsWrapper::assert_correct_destination(&request, email);
+}
+
+#[tokio::test]
+async fn every_invalid_field_is_reported_with_its_own_code() {
+    // Arrange
+    let app = spawn_app().await;
+    let response = app
+        .post_attribute(&json!({"name": "seats", "type": "number"}))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    // Act
+    let response = app
+        .post_api_subscriptions(&json!({
+            "email": "not-an-email",
+            "list": "Not A Slug",
+            "attributes": {"seats": "many", "colour": "blue", "company": null},
+        }))
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 400);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["code"], "validation_failed");
+    let fields = &body["fields"];
+    assert_eq!(fields["email"]["code"], "invalid_email");
+    assert_eq!(fields["name"]["code"], "required");
+    assert_eq!(fields["list"]["code"], "invalid_list");
+    assert_eq!(fields["attributes.seats"]["code"], "invalid_attribute");
+    assert_eq!(fields["attributes.colour"]["code"], "unknown_attribute");
+    assert_eq!(fields["attributes.company"]["code"], "unknown_attribute");
+    assert_eq!(fields.as_object().unwrap().len(), 6);
+    assert!(fields["email"]["message"].is_string());
+}
+
+#[tokio::test]
+async fn an_unknown_list_is_reported_on_the_list_field() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app
+        .post_api_subscriptions(&json!({
+            "name": "le guin",
+            "email": "ursula_le_guin@gmail.com",
+            "list": "rust-weekly",
+        }))
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 400);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["fields"]["list"]["code"], "unknown_list");
+}
+
+#[tokio::test]
+async fn typed_attribute_values_are_accepted() {
+    // Arrange
+    let app = spawn_app().await;
+    for body in [
+        json!({"name": "seats", "type": "number"}),
+        json!({"name": "beta", "type": "bool"}),
+    ] {
+        assert_eq!(app.post_attribute(&body).await.status().as_u16(), 201);
+    }
+
+    // Act
+    let response = app
+        .post_api_subscriptions(&json!({
+            "name": "le guin",
+            "email": "ursula_le_guin@gmail.com",
+            "attributes": {"seats": 12, "beta": true},
+        }))
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let saved = sqlx::query!("SELECT attributes FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.attributes, json!({"seats": 12, "beta": true}));
+}
+
+#[tokio::test]
+async fn malformed_bodies_are_rejected_with_a_catalogued_code() {
+    // Arrange
+    let app = spawn_app().await;
+    let test_cases = [
+        ("application/json", "{\"name\":", 400, "malformed_body"),
+        ("application/json", "{\"name\": 42}", 400, "malformed_body"),
+        (
+            "text/plain",
+            "{\"name\": \"le guin\"}",
+            415,
+            "unsupported_media_type",
+        ),
+    ];
+
+    for (content_type, body, status, code) in test_cases {
+        // Act
+        let response = reqwest::Client::new()
+            .post(format!("{}/api/v1/subscriptions", &app.address))
+            .header("Content-Type", content_type)
+            .body(body)
+            .send()
+            .await
+            .expect("Failed to execute request.");
+
+        // Assert
+        assert_eq!(response.status().as_u16(), status, "Body: {}", body);
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["code"], code);
+    }
+}
+
+#[tokio::test]
+async fn allowed_origins_pass_the_cors_preflight() {
+    // Arrange
+    let origin = "https://app.example.com";
+    let app = spawn_app_with(|c| c.api.allowed_origins = vec![origin.into()]).await;
+
+    // Act
+    let response = preflight(&app, origin).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 204);
+    let headers = response.headers();
+    assert_eq!(headers["access-control-allow-origin"], origin);
+    assert!(headers["access-control-allow-methods"]
+        .to_str()
+        .unwrap()
+        .contains("POST"));
+    assert!(headers["access-control-allow-headers"]
+        .to_str()
+        .unwrap()
+        .contains("Content-Type"));
+
+    // Act - Part 2 - The actual request
+    let response = post_from_origin(&app, origin).await;
+
+    // Assert - Part 2
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(response.headers()["access-control-allow-origin"], origin);
+    assert_eq!(response.headers()["vary"], "Origin");
+}
+
+#[tokio::test]
+async fn other_origins_get_no_cors_headers() {
+    // Arrange
+    let app =
+        spawn_app_with(|c| c.api.allowed_origins = vec!["https://app.example.com".into()]).await;
+
+    // Act
+    let preflight = preflight(&app, "https://evil.example.com").await;
+    let response = post_from_origin(&app, "https://evil.example.com").await;
+
+    // Assert
+    assert!(!preflight
+        .headers()
+        .contains_key("access-control-allow-origin"));
+    assert!(!response
+        .headers()
+        .contains_key("access-control-allow-origin"));
+}
+
+#[tokio::test]
+async fn a_wildcard_allows_any_origin() {
+    // Arrange
+    let app = spawn_app_with(|c| c.api.allowed_origins = vec!["*".into()]).await;
+
+    // Act
+    let response = preflight(&app, "https://anywhere.example.com").await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 204);
+    assert_eq!(
+        response.headers()["access-control-allow-origin"],
+        "https://anywhere.example.com"
+    );
+}
@@ -0,0 +1,154 @@
+use crate::api::helpers::{create_confirmed_subscriber, issue_body, spawn_app, TestApp};
+
+async fn stored_slug(app: &TestApp, title: &str) -> Option<String> {
+    sqlx::query!("SELECT slug FROM newsletter_issues WHERE title = $1", title)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .slug
+}
+
+#[tokio::test]
+async fn published_issues_are_listed_in_the_archive_but_drafts_are_not() {
+    // Arrange
+    let app = spawn_app().await;
+    app.publish("Published issue").await;
+    app.create_draft_issue(&issue_body("Draft issue")).await;
+    let slug = stored_slug(&app, "Published issue").await.unwrap();
+
+    // Act
+    let response = app.get_archive("").await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let html = response.text().await.unwrap();
+    assert!(html.contains("Published issue"));
+    assert!(html.contains(&format!("/archive/{}", slug)));
+    assert!(!html.contains("Draft issue"));
+    assert!(stored_slug(&app, "Draft issue").await.is_none());
+}
+
+#[tokio::test]
+async fn archived_issues_are_rendered_without_merge_tags() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.post_newsletters(&issue_body("Published issue"))
+        .await
+        .error_for_status()
+        .unwrap();
+    app.dispatch_all_pending_emails().await;
+    let slug = stored_slug(&app, "Published issue").await.unwrap();
+
+    // Act
+    let response = app.get_archived_issue(&slug).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let html = response.text().await.unwrap();
+    assert!(html.contains("<p>Hi , this is Published issue as HTML</p>"));
+    assert!(!html.contains("{{name}}"));
+    assert!(!html.contains("le guin"));
+}
+
+#[tokio::test]
+async fn issues_are_only_listed_once_delivered_but_can_be_read_meanwhile() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.post_newsletters(&issue_body("Sending issue"))
+        .await
+        .error_for_status()
+        .unwrap();
+    let slug = stored_slug(&app, "Sending issue").await.unwrap();
+
+    // Act
+    let index = app.get_archive("").await.text().await.unwrap();
+    let issue = app.get_archived_issue(&slug).await;
+
+    // Assert
+    assert!(!index.contains("Sending issue"));
+    assert_eq!(issue.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn the_archive_speaks_the_language_of_the_browser() {
+    // Arrange
+    let app = spawn_app().await;
+    app.publish("Published issue").await;
+
+    // Act
+    let response = reqwest::Client::new()
+        .get(format!("{}/archive", &app.address))
+        .header("Accept-Language", "pt-PT,pt;q=0.9,en;q=0.5")
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    let html = response.text().await.unwrap();
+    assert!(html.contains(r#"<html lang="pt">"#));
+    assert!(html.contains("Arquivo da newsletter"));
+    assert!(!html.contains("Newsletter archive"));
+}
+
+#[tokio::test]
+async fn unknown_archive_pages_return_404() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app.get_archived_issue("does-not-exist").await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn the_archive_is_paginated() {
+    // Arrange
+    let app = spawn_app().await;
+    for i in 0..12 {
+        app.publish(&format!("Issue number {:02}", i)).await;
+    }
+
+    // Act
+    let first_page = app.get_archive("").await.text().await.unwrap();
+    let second_page = app.get_archive("?page=2").await.text().await.unwrap();
+
+    // Assert
+    assert_eq!(first_page.matches("<li>").count(), 10);
+    assert!(first_page.contains("Issue number 11"));
+    assert!(first_page.contains(r#"href="/archive?page=2""#));
+    assert_eq!(second_page.matches("<li>").count(), 2);
+    assert!(second_page.contains("Issue number 00"));
+    assert!(second_page.contains(r#"href="/archive?page=1""#));
+    assert!(!second_page.contains(r#"href="/archive?page=3""#));
+}
+
+#[tokio::test]
+async fn delivered_issues_are_personalized_and_link_to_the_archive() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.post_newsletters(&issue_body("Published issue"))
+        .await
+        .error_for_status()
+        .unwrap();
+    let slug = stored_slug(&app, "Published issue").await.unwrap();
+
+    // Act
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let request = app.aws_request_wrapper.expect_one_request();
+    let body = request.content().unwrap().simple().unwrap().body().unwrap();
+    let html = body.html().unwrap().data();
+    let text = body.text().unwrap().data();
+    let archive_link = format!("/archive/{}", slug);
+    assert!(html.contains(&archive_link));
+    assert!(html.contains("View in browser"));
+    assert!(html.contains("Hi le guin, this is Published issue as HTML"));
+    assert!(text.contains(&archive_link));
+    assert!(text.contains("Hi le guin, this is Published issue as plain text"));
+}
@@ -0,0 +1,273 @@
+use crate::api::helpers::{spawn_app, TestApp};
+use crate::aws_ses_rules::AwsRequestsWrapper;
+
+async fn define_attributes(app: &TestApp) {
+    for body in [
+        serde_json::json!({"name": "company", "type": "string"}),
+        serde_json::json!({"name": "seats", "type": "number"}),
+        serde_json::json!({"name": "beta", "type": "bool"}),
+        serde_json::json!({"name": "founded", "type": "date"}),
+        serde_json::json!({"name": "country", "type": "enum", "options": ["PT", "FR"]}),
+    ] {
+        let response = app.post_attribute(&body).await;
+        assert_eq!(response.status().as_u16(), 201);
+    }
+}
+
+async fn stored_attributes(app: &TestApp) -> serde_json::Value {
+    sqlx::query!("SELECT attributes FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .attributes
+}
+
+async fn follow_confirmation_link(app: &TestApp) {
+    let request = app.aws_request_wrapper.expect_one_request_and_remove();
+    let confirmation_links = app.extract_confirmation_links(&request);
+    reqwest::get(confirmation_links.html)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+}
+
+async fn subscribe_and_confirm_with(app: &TestApp, body: &str) {
+    app.post_subscriptions(body.into())
+        .await
+        .error_for_status()
+        .unwrap();
+    follow_confirmation_link(app).await;
+}
+
+#[tokio::test]
+async fn attribute_definitions_are_listed_after_creation() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    define_attributes(&app).await;
+
+    // Assert
+    let definitions: serde_json::Value = app.get_attributes().await.json().await.unwrap();
+    assert_eq!(definitions.as_array().unwrap().len(), 5);
+    assert_eq!(
+        definitions[2],
+        serde_json::json!({"name": "country", "type": "enum", "options": ["PT", "FR"]})
+    );
+}
+
+#[tokio::test]
+async fn defining_an_existing_attribute_returns_a_409() {
+    // Arrange
+    let app = spawn_app().await;
+    define_attributes(&app).await;
+
+    // Act
+    let response = app
+        .post_attribute(&serde_json::json!({"name": "company", "type": "number"}))
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 409);
+}
+
+#[tokio::test]
+async fn invalid_attribute_definitions_are_rejected_with_a_400() {
+    // Arrange
+    let app = spawn_app().await;
+    let test_cases = vec![
+        (
+            serde_json::json!({"name": "company", "type": "text"}),
+            "unknown type",
+        ),
+        (
+            serde_json::json!({"name": "country", "type": "enum"}),
+            "enum without options",
+        ),
+        (
+            serde_json::json!({"name": "email", "type": "string"}),
+            "reserved name",
+        ),
+        (
+            serde_json::json!({"name": "Company Name", "type": "string"}),
+            "invalid name",
+        ),
+    ];
+
+    for (body, description) in test_cases {
+        // Act
+        let response = app.post_attribute(&body).await;
+
+        // Assert
+        assert_eq!(
+            response.status().as_u16(),
+            400,
+            "The API did not fail with 400 Bad Request for an {}.",
+            description
+        );
+    }
+}
+
+#[tokio::test]
+async fn subscribe_stores_typed_attributes() {
+    // Arrange
+    let app = spawn_app().await;
+    define_attributes(&app).await;
+
+    // Act
+    let response = app
+        .post_subscriptions(
+            "name=le guin&email=ursula_le_guin@gmail.com&company=ACME&seats=12\
+            &beta=on&founded=1999-12-31&country=PT"
+                .into(),
+        )
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        stored_attributes(&app).await,
+        serde_json::json!({
+            "company": "ACME",
+            "seats": 12,
+            "beta": true,
+            "founded": "1999-12-31",
+            "country": "PT"
+        })
+    );
+}
+
+#[tokio::test]
+async fn subscribe_rejects_invalid_or_undefined_attributes_with_a_400() {
+    // Arrange
+    let app = spawn_app().await;
+    define_attributes(&app).await;
+    let test_cases = vec![
+        ("seats=a%20few", "invalid number"),
+        ("country=ES", "value outside of the enum"),
+        ("founded=yesterday", "invalid date"),
+        ("shoe_size=42", "undefined attribute"),
+    ];
+
+    for (fields, description) in test_cases {
+        // Act
+        let response = app
+            .post_subscriptions(format!(
+                "name=le guin&email=ursula_le_guin@gmail.com&{}",
+                fields
+            ))
+            .await;
+
+        // Assert
+        assert_eq!(
+            response.status().as_u16(),
+            400,
+            "The API did not fail with 400 Bad Request for an {}.",
+            description
+        );
+    }
+}
+
+#[tokio::test]
+async fn subscribing_again_merges_the_attributes_once_confirmed() {
+    // Arrange
+    let app = spawn_app().await;
+    define_attributes(&app).await;
+    app.create_list("rust-weekly").await;
+    subscribe_and_confirm_with(
+        &app,
+        "name=le guin&email=ursula_le_guin@gmail.com&company=ACME&country=PT",
+    )
+    .await;
+
+    // Act - Part 1 - Subscribe to another list
+    app.post_subscriptions(
+        "name=le guin&email=ursula_le_guin@gmail.com&list=rust-weekly&country=FR".into(),
+    )
+    .await
+    .error_for_status()
+    .unwrap();
+
+    // Assert - Part 1
+    assert_eq!(
+        stored_attributes(&app).await,
+        serde_json::json!({"company": "ACME", "country": "PT"})
+    );
+
+    // Act - Part 2 - Confirm it
+    follow_confirmation_link(&app).await;
+
+    // Assert - Part 2
+    assert_eq!(
+        stored_attributes(&app).await,
+        serde_json::json!({"company": "ACME", "country": "FR"})
+    );
+}
+
+#[tokio::test]
+async fn subscribing_again_cannot_change_the_attributes_of_a_confirmed_subscriber() {
+    // Arrange
+    let app = spawn_app().await;
+    define_attributes(&app).await;
+    subscribe_and_confirm_with(
+        &app,
+        "name=le guin&email=ursula_le_guin@gmail.com&company=ACME",
+    )
+    .await;
+
+    // Act
+    app.post_subscriptions("name=mallory&email=ursula_le_guin@gmail.com&company=EVIL".into())
+        .await
+        .error_for_status()
+        .unwrap();
+
+    // Assert
+    assert_eq!(
+        stored_attributes(&app).await,
+        serde_json::json!({"company": "ACME"})
+    );
+    app.aws_request_wrapper.expect_zero_requests();
+}
+
+#[tokio::test]
+async fn attributes_can_be_used_in_segments_and_templates() {
+    // Arrange
+    let app = spawn_app().await;
+    define_attributes(&app).await;
+    for (email, country) in [("pt@example.com", "PT"), ("fr@example.com", "FR")] {
+        app.post_subscriptions(format!(
+            "name=reader&email={}&company=ACME&country={}",
+            email, country
+        ))
+        .await
+        .error_for_status()
+        .unwrap();
+        let request = app.aws_request_wrapper.expect_one_request_and_remove();
+        let confirmation_links = app.extract_confirmation_links(&request);
+        reqwest::get(confirmation_links.html)
+            .await
+            .unwrap()
+            .error_for_status()
+            .unwrap();
+    }
+
+    // Act
+    app.post_newsletters(&serde_json::json!({
+        "title": "Olá",
+        "content": {
+            "text": "Hello {{attributes.company}} from {{attributes.country}}",
+            "html": "<p>Hello {{attributes.company}}</p>"
+        },
+        "segment": r#"attributes.country = "PT""#
+    }))
+    .await
+    .error_for_status()
+    .unwrap();
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let request = app.aws_request_wrapper.expect_one_request();
+    AwsRequestsWrapper::assert_correct_destination(&request, "pt@example.com");
+    AwsRequestsWrapper::assert_correct_body_text(&request, "Hello ACME from PT");
+}
@@ -0,0 +1,162 @@
+use crate::api::helpers::{spawn_app, spawn_app_with, TestApp, CAPTCHA_PASS};
+use chrono::{Duration, Utc};
+use secrecy::Secret;
+use zero2prod::bot_protection::issue_form_token;
+use zero2prod::configuration::{CaptchaSettings, FormTokenSettings, Settings};
+
+const BODY: &str = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+const FORM_TOKEN_SECRET: &str = "form-token-secret";
+
+async fn stored_subscribers(app: &TestApp) -> i64 {
+    sqlx::query_scalar("SELECT count(*) FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+}
+
+/// Bots are answered as if they had subscribed, but nothing happens.
+async fn assert_silently_dropped(app: &TestApp, response: reqwest::Response) {
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(response.text().await.unwrap().contains("Check your inbox"));
+    assert_eq!(stored_subscribers(app).await, 0);
+    app.aws_request_wrapper.expect_zero_requests();
+}
+
+fn with_form_tokens(min_fill_seconds: u64) -> impl FnOnce(&mut Settings) {
+    move |c: &mut Settings| {
+        c.bot_protection.form_token = Some(FormTokenSettings {
+            secret: Secret::new(FORM_TOKEN_SECRET.to_string()),
+            min_fill_seconds,
+        })
+    }
+}
+
+#[tokio::test]
+async fn a_filled_in_honeypot_drops_the_subscription() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app
+        .post_subscriptions(format!("{}&hp_website=http%3A%2F%2Fspam.example", BODY))
+        .await;
+
+    // Assert
+    assert_silently_dropped(&app, response).await;
+}
+
+#[tokio::test]
+async fn an_empty_honeypot_is_not_taken_for_an_attribute() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app
+        .post_subscriptions(format!("{}&hp_website=", BODY))
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(stored_subscribers(&app).await, 1);
+    app.aws_request_wrapper.expect_one_request();
+}
+
+#[tokio::test]
+async fn forms_submitted_faster_than_the_minimum_fill_time_are_dropped() {
+    // Arrange
+    let app = spawn_app_with(with_form_tokens(60)).await;
+    let token: serde_json::Value = app.get_form_token().await.json().await.unwrap();
+    let token = token["token"].as_str().unwrap();
+
+    // Act
+    let response = app
+        .post_subscriptions(format!("{}&form_token={}", BODY, token))
+        .await;
+
+    // Assert
+    assert_silently_dropped(&app, response).await;
+}
+
+#[tokio::test]
+async fn forms_without_a_valid_form_token_are_dropped() {
+    // Arrange
+    let app = spawn_app_with(with_form_tokens(3)).await;
+    let forged = issue_form_token(
+        &Secret::new("another-secret".to_string()),
+        Utc::now() - Duration::minutes(5),
+    );
+
+    for body in [BODY.to_string(), format!("{}&form_token={}", BODY, forged)] {
+        // Act
+        let response = app.post_subscriptions(body).await;
+
+        // Assert
+        assert_silently_dropped(&app, response).await;
+    }
+}
+
+#[tokio::test]
+async fn forms_filled_in_at_a_human_pace_are_accepted() {
+    // Arrange
+    let app = spawn_app_with(with_form_tokens(3)).await;
+    let token = issue_form_token(
+        &Secret::new(FORM_TOKEN_SECRET.to_string()),
+        Utc::now() - Duration::minutes(5),
+    );
+
+    // Act
+    let response = app
+        .post_subscriptions(format!("{}&form_token={}", BODY, token))
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(stored_subscribers(&app).await, 1);
+    app.aws_request_wrapper.expect_one_request();
+}
+
+#[tokio::test]
+async fn the_form_token_endpoint_returns_404_while_tokens_are_disabled() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app.get_form_token().await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn the_captcha_must_be_solved_when_enabled() {
+    // Arrange
+    let app = spawn_app_with(|c: &mut Settings| {
+        c.bot_protection.captcha = Some(CaptchaSettings {
+            verify_url: "http://127.0.0.1:1/siteverify".into(),
+            secret: Secret::new("captcha-secret".to_string()),
+            response_field: "h-captcha-response".into(),
+        })
+    })
+    .await;
+
+    for body in [
+        BODY.to_string(),
+        format!("{}&h-captcha-response=captcha-fail", BODY),
+    ] {
+        // Act
+        let response = app.post_subscriptions(body).await;
+
+        // Assert
+        assert_silently_dropped(&app, response).await;
+    }
+
+    // Act
+    let response = app
+        .post_subscriptions(format!("{}&h-captcha-response={}", BODY, CAPTCHA_PASS))
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(stored_subscribers(&app).await, 1);
+    app.aws_request_wrapper.expect_one_request();
+}
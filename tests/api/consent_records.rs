@@ -0,0 +1,105 @@
+use crate::api::helpers::spawn_app;
+use uuid::Uuid;
+
+#[tokio::test]
+async fn subscribing_and_confirming_are_both_recorded_as_consents() {
+    // Arrange
+    let app = spawn_app().await;
+    let client = reqwest::Client::builder()
+        .user_agent("consent-test/1.0")
+        .build()
+        .unwrap();
+    client
+        .post(format!("{}/subscriptions", &app.address))
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .body("name=le guin&email=ursula_le_guin@gmail.com&source=homepage-footer")
+        .send()
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+    let request = app.aws_request_wrapper.expect_one_request_and_remove();
+    let confirmation_links = app.extract_confirmation_links(&request);
+
+    // Act
+    client
+        .get(confirmation_links.html)
+        .send()
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+
+    // Assert
+    let response = app
+        .get_subscriber(&app.subscriber_id("ursula_le_guin@gmail.com").await)
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    let subscriber: serde_json::Value = response.json().await.unwrap();
+    let consents = subscriber["consents"].as_array().unwrap();
+    assert_eq!(consents.len(), 2);
+    for (consent, event, form_source) in [
+        (&consents[0], "subscribe", "homepage-footer"),
+        (&consents[1], "confirm", "confirmation_email"),
+    ] {
+        assert_eq!(consent["event"], event);
+        assert_eq!(consent["form_source"], form_source);
+        assert_eq!(consent["list"], "default");
+        assert_eq!(consent["ip_address"], "127.0.0.1");
+        assert_eq!(consent["user_agent"], "consent-test/1.0");
+        assert_eq!(consent["consent_text_version"], "2026-10-19");
+        assert!(consent["recorded_at"].is_string());
+    }
+}
+
+#[tokio::test]
+async fn subscriptions_without_a_form_source_come_from_the_subscription_form() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    app.post_subscriptions("name=le guin&email=ursula_le_guin@gmail.com".into())
+        .await
+        .error_for_status()
+        .unwrap();
+
+    // Assert
+    let record = sqlx::query!("SELECT event, form_source, user_agent FROM consent_records")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(record.event, "subscribe");
+    assert_eq!(record.form_source, "subscription_form");
+    assert_eq!(record.user_agent, None);
+}
+
+#[tokio::test]
+async fn subscribe_returns_a_400_for_an_invalid_form_source() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app
+        .post_subscriptions("name=le guin&email=ursula_le_guin@gmail.com&source=<script>".into())
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 400);
+    let records = sqlx::query_scalar!("SELECT COUNT(*) FROM consent_records")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(records, Some(0));
+}
+
+#[tokio::test]
+async fn getting_an_unknown_subscriber_returns_a_404() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app.get_subscriber(&Uuid::new_v4().to_string()).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 404);
+}
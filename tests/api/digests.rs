@@ -0,0 +1,176 @@
+use crate::api::helpers::{spawn_app, subscribe_and_confirm, TestApp};
+use crate::aws_ses_rules::AwsRequestsWrapper;
+
+const EMAIL: &str = "ursula_le_guin@gmail.com";
+
+async fn spawn_app_with_subscriber(frequency: &str) -> TestApp {
+    let app = spawn_app().await;
+    subscribe_and_confirm(&app, EMAIL, "default").await;
+    sqlx::query!(
+        "UPDATE subscriptions SET digest_frequency = $1 WHERE email = $2",
+        frequency,
+        EMAIL
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+    app
+}
+
+/// Moves every queued issue and sent digest `days` into the past.
+async fn travel_forward(app: &TestApp, days: i32) {
+    sqlx::query!(
+        "UPDATE digest_queue SET queued_at = queued_at - make_interval(days => $1)",
+        days
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+    sqlx::query!(
+        "UPDATE digests SET created_at = created_at - make_interval(days => $1)",
+        days
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+async fn digest_subscribers_get_every_issue_of_the_week_in_one_email() {
+    // Arrange
+    let app = spawn_app_with_subscriber("weekly").await;
+    app.publish("First issue").await;
+    app.publish("Second issue").await;
+    app.aws_request_wrapper.expect_zero_requests();
+
+    // Act - Part 1 - The week is not over yet
+    travel_forward(&app, 6).await;
+    app.dispatch_all_due_digests().await;
+
+    // Assert - Part 1
+    app.aws_request_wrapper.expect_zero_requests();
+
+    // Act - Part 2 - The week is over
+    travel_forward(&app, 1).await;
+    app.dispatch_all_due_digests().await;
+
+    // Assert - Part 2
+    let request = app.aws_request_wrapper.expect_one_request_and_remove();
+    AwsRequestsWrapper::assert_correct_subject(&request, "Your newsletter digest");
+    AwsRequestsWrapper::assert_correct_body_text(&request, "First issue as plain text");
+    AwsRequestsWrapper::assert_correct_body_text(&request, "Second issue as plain text");
+    let body = request.content().unwrap().simple().unwrap().body().unwrap();
+    assert!(body
+        .html()
+        .unwrap()
+        .data()
+        .contains("this is Second issue as HTML</p>"));
+    let covered = sqlx::query!(
+        r#"
+        SELECT digests.status, COUNT(*) AS "issues!"
+        FROM digests JOIN digest_issues ON digest_issues.digest_id = digests.id
+        GROUP BY digests.id
+        "#
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(covered.status, "sent");
+    assert_eq!(covered.issues, 2);
+    let statuses = sqlx::query_scalar!("SELECT status FROM issue_deliveries")
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(statuses, vec!["sent", "sent"]);
+
+    // Act - Part 3 - Nothing is left to send
+    travel_forward(&app, 7).await;
+    app.dispatch_all_due_digests().await;
+
+    // Assert - Part 3
+    app.aws_request_wrapper.expect_zero_requests();
+}
+
+#[tokio::test]
+async fn the_next_digest_waits_a_full_period_after_the_previous_one() {
+    // Arrange
+    let app = spawn_app_with_subscriber("daily").await;
+    app.publish("First issue").await;
+    travel_forward(&app, 1).await;
+    app.dispatch_all_due_digests().await;
+    app.aws_request_wrapper.expect_one_request_and_remove();
+    app.publish("Second issue").await;
+
+    // Act - Part 1 - Queued long ago, but the last digest is recent
+    sqlx::query!("UPDATE digest_queue SET queued_at = queued_at - interval '3 days'")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+    app.dispatch_all_due_digests().await;
+
+    // Assert - Part 1
+    app.aws_request_wrapper.expect_zero_requests();
+
+    // Act - Part 2
+    travel_forward(&app, 1).await;
+    app.dispatch_all_due_digests().await;
+
+    // Assert - Part 2
+    let request = app.aws_request_wrapper.expect_one_request();
+    AwsRequestsWrapper::assert_correct_body_text(&request, "Second issue as plain text");
+    let body = request.content().unwrap().simple().unwrap().body().unwrap();
+    assert!(!body.text().unwrap().data().contains("First issue"));
+}
+
+#[tokio::test]
+async fn switching_back_to_immediate_delivery_sends_the_pending_digest_at_once() {
+    // Arrange
+    let app = spawn_app_with_subscriber("weekly").await;
+    app.publish("First issue").await;
+
+    // Act
+    sqlx::query!(
+        "UPDATE subscriptions SET digest_frequency = 'immediate' WHERE email = $1",
+        EMAIL
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+    app.dispatch_all_due_digests().await;
+
+    // Assert
+    let request = app.aws_request_wrapper.expect_one_request_and_remove();
+    AwsRequestsWrapper::assert_correct_body_text(&request, "First issue as plain text");
+    app.publish("Second issue").await;
+    let request = app.aws_request_wrapper.expect_one_request();
+    AwsRequestsWrapper::assert_correct_subject(&request, "Second issue");
+}
+
+#[tokio::test]
+async fn issues_of_lists_left_before_the_digest_are_skipped() {
+    // Arrange
+    let app = spawn_app_with_subscriber("daily").await;
+    app.publish("First issue").await;
+    sqlx::query!("UPDATE list_subscriptions SET status = 'unsubscribed'")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+
+    // Act
+    travel_forward(&app, 1).await;
+    app.dispatch_all_due_digests().await;
+
+    // Assert
+    app.aws_request_wrapper.expect_zero_requests();
+    let delivery = sqlx::query!("SELECT status, error FROM issue_deliveries")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(delivery.status, "skipped");
+    assert_eq!(delivery.error.as_deref(), Some("unsubscribed"));
+    let queued = sqlx::query_scalar!(r#"SELECT COUNT(*) AS "count!" FROM digest_queue"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(queued, 0);
+}
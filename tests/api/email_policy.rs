@@ -0,0 +1,71 @@
+use crate::api::helpers::{spawn_app, spawn_app_with};
+use serde_json::json;
+use zero2prod::configuration::Settings;
+
+#[tokio::test]
+async fn disposable_and_role_addresses_are_rejected_with_a_400() {
+    // Arrange
+    let app = spawn_app().await;
+    let test_cases = vec![
+        ("bot%40mailinator.com", "disposable email provider"),
+        ("bot%40inbox.yopmail.com", "disposable email provider"),
+        ("noreply%40example.com", "role address"),
+        ("Abuse%2Bnews%40example.com", "role address"),
+    ];
+
+    for (email, reason) in test_cases {
+        // Act
+        let response = app
+            .post_subscriptions(format!("name=le%20guin&email={}", email))
+            .await;
+
+        // Assert
+        assert_eq!(
+            response.status().as_u16(),
+            400,
+            "The API did not reject {}.",
+            email
+        );
+        assert!(response.text().await.unwrap().contains(reason));
+    }
+    app.aws_request_wrapper.expect_zero_requests();
+}
+
+#[tokio::test]
+async fn the_api_reports_disallowed_addresses_on_the_email_field() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app
+        .post_api_subscriptions(&json!({"name": "le guin", "email": "bot@mailinator.com"}))
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 400);
+    let problem: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(problem["fields"]["email"]["code"], "disallowed_email");
+}
+
+#[tokio::test]
+async fn denied_domains_are_rejected_and_allowed_domains_are_exempt() {
+    // Arrange
+    let app = spawn_app_with(|c: &mut Settings| {
+        c.email_policy.denied_domains = vec!["competitor.example".into()];
+        c.email_policy.allowed_domains = vec!["mailinator.com".into()];
+    })
+    .await;
+
+    // Act
+    let denied = app
+        .post_subscriptions("name=le%20guin&email=ursula%40competitor.example".into())
+        .await;
+    let allowed = app
+        .post_subscriptions("name=le%20guin&email=ursula%40mailinator.com".into())
+        .await;
+
+    // Assert
+    assert_eq!(denied.status().as_u16(), 400);
+    assert_eq!(allowed.status().as_u16(), 200);
+    app.aws_request_wrapper.expect_one_request();
+}
@@ -0,0 +1,146 @@
+use crate::api::helpers::{issue_body, spawn_app, spawn_app_with, TestApp};
+use std::num::NonZeroU16;
+
+async fn get_feed(app: &TestApp, path: &str, headers: &[(&str, &str)]) -> reqwest::Response {
+    let mut request = reqwest::Client::new().get(format!("{}/{}", &app.address, path));
+    for (name, value) in headers {
+        request = request.header(*name, *value);
+    }
+    request.send().await.expect("Failed to execute request.")
+}
+
+#[tokio::test]
+async fn the_atom_feed_lists_published_issues_with_absolute_links() {
+    // Arrange
+    let app = spawn_app().await;
+    app.publish("First & foremost").await;
+    app.create_draft_issue(&issue_body("Draft issue")).await;
+
+    // Act
+    let response = get_feed(&app, "feed.xml", &[]).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        response.headers()["content-type"],
+        "application/atom+xml; charset=utf-8"
+    );
+    let xml = response.text().await.unwrap();
+    assert!(xml.contains(r#"<feed xmlns="http://www.w3.org/2005/Atom">"#));
+    assert!(xml.contains("<title>First &amp; foremost</title>"));
+    assert!(xml.contains(r#"<link href="http://127.0.0.1/archive/first-foremost-"#));
+    assert!(xml.contains("&lt;p&gt;Hi , this is First &amp; foremost as HTML&lt;/p&gt;"));
+    assert!(!xml.contains("Draft issue"));
+    assert_eq!(xml.matches("<entry>").count(), 1);
+}
+
+#[tokio::test]
+async fn feeds_list_at_most_the_configured_number_of_issues() {
+    // Arrange
+    let app = spawn_app_with(|c| c.feed.max_entries = NonZeroU16::new(1).unwrap()).await;
+    app.publish("First issue").await;
+    app.publish("Second issue").await;
+
+    // Act
+    let xml = get_feed(&app, "feed.xml", &[]).await.text().await.unwrap();
+
+    // Assert
+    assert_eq!(xml.matches("<entry>").count(), 1);
+    assert!(xml.contains("<title>Second issue</title>"));
+}
+
+#[tokio::test]
+async fn the_rss_feed_lists_published_issues_with_absolute_links() {
+    // Arrange
+    let app = spawn_app().await;
+    app.publish("First issue").await;
+
+    // Act
+    let response = get_feed(&app, "rss.xml", &[]).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        response.headers()["content-type"],
+        "application/rss+xml; charset=utf-8"
+    );
+    let xml = response.text().await.unwrap();
+    assert!(xml.contains(r#"<rss version="2.0""#));
+    assert!(xml.contains("<title>First issue</title>"));
+    assert!(xml.contains("<link>http://127.0.0.1/archive/first-issue-"));
+    assert_eq!(xml.matches("<item>").count(), 1);
+}
+
+#[tokio::test]
+async fn an_empty_feed_is_still_served() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = get_feed(&app, "feed.xml", &[]).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let xml = response.text().await.unwrap();
+    assert!(xml.contains("<updated>1970-01-01T00:00:00+00:00</updated>"));
+    assert_eq!(xml.matches("<entry>").count(), 0);
+}
+
+#[tokio::test]
+async fn feeds_are_limited_to_the_configured_number_of_entries() {
+    // Arrange
+    let app = spawn_app().await;
+    for i in 0..21 {
+        app.publish(&format!("Issue number {:02}", i)).await;
+    }
+
+    // Act
+    let atom = get_feed(&app, "feed.xml", &[]).await.text().await.unwrap();
+    let rss = get_feed(&app, "rss.xml", &[]).await.text().await.unwrap();
+
+    // Assert
+    assert_eq!(atom.matches("<entry>").count(), 20);
+    assert!(!atom.contains("Issue number 00"));
+    assert_eq!(rss.matches("<item>").count(), 20);
+}
+
+#[tokio::test]
+async fn feeds_answer_304_when_the_etag_matches() {
+    for path in ["feed.xml", "rss.xml"] {
+        // Arrange
+        let app = spawn_app().await;
+        app.publish("First issue").await;
+        let first = get_feed(&app, path, &[]).await;
+        let etag = first.headers()["etag"].to_str().unwrap().to_owned();
+
+        // Act
+        let cached = get_feed(&app, path, &[("If-None-Match", &etag)]).await;
+        app.publish("Second issue").await;
+        let stale = get_feed(&app, path, &[("If-None-Match", &etag)]).await;
+
+        // Assert
+        assert_eq!(cached.status().as_u16(), 304);
+        assert_eq!(cached.headers()["etag"].to_str().unwrap(), etag);
+        assert_eq!(stale.status().as_u16(), 200);
+        assert_ne!(stale.headers()["etag"].to_str().unwrap(), etag);
+    }
+}
+
+#[tokio::test]
+async fn feeds_answer_304_when_not_modified_since() {
+    // Arrange
+    let app = spawn_app().await;
+    app.publish("First issue").await;
+    let first = get_feed(&app, "feed.xml", &[]).await;
+    let last_modified = first.headers()["last-modified"]
+        .to_str()
+        .unwrap()
+        .to_owned();
+
+    // Act
+    let response = get_feed(&app, "feed.xml", &[("If-Modified-Since", &last_modified)]).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 304);
+    assert!(response.text().await.unwrap().is_empty());
+}
@@ -1,21 +1,64 @@
 use crate::aws_ses_rules::{aws_client_interceptor, aws_ses_client, AwsRequestsWrapper};
 use aws_sdk_sesv2::operation::send_email::SendEmailInput;
+use http::Uri;
 use secrecy::Secret;
 use sqlx::{Connection, Executor, PgConnection, PgPool};
+use std::net::IpAddr;
 use std::sync::{Arc, LazyLock};
 use uuid::Uuid;
 use zero2prod::bootstrap::Dependencies;
-use zero2prod::configuration::{get_configuration, DatabaseSettings};
+use zero2prod::bot_protection::CaptchaVerifier;
+use zero2prod::configuration::{get_configuration, DatabaseSettings, Settings};
+use zero2prod::digest_worker::try_send_due_digest;
+use zero2prod::email::email_client::{EmailClient, EmailService};
+use zero2prod::email::welcome_email::WelcomeEmail;
+use zero2prod::i18n::Catalogs;
+use zero2prod::issue_delivery_worker::{try_execute_task, ExecutionOutcome};
+use zero2prod::opt_in_worker::try_send_opt_in_email;
+use zero2prod::scheduler::{try_enqueue_due_issue, try_pick_due_ab_test_winner, SchedulerOutcome};
 use zero2prod::startup::{get_connection_pool, Application};
 use zero2prod::telemetry::{get_subscriber, init_subscriber};
+use zero2prod::welcome_email_worker::try_send_welcome_email;
+
+pub const SES_NOTIFICATIONS_TOKEN: &str = "ses-notifications-token";
+
+/// Sent by the admin helpers as their bearer token.
+pub const ADMIN_TOKEN: &str = "admin-token";
+
+/// The only CAPTCHA response `StubCaptchaVerifier` accepts.
+pub const CAPTCHA_PASS: &str = "captcha-pass";
+
+/// Stands in for the CAPTCHA provider, so that tests do not call it.
+struct StubCaptchaVerifier;
+
+#[async_trait::async_trait]
+impl CaptchaVerifier for StubCaptchaVerifier {
+    async fn verify(
+        &self,
+        response: &str,
+        _client_ip: Option<IpAddr>,
+    ) -> Result<bool, anyhow::Error> {
+        Ok(response == CAPTCHA_PASS)
+    }
+}
 
 pub async fn spawn_app() -> TestApp {
+    spawn_app_with(|_| {}).await
+}
+
+/// Spawns the application after letting the test adjust its configuration.
+pub async fn spawn_app_with(customise: impl FnOnce(&mut Settings)) -> TestApp {
     LazyLock::force(&TRACING);
 
     let configuration = {
         let mut configuration = get_configuration().expect("Failed to read configuration.");
         configuration.database.database_name = Uuid::new_v4().to_string();
         configuration.application.port = 0;
+        configuration.email_client.notifications_token =
+            Some(Secret::new(SES_NOTIFICATIONS_TOKEN.to_string()));
+        configuration.admin.api_token = Some(Secret::new(ADMIN_TOKEN.to_string()));
+        configuration.admin.test_recipients = vec!["editor@example.com".to_string()];
+        customise(&mut configuration);
         configuration
     };
 
@@ -25,9 +68,18 @@ pub async fn spawn_app() -> TestApp {
     let requests = aws_client_interceptor.captured_requests();
     let aws_ses_client = aws_ses_client(aws_client_interceptor);
 
+    let email_client: Arc<dyn EmailClient> = Arc::new(aws_ses_client);
     let dependencies = Dependencies {
-        email_client: Arc::new(aws_ses_client),
+        email_client: email_client.clone(),
+        captcha_verifier: Some(Arc::new(StubCaptchaVerifier)),
     };
+    let email_service = EmailService::new(configuration.email_client.sender().unwrap());
+    let welcome_email = configuration
+        .subscriptions
+        .welcome_email
+        .as_ref()
+        .map(|settings| WelcomeEmail::load(settings).unwrap());
+    let catalogs = Catalogs::new(&configuration.i18n).unwrap();
 
     let application = Application::build(configuration.clone(), dependencies)
         .await
@@ -35,21 +87,34 @@ pub async fn spawn_app() -> TestApp {
     let application_port = application.port();
     let address = format!("http://127.0.0.1:{}", application_port);
 
-    let _ = tokio::spawn(application.run_until_stopped());
+    drop(tokio::spawn(application.run_until_stopped()));
 
     TestApp {
         address,
         port: application_port,
         db_pool: get_connection_pool(&configuration.database),
         aws_request_wrapper: AwsRequestsWrapper::new(requests),
+        email_service,
+        email_client,
+        base_url: configuration.application.base_url,
+        welcome_email,
+        catalogs,
     }
 }
 
+/// Query string parameters, as sent by `reqwest`.
+pub type QueryPairs<'a> = [(&'a str, &'a str)];
+
 pub struct TestApp {
     pub address: String,
     pub port: u16,
     pub db_pool: PgPool,
     pub aws_request_wrapper: AwsRequestsWrapper,
+    pub email_service: EmailService,
+    pub email_client: Arc<dyn EmailClient>,
+    pub base_url: Uri,
+    pub welcome_email: Option<WelcomeEmail>,
+    pub catalogs: Catalogs,
 }
 
 pub struct ConfirmationLinks {
@@ -58,6 +123,99 @@ pub struct ConfirmationLinks {
 }
 
 impl TestApp {
+    pub async fn dispatch_all_pending_emails(&self) {
+        loop {
+            if let ExecutionOutcome::EmptyQueue = try_execute_task(
+                &self.db_pool,
+                &self.email_service,
+                self.email_client.as_ref(),
+                &self.base_url,
+                &self.catalogs,
+            )
+            .await
+            .unwrap()
+            {
+                break;
+            }
+        }
+    }
+
+    pub async fn dispatch_all_opt_in_emails(&self) {
+        loop {
+            if let ExecutionOutcome::EmptyQueue = try_send_opt_in_email(
+                &self.db_pool,
+                &self.email_service,
+                self.email_client.as_ref(),
+                &self.base_url,
+                &self.catalogs,
+            )
+            .await
+            .unwrap()
+            {
+                break;
+            }
+        }
+    }
+
+    pub async fn dispatch_all_welcome_emails(&self) {
+        let welcome_email = self
+            .welcome_email
+            .as_ref()
+            .expect("The welcome email is not configured.");
+        loop {
+            if let ExecutionOutcome::EmptyQueue = try_send_welcome_email(
+                &self.db_pool,
+                &self.email_service,
+                self.email_client.as_ref(),
+                &self.base_url,
+                welcome_email,
+                &self.catalogs,
+            )
+            .await
+            .unwrap()
+            {
+                break;
+            }
+        }
+    }
+
+    pub async fn dispatch_all_due_digests(&self) {
+        loop {
+            if let ExecutionOutcome::EmptyQueue = try_send_due_digest(
+                &self.db_pool,
+                &self.email_service,
+                self.email_client.as_ref(),
+                &self.base_url,
+                &self.catalogs,
+            )
+            .await
+            .unwrap()
+            {
+                break;
+            }
+        }
+    }
+
+    pub async fn enqueue_all_due_issues(&self) {
+        loop {
+            if let SchedulerOutcome::NoDueIssues =
+                try_enqueue_due_issue(&self.db_pool).await.unwrap()
+            {
+                break;
+            }
+        }
+    }
+
+    pub async fn pick_all_due_ab_test_winners(&self) {
+        loop {
+            if let SchedulerOutcome::NoDueIssues =
+                try_pick_due_ab_test_winner(&self.db_pool).await.unwrap()
+            {
+                break;
+            }
+        }
+    }
+
     pub async fn post_subscriptions(&self, body: String) -> reqwest::Response {
         reqwest::Client::new()
             .post(format!("{}/subscriptions", &self.address))
@@ -68,6 +226,23 @@ impl TestApp {
             .expect("Failed to execute request.")
     }
 
+    pub async fn get_form_token(&self) -> reqwest::Response {
+        reqwest::Client::new()
+            .get(format!("{}/subscriptions/form-token", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_api_subscriptions(&self, body: &serde_json::Value) -> reqwest::Response {
+        reqwest::Client::new()
+            .post(format!("{}/api/v1/subscriptions", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
     pub fn extract_confirmation_links(&self, request: &SendEmailInput) -> ConfirmationLinks {
         let get_link = |s: &str| {
             let links: Vec<_> = linkify::LinkFinder::new()
@@ -89,6 +264,450 @@ impl TestApp {
 
         ConfirmationLinks { html, plain_text }
     }
+
+    pub async fn post_newsletters(&self, body: &serde_json::Value) -> reqwest::Response {
+        reqwest::Client::new()
+            .post(format!("{}/newsletters", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_issue(&self, body: &serde_json::Value) -> reqwest::Response {
+        reqwest::Client::new()
+            .post(format!("{}/admin/issues", &self.address))
+            .bearer_auth(ADMIN_TOKEN)
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn put_issue(&self, issue_id: &str, body: &serde_json::Value) -> reqwest::Response {
+        reqwest::Client::new()
+            .put(format!("{}/admin/issues/{}", &self.address, issue_id))
+            .bearer_auth(ADMIN_TOKEN)
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_issue_preview(&self, issue_id: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .get(format!(
+                "{}/admin/issues/{}/preview",
+                &self.address, issue_id
+            ))
+            .bearer_auth(ADMIN_TOKEN)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_issue_test_send(
+        &self,
+        issue_id: &str,
+        body: &serde_json::Value,
+    ) -> reqwest::Response {
+        reqwest::Client::new()
+            .post(format!("{}/admin/issues/{}/test", &self.address, issue_id))
+            .bearer_auth(ADMIN_TOKEN)
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_issue_publish(&self, issue_id: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .post(format!(
+                "{}/admin/issues/{}/publish",
+                &self.address, issue_id
+            ))
+            .bearer_auth(ADMIN_TOKEN)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_issue_schedule(
+        &self,
+        issue_id: &str,
+        body: &serde_json::Value,
+    ) -> reqwest::Response {
+        reqwest::Client::new()
+            .post(format!(
+                "{}/admin/issues/{}/schedule",
+                &self.address, issue_id
+            ))
+            .bearer_auth(ADMIN_TOKEN)
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn put_issue_schedule(
+        &self,
+        issue_id: &str,
+        body: &serde_json::Value,
+    ) -> reqwest::Response {
+        reqwest::Client::new()
+            .put(format!(
+                "{}/admin/issues/{}/schedule",
+                &self.address, issue_id
+            ))
+            .bearer_auth(ADMIN_TOKEN)
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn delete_issue_schedule(&self, issue_id: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .delete(format!(
+                "{}/admin/issues/{}/schedule",
+                &self.address, issue_id
+            ))
+            .bearer_auth(ADMIN_TOKEN)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_archive(&self, query: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .get(format!("{}/archive{}", &self.address, query))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_archived_issue(&self, slug: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .get(format!("{}/archive/{}", &self.address, slug))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_issue_report(&self, issue_id: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .get(format!("{}/admin/issues/{}", &self.address, issue_id))
+            .bearer_auth(ADMIN_TOKEN)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_issue_deliveries(&self, issue_id: &str, query: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .get(format!(
+                "{}/admin/issues/{}/deliveries{}",
+                &self.address, issue_id, query
+            ))
+            .bearer_auth(ADMIN_TOKEN)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_ses_notification(&self, token: &str, body: String) -> reqwest::Response {
+        reqwest::Client::new()
+            .post(format!("{}/webhooks/ses/{}", &self.address, token))
+            .header("Content-Type", "text/plain; charset=UTF-8")
+            .body(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_list(&self, body: &serde_json::Value) -> reqwest::Response {
+        reqwest::Client::new()
+            .post(format!("{}/admin/lists", &self.address))
+            .bearer_auth(ADMIN_TOKEN)
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_lists(&self) -> reqwest::Response {
+        reqwest::Client::new()
+            .get(format!("{}/admin/lists", &self.address))
+            .bearer_auth(ADMIN_TOKEN)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// Creates a mailing list named after its slug.
+    pub async fn create_list(&self, slug: &str) {
+        let response = self
+            .post_list(&serde_json::json!({"slug": slug, "name": slug}))
+            .await;
+        assert_eq!(response.status().as_u16(), 201);
+    }
+
+    pub async fn post_subscriber_tag(&self, subscriber_id: &str, tag: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .post(format!(
+                "{}/admin/subscribers/{}/tags",
+                &self.address, subscriber_id
+            ))
+            .bearer_auth(ADMIN_TOKEN)
+            .json(&serde_json::json!({ "tag": tag }))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn delete_subscriber_tag(&self, subscriber_id: &str, tag: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .delete(format!(
+                "{}/admin/subscribers/{}/tags/{}",
+                &self.address, subscriber_id, tag
+            ))
+            .bearer_auth(ADMIN_TOKEN)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_segment_count(&self, body: &serde_json::Value) -> reqwest::Response {
+        reqwest::Client::new()
+            .post(format!("{}/admin/segments/count", &self.address))
+            .bearer_auth(ADMIN_TOKEN)
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_attribute(&self, body: &serde_json::Value) -> reqwest::Response {
+        reqwest::Client::new()
+            .post(format!("{}/admin/attributes", &self.address))
+            .bearer_auth(ADMIN_TOKEN)
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_attributes(&self) -> reqwest::Response {
+        reqwest::Client::new()
+            .get(format!("{}/admin/attributes", &self.address))
+            .bearer_auth(ADMIN_TOKEN)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_preferences(&self, token: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .get(format!("{}/preferences/{}", &self.address, token))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_preferences(&self, token: &str, body: String) -> reqwest::Response {
+        reqwest::Client::new()
+            .post(format!("{}/preferences/{}", &self.address, token))
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .body(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_own_data(&self, token: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .get(format!("{}/preferences/{}/export", &self.address, token))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_own_erasure(&self, token: &str, body: String) -> reqwest::Response {
+        reqwest::Client::new()
+            .post(format!("{}/preferences/{}/erase", &self.address, token))
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .body(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_subscribers(&self, query: &[(&str, &str)]) -> reqwest::Response {
+        reqwest::Client::new()
+            .get(format!("{}/admin/subscribers", &self.address))
+            .bearer_auth(ADMIN_TOKEN)
+            .query(query)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_subscriber(&self, subscriber_id: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .get(format!(
+                "{}/admin/subscribers/{}",
+                &self.address, subscriber_id
+            ))
+            .bearer_auth(ADMIN_TOKEN)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_subscriber_export(&self, email: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .get(format!("{}/admin/subscribers/export", &self.address))
+            .bearer_auth(ADMIN_TOKEN)
+            .query(&[("email", email)])
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_subscriber_erasure(&self, email: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .post(format!("{}/admin/subscribers/erase", &self.address))
+            .bearer_auth(ADMIN_TOKEN)
+            .json(&serde_json::json!({ "email": email }))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_import(&self, query: &[(&str, &str)], csv: String) -> reqwest::Response {
+        reqwest::Client::new()
+            .post(format!("{}/admin/imports", &self.address))
+            .bearer_auth(ADMIN_TOKEN)
+            .query(query)
+            .header("Content-Type", "text/csv")
+            .body(csv)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_import(&self, import_id: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .get(format!("{}/admin/imports/{}", &self.address, import_id))
+            .bearer_auth(ADMIN_TOKEN)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_import_errors(&self, import_id: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .get(format!(
+                "{}/admin/imports/{}/errors",
+                &self.address, import_id
+            ))
+            .bearer_auth(ADMIN_TOKEN)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// Creates a draft issue and returns its id.
+    pub async fn create_draft_issue(&self, body: &serde_json::Value) -> String {
+        let response = self.post_issue(body).await;
+        assert_eq!(response.status().as_u16(), 201);
+        let created: serde_json::Value = response.json().await.unwrap();
+        created["id"].as_str().unwrap().to_owned()
+    }
+
+    /// Publishes an [`issue_body`] titled `title` and delivers it.
+    pub async fn publish(&self, title: &str) {
+        self.post_newsletters(&issue_body(title))
+            .await
+            .error_for_status()
+            .unwrap();
+        self.dispatch_all_pending_emails().await;
+    }
+
+    pub async fn subscriber_id(&self, email: &str) -> String {
+        sqlx::query!("SELECT id FROM subscriptions WHERE email = $1", email)
+            .fetch_one(&self.db_pool)
+            .await
+            .unwrap()
+            .id
+            .to_string()
+    }
+
+    pub async fn preferences_token(&self, email: &str) -> String {
+        sqlx::query!(
+            "SELECT preferences_token FROM subscriptions WHERE email = $1",
+            email
+        )
+        .fetch_one(&self.db_pool)
+        .await
+        .unwrap()
+        .preferences_token
+    }
+}
+
+/// A newsletter issue titled `title`. Both bodies greet the subscriber and
+/// name the issue, so that deliveries of different issues can be told apart.
+pub fn issue_body(title: &str) -> serde_json::Value {
+    serde_json::json!({
+        "title": title,
+        "content": {
+            "text": format!("Hi {{{{name}}}}, this is {} as plain text", title),
+            "html": format!("<p>Hi {{{{name}}}}, this is {} as HTML</p>", title)
+        }
+    })
+}
+
+/// An [`issue_body`] sent to the list with the given slug.
+pub fn list_issue_body(list: &str) -> serde_json::Value {
+    let mut body = issue_body("Newsletter title");
+    body["list"] = list.into();
+    body
+}
+
+pub async fn create_confirmed_subscriber(app: &TestApp) {
+    let confirmation_link = create_unconfirmed_subscriber(app).await;
+    reqwest::get(confirmation_link.html)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+}
+
+/// Subscribes `email` to the list and follows the confirmation link.
+pub async fn subscribe_and_confirm(app: &TestApp, email: &str, list: &str) {
+    app.post_subscriptions(format!("name=le guin&email={}&list={}", email, list))
+        .await
+        .error_for_status()
+        .unwrap();
+    let request = app.aws_request_wrapper.expect_one_request_and_remove();
+    let confirmation_links = app.extract_confirmation_links(&request);
+    reqwest::get(confirmation_links.html)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+}
+
+pub async fn create_unconfirmed_subscriber(app: &TestApp) -> ConfirmationLinks {
+    let body = "name=le guin&email=ursula_le_guin@gmail.com";
+
+    app.post_subscriptions(body.into())
+        .await
+        .error_for_status()
+        .unwrap();
+
+    let request = app.aws_request_wrapper.expect_one_request_and_remove();
+    app.extract_confirmation_links(&request)
 }
 
 static TRACING: LazyLock<()> = LazyLock::new(|| {
@@ -0,0 +1,329 @@
+use crate::api::helpers::{spawn_app, spawn_app_with, subscribe_and_confirm, QueryPairs, TestApp};
+
+const CONFIRMED: [(&str, &str); 2] = [
+    ("mode", "confirmed"),
+    (
+        "attestation",
+        "Exported from our old provider, where every contact opted in.",
+    ),
+];
+
+async fn statuses(app: &TestApp) -> Vec<(String, String)> {
+    sqlx::query!(
+        r#"
+        SELECT subscriptions.email, list_subscriptions.status
+        FROM list_subscriptions
+        JOIN subscriptions ON subscriptions.id = list_subscriptions.subscriber_id
+        ORDER BY subscriptions.email
+        "#
+    )
+    .fetch_all(&app.db_pool)
+    .await
+    .unwrap()
+    .into_iter()
+    .map(|r| (r.email, r.status))
+    .collect()
+}
+
+#[tokio::test]
+async fn a_confirmed_import_adds_valid_rows_and_reports_the_others() {
+    // Arrange
+    let app = spawn_app().await;
+    subscribe_and_confirm(&app, "existing@example.com", "default").await;
+    let csv = "Email,Name\n\
+        ada@example.com,Ada Lovelace\n\
+        not-an-email,Someone\n\
+        grace@example.com,Grace Hopper\n\
+        ADA@example.com,Ada again\n\
+        existing@example.com,Existing\n\
+        alan@example.com\n"
+        .to_string();
+
+    // Act
+    let response = app.post_import(&CONFIRMED, csv).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 201);
+    let job: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(job["status"], "completed");
+    assert_eq!(job["mode"], "confirmed");
+    assert_eq!(job["list"], "default");
+    assert_eq!(
+        job["rows"],
+        serde_json::json!({"total": 6, "imported": 2, "duplicates": 2, "failed": 2})
+    );
+    assert_eq!(
+        statuses(&app).await,
+        vec![
+            ("ada@example.com".into(), "confirmed".into()),
+            ("existing@example.com".into(), "confirmed".into()),
+            ("grace@example.com".into(), "confirmed".into()),
+        ]
+    );
+    let consent = sqlx::query!(
+        r#"
+        SELECT consent_records.form_source FROM consent_records
+        JOIN subscriptions ON subscriptions.id = consent_records.subscriber_id
+        WHERE subscriptions.email = 'ada@example.com'
+        "#
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(
+        consent.form_source,
+        format!("import-{}", job["id"].as_str().unwrap())
+    );
+    app.aws_request_wrapper.expect_zero_requests();
+
+    let report = app
+        .get_import_errors(job["id"].as_str().unwrap())
+        .await
+        .text()
+        .await
+        .unwrap();
+    let rows: Vec<&str> = report.lines().collect();
+    assert_eq!(rows[0], "row,email,error");
+    assert_eq!(rows.len(), 5);
+    assert!(rows[1].starts_with("3,,"));
+    assert!(rows[2].starts_with("5,ADA@example.com,"));
+    assert!(rows[3].starts_with("6,existing@example.com,"));
+    assert!(rows[4].starts_with("7,,"));
+}
+
+#[tokio::test]
+async fn an_opt_in_import_sends_confirmation_emails() {
+    // Arrange
+    let app = spawn_app().await;
+    let csv = "email,name\nada@example.com,Ada Lovelace\n".to_string();
+
+    // Act - Part 1 - Import
+    let response = app.post_import(&[("mode", "opt_in")], csv).await;
+
+    // Assert - Part 1
+    assert_eq!(response.status().as_u16(), 201);
+    assert_eq!(
+        statuses(&app).await,
+        vec![("ada@example.com".into(), "pending_confirmation".into())]
+    );
+
+    // Act - Part 2 - Send the opt-in emails and confirm
+    app.dispatch_all_opt_in_emails().await;
+    let request = app.aws_request_wrapper.expect_one_request_and_remove();
+    let confirmation_links = app.extract_confirmation_links(&request);
+    reqwest::get(confirmation_links.html)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+
+    // Assert - Part 2
+    assert_eq!(
+        statuses(&app).await,
+        vec![("ada@example.com".into(), "confirmed".into())]
+    );
+    app.dispatch_all_opt_in_emails().await;
+    app.aws_request_wrapper.expect_zero_requests();
+}
+
+#[tokio::test]
+async fn imports_fill_in_custom_attributes() {
+    // Arrange
+    let app = spawn_app().await;
+    app.post_attribute(&serde_json::json!({"name": "seats", "type": "number"}))
+        .await
+        .error_for_status()
+        .unwrap();
+    let csv = "email,name,seats\nada@example.com,Ada,12\ngrace@example.com,Grace,many\n".into();
+
+    // Act
+    let response = app.post_import(&CONFIRMED, csv).await;
+
+    // Assert
+    let job: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(job["rows"]["imported"], 1);
+    assert_eq!(job["rows"]["failed"], 1);
+    let attributes = sqlx::query!("SELECT attributes FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .attributes;
+    assert_eq!(attributes, serde_json::json!({"seats": 12}));
+}
+
+#[tokio::test]
+async fn erased_addresses_are_not_imported_again() {
+    // Arrange
+    let app = spawn_app().await;
+    subscribe_and_confirm(&app, "erased@example.com", "default").await;
+    app.post_subscriber_erasure("erased@example.com")
+        .await
+        .error_for_status()
+        .unwrap();
+
+    // Act
+    let response = app
+        .post_import(&CONFIRMED, "email,name\nErased@example.com,Erased\n".into())
+        .await;
+
+    // Assert
+    let job: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(job["rows"]["imported"], 0);
+    assert_eq!(job["rows"]["failed"], 1);
+    assert!(statuses(&app).await.is_empty());
+    let report = app
+        .get_import_errors(job["id"].as_str().unwrap())
+        .await
+        .text()
+        .await
+        .unwrap();
+    assert!(!report.to_lowercase().contains("erased@example.com"));
+    assert!(report.lines().nth(1).unwrap().starts_with("2,,"));
+}
+
+#[tokio::test]
+async fn aliases_of_erased_addresses_are_not_imported_again() {
+    // Arrange
+    let app = spawn_app_with(|c| c.subscriptions.fold_email_aliases = true).await;
+    subscribe_and_confirm(&app, "ursula_le_guin@gmail.com", "default").await;
+    app.post_subscriber_erasure("ursula_le_guin@gmail.com")
+        .await
+        .error_for_status()
+        .unwrap();
+
+    // Act
+    let response = app
+        .post_import(
+            &CONFIRMED,
+            "email,name
+u.rsula_le_guin+x@gmail.com,Ursula
+"
+            .into(),
+        )
+        .await;
+
+    // Assert
+    let job: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(job["rows"]["failed"], 1);
+    assert!(statuses(&app).await.is_empty());
+}
+
+#[tokio::test]
+async fn large_imports_are_written_in_batches() {
+    // Arrange
+    let app = spawn_app().await;
+    let mut csv = "email,name\n".to_string();
+    for i in 0..2500 {
+        csv.push_str(&format!("reader{}@example.com,Reader {}\n", i, i));
+    }
+
+    // Act
+    let response = app.post_import(&CONFIRMED, csv).await;
+
+    // Assert
+    let job: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(job["rows"]["total"], 2500);
+    assert_eq!(job["rows"]["imported"], 2500);
+    let job = app
+        .get_import(job["id"].as_str().unwrap())
+        .await
+        .json::<serde_json::Value>()
+        .await
+        .unwrap();
+    assert_eq!(job["rows"]["imported"], 2500);
+    let subscribers = sqlx::query_scalar!("SELECT COUNT(*) FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(subscribers, Some(2500));
+}
+
+#[tokio::test]
+async fn invalid_imports_are_rejected_before_any_row_is_read() {
+    // Arrange
+    let app = spawn_app().await;
+    let csv = "email,name\nada@example.com,Ada\n";
+    let test_cases: Vec<(&QueryPairs, &str, &str)> = vec![
+        (
+            &[("mode", "confirmed")],
+            csv,
+            "a confirmed import without attestation",
+        ),
+        (&[("mode", "silent")], csv, "an unknown mode"),
+        (
+            &[("mode", "opt_in"), ("list", "nope")],
+            csv,
+            "an unknown list",
+        ),
+        (&[("mode", "opt_in")], "", "an empty file"),
+        (
+            &[("mode", "opt_in")],
+            "email\nada@example.com\n",
+            "no name column",
+        ),
+        (
+            &[("mode", "opt_in")],
+            "email,name,shoe_size\nada@example.com,Ada,42\n",
+            "an unknown column",
+        ),
+    ];
+
+    for (query, csv, description) in test_cases {
+        // Act
+        let response = app.post_import(query, csv.to_string()).await;
+
+        // Assert
+        assert_eq!(
+            response.status().as_u16(),
+            400,
+            "The API did not fail with 400 Bad Request for {}.",
+            description
+        );
+    }
+    let jobs = sqlx::query_scalar!("SELECT COUNT(*) FROM import_jobs")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(jobs, Some(0));
+}
+
+#[tokio::test]
+async fn getting_an_unknown_import_returns_a_404() {
+    // Arrange
+    let app = spawn_app().await;
+    let import_id = uuid::Uuid::new_v4().to_string();
+
+    // Act
+    let job_response = app.get_import(&import_id).await;
+    let errors_response = app.get_import_errors(&import_id).await;
+
+    // Assert
+    assert_eq!(job_response.status().as_u16(), 404);
+    assert_eq!(errors_response.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn unauthenticated_imports_are_rejected_with_a_401() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = reqwest::Client::new()
+        .post(format!("{}/admin/imports", app.address))
+        .query(&[("mode", "opt_in")])
+        .header("Content-Type", "text/csv")
+        .body("email,name\nada@example.com,Ada\n")
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 401);
+    assert!(statuses(&app).await.is_empty());
+    let jobs = sqlx::query_scalar!("SELECT COUNT(*) FROM import_jobs")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(jobs, Some(0));
+    app.aws_request_wrapper.expect_zero_requests();
+}
@@ -0,0 +1,249 @@
+use crate::api::helpers::{
+    create_confirmed_subscriber, issue_body, spawn_app, TestApp, SES_NOTIFICATIONS_TOKEN,
+};
+use chrono::Utc;
+use uuid::Uuid;
+
+async fn insert_confirmed_subscriber(app: &TestApp, email: &str) {
+    let subscriber_id = Uuid::new_v4();
+    sqlx::query!(
+        r#"
+        INSERT INTO subscriptions (
+            id, email, normalized_email, name, subscribed_at, preferences_token
+        )
+        VALUES ($1, $2, lower($2), 'someone', $3, $4)
+        "#,
+        subscriber_id,
+        email,
+        Utc::now(),
+        Uuid::new_v4().to_string()
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+    sqlx::query!(
+        r#"
+        INSERT INTO list_subscriptions (
+            list_id, subscriber_id, status, unsubscribe_token, subscribed_at, updated_at
+        )
+        SELECT id, $1, 'confirmed', $2, $3, $3 FROM lists WHERE slug = 'default'
+        "#,
+        subscriber_id,
+        Uuid::new_v4().to_string(),
+        Utc::now()
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+}
+
+/// Publishes an issue to a valid and an invalid stored address and delivers it.
+async fn publish_issue(app: &TestApp) -> String {
+    create_confirmed_subscriber(app).await;
+    insert_confirmed_subscriber(app, "definitely-not-an-email").await;
+    let issue_id = app
+        .create_draft_issue(&issue_body("Newsletter title"))
+        .await;
+    app.post_issue_publish(&issue_id)
+        .await
+        .error_for_status()
+        .unwrap();
+    issue_id
+}
+
+fn bounce_notification(email: &str) -> String {
+    let message = serde_json::json!({
+        "notificationType": "Bounce",
+        "bounce": {
+            "bounceType": "Permanent",
+            "bouncedRecipients": [{"emailAddress": email}]
+        },
+        "mail": {"destination": [email]}
+    });
+    serde_json::json!({
+        "Type": "Notification",
+        "Message": message.to_string()
+    })
+    .to_string()
+}
+
+#[tokio::test]
+async fn the_report_counts_queued_deliveries_before_the_worker_runs() {
+    // Arrange
+    let app = spawn_app().await;
+    let issue_id = publish_issue(&app).await;
+
+    // Act
+    let report: serde_json::Value = app.get_issue_report(&issue_id).await.json().await.unwrap();
+
+    // Assert
+    assert_eq!(report["status"], "sending");
+    assert_eq!(report["deliveries"]["total"], 2);
+    assert_eq!(report["deliveries"]["queued"], 2);
+}
+
+#[tokio::test]
+async fn the_report_counts_sent_and_skipped_deliveries() {
+    // Arrange
+    let app = spawn_app().await;
+    let issue_id = publish_issue(&app).await;
+
+    // Act
+    app.dispatch_all_pending_emails().await;
+    let response = app.get_issue_report(&issue_id).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let report: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(report["status"], "sent");
+    assert_eq!(
+        report["deliveries"],
+        serde_json::json!({
+            "total": 2, "queued": 0, "sent": 1, "failed": 0, "skipped": 1, "bounced": 0
+        })
+    );
+    let events: Vec<_> = report["timeline"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|e| e["event"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        events,
+        vec![
+            "created",
+            "published",
+            "first_delivery_attempt",
+            "last_delivery_attempt",
+            "sent"
+        ]
+    );
+}
+
+#[tokio::test]
+async fn the_report_of_an_unknown_issue_returns_404() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let report = app.get_issue_report(&Uuid::new_v4().to_string()).await;
+    let deliveries = app
+        .get_issue_deliveries(&Uuid::new_v4().to_string(), "")
+        .await;
+
+    // Assert
+    assert_eq!(report.status().as_u16(), 404);
+    assert_eq!(deliveries.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn recipient_statuses_are_paginated_and_filterable() {
+    // Arrange
+    let app = spawn_app().await;
+    let issue_id = publish_issue(&app).await;
+    app.dispatch_all_pending_emails().await;
+
+    // Act
+    let second_page: serde_json::Value = app
+        .get_issue_deliveries(&issue_id, "?page=2&per_page=1")
+        .await
+        .json()
+        .await
+        .unwrap();
+    let skipped: serde_json::Value = app
+        .get_issue_deliveries(&issue_id, "?status=skipped")
+        .await
+        .json()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(second_page["total"], 2);
+    assert_eq!(second_page["items"].as_array().unwrap().len(), 1);
+    assert_eq!(second_page["items"][0]["email"], "ursula_le_guin@gmail.com");
+    assert_eq!(skipped["total"], 1);
+    assert_eq!(skipped["items"][0]["email"], "definitely-not-an-email");
+    assert!(skipped["items"][0]["error"].is_string());
+}
+
+#[tokio::test]
+async fn invalid_delivery_filters_are_rejected_with_a_400() {
+    // Arrange
+    let app = spawn_app().await;
+    let issue_id = publish_issue(&app).await;
+
+    for query in ["?status=delivered", "?format=xml"] {
+        // Act
+        let response = app.get_issue_deliveries(&issue_id, query).await;
+
+        // Assert
+        assert_eq!(response.status().as_u16(), 400, "query was {}", query);
+    }
+}
+
+#[tokio::test]
+async fn recipient_statuses_can_be_exported_as_csv() {
+    // Arrange
+    let app = spawn_app().await;
+    let issue_id = publish_issue(&app).await;
+    app.dispatch_all_pending_emails().await;
+
+    // Act
+    let response = app.get_issue_deliveries(&issue_id, "?format=csv").await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        response.headers()["content-type"],
+        "text/csv; charset=utf-8"
+    );
+    let csv = response.text().await.unwrap();
+    let lines: Vec<_> = csv.lines().collect();
+    assert_eq!(lines.len(), 3);
+    assert_eq!(lines[0], "email,status,error,queued_at,updated_at");
+    assert!(lines[1].starts_with("definitely-not-an-email,skipped,"));
+    assert!(lines[2].starts_with("ursula_le_guin@gmail.com,sent,,"));
+}
+
+#[tokio::test]
+async fn ses_bounce_notifications_mark_the_delivery_as_bounced() {
+    // Arrange
+    let app = spawn_app().await;
+    let issue_id = publish_issue(&app).await;
+    app.dispatch_all_pending_emails().await;
+
+    // Act
+    let response = app
+        .post_ses_notification(
+            SES_NOTIFICATIONS_TOKEN,
+            bounce_notification("ursula_le_guin@gmail.com"),
+        )
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let report: serde_json::Value = app.get_issue_report(&issue_id).await.json().await.unwrap();
+    assert_eq!(report["deliveries"]["sent"], 0);
+    assert_eq!(report["deliveries"]["bounced"], 1);
+}
+
+#[tokio::test]
+async fn ses_notifications_with_the_wrong_token_are_rejected() {
+    // Arrange
+    let app = spawn_app().await;
+    let issue_id = publish_issue(&app).await;
+    app.dispatch_all_pending_emails().await;
+
+    // Act
+    let response = app
+        .post_ses_notification(
+            "wrong-token",
+            bounce_notification("ursula_le_guin@gmail.com"),
+        )
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 404);
+    let report: serde_json::Value = app.get_issue_report(&issue_id).await.json().await.unwrap();
+    assert_eq!(report["deliveries"]["bounced"], 0);
+}
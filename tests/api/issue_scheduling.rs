@@ -0,0 +1,244 @@
+use crate::api::helpers::{create_confirmed_subscriber, issue_body, spawn_app, TestApp};
+use chrono::{TimeZone, Utc};
+use uuid::Uuid;
+
+fn schedule_body() -> serde_json::Value {
+    serde_json::json!({
+        "scheduled_for": "2099-01-19T08:00:00",
+        "timezone": "America/New_York"
+    })
+}
+
+async fn make_issue_due(app: &TestApp, issue_id: &str) {
+    sqlx::query!(
+        "UPDATE newsletter_issues SET scheduled_for = now() - interval '1 minute' WHERE id = $1",
+        Uuid::parse_str(issue_id).unwrap()
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+async fn scheduling_a_draft_stores_the_delivery_time_in_utc() {
+    // Arrange
+    let app = spawn_app().await;
+    let issue_id = app
+        .create_draft_issue(&issue_body("Newsletter title"))
+        .await;
+
+    // Act
+    let response = app.post_issue_schedule(&issue_id, &schedule_body()).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+
+    let saved = sqlx::query!("SELECT status, scheduled_for FROM newsletter_issues",)
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved issue.");
+    assert_eq!(saved.status, "scheduled");
+    assert_eq!(
+        saved.scheduled_for,
+        Some(Utc.with_ymd_and_hms(2099, 1, 19, 13, 0, 0).unwrap())
+    );
+}
+
+#[tokio::test]
+async fn scheduling_returns_400_for_invalid_times() {
+    // Arrange
+    let app = spawn_app().await;
+    let issue_id = app
+        .create_draft_issue(&issue_body("Newsletter title"))
+        .await;
+    let test_cases = vec![
+        (
+            serde_json::json!({"scheduled_for": "2001-01-01T08:00:00Z"}),
+            "a time in the past",
+        ),
+        (
+            serde_json::json!({"scheduled_for": "2099-01-19T08:00:00", "timezone": "Nowhere/Land"}),
+            "an unknown timezone",
+        ),
+        (
+            serde_json::json!({"scheduled_for": "next monday"}),
+            "an unparseable time",
+        ),
+    ];
+
+    for (invalid_body, error_message) in test_cases {
+        // Act
+        let response = app.post_issue_schedule(&issue_id, &invalid_body).await;
+
+        // Assert
+        assert_eq!(
+            400,
+            response.status().as_u16(),
+            "The API did not fail with 400 Bad Request when the payload had {}.",
+            error_message
+        );
+    }
+}
+
+#[tokio::test]
+async fn scheduled_issues_can_no_longer_be_edited() {
+    // Arrange
+    let app = spawn_app().await;
+    let issue_id = app
+        .create_draft_issue(&issue_body("Newsletter title"))
+        .await;
+    app.post_issue_schedule(&issue_id, &schedule_body())
+        .await
+        .error_for_status()
+        .unwrap();
+
+    // Act
+    let response = app
+        .put_issue(&issue_id, &issue_body("Newsletter title"))
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 409);
+}
+
+#[tokio::test]
+async fn issues_are_not_delivered_before_they_are_due() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    let issue_id = app
+        .create_draft_issue(&issue_body("Newsletter title"))
+        .await;
+    app.post_issue_schedule(&issue_id, &schedule_body())
+        .await
+        .error_for_status()
+        .unwrap();
+
+    // Act
+    app.enqueue_all_due_issues().await;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    app.aws_request_wrapper.expect_zero_requests();
+    let saved = sqlx::query!("SELECT status FROM newsletter_issues",)
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved issue.");
+    assert_eq!(saved.status, "scheduled");
+}
+
+#[tokio::test]
+async fn due_issues_are_delivered_exactly_once_by_concurrent_schedulers() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    let issue_id = app
+        .create_draft_issue(&issue_body("Newsletter title"))
+        .await;
+    app.post_issue_schedule(&issue_id, &schedule_body())
+        .await
+        .error_for_status()
+        .unwrap();
+    make_issue_due(&app, &issue_id).await;
+
+    // Act
+    tokio::join!(
+        app.enqueue_all_due_issues(),
+        app.enqueue_all_due_issues(),
+        app.enqueue_all_due_issues()
+    );
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    app.aws_request_wrapper.expect_one_request();
+    let saved = sqlx::query!("SELECT status, published_at FROM newsletter_issues",)
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved issue.");
+    assert_eq!(saved.status, "sent");
+    assert!(saved.published_at.is_some());
+}
+
+#[tokio::test]
+async fn cancelled_issues_return_to_draft_and_are_not_delivered() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    let issue_id = app
+        .create_draft_issue(&issue_body("Newsletter title"))
+        .await;
+    app.post_issue_schedule(&issue_id, &schedule_body())
+        .await
+        .error_for_status()
+        .unwrap();
+    make_issue_due(&app, &issue_id).await;
+
+    // Act
+    let response = app.delete_issue_schedule(&issue_id).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    app.enqueue_all_due_issues().await;
+    app.dispatch_all_pending_emails().await;
+    app.aws_request_wrapper.expect_zero_requests();
+
+    let saved = sqlx::query!("SELECT status, scheduled_for FROM newsletter_issues",)
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved issue.");
+    assert_eq!(saved.status, "draft");
+    assert!(saved.scheduled_for.is_none());
+}
+
+#[tokio::test]
+async fn rescheduling_updates_the_delivery_time() {
+    // Arrange
+    let app = spawn_app().await;
+    let issue_id = app
+        .create_draft_issue(&issue_body("Newsletter title"))
+        .await;
+    app.post_issue_schedule(&issue_id, &schedule_body())
+        .await
+        .error_for_status()
+        .unwrap();
+
+    // Act
+    let response = app
+        .put_issue_schedule(
+            &issue_id,
+            &serde_json::json!({"scheduled_for": "2099-02-01T09:30:00Z"}),
+        )
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+
+    let saved = sqlx::query!("SELECT status, scheduled_for FROM newsletter_issues",)
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved issue.");
+    assert_eq!(saved.status, "scheduled");
+    assert_eq!(
+        saved.scheduled_for,
+        Some(Utc.with_ymd_and_hms(2099, 2, 1, 9, 30, 0).unwrap())
+    );
+}
+
+#[tokio::test]
+async fn only_scheduled_issues_can_be_rescheduled_or_cancelled() {
+    // Arrange
+    let app = spawn_app().await;
+    let issue_id = app
+        .create_draft_issue(&issue_body("Newsletter title"))
+        .await;
+
+    // Act
+    let reschedule = app.put_issue_schedule(&issue_id, &schedule_body()).await;
+    let cancel = app.delete_issue_schedule(&issue_id).await;
+    let unknown = app.delete_issue_schedule(&Uuid::new_v4().to_string()).await;
+
+    // Assert
+    assert_eq!(reschedule.status().as_u16(), 409);
+    assert_eq!(cancel.status().as_u16(), 409);
+    assert_eq!(unknown.status().as_u16(), 404);
+}
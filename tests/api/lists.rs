@@ -0,0 +1,319 @@
+use crate::api::helpers::{
+    create_confirmed_subscriber, list_issue_body, spawn_app, subscribe_and_confirm, TestApp,
+};
+
+async fn list_statuses(app: &TestApp, email: &str) -> Vec<(String, String)> {
+    sqlx::query!(
+        r#"
+        SELECT lists.slug, list_subscriptions.status
+        FROM list_subscriptions
+        JOIN lists ON lists.id = list_subscriptions.list_id
+        JOIN subscriptions ON subscriptions.id = list_subscriptions.subscriber_id
+        WHERE subscriptions.email = $1
+        ORDER BY lists.slug
+        "#,
+        email
+    )
+    .fetch_all(&app.db_pool)
+    .await
+    .unwrap()
+    .into_iter()
+    .map(|r| (r.slug, r.status))
+    .collect()
+}
+
+async fn sent_deliveries(app: &TestApp) -> Vec<String> {
+    sqlx::query!(
+        r#"
+        SELECT subscriber_email FROM issue_deliveries
+        WHERE status = 'sent'
+        ORDER BY subscriber_email
+        "#
+    )
+    .fetch_all(&app.db_pool)
+    .await
+    .unwrap()
+    .into_iter()
+    .map(|r| r.subscriber_email)
+    .collect()
+}
+
+#[tokio::test]
+async fn a_default_list_exists_out_of_the_box() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+
+    // Act
+    let response = app.get_lists().await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let lists: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(lists.as_array().unwrap().len(), 1);
+    assert_eq!(lists[0]["slug"], "default");
+    assert_eq!(lists[0]["confirmed_subscribers"], 1);
+}
+
+#[tokio::test]
+async fn lists_can_be_created() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app
+        .post_list(&serde_json::json!({"slug": "rust-weekly", "name": "Rust Weekly"}))
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 201);
+    let lists: serde_json::Value = app.get_lists().await.json().await.unwrap();
+    assert_eq!(lists[1]["slug"], "rust-weekly");
+    assert_eq!(lists[1]["name"], "Rust Weekly");
+    assert_eq!(lists[1]["confirmed_subscribers"], 0);
+}
+
+#[tokio::test]
+async fn creating_a_list_with_a_taken_slug_returns_a_409() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app
+        .post_list(&serde_json::json!({"slug": "default", "name": "Another"}))
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 409);
+}
+
+#[tokio::test]
+async fn creating_a_list_with_invalid_data_returns_a_400() {
+    // Arrange
+    let app = spawn_app().await;
+    let test_cases = vec![
+        (
+            serde_json::json!({"slug": "Rust Weekly", "name": "Rust Weekly"}),
+            "invalid slug",
+        ),
+        (
+            serde_json::json!({"slug": "rust-weekly", "name": " "}),
+            "empty name",
+        ),
+    ];
+
+    for (body, description) in test_cases {
+        // Act
+        let response = app.post_list(&body).await;
+
+        // Assert
+        assert_eq!(
+            response.status().as_u16(),
+            400,
+            "The API did not fail with 400 Bad Request when the payload had an {}.",
+            description
+        );
+    }
+}
+
+#[tokio::test]
+async fn subscribing_to_an_unknown_list_returns_a_400() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app
+        .post_subscriptions("name=le guin&email=ursula_le_guin@gmail.com&list=unknown".into())
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 400);
+    app.aws_request_wrapper.expect_zero_requests();
+}
+
+#[tokio::test]
+async fn one_subscriber_can_join_several_lists_with_their_own_status() {
+    // Arrange
+    let app = spawn_app().await;
+    let email = "ursula_le_guin@gmail.com";
+    app.create_list("rust-weekly").await;
+    subscribe_and_confirm(&app, email, "default").await;
+
+    // Act
+    app.post_subscriptions(format!("name=le guin&email={}&list=rust-weekly", email))
+        .await
+        .error_for_status()
+        .unwrap();
+
+    // Assert
+    let subscribers = sqlx::query!("SELECT COUNT(*) as count FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(subscribers.count, Some(1));
+    assert_eq!(
+        list_statuses(&app, email).await,
+        vec![
+            ("default".to_string(), "confirmed".to_string()),
+            (
+                "rust-weekly".to_string(),
+                "pending_confirmation".to_string()
+            ),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn subscribing_again_to_a_confirmed_list_sends_no_email() {
+    // Arrange
+    let app = spawn_app().await;
+    let email = "ursula_le_guin@gmail.com";
+    subscribe_and_confirm(&app, email, "default").await;
+
+    // Act
+    let response = app
+        .post_subscriptions(format!("name=le guin&email={}", email))
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    app.aws_request_wrapper.expect_zero_requests();
+    assert_eq!(
+        list_statuses(&app, email).await,
+        vec![("default".to_string(), "confirmed".to_string())]
+    );
+}
+
+#[tokio::test]
+async fn issues_are_delivered_only_to_the_confirmed_subscribers_of_their_list() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_list("rust-weekly").await;
+    subscribe_and_confirm(&app, "default-reader@example.com", "default").await;
+    subscribe_and_confirm(&app, "rust-reader@example.com", "rust-weekly").await;
+
+    // Act
+    let response = app.post_newsletters(&list_issue_body("rust-weekly")).await;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        sent_deliveries(&app).await,
+        vec!["rust-reader@example.com".to_string()]
+    );
+}
+
+#[tokio::test]
+async fn publishing_to_an_unknown_list_returns_a_400() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let newsletter = app.post_newsletters(&list_issue_body("unknown")).await;
+    let draft = app.post_issue(&list_issue_body("unknown")).await;
+
+    // Assert
+    assert_eq!(newsletter.status().as_u16(), 400);
+    assert_eq!(draft.status().as_u16(), 400);
+}
+
+#[tokio::test]
+async fn drafts_are_delivered_to_the_list_they_were_moved_to() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_list("rust-weekly").await;
+    subscribe_and_confirm(&app, "default-reader@example.com", "default").await;
+    subscribe_and_confirm(&app, "rust-reader@example.com", "rust-weekly").await;
+    let issue_id = app.create_draft_issue(&list_issue_body("default")).await;
+
+    // Act
+    app.put_issue(&issue_id, &list_issue_body("rust-weekly"))
+        .await
+        .error_for_status()
+        .unwrap();
+    app.post_issue_publish(&issue_id)
+        .await
+        .error_for_status()
+        .unwrap();
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    assert_eq!(
+        sent_deliveries(&app).await,
+        vec!["rust-reader@example.com".to_string()]
+    );
+}
+
+#[tokio::test]
+async fn the_unsubscribe_link_in_an_issue_leaves_only_that_list() {
+    // Arrange
+    let app = spawn_app().await;
+    let email = "ursula_le_guin@gmail.com";
+    app.create_list("rust-weekly").await;
+    subscribe_and_confirm(&app, email, "default").await;
+    subscribe_and_confirm(&app, email, "rust-weekly").await;
+    app.post_newsletters(&list_issue_body("rust-weekly"))
+        .await
+        .error_for_status()
+        .unwrap();
+    app.dispatch_all_pending_emails().await;
+    let request = app.aws_request_wrapper.expect_one_request_and_remove();
+    let text = request
+        .content()
+        .unwrap()
+        .simple()
+        .unwrap()
+        .body()
+        .unwrap()
+        .text()
+        .unwrap()
+        .data();
+    let unsubscribe_link = text
+        .lines()
+        .find_map(|line| line.strip_prefix("Unsubscribe: "))
+        .unwrap();
+    let mut unsubscribe_link = reqwest::Url::parse(unsubscribe_link).unwrap();
+    unsubscribe_link.set_port(Some(app.port)).unwrap();
+
+    // Act
+    let response = reqwest::get(unsubscribe_link).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(response
+        .text()
+        .await
+        .unwrap()
+        .contains("You will no longer receive rust-weekly"));
+    assert_eq!(
+        list_statuses(&app, email).await,
+        vec![
+            ("default".to_string(), "confirmed".to_string()),
+            ("rust-weekly".to_string(), "unsubscribed".to_string()),
+        ]
+    );
+    app.post_newsletters(&list_issue_body("rust-weekly"))
+        .await
+        .error_for_status()
+        .unwrap();
+    app.dispatch_all_pending_emails().await;
+    app.aws_request_wrapper.expect_zero_requests();
+}
+
+#[tokio::test]
+async fn unsubscribing_with_an_unknown_token_returns_a_401() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = reqwest::get(format!(
+        "{}/subscriptions/unsubscribe?token=not-a-token",
+        app.address
+    ))
+    .await
+    .unwrap();
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 401);
+}
@@ -0,0 +1,201 @@
+use crate::api::helpers::{spawn_app, TestApp};
+use crate::aws_ses_rules::AwsRequestsWrapper;
+
+const EMAIL: &str = "ursula_le_guin@gmail.com";
+
+async fn post_subscriptions_in(app: &TestApp, accept_language: &str) -> reqwest::Response {
+    reqwest::Client::new()
+        .post(format!("{}/subscriptions", &app.address))
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .header("Accept", "text/html")
+        .header("Accept-Language", accept_language)
+        .body(format!("name=le guin&email={}", EMAIL))
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+async fn stored_locale(app: &TestApp) -> Option<String> {
+    sqlx::query!("SELECT locale FROM subscriptions WHERE email = $1", EMAIL)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .locale
+}
+
+/// Subscribes with the form's language picker and follows the confirmation
+/// link.
+async fn subscribe_and_confirm_in(app: &TestApp, locale: &str) {
+    app.post_subscriptions(format!("name=le guin&email={}&locale={}", EMAIL, locale))
+        .await
+        .error_for_status()
+        .unwrap();
+    let request = app.aws_request_wrapper.expect_one_request_and_remove();
+    let confirmation_links = app.extract_confirmation_links(&request);
+    reqwest::get(confirmation_links.html)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+}
+
+#[tokio::test]
+async fn pages_and_emails_are_in_the_language_the_browser_asks_for() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = post_subscriptions_in(&app, "pt-BR,pt;q=0.9,en;q=0.8").await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(response.headers()["content-language"], "pt");
+    let page = response.text().await.unwrap();
+    assert!(page.contains(r#"<html lang="pt">"#));
+    assert!(page.contains("Verifique a sua caixa de correio"));
+    assert_eq!(stored_locale(&app).await.as_deref(), Some("pt-BR"));
+    let request = app.aws_request_wrapper.expect_one_request();
+    AwsRequestsWrapper::assert_correct_subject(&request, "Bem-vindo");
+    AwsRequestsWrapper::assert_correct_body_text(&request, "para confirmar a sua subscrição");
+}
+
+#[tokio::test]
+async fn unsupported_languages_get_the_default_locale() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = post_subscriptions_in(&app, "de-DE,fr;q=0.5").await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(response.headers()["content-language"], "en");
+    assert!(response.text().await.unwrap().contains("Check your inbox"));
+    assert_eq!(stored_locale(&app).await, None);
+    let request = app.aws_request_wrapper.expect_one_request();
+    AwsRequestsWrapper::assert_correct_subject(&request, "Welcome");
+}
+
+#[tokio::test]
+async fn validation_messages_are_translated() {
+    // Arrange
+    let app = spawn_app().await;
+    let body = serde_json::json!({ "email": "not-an-email", "locale": "pt" });
+
+    // Act
+    let response = app.post_api_subscriptions(&body).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 400);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(
+        body["fields"]["email"]["message"],
+        "not-an-email não é um email válido."
+    );
+}
+
+#[tokio::test]
+async fn malformed_locales_are_rejected() {
+    // Arrange
+    let app = spawn_app().await;
+    let body = serde_json::json!({ "email": EMAIL, "locale": "not a locale" });
+
+    // Act
+    let response = app.post_api_subscriptions(&body).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 400);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["fields"]["locale"]["code"], "invalid_locale");
+}
+
+#[tokio::test]
+async fn subscribers_receive_the_variant_of_an_issue_in_their_language() {
+    // Arrange
+    let app = spawn_app().await;
+    subscribe_and_confirm_in(&app, "pt-BR").await;
+    let body = serde_json::json!({
+        "title": "Newsletter title",
+        "content": {
+            "text": "Newsletter body as plain text",
+            "html": "<p>Newsletter body as HTML</p>"
+        },
+        "variants": {
+            "pt": {
+                "title": "Título da newsletter",
+                "content": {
+                    "text": "Corpo da newsletter em texto",
+                    "html": "<p>Corpo da newsletter em HTML</p>"
+                }
+            }
+        }
+    });
+
+    // Act
+    app.post_newsletters(&body)
+        .await
+        .error_for_status()
+        .unwrap();
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let request = app.aws_request_wrapper.expect_one_request();
+    AwsRequestsWrapper::assert_correct_subject(&request, "Título da newsletter");
+    AwsRequestsWrapper::assert_correct_body_text(&request, "Corpo da newsletter em texto");
+    AwsRequestsWrapper::assert_correct_body_text(&request, "Gerir as suas preferências");
+}
+
+#[tokio::test]
+async fn issues_without_a_matching_variant_are_sent_as_written() {
+    // Arrange
+    let app = spawn_app().await;
+    subscribe_and_confirm_in(&app, "pt").await;
+    let body = serde_json::json!({
+        "title": "Newsletter title",
+        "content": {
+            "text": "Newsletter body as plain text",
+            "html": "<p>Newsletter body as HTML</p>"
+        },
+        "variants": {
+            "fr": {
+                "title": "Titre de la lettre",
+                "content": { "text": "Texte", "html": "<p>Texte</p>" }
+            }
+        }
+    });
+
+    // Act
+    app.post_newsletters(&body)
+        .await
+        .error_for_status()
+        .unwrap();
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let request = app.aws_request_wrapper.expect_one_request();
+    AwsRequestsWrapper::assert_correct_subject(&request, "Newsletter title");
+    AwsRequestsWrapper::assert_correct_body_text(&request, "Newsletter body as plain text");
+}
+
+#[tokio::test]
+async fn subscribers_can_change_their_language_in_the_preference_center() {
+    // Arrange
+    let app = spawn_app().await;
+    subscribe_and_confirm_in(&app, "en").await;
+    let token = app.preferences_token(EMAIL).await;
+
+    // Act
+    let response = app
+        .post_preferences(
+            &token,
+            "name=le guin&lists=default&digest_frequency=immediate&locale=pt".into(),
+        )
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let html = response.text().await.unwrap();
+    assert!(html.contains("As suas preferências foram guardadas."));
+    assert!(html.contains(r#"<option value="pt" selected>"#));
+    assert_eq!(stored_locale(&app).await.as_deref(), Some("pt"));
+}
@@ -1,5 +1,29 @@
+mod ab_tests;
+mod admin_auth;
+mod admin_issues;
+mod api_subscriptions;
+mod archive;
+mod attributes;
+mod bot_protection;
+mod consent_records;
+mod digests;
+mod email_policy;
+mod feeds;
 mod health_check;
 mod helpers;
+mod imports;
+mod issue_reports;
+mod issue_scheduling;
+mod lists;
+mod localization;
 mod newsletter;
+mod outcome_pages;
+mod preferences;
+mod problem_details;
+mod rate_limits;
+mod segments;
+mod subscriber_data;
+mod subscribers;
 mod subscriptions;
 mod subscriptions_confirm;
+mod tracking;
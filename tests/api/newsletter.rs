@@ -1,4 +1,4 @@
-use crate::api::helpers::{spawn_app, ConfirmationLinks, TestApp};
+use crate::api::helpers::{create_confirmed_subscriber, create_unconfirmed_subscriber, spawn_app};
 
 #[tokio::test]
 async fn newsletters_are_not_delivered_to_unconfirmed_subscribers() {
@@ -24,6 +24,7 @@ async fn newsletters_are_not_delivered_to_unconfirmed_subscribers() {
 
     // Assert
     assert_eq!(response.status().as_u16(), 200);
+    app.dispatch_all_pending_emails().await;
     app.aws_request_wrapper.expect_zero_requests();
 }
 
@@ -51,9 +52,39 @@ async fn newsletters_are_delivered_to_confirmed_subscribers() {
 
     // Assert
     assert_eq!(response.status().as_u16(), 200);
+    app.dispatch_all_pending_emails().await;
     app.aws_request_wrapper.expect_one_request();
 }
 
+#[tokio::test]
+async fn published_newsletters_are_stored_as_sent_issues() {
+    // Arrange
+    let app = spawn_app().await;
+
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter tittle",
+        "content": {
+            "text": "Newsletter body as plain text",
+            "html": "<p>Newsletter body as HTML</p>"
+        }
+    });
+
+    // Act
+    let response = app.post_newsletters(&newsletter_request_body).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    app.dispatch_all_pending_emails().await;
+
+    let saved = sqlx::query!("SELECT title, status, published_at FROM newsletter_issues",)
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved issue.");
+    assert_eq!(saved.title, "Newsletter tittle");
+    assert_eq!(saved.status, "sent");
+    assert!(saved.published_at.is_some());
+}
+
 #[tokio::test]
 async fn newsletters_returns_400_for_invalid_data() {
     // Arrange
@@ -92,24 +123,3 @@ async fn newsletters_returns_400_for_invalid_data() {
         )
     }
 }
-
-async fn create_confirmed_subscriber(app: &TestApp) {
-    let confirmation_link = create_unconfirmed_subscriber(app).await;
-    reqwest::get(confirmation_link.html)
-        .await
-        .unwrap()
-        .error_for_status()
-        .unwrap();
-}
-
-async fn create_unconfirmed_subscriber(app: &TestApp) -> ConfirmationLinks {
-    let body = "name=le guin&email=ursula_le_guin@gmail.com";
-
-    app.post_subscriptions(body.into())
-        .await
-        .error_for_status()
-        .unwrap();
-
-    let request = app.aws_request_wrapper.expect_one_request_and_remove();
-    app.extract_confirmation_links(&request)
-}
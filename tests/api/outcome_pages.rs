@@ -0,0 +1,212 @@
+use crate::api::helpers::{spawn_app, spawn_app_with, TestApp};
+
+const BODY: &str = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+
+async fn post_subscriptions_accepting(app: &TestApp, accept: &str) -> reqwest::Response {
+    reqwest::Client::new()
+        .post(format!("{}/subscriptions", &app.address))
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .header("Accept", accept)
+        .body(BODY)
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+async fn get_accepting(url: reqwest::Url, accept: &str) -> reqwest::Response {
+    reqwest::Client::new()
+        .get(url)
+        .header("Accept", accept)
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+fn assert_is_html(response: &reqwest::Response) {
+    assert!(response.headers()["content-type"]
+        .to_str()
+        .unwrap()
+        .starts_with("text/html"));
+}
+
+#[tokio::test]
+async fn browsers_are_asked_to_check_their_inbox_after_subscribing() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = post_subscriptions_accepting(&app, "text/html,*/*;q=0.8").await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    assert_is_html(&response);
+    let page = response.text().await.unwrap();
+    assert!(page.contains("Check your inbox"));
+    assert!(page.contains("ursula_le_guin@gmail.com"));
+}
+
+#[tokio::test]
+async fn json_clients_get_machine_readable_outcomes() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act - Part 1 - Subscribe
+    let response = post_subscriptions_accepting(&app, "application/json").await;
+
+    // Assert - Part 1
+    assert_eq!(response.status().as_u16(), 200);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["code"], "confirmation_sent");
+
+    // Act - Part 2 - Confirm
+    let request = app.aws_request_wrapper.expect_one_request_and_remove();
+    let confirmation_links = app.extract_confirmation_links(&request);
+    let response = get_accepting(confirmation_links.html.clone(), "application/json").await;
+
+    // Assert - Part 2
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["code"], "subscription_confirmed");
+
+    // Act - Part 3 - An unknown token
+    let mut unknown_link = confirmation_links.html;
+    unknown_link.set_query(Some("subscription_token=not-a-token"));
+    let response = get_accepting(unknown_link, "application/json").await;
+
+    // Assert - Part 3
+    assert_eq!(response.status().as_u16(), 401);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["code"], "invalid_token");
+}
+
+#[tokio::test]
+async fn invalid_subscriptions_render_the_reason() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app
+        .post_subscriptions("name=le%20guin&email=not-an-email".into())
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 400);
+    assert_is_html(&response);
+    let page = response.text().await.unwrap();
+    assert!(page.contains("We could not subscribe you"));
+    assert!(page.contains("not-an-email is not a valid"));
+}
+
+#[tokio::test]
+async fn unknown_lists_render_the_reason() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app.post_subscriptions(format!("{}&list=nope", BODY)).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 400);
+    let page = response.text().await.unwrap();
+    assert!(page.contains("We could not subscribe you"));
+    assert!(page.contains("nope"));
+}
+
+#[tokio::test]
+async fn unexpected_errors_do_not_reveal_their_cause() {
+    // Arrange
+    let app = spawn_app().await;
+    sqlx::query("ALTER TABLE subscriptions DROP COLUMN email;")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+
+    // Act
+    let response = app.post_subscriptions(BODY.into()).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 500);
+    let page = response.text().await.unwrap();
+    assert!(page.contains("Something went wrong"));
+    assert!(!page.contains("column"));
+}
+
+#[tokio::test]
+async fn unknown_confirmation_links_render_an_error_page() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = reqwest::get(format!(
+        "{}/subscriptions/confirm?subscription_token=not-a-token",
+        app.address
+    ))
+    .await
+    .unwrap();
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 401);
+    assert_is_html(&response);
+    assert!(response
+        .text()
+        .await
+        .unwrap()
+        .contains("This link is not valid"));
+}
+
+#[tokio::test]
+async fn expired_confirmation_links_do_not_confirm() {
+    // Arrange
+    let app = spawn_app().await;
+    app.post_subscriptions(BODY.into())
+        .await
+        .error_for_status()
+        .unwrap();
+    let request = app.aws_request_wrapper.expect_one_request_and_remove();
+    let confirmation_links = app.extract_confirmation_links(&request);
+    sqlx::query!("UPDATE subscription_tokens SET created_at = now() - interval '73 hours'")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+
+    // Act
+    let response = reqwest::get(confirmation_links.html).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 410);
+    assert!(response
+        .text()
+        .await
+        .unwrap()
+        .contains("This link has expired"));
+    let status = sqlx::query_scalar!("SELECT status FROM list_subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(status, "pending_confirmation");
+}
+
+#[tokio::test]
+async fn pages_carry_the_configured_branding() {
+    // Arrange
+    let app = spawn_app_with(|c| {
+        c.branding.name = "The <Rust> Letter".into();
+        c.branding.logo_url = Some("https://example.com/logo.png".into());
+        c.branding.accent_color = "#b7410e".into();
+    })
+    .await;
+
+    // Act
+    let response = reqwest::get(format!(
+        "{}/subscriptions/unsubscribe?token=not-a-token",
+        app.address
+    ))
+    .await
+    .unwrap();
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 401);
+    let page = response.text().await.unwrap();
+    assert!(page.contains("The &lt;Rust&gt; Letter"));
+    assert!(page.contains(r#"src="https://example.com/logo.png""#));
+    assert!(page.contains("#b7410e"));
+}
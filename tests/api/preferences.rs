@@ -0,0 +1,312 @@
+use crate::api::helpers::{issue_body, spawn_app, subscribe_and_confirm, TestApp};
+
+const EMAIL: &str = "ursula_le_guin@gmail.com";
+
+/// A subscriber confirmed on the default list, with `rust-weekly` available.
+async fn spawn_app_with_subscriber() -> (TestApp, String) {
+    let app = spawn_app().await;
+    app.create_list("rust-weekly").await;
+    subscribe_and_confirm(&app, EMAIL, "default").await;
+    let token = app.preferences_token(EMAIL).await;
+    (app, token)
+}
+
+async fn preference_changes(app: &TestApp) -> Vec<(String, Option<String>, String)> {
+    sqlx::query!(
+        r#"
+        SELECT field, new_value, source FROM subscriber_preference_changes
+        ORDER BY field
+        "#
+    )
+    .fetch_all(&app.db_pool)
+    .await
+    .unwrap()
+    .into_iter()
+    .map(|r| (r.field, r.new_value, r.source))
+    .collect()
+}
+
+#[tokio::test]
+async fn the_preference_center_shows_the_current_preferences() {
+    // Arrange
+    let (app, token) = spawn_app_with_subscriber().await;
+
+    // Act
+    let response = app.get_preferences(&token).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let html = response.text().await.unwrap();
+    assert!(html.contains(r#"value="le guin""#));
+    assert!(html.contains(r#"name="lists" value="default" checked"#));
+    assert!(html.contains(r#"name="lists" value="rust-weekly">"#));
+}
+
+#[tokio::test]
+async fn the_preference_center_returns_a_404_for_an_unknown_token() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let get_response = app.get_preferences("not-a-token").await;
+    let post_response = app
+        .post_preferences(
+            "not-a-token",
+            "name=le guin&digest_frequency=immediate".into(),
+        )
+        .await;
+
+    // Assert
+    assert_eq!(get_response.status().as_u16(), 404);
+    assert_eq!(post_response.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn saving_preferences_updates_the_subscriber_and_records_each_change() {
+    // Arrange
+    let (app, token) = spawn_app_with_subscriber().await;
+
+    // Act
+    let response = app
+        .post_preferences(
+            &token,
+            "name=ursula&lists=rust-weekly&digest_frequency=weekly&pause_weeks=".into(),
+        )
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(response
+        .text()
+        .await
+        .unwrap()
+        .contains("Your preferences have been saved."));
+    let saved = sqlx::query!(
+        "SELECT name, digest_frequency, paused_until FROM subscriptions WHERE email = $1",
+        EMAIL
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(saved.name, "ursula");
+    assert_eq!(saved.digest_frequency, "weekly");
+    assert!(saved.paused_until.is_none());
+    let statuses = sqlx::query!(
+        r#"
+        SELECT lists.slug, list_subscriptions.status
+        FROM list_subscriptions JOIN lists ON lists.id = list_subscriptions.list_id
+        ORDER BY lists.slug
+        "#
+    )
+    .fetch_all(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(statuses.len(), 2);
+    assert_eq!(statuses[0].status, "unsubscribed");
+    assert_eq!(statuses[1].status, "confirmed");
+    let source = "preference_center".to_string();
+    assert_eq!(
+        preference_changes(&app).await,
+        vec![
+            (
+                "digest_frequency".into(),
+                Some("weekly".into()),
+                source.clone()
+            ),
+            (
+                "list:default".into(),
+                Some("unsubscribed".into()),
+                source.clone()
+            ),
+            (
+                "list:rust-weekly".into(),
+                Some("confirmed".into()),
+                source.clone()
+            ),
+            ("name".into(), Some("ursula".into()), source),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn lists_picked_in_the_preference_center_record_consent() {
+    // Arrange
+    let (app, token) = spawn_app_with_subscriber().await;
+
+    // Act
+    app.post_preferences(
+        &token,
+        "name=le guin&lists=default&lists=rust-weekly&digest_frequency=immediate".into(),
+    )
+    .await
+    .error_for_status()
+    .unwrap();
+
+    // Assert
+    let consent = sqlx::query!(
+        r#"
+        SELECT consent_records.event, consent_records.form_source
+        FROM consent_records JOIN lists ON lists.id = consent_records.list_id
+        WHERE lists.slug = 'rust-weekly'
+        "#
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(consent.event, "confirm");
+    assert_eq!(consent.form_source, "preference_center");
+}
+
+#[tokio::test]
+async fn saving_unchanged_preferences_records_nothing() {
+    // Arrange
+    let (app, token) = spawn_app_with_subscriber().await;
+
+    // Act
+    let response = app
+        .post_preferences(
+            &token,
+            "name=le guin&lists=default&digest_frequency=immediate".into(),
+        )
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(preference_changes(&app).await.is_empty());
+}
+
+#[tokio::test]
+async fn paused_subscribers_receive_no_issues_until_they_resume() {
+    // Arrange
+    let (app, token) = spawn_app_with_subscriber().await;
+    app.post_preferences(
+        &token,
+        "name=le guin&lists=default&digest_frequency=immediate&pause_weeks=4".into(),
+    )
+    .await
+    .error_for_status()
+    .unwrap();
+
+    // Act - Part 1 - Publish while paused
+    let count_response = app.post_segment_count(&serde_json::json!({})).await;
+    app.publish("Newsletter title").await;
+
+    // Assert - Part 1
+    let count: serde_json::Value = count_response.json().await.unwrap();
+    assert_eq!(count["recipients"], 0);
+    app.aws_request_wrapper.expect_zero_requests();
+
+    // Act - Part 2 - Resume and publish again
+    app.post_preferences(
+        &token,
+        "name=le guin&lists=default&digest_frequency=immediate&pause_weeks=0".into(),
+    )
+    .await
+    .error_for_status()
+    .unwrap();
+    app.publish("Newsletter title").await;
+
+    // Assert - Part 2
+    app.aws_request_wrapper.expect_one_request_and_remove();
+    let changes = preference_changes(&app).await;
+    assert_eq!(changes.len(), 2);
+    assert!(changes.iter().all(|(field, _, _)| field == "paused_until"));
+}
+
+#[tokio::test]
+async fn the_preference_center_returns_a_400_for_invalid_preferences() {
+    // Arrange
+    let (app, token) = spawn_app_with_subscriber().await;
+    let test_cases = vec![
+        ("digest_frequency=immediate", "missing name"),
+        ("name=le guin", "missing digest frequency"),
+        (
+            "name=le guin&digest_frequency=hourly",
+            "unknown digest frequency",
+        ),
+        (
+            "name=le guin&digest_frequency=immediate&pause_weeks=53",
+            "pause too long",
+        ),
+        (
+            "name=le guin&digest_frequency=immediate&lists=nope",
+            "unknown list",
+        ),
+        (
+            "name=le guin&digest_frequency=immediate&colour=blue",
+            "unknown field",
+        ),
+    ];
+
+    for (body, description) in test_cases {
+        // Act
+        let response = app.post_preferences(&token, body.into()).await;
+
+        // Assert
+        assert_eq!(
+            response.status().as_u16(),
+            400,
+            "The API did not fail with 400 Bad Request when the payload had {}.",
+            description
+        );
+    }
+    assert!(preference_changes(&app).await.is_empty());
+}
+
+#[tokio::test]
+async fn issues_link_to_the_preference_center() {
+    // Arrange
+    let (app, token) = spawn_app_with_subscriber().await;
+    app.post_newsletters(&issue_body("Newsletter title"))
+        .await
+        .error_for_status()
+        .unwrap();
+
+    // Act
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let request = app.aws_request_wrapper.expect_one_request_and_remove();
+    let body = request.content().unwrap().simple().unwrap().body().unwrap();
+    let text = body.text().unwrap().data();
+    let link = text
+        .lines()
+        .find_map(|line| line.strip_prefix("Manage your preferences: "))
+        .unwrap();
+    assert!(link.ends_with(&format!("/preferences/{}", token)));
+    assert!(body
+        .html()
+        .unwrap()
+        .data()
+        .contains("Manage your preferences"));
+}
+
+#[tokio::test]
+async fn unsubscribe_links_are_recorded_in_the_preference_audit_trail() {
+    // Arrange
+    let (app, _) = spawn_app_with_subscriber().await;
+    let unsubscribe_token = sqlx::query!("SELECT unsubscribe_token FROM list_subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .unsubscribe_token;
+
+    // Act
+    let response = reqwest::get(format!(
+        "{}/subscriptions/unsubscribe?token={}",
+        app.address, unsubscribe_token
+    ))
+    .await
+    .unwrap();
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        preference_changes(&app).await,
+        vec![(
+            "list:default".into(),
+            Some("unsubscribed".into()),
+            "unsubscribe_link".into()
+        )]
+    );
+}
@@ -0,0 +1,139 @@
+use crate::api::helpers::{spawn_app, ADMIN_TOKEN};
+use uuid::Uuid;
+
+async fn assert_is_problem(response: reqwest::Response, status: u16) -> serde_json::Value {
+    assert_eq!(response.status().as_u16(), status);
+    assert_eq!(
+        response.headers()["content-type"],
+        "application/problem+json"
+    );
+    let problem: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(problem["status"], status);
+    for member in ["type", "title", "detail", "request_id"] {
+        assert!(
+            problem[member].is_string(),
+            "Missing {}: {}",
+            member,
+            problem
+        );
+    }
+    problem
+}
+
+#[tokio::test]
+async fn validation_errors_are_described_as_problems() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app
+        .post_list(&serde_json::json!({"slug": "Not A Slug", "name": "Rust"}))
+        .await;
+
+    // Assert
+    let problem = assert_is_problem(response, 400).await;
+    assert_eq!(problem["type"], "/problems/validation-failed");
+    assert!(problem["detail"]
+        .as_str()
+        .unwrap()
+        .contains("is not a valid list slug"));
+}
+
+#[tokio::test]
+async fn missing_resources_are_described_as_problems() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = reqwest::Client::new()
+        .get(format!("{}/admin/issues/{}", &app.address, Uuid::new_v4()))
+        .bearer_auth(ADMIN_TOKEN)
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    let problem = assert_is_problem(response, 404).await;
+    assert_eq!(problem["code"], "not_found");
+}
+
+#[tokio::test]
+async fn unknown_addresses_are_described_as_problems() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = reqwest::get(format!("{}/no/such/page", &app.address))
+        .await
+        .unwrap();
+
+    // Assert
+    assert_is_problem(response, 404).await;
+}
+
+#[tokio::test]
+async fn malformed_bodies_and_queries_are_described_as_problems() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let body = reqwest::Client::new()
+        .post(format!("{}/admin/lists", &app.address))
+        .bearer_auth(ADMIN_TOKEN)
+        .header("Content-Type", "application/json")
+        .body("{\"slug\":")
+        .send()
+        .await
+        .unwrap();
+    let query = reqwest::Client::new()
+        .get(format!("{}/admin/subscribers?limit=many", &app.address))
+        .bearer_auth(ADMIN_TOKEN)
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    let problem = assert_is_problem(body, 400).await;
+    assert_eq!(problem["code"], "malformed_body");
+    let problem = assert_is_problem(query, 400).await;
+    assert_eq!(problem["code"], "malformed_query");
+}
+
+#[tokio::test]
+async fn internal_causes_are_kept_out_of_the_response() {
+    // Arrange
+    let app = spawn_app().await;
+    sqlx::query("ALTER TABLE lists DROP COLUMN name;")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+
+    // Act
+    let response = app.get_lists().await;
+
+    // Assert
+    let problem = assert_is_problem(response, 500).await;
+    assert_eq!(problem["code"], "unexpected_error");
+    let body = problem.to_string();
+    assert!(!body.contains("column"));
+    assert!(!body.contains("Failed to"));
+}
+
+#[tokio::test]
+async fn every_problem_has_its_own_request_id() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let first = reqwest::get(format!("{}/no/such/page", &app.address))
+        .await
+        .unwrap();
+    let second = reqwest::get(format!("{}/no/such/page", &app.address))
+        .await
+        .unwrap();
+
+    // Assert
+    let first = assert_is_problem(first, 404).await;
+    let second = assert_is_problem(second, 404).await;
+    assert_ne!(first["request_id"], second["request_id"]);
+}
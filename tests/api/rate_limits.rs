@@ -0,0 +1,275 @@
+use crate::api::helpers::{spawn_app_with, TestApp};
+use zero2prod::configuration::{Limit, RateLimitStoreKind, Settings};
+
+fn limit(max_requests: u32) -> Option<Limit> {
+    Some(Limit {
+        max_requests,
+        window_seconds: 3600,
+    })
+}
+
+/// Only the given limits are enforced.
+fn only(settings: &mut Settings, per_ip: Option<Limit>, per_email: Option<Limit>) {
+    settings.rate_limit.per_ip = per_ip;
+    settings.rate_limit.per_email = per_email;
+    settings.rate_limit.per_endpoint = None;
+    settings.rate_limit.global = None;
+}
+
+async fn subscribe_from(app: &TestApp, email: &str, forwarded_for: &str) -> reqwest::Response {
+    reqwest::Client::new()
+        .post(format!("{}/subscriptions", &app.address))
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .header("X-Forwarded-For", forwarded_for)
+        .body(format!("name=le%20guin&email={}", email))
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+fn retry_after(response: &reqwest::Response) -> u64 {
+    response.headers()["retry-after"]
+        .to_str()
+        .unwrap()
+        .parse()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn clients_over_the_per_ip_limit_get_a_429() {
+    // Arrange
+    let app = spawn_app_with(|c| only(c, limit(2), None)).await;
+
+    // Act
+    let mut responses = Vec::new();
+    for i in 0..3 {
+        let body = format!("name=le%20guin&email=ursula{}%40example.com", i);
+        responses.push(app.post_subscriptions(body).await);
+    }
+
+    // Assert
+    assert_eq!(responses[0].status().as_u16(), 200);
+    assert_eq!(responses[1].status().as_u16(), 200);
+    let limited = responses.pop().unwrap();
+    assert_eq!(limited.status().as_u16(), 429);
+    assert!((1..=3600).contains(&retry_after(&limited)));
+    let problem: serde_json::Value = limited.json().await.unwrap();
+    assert_eq!(problem["code"], "rate_limited");
+    let subscribers: i64 = sqlx::query_scalar("SELECT count(*) FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(subscribers, 2);
+}
+
+#[tokio::test]
+async fn the_same_address_is_not_emailed_past_the_per_email_limit() {
+    // Arrange
+    let app = spawn_app_with(|c| only(c, None, limit(1))).await;
+    let body = "name=le%20guin&email=Ursula%40example.com".to_string();
+
+    // Act
+    let first = app.post_subscriptions(body).await;
+    let second = app
+        .post_api_subscriptions(&serde_json::json!({
+            "name": "le guin",
+            "email": "ursula@EXAMPLE.com",
+        }))
+        .await;
+
+    // Assert
+    assert_eq!(first.status().as_u16(), 200);
+    assert_eq!(second.status().as_u16(), 429);
+    assert!(retry_after(&second) > 0);
+    let problem: serde_json::Value = second.json().await.unwrap();
+    assert_eq!(problem["code"], "rate_limited");
+    app.aws_request_wrapper.expect_one_request();
+}
+
+#[tokio::test]
+async fn forwarded_addresses_are_only_trusted_from_trusted_proxies() {
+    // Arrange
+    let trusted = spawn_app_with(|c| {
+        only(c, limit(1), None);
+        c.rate_limit.trusted_proxies = vec!["127.0.0.1".parse().unwrap()];
+    })
+    .await;
+    let untrusted = spawn_app_with(|c| only(c, limit(1), None)).await;
+
+    // Act
+    let trusted_responses = [
+        subscribe_from(&trusted, "a%40example.com", "203.0.113.1").await,
+        subscribe_from(&trusted, "b%40example.com", "203.0.113.2").await,
+        subscribe_from(&trusted, "c%40example.com", "198.51.100.9, 203.0.113.1").await,
+    ];
+    let untrusted_responses = [
+        subscribe_from(&untrusted, "a%40example.com", "203.0.113.1").await,
+        subscribe_from(&untrusted, "b%40example.com", "203.0.113.2").await,
+    ];
+
+    // Assert
+    let statuses = |responses: &[reqwest::Response]| {
+        responses
+            .iter()
+            .map(|r| r.status().as_u16())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(statuses(&trusted_responses), [200, 200, 429]);
+    assert_eq!(statuses(&untrusted_responses), [200, 429]);
+    let ip_addresses: Vec<Option<String>> =
+        sqlx::query_scalar("SELECT ip_address FROM consent_records ORDER BY recorded_at")
+            .fetch_all(&trusted.db_pool)
+            .await
+            .unwrap();
+    assert_eq!(
+        ip_addresses,
+        [
+            Some("203.0.113.1".to_string()),
+            Some("203.0.113.2".to_string())
+        ]
+    );
+}
+
+#[tokio::test]
+async fn the_postgres_store_counts_hits_in_the_database() {
+    // Arrange
+    let app = spawn_app_with(|c| {
+        only(c, limit(1), None);
+        c.rate_limit.store = RateLimitStoreKind::Postgres;
+    })
+    .await;
+
+    // Act
+    let first = app
+        .post_subscriptions("name=le%20guin&email=a%40example.com".into())
+        .await;
+    let second = app
+        .post_subscriptions("name=le%20guin&email=b%40example.com".into())
+        .await;
+
+    // Assert
+    assert_eq!(first.status().as_u16(), 200);
+    assert_eq!(second.status().as_u16(), 429);
+    let hits: i32 =
+        sqlx::query_scalar("SELECT hits FROM rate_limit_counters WHERE key = 'ip:127.0.0.1'")
+            .fetch_one(&app.db_pool)
+            .await
+            .unwrap();
+    assert_eq!(hits, 2);
+}
+
+#[tokio::test]
+async fn per_email_counters_do_not_store_the_address() {
+    // Arrange
+    let app = spawn_app_with(|c| {
+        only(c, None, limit(5));
+        c.rate_limit.store = RateLimitStoreKind::Postgres;
+    })
+    .await;
+
+    // Act
+    app.post_subscriptions("name=le%20guin&email=ursula%40example.com".into())
+        .await
+        .error_for_status()
+        .unwrap();
+
+    // Assert
+    let keys: Vec<String> = sqlx::query_scalar("SELECT key FROM rate_limit_counters")
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(keys.len(), 1);
+    assert!(keys[0].starts_with("email:"));
+    assert!(!keys[0].contains("ursula"));
+}
+
+#[tokio::test]
+async fn browsers_over_the_per_email_limit_get_a_page() {
+    // Arrange
+    let app = spawn_app_with(|c| only(c, None, limit(1))).await;
+    let body = "name=le%20guin&email=ursula%40example.com";
+
+    // Act
+    app.post_subscriptions(body.into()).await;
+    let response = reqwest::Client::new()
+        .post(format!("{}/subscriptions", &app.address))
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .header("Accept", "text/html")
+        .body(body)
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 429);
+    assert!(retry_after(&response) > 0);
+    let page = response.text().await.unwrap();
+    assert!(page.contains("Please try again later"));
+}
+
+#[tokio::test]
+async fn guessing_link_tokens_is_rate_limited() {
+    // Arrange
+    let app = spawn_app_with(|c| only(c, limit(3), None)).await;
+
+    // Act
+    let mut statuses = Vec::new();
+    for path in [
+        "/subscriptions/confirm?subscription_token=guess",
+        "/subscriptions/unsubscribe?token=guess",
+        "/preferences/guess",
+        "/preferences/another-guess",
+    ] {
+        let response = reqwest::get(format!("{}{}", &app.address, path))
+            .await
+            .unwrap();
+        statuses.push(response.status().as_u16());
+    }
+
+    // Assert
+    assert_ne!(statuses[0], 429);
+    assert_ne!(statuses[1], 429);
+    assert_ne!(statuses[2], 429);
+    assert_eq!(statuses[3], 429);
+}
+
+#[tokio::test]
+async fn tokens_in_the_path_count_towards_one_endpoint() {
+    // Arrange
+    let app = spawn_app_with(|c| {
+        only(c, None, None);
+        c.rate_limit.per_endpoint = limit(1);
+    })
+    .await;
+
+    // Act
+    let first = app.get_preferences("guess").await;
+    let second = app.get_preferences("another-guess").await;
+
+    // Assert
+    assert_eq!(first.status().as_u16(), 404);
+    assert_eq!(second.status().as_u16(), 429);
+}
+
+#[tokio::test]
+async fn aliases_of_an_address_share_its_per_email_limit() {
+    // Arrange
+    let app = spawn_app_with(|c| {
+        only(c, None, limit(1));
+        c.subscriptions.fold_email_aliases = true;
+    })
+    .await;
+
+    // Act
+    let first = app
+        .post_subscriptions("name=le%20guin&email=ursula_le_guin%40gmail.com".into())
+        .await;
+    let second = app
+        .post_subscriptions("name=le%20guin&email=u.rsula_le_guin%2Bx%40googlemail.com".into())
+        .await;
+
+    // Assert
+    assert_eq!(first.status().as_u16(), 200);
+    assert_eq!(second.status().as_u16(), 429);
+    app.aws_request_wrapper.expect_one_request();
+}
@@ -0,0 +1,217 @@
+use crate::api::helpers::{spawn_app, subscribe_and_confirm, TestApp};
+use crate::aws_ses_rules::AwsRequestsWrapper;
+use chrono::{Duration, Utc};
+use uuid::Uuid;
+
+/// Three confirmed subscribers: `beta` is tagged, `old` joined 60 days ago and
+/// `pro` has custom attributes.
+async fn spawn_app_with_subscribers() -> TestApp {
+    let app = spawn_app().await;
+    for email in ["beta@example.com", "old@example.com", "pro@example.com"] {
+        subscribe_and_confirm(&app, email, "default").await;
+    }
+    let beta = app.subscriber_id("beta@example.com").await;
+    app.post_subscriber_tag(&beta, "beta")
+        .await
+        .error_for_status()
+        .unwrap();
+    sqlx::query!(
+        r#"
+        UPDATE list_subscriptions SET subscribed_at = $1
+        FROM subscriptions
+        WHERE subscriptions.id = list_subscriptions.subscriber_id
+          AND subscriptions.email = 'old@example.com'
+        "#,
+        Utc::now() - Duration::days(60)
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+    sqlx::query!(
+        r#"
+        UPDATE subscriptions
+        SET attributes = '{"plan": "pro", "seats": 12, "trial": false}'
+        WHERE email = 'pro@example.com'
+        "#
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+    app
+}
+
+async fn count(app: &TestApp, segment: &str) -> i64 {
+    let response = app
+        .post_segment_count(&serde_json::json!({ "segment": segment }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200, "segment was {}", segment);
+    let body: serde_json::Value = response.json().await.unwrap();
+    body["recipients"].as_i64().unwrap()
+}
+
+#[tokio::test]
+async fn the_dry_run_counts_every_confirmed_subscriber_without_a_segment() {
+    // Arrange
+    let app = spawn_app_with_subscribers().await;
+    app.post_subscriptions("name=le guin&email=pending@example.com".into())
+        .await
+        .error_for_status()
+        .unwrap();
+
+    // Act
+    let response = app.post_segment_count(&serde_json::json!({})).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["recipients"], 3);
+}
+
+#[tokio::test]
+async fn the_dry_run_counts_the_subscribers_matching_the_segment() {
+    // Arrange
+    let app = spawn_app_with_subscribers().await;
+    let test_cases = [
+        (r#"tag = "beta""#, 1),
+        (r#"tag != "beta""#, 2),
+        ("subscribed_at > 30 days ago", 2),
+        (r#"subscribed_at < 30 days ago or tag = "beta""#, 2),
+        (r#"attributes.plan = "pro""#, 1),
+        (r#"attributes.plan != "pro""#, 2),
+        ("attributes.seats >= 10", 1),
+        ("attributes.seats < 10", 0),
+        ("attributes.trial = false", 1),
+        (r#"attributes.plan = "pro" and not tag = "beta""#, 1),
+    ];
+
+    for (segment, expected) in test_cases {
+        // Act
+        let recipients = count(&app, segment).await;
+
+        // Assert
+        assert_eq!(recipients, expected, "segment was {}", segment);
+    }
+}
+
+#[tokio::test]
+async fn invalid_segments_are_rejected_with_a_400() {
+    // Arrange
+    let app = spawn_app().await;
+
+    for body in [
+        serde_json::json!({ "segment": "tag = beta" }),
+        serde_json::json!({ "segment": r#"tag = "beta""#, "list": "unknown" }),
+        serde_json::json!({ "segment": "subscribed_at > 100000000 days ago" }),
+        serde_json::json!({ "segment": r#"status = "unsubscribed""# }),
+    ] {
+        // Act
+        let response = app.post_segment_count(&body).await;
+
+        // Assert
+        assert_eq!(response.status().as_u16(), 400, "body was {}", body);
+    }
+}
+
+#[tokio::test]
+async fn removed_tags_no_longer_match() {
+    // Arrange
+    let app = spawn_app_with_subscribers().await;
+    let beta = app.subscriber_id("beta@example.com").await;
+
+    // Act
+    let response = app.delete_subscriber_tag(&beta, "beta").await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(count(&app, r#"tag = "beta""#).await, 0);
+}
+
+#[tokio::test]
+async fn tagging_an_unknown_subscriber_returns_a_404() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app
+        .post_subscriber_tag(&Uuid::new_v4().to_string(), "beta")
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn invalid_tags_are_rejected_with_a_400() {
+    // Arrange
+    let app = spawn_app_with_subscribers().await;
+    let beta = app.subscriber_id("beta@example.com").await;
+
+    // Act
+    let response = app.post_subscriber_tag(&beta, "Beta Testers").await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[tokio::test]
+async fn newsletters_with_a_segment_are_delivered_to_matching_subscribers_only() {
+    // Arrange
+    let app = spawn_app_with_subscribers().await;
+    let body = serde_json::json!({
+        "title": "Beta news",
+        "content": {"text": "Beta news", "html": "<p>Beta news</p>"},
+        "segment": r#"tag = "beta""#
+    });
+
+    // Act
+    let response = app.post_newsletters(&body).await;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let request = app.aws_request_wrapper.expect_one_request();
+    AwsRequestsWrapper::assert_correct_destination(&request, "beta@example.com");
+}
+
+#[tokio::test]
+async fn publishing_with_an_invalid_segment_returns_a_400() {
+    // Arrange
+    let app = spawn_app().await;
+    let body = serde_json::json!({
+        "title": "Beta news",
+        "content": {"text": "Beta news", "html": "<p>Beta news</p>"},
+        "segment": "tag ="
+    });
+
+    // Act
+    let newsletter = app.post_newsletters(&body).await;
+    let draft = app.post_issue(&body).await;
+
+    // Assert
+    assert_eq!(newsletter.status().as_u16(), 400);
+    assert_eq!(draft.status().as_u16(), 400);
+}
+
+#[tokio::test]
+async fn drafts_keep_their_segment_until_published() {
+    // Arrange
+    let app = spawn_app_with_subscribers().await;
+    let issue_id = app
+        .create_draft_issue(&serde_json::json!({
+            "title": "Pro news",
+            "content": {"text": "Pro news", "html": "<p>Pro news</p>"},
+            "segment": "attributes.seats > 10"
+        }))
+        .await;
+
+    // Act
+    app.post_issue_publish(&issue_id)
+        .await
+        .error_for_status()
+        .unwrap();
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let request = app.aws_request_wrapper.expect_one_request();
+    AwsRequestsWrapper::assert_correct_destination(&request, "pro@example.com");
+}
@@ -0,0 +1,206 @@
+use crate::api::helpers::{spawn_app, subscribe_and_confirm, TestApp};
+
+const EMAIL: &str = "ursula_le_guin@gmail.com";
+
+/// A confirmed subscriber with a tag, who has received one issue.
+async fn spawn_app_with_subscriber() -> TestApp {
+    let app = spawn_app().await;
+    subscribe_and_confirm(&app, EMAIL, "default").await;
+    app.post_subscriber_tag(&app.subscriber_id(EMAIL).await, "beta")
+        .await
+        .error_for_status()
+        .unwrap();
+    app.publish("Newsletter title").await;
+    app.aws_request_wrapper.expect_one_request_and_remove();
+    app
+}
+
+async fn assert_subscriber_was_erased(app: &TestApp) {
+    for table_count in [
+        sqlx::query_scalar!("SELECT COUNT(*) FROM subscriptions")
+            .fetch_one(&app.db_pool)
+            .await
+            .unwrap(),
+        sqlx::query_scalar!("SELECT COUNT(*) FROM list_subscriptions")
+            .fetch_one(&app.db_pool)
+            .await
+            .unwrap(),
+        sqlx::query_scalar!("SELECT COUNT(*) FROM subscription_tokens")
+            .fetch_one(&app.db_pool)
+            .await
+            .unwrap(),
+        sqlx::query_scalar!("SELECT COUNT(*) FROM subscriber_tags")
+            .fetch_one(&app.db_pool)
+            .await
+            .unwrap(),
+        sqlx::query_scalar!("SELECT COUNT(*) FROM consent_records")
+            .fetch_one(&app.db_pool)
+            .await
+            .unwrap(),
+    ] {
+        assert_eq!(table_count, Some(0));
+    }
+    let delivery = sqlx::query!("SELECT subscriber_email, status FROM issue_deliveries")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert!(delivery.subscriber_email.starts_with("erased:"));
+    assert!(!delivery.subscriber_email.contains(EMAIL));
+    let email_hash = sqlx::query_scalar!("SELECT email_hash FROM erased_subscribers")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert!(!delivery.subscriber_email.contains(&email_hash));
+    assert_eq!(delivery.status, "sent");
+    let response = app.get_subscriber_export(EMAIL).await;
+    assert_eq!(response.status().as_u16(), 410);
+}
+
+#[tokio::test]
+async fn the_export_contains_everything_held_about_the_subscriber() {
+    // Arrange
+    let app = spawn_app_with_subscriber().await;
+
+    // Act
+    let response = app.get_subscriber_export(EMAIL).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let data: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(data["profile"]["email"], EMAIL);
+    assert_eq!(data["profile"]["name"], "le guin");
+    assert_eq!(data["profile"]["tags"], serde_json::json!(["beta"]));
+    assert_eq!(data["lists"][0]["list"], "default");
+    assert_eq!(data["lists"][0]["status"], "confirmed");
+    assert_eq!(data["consents"][0]["event"], "subscribe");
+    assert_eq!(data["consents"][1]["event"], "confirm");
+    assert_eq!(data["deliveries"][0]["issue_title"], "Newsletter title");
+    assert_eq!(data["deliveries"][0]["status"], "sent");
+}
+
+#[tokio::test]
+async fn exporting_an_unknown_address_returns_a_404() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app.get_subscriber_export(EMAIL).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn erasing_a_subscriber_removes_their_data_and_keeps_a_tombstone() {
+    // Arrange
+    let app = spawn_app_with_subscriber().await;
+
+    // Act
+    let response = app.post_subscriber_erasure(EMAIL).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    assert_subscriber_was_erased(&app).await;
+    let tombstone = sqlx::query!("SELECT email_hash, source FROM erased_subscribers")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(tombstone.email_hash.len(), 64);
+    assert!(!tombstone.email_hash.contains(EMAIL));
+    assert_eq!(tombstone.source, "admin");
+}
+
+#[tokio::test]
+async fn erasing_an_unknown_or_erased_address_fails() {
+    // Arrange
+    let app = spawn_app_with_subscriber().await;
+    app.post_subscriber_erasure(EMAIL)
+        .await
+        .error_for_status()
+        .unwrap();
+
+    // Act
+    let erased_response = app.post_subscriber_erasure(EMAIL).await;
+    let unknown_response = app.post_subscriber_erasure("someone@example.com").await;
+
+    // Assert
+    assert_eq!(erased_response.status().as_u16(), 410);
+    assert_eq!(unknown_response.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn subscribers_can_download_their_own_data() {
+    // Arrange
+    let app = spawn_app_with_subscriber().await;
+    let token = app.preferences_token(EMAIL).await;
+    let page = app.get_preferences(&token).await.text().await.unwrap();
+    assert!(page.contains(&format!(r#"href="/preferences/{}/export""#, token)));
+
+    // Act
+    let response = app.get_own_data(&token).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(response.headers()["content-disposition"]
+        .to_str()
+        .unwrap()
+        .starts_with("attachment"));
+    let data: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(data["profile"]["email"], EMAIL);
+    assert_eq!(app.get_own_data("not-a-token").await.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn subscribers_can_erase_their_own_data_once_they_confirm() {
+    // Arrange
+    let app = spawn_app_with_subscriber().await;
+    let token = app.preferences_token(EMAIL).await;
+
+    // Act - Part 1 - Without confirmation
+    let response = app.post_own_erasure(&token, "".into()).await;
+
+    // Assert - Part 1
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(
+        app.get_subscriber_export(EMAIL).await.status().as_u16(),
+        200
+    );
+
+    // Act - Part 2 - Confirmed
+    let response = app.post_own_erasure(&token, "confirm=yes".into()).await;
+
+    // Assert - Part 2
+    assert_eq!(response.status().as_u16(), 200);
+    assert_subscriber_was_erased(&app).await;
+    assert_eq!(app.get_preferences(&token).await.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn admins_must_authenticate_to_export_or_erase_a_subscriber() {
+    // Arrange
+    let app = spawn_app_with_subscriber().await;
+    let client = reqwest::Client::new();
+
+    // Act
+    let export = client
+        .get(format!("{}/admin/subscribers/export", app.address))
+        .query(&[("email", EMAIL)])
+        .send()
+        .await
+        .unwrap();
+    let erasure = client
+        .post(format!("{}/admin/subscribers/erase", app.address))
+        .json(&serde_json::json!({ "email": EMAIL }))
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(export.status().as_u16(), 401);
+    assert_eq!(erasure.status().as_u16(), 401);
+    let subscribers = sqlx::query_scalar!(r#"SELECT COUNT(*) AS "count!" FROM subscriptions"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(subscribers, 1);
+}
@@ -0,0 +1,252 @@
+use crate::api::helpers::{spawn_app, subscribe_and_confirm, QueryPairs, TestApp};
+
+/// Three confirmed subscribers and one pending, in sign-up order.
+async fn spawn_app_with_subscribers() -> TestApp {
+    let app = spawn_app().await;
+    for email in [
+        "ursula@example.com",
+        "octavia@example.com",
+        "Ted.Chiang@example.com",
+    ] {
+        subscribe_and_confirm(&app, email, "default").await;
+    }
+    app.post_subscriptions("name=N%20K%20Jemisin&email=nk@example.com".into())
+        .await
+        .error_for_status()
+        .unwrap();
+    app.aws_request_wrapper.expect_one_request_and_remove();
+    app
+}
+
+async fn emails(response: reqwest::Response) -> (Vec<String>, Option<String>) {
+    assert_eq!(response.status().as_u16(), 200);
+    let page: serde_json::Value = response.json().await.unwrap();
+    let emails = page["items"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|s| s["email"].as_str().unwrap().to_owned())
+        .collect();
+    (emails, page["next_cursor"].as_str().map(str::to_owned))
+}
+
+#[tokio::test]
+async fn subscribers_are_listed_page_by_page_with_a_cursor() {
+    // Arrange
+    let app = spawn_app_with_subscribers().await;
+
+    // Act - Part 1 - First page
+    let (first, cursor) = emails(app.get_subscribers(&[("limit", "3")]).await).await;
+
+    // Assert - Part 1
+    assert_eq!(
+        first,
+        vec![
+            "ursula@example.com",
+            "octavia@example.com",
+            "Ted.Chiang@example.com"
+        ]
+    );
+    let cursor = cursor.expect("The first page did not point to the next one.");
+
+    // Act - Part 2 - Second page
+    let (second, cursor) = emails(
+        app.get_subscribers(&[("limit", "3"), ("cursor", &cursor)])
+            .await,
+    )
+    .await;
+
+    // Assert - Part 2
+    assert_eq!(second, vec!["nk@example.com"]);
+    assert_eq!(cursor, None);
+}
+
+#[tokio::test]
+async fn subscribers_can_be_filtered_by_status_date_and_search() {
+    // Arrange
+    let app = spawn_app_with_subscribers().await;
+    sqlx::query!(
+        "UPDATE subscriptions SET subscribed_at = '2020-01-01T00:00:00Z' WHERE email = $1",
+        "ursula@example.com"
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+    let test_cases: Vec<(&QueryPairs, Vec<&str>)> = vec![
+        (
+            &[("status", "pending_confirmation")],
+            vec!["nk@example.com"],
+        ),
+        (
+            &[("status", "confirmed"), ("list", "default")],
+            vec![
+                "ursula@example.com",
+                "octavia@example.com",
+                "Ted.Chiang@example.com",
+            ],
+        ),
+        (&[("status", "confirmed"), ("list", "weekly")], vec![]),
+        (
+            &[("subscribed_before", "2021-01-01T00:00:00Z")],
+            vec!["ursula@example.com"],
+        ),
+        (
+            &[
+                ("subscribed_after", "2021-01-01T00:00:00Z"),
+                ("search", "TED"),
+            ],
+            vec!["Ted.Chiang@example.com"],
+        ),
+        (&[("search", "jemisin")], vec!["nk@example.com"]),
+        (&[("search", "%")], vec![]),
+    ];
+
+    for (query, expected) in test_cases {
+        // Act
+        let (result, _) = emails(app.get_subscribers(query).await).await;
+
+        // Assert
+        assert_eq!(result, expected, "Unexpected subscribers for {:?}.", query);
+    }
+}
+
+#[tokio::test]
+async fn listed_subscribers_include_their_list_memberships() {
+    // Arrange
+    let app = spawn_app_with_subscribers().await;
+
+    // Act
+    let response = app.get_subscribers(&[("search", "nk@")]).await;
+
+    // Assert
+    let page: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(page["items"][0]["name"], "N K Jemisin");
+    assert_eq!(
+        page["items"][0]["lists"],
+        serde_json::json!([{"list": "default", "status": "pending_confirmation"}])
+    );
+}
+
+#[tokio::test]
+async fn every_matching_subscriber_can_be_exported_as_csv() {
+    // Arrange
+    let app = spawn_app_with_subscribers().await;
+
+    // Act
+    let response = app
+        .get_subscribers(&[("format", "csv"), ("status", "confirmed"), ("limit", "1")])
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(response.headers()["content-type"]
+        .to_str()
+        .unwrap()
+        .starts_with("text/csv"));
+    let body = response.text().await.unwrap();
+    let rows: Vec<&str> = body.lines().collect();
+    assert_eq!(rows[0], "id,email,name,subscribed_at,lists");
+    assert_eq!(rows.len(), 4);
+    assert!(rows[1].contains(",ursula@example.com,le guin,"));
+    assert!(rows[1].ends_with(",default:confirmed"));
+}
+
+#[tokio::test]
+async fn csv_exports_escape_fields_that_look_like_formulas() {
+    // Arrange
+    let app = spawn_app().await;
+    app.post_subscriptions("name=%3D1%2B1&email=formula%40example.com".into())
+        .await
+        .error_for_status()
+        .unwrap();
+
+    // Act
+    let response = app.get_subscribers(&[("format", "csv")]).await;
+
+    // Assert
+    let body = response.text().await.unwrap();
+    let rows: Vec<&str> = body.lines().collect();
+    assert!(rows[1].contains(",formula@example.com,'=1+1,"));
+}
+
+#[tokio::test]
+async fn listing_or_exporting_subscribers_requires_an_admin_token() {
+    // Arrange
+    let app = spawn_app_with_subscribers().await;
+
+    for format in ["json", "csv", "ndjson"] {
+        // Act
+        let response = reqwest::Client::new()
+            .get(format!("{}/admin/subscribers", app.address))
+            .query(&[("format", format)])
+            .send()
+            .await
+            .unwrap();
+
+        // Assert
+        assert_eq!(
+            response.status().as_u16(),
+            401,
+            "The {} listing was served without an admin token.",
+            format
+        );
+    }
+}
+
+#[tokio::test]
+async fn every_matching_subscriber_can_be_exported_as_ndjson() {
+    // Arrange
+    let app = spawn_app_with_subscribers().await;
+
+    // Act
+    let response = app.get_subscribers(&[("format", "ndjson")]).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        response.headers()["content-type"].to_str().unwrap(),
+        "application/x-ndjson"
+    );
+    let body = response.text().await.unwrap();
+    let emails: Vec<String> = body
+        .lines()
+        .map(|line| {
+            let subscriber: serde_json::Value = serde_json::from_str(line).unwrap();
+            subscriber["email"].as_str().unwrap().to_owned()
+        })
+        .collect();
+    assert_eq!(
+        emails,
+        vec![
+            "ursula@example.com",
+            "octavia@example.com",
+            "Ted.Chiang@example.com",
+            "nk@example.com"
+        ]
+    );
+}
+
+#[tokio::test]
+async fn invalid_listing_parameters_are_rejected() {
+    // Arrange
+    let app = spawn_app().await;
+    let test_cases: Vec<(&[(&str, &str)], &str)> = vec![
+        (&[("status", "sleeping")], "an unknown status"),
+        (&[("cursor", "not-a-cursor")], "a malformed cursor"),
+        (&[("format", "xml")], "an unsupported format"),
+        (&[("subscribed_after", "yesterday")], "a malformed date"),
+    ];
+
+    for (query, description) in test_cases {
+        // Act
+        let response = app.get_subscribers(query).await;
+
+        // Assert
+        assert_eq!(
+            response.status().as_u16(),
+            400,
+            "The API did not fail with 400 Bad Request for {}.",
+            description
+        );
+    }
+}
@@ -1,5 +1,6 @@
-use crate::api::helpers::spawn_app;
+use crate::api::helpers::{spawn_app, spawn_app_with, TestApp};
 use crate::aws_ses_rules::AwsRequestsWrapper;
+use zero2prod::configuration::Settings;
 
 #[tokio::test]
 async fn subscribe_returns_a_200_for_valid_form_data() {
@@ -29,10 +30,16 @@ async fn subscribe_persists_the_new_subscriber() {
     // Assert
     assert_eq!(200, response.status().as_u16());
 
-    let saved = sqlx::query!("SELECT email, name, status FROM subscriptions",)
-        .fetch_one(&app.db_pool)
-        .await
-        .expect("Failed to fetch saved subscription.");
+    let saved = sqlx::query!(
+        r#"
+        SELECT email, name, status
+        FROM subscriptions
+        JOIN list_subscriptions ON list_subscriptions.subscriber_id = subscriptions.id
+        "#
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .expect("Failed to fetch saved subscription.");
     assert_eq!(saved.email, email);
     assert_eq!(saved.name, name);
     assert_eq!(saved.status, "pending_confirmation");
@@ -124,3 +131,152 @@ async fn subscribe_fails_if_there_is_a_fatal_database_error() {
     // Assert
     assert_eq!(500, response.status().as_u16());
 }
+
+async fn stored_emails(app: &TestApp) -> Vec<(String, String)> {
+    sqlx::query!("SELECT email, normalized_email FROM subscriptions")
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|r| (r.email, r.normalized_email))
+        .collect()
+}
+
+#[tokio::test]
+async fn addresses_that_only_differ_in_case_are_the_same_subscriber() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    for email in ["Ursula@Example.com", "ursula@EXAMPLE.com"] {
+        app.post_subscriptions(format!("name=le%20guin&email={}", email))
+            .await
+            .error_for_status()
+            .unwrap();
+    }
+
+    // Assert
+    assert_eq!(
+        stored_emails(&app).await,
+        vec![(
+            "Ursula@example.com".to_string(),
+            "ursula@example.com".to_string()
+        )]
+    );
+}
+
+#[tokio::test]
+async fn internationalized_domains_are_stored_in_ascii() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    app.post_subscriptions("name=le%20guin&email=ursula%40B%C3%BCcher.example".into())
+        .await
+        .error_for_status()
+        .unwrap();
+
+    // Assert
+    let request = app.aws_request_wrapper.expect_one_request();
+    AwsRequestsWrapper::assert_correct_destination(&request, "ursula@xn--bcher-kva.example");
+    assert_eq!(
+        stored_emails(&app).await[0].0,
+        "ursula@xn--bcher-kva.example"
+    );
+}
+
+#[tokio::test]
+async fn subscribers_stored_with_unicode_domains_are_kept_apart_until_merged() {
+    // Arrange
+    // Rows from before domains were stored in ASCII keep the key the
+    // normalization migration gave them.
+    let app = spawn_app().await;
+    sqlx::query!(
+        r#"
+        INSERT INTO subscriptions (
+            id, email, normalized_email, name, subscribed_at, preferences_token
+        )
+        VALUES ($1, $2, lower($2), 'le guin', now(), $3)
+        "#,
+        uuid::Uuid::new_v4(),
+        "ursula@bücher.example",
+        uuid::Uuid::new_v4().to_string()
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+
+    // Act
+    app.post_subscriptions("name=le%20guin&email=ursula%40b%C3%BCcher.example".into())
+        .await
+        .error_for_status()
+        .unwrap();
+
+    // Assert
+    let mut emails = stored_emails(&app).await;
+    emails.sort();
+    assert_eq!(
+        emails,
+        vec![
+            (
+                "ursula@bücher.example".to_string(),
+                "ursula@bücher.example".to_string()
+            ),
+            (
+                "ursula@xn--bcher-kva.example".to_string(),
+                "ursula@xn--bcher-kva.example".to_string()
+            ),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn gmail_aliases_are_the_same_subscriber_when_folding_is_enabled() {
+    // Arrange
+    let app = spawn_app_with(|c: &mut Settings| c.subscriptions.fold_email_aliases = true).await;
+
+    // Act
+    for email in [
+        "ursula.le.guin%40gmail.com",
+        "ursulaleguin%2Bnews%40googlemail.com",
+    ] {
+        app.post_subscriptions(format!("name=le%20guin&email={}", email))
+            .await
+            .error_for_status()
+            .unwrap();
+    }
+
+    // Assert
+    assert_eq!(
+        stored_emails(&app).await,
+        vec![(
+            "ursula.le.guin@gmail.com".to_string(),
+            "ursulaleguin@gmail.com".to_string()
+        )]
+    );
+}
+
+#[tokio::test]
+async fn emails_are_sent_from_the_configured_sender_name_and_reply_to_address() {
+    // Arrange
+    let app = spawn_app_with(|c: &mut Settings| {
+        c.email_client.sender_email = "news@example.com".into();
+        c.email_client.sender_name = Some("Rust Weekly".into());
+        c.email_client.reply_to = Some("editor@example.com".into());
+    })
+    .await;
+
+    // Act
+    app.post_subscriptions("name=le%20guin&email=ursula%40example.com".into())
+        .await
+        .error_for_status()
+        .unwrap();
+
+    // Assert
+    let request = app.aws_request_wrapper.expect_one_request();
+    assert_eq!(
+        request.from_email_address(),
+        Some("\"Rust Weekly\" <news@example.com>")
+    );
+    assert_eq!(request.reply_to_addresses(), ["editor@example.com"]);
+}
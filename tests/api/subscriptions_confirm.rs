@@ -1,4 +1,33 @@
-use crate::api::helpers::spawn_app;
+use crate::api::helpers::{spawn_app, spawn_app_with, TestApp};
+use crate::aws_ses_rules::AwsRequestsWrapper;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use uuid::Uuid;
+use zero2prod::configuration::{AttachmentSettings, WelcomeEmailSettings};
+
+/// Subscribes an address and follows the link in its confirmation email.
+async fn subscribe_and_follow_confirmation_link(app: &TestApp) -> reqwest::Response {
+    app.post_subscriptions("name=le%20guin&email=ursula_le_guin%40gmail.com".into())
+        .await
+        .error_for_status()
+        .unwrap();
+    let request = app.aws_request_wrapper.expect_one_request_and_remove();
+    let confirmation_links = app.extract_confirmation_links(&request);
+    reqwest::get(confirmation_links.html).await.unwrap()
+}
+
+async fn spawn_app_with_welcome_email(attachment: Option<AttachmentSettings>) -> TestApp {
+    spawn_app_with(|c| {
+        c.subscriptions.welcome_email = Some(WelcomeEmailSettings {
+            subject: "Welcome to {{list_name}}".into(),
+            html_template: "<p>Hi {{name}}, welcome aboard!</p>".into(),
+            text_template: "Hi {{name}}, welcome aboard!".into(),
+            attachment,
+            translations: Default::default(),
+        })
+    })
+    .await
+}
 
 #[tokio::test]
 async fn confirmations_without_token_are_rejected_with_a_400() {
@@ -53,12 +82,175 @@ async fn clicking_on_the_confirmation_link_confirms_a_subscriber() {
     reqwest::get(confirmation_links.html).await.unwrap();
 
     // Assert
-    let saved = sqlx::query!("SELECT email, name, status FROM subscriptions",)
-        .fetch_one(&app.db_pool)
-        .await
-        .expect("Failed to fetch saved subscription.");
+    let saved = sqlx::query!(
+        r#"
+        SELECT email, name, status
+        FROM subscriptions
+        JOIN list_subscriptions ON list_subscriptions.subscriber_id = subscriptions.id
+        "#
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .expect("Failed to fetch saved subscription.");
 
     assert_eq!(saved.email, "ursula_le_guin@gmail.com");
     assert_eq!(saved.name, "le guin");
     assert_eq!(saved.status, "confirmed");
 }
+
+#[tokio::test]
+async fn confirming_renders_an_html_page() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = subscribe_and_follow_confirmation_link(&app).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(response.headers()["content-type"]
+        .to_str()
+        .unwrap()
+        .starts_with("text/html"));
+    let page = response.text().await.unwrap();
+    assert!(page.contains("You are subscribed"));
+    assert!(page.contains("Newsletter"));
+    assert!(page.contains(r#"href="/preferences/"#));
+}
+
+#[tokio::test]
+async fn no_welcome_email_is_queued_unless_one_is_configured() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    subscribe_and_follow_confirmation_link(&app)
+        .await
+        .error_for_status()
+        .unwrap();
+
+    // Assert
+    let queued = sqlx::query_scalar!("SELECT COUNT(*) FROM welcome_email_queue")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(queued, Some(0));
+}
+
+#[tokio::test]
+async fn confirmed_subscribers_are_welcomed_through_the_queue() {
+    // Arrange
+    let app = spawn_app_with_welcome_email(None).await;
+
+    // Act - Part 1 - Confirm
+    subscribe_and_follow_confirmation_link(&app)
+        .await
+        .error_for_status()
+        .unwrap();
+
+    // Assert - Part 1 - Nothing is sent while confirming
+    app.aws_request_wrapper.expect_zero_requests();
+
+    // Act - Part 2 - Deliver the queue
+    app.dispatch_all_welcome_emails().await;
+
+    // Assert - Part 2
+    let request = app.aws_request_wrapper.expect_one_request_and_remove();
+    AwsRequestsWrapper::assert_correct_destination(&request, "ursula_le_guin@gmail.com");
+    AwsRequestsWrapper::assert_correct_subject(&request, "Welcome to Newsletter");
+    AwsRequestsWrapper::assert_correct_body_text(&request, "Hi le guin, welcome aboard!");
+    AwsRequestsWrapper::assert_correct_body_text(&request, "Unsubscribe: ");
+}
+
+#[tokio::test]
+async fn following_the_confirmation_link_twice_welcomes_once() {
+    // Arrange
+    let app = spawn_app_with_welcome_email(None).await;
+    app.post_subscriptions("name=le%20guin&email=ursula_le_guin%40gmail.com".into())
+        .await
+        .error_for_status()
+        .unwrap();
+    let request = app.aws_request_wrapper.expect_one_request_and_remove();
+    let confirmation_links = app.extract_confirmation_links(&request);
+
+    // Act
+    for _ in 0..2 {
+        reqwest::get(confirmation_links.html.clone())
+            .await
+            .unwrap()
+            .error_for_status()
+            .unwrap();
+        app.dispatch_all_welcome_emails().await;
+    }
+
+    // Assert
+    app.aws_request_wrapper.expect_one_request();
+}
+
+#[tokio::test]
+async fn following_the_confirmation_link_again_changes_nothing() {
+    // Arrange
+    let app = spawn_app().await;
+    app.post_subscriptions("name=le%20guin&email=ursula_le_guin%40gmail.com".into())
+        .await
+        .error_for_status()
+        .unwrap();
+    let request = app.aws_request_wrapper.expect_one_request_and_remove();
+    let confirmation_links = app.extract_confirmation_links(&request);
+    reqwest::get(confirmation_links.html.clone())
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+    sqlx::query!("UPDATE list_subscriptions SET status = 'unsubscribed'")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+
+    // Act
+    let response = reqwest::get(confirmation_links.html).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let status = sqlx::query_scalar!("SELECT status FROM list_subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(status, "unsubscribed");
+    let confirmations =
+        sqlx::query_scalar!("SELECT COUNT(*) FROM consent_records WHERE event = 'confirm'")
+            .fetch_one(&app.db_pool)
+            .await
+            .unwrap();
+    assert_eq!(confirmations, Some(1));
+}
+
+#[tokio::test]
+async fn the_welcome_email_carries_the_configured_attachment() {
+    // Arrange
+    let path = std::env::temp_dir().join(format!("{}.pdf", Uuid::new_v4()));
+    std::fs::write(&path, b"%PDF-1.4 reading list").unwrap();
+    let app = spawn_app_with_welcome_email(Some(AttachmentSettings {
+        path: path.to_str().unwrap().into(),
+        content_type: "application/pdf".into(),
+    }))
+    .await;
+    subscribe_and_follow_confirmation_link(&app)
+        .await
+        .error_for_status()
+        .unwrap();
+
+    // Act
+    app.dispatch_all_welcome_emails().await;
+
+    // Assert
+    let request = app.aws_request_wrapper.expect_one_request_and_remove();
+    let raw = request.content().unwrap().raw().unwrap().data().as_ref();
+    let message = String::from_utf8(raw.to_vec()).unwrap();
+    assert!(message.contains(&format!(
+        "filename=\"{}\"",
+        path.file_name().unwrap().to_str().unwrap()
+    )));
+    assert!(message.contains(&STANDARD.encode("%PDF-1.4 reading list")));
+    std::fs::remove_file(path).unwrap();
+}
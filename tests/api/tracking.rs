@@ -0,0 +1,179 @@
+use crate::api::helpers::{spawn_app, subscribe_and_confirm, TestApp};
+use uuid::Uuid;
+
+const EMAIL: &str = "ursula_le_guin@gmail.com";
+
+async fn deliver_issue(app: &TestApp) -> String {
+    subscribe_and_confirm(app, EMAIL, "default").await;
+    app.post_newsletters(&serde_json::json!({
+        "title": "Newsletter title",
+        "content": {
+            "text": "Newsletter body as plain text",
+            "html": r#"<p>Read <a href="https://example.com/post?id=1&amp;ref=mail">the post</a></p>"#
+        }
+    }))
+    .await
+    .error_for_status()
+    .unwrap();
+    app.dispatch_all_pending_emails().await;
+    let request = app.aws_request_wrapper.expect_one_request_and_remove();
+    let body = request.content().unwrap().simple().unwrap().body().unwrap();
+    body.html().unwrap().data().to_owned()
+}
+
+async fn tracking_token(app: &TestApp) -> String {
+    sqlx::query_scalar!("SELECT tracking_token FROM issue_deliveries")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+}
+
+fn no_redirects() -> reqwest::Client {
+    reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn delivered_issues_embed_an_open_pixel_and_tracked_links() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let html = deliver_issue(&app).await;
+
+    // Assert
+    let token = tracking_token(&app).await;
+    assert!(html.contains(&format!("{}track/open/{}", app.base_url, token)));
+    assert!(html.contains(&format!("{}track/click/{}/", app.base_url, token)));
+    assert!(!html.contains("https://example.com/post"));
+}
+
+#[tokio::test]
+async fn loading_the_open_pixel_records_the_first_open() {
+    // Arrange
+    let app = spawn_app().await;
+    deliver_issue(&app).await;
+    let token = tracking_token(&app).await;
+    let url = format!("{}/track/open/{}", app.address, token);
+
+    // Act
+    let response = reqwest::get(&url).await.unwrap();
+    let opened_at = sqlx::query_scalar!("SELECT opened_at FROM issue_deliveries")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    reqwest::get(&url).await.unwrap();
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+    assert_eq!(response.headers()["content-type"], "image/gif");
+    assert_eq!(response.headers()["cache-control"], "no-store");
+    let delivery = sqlx::query!("SELECT opened_at, clicked_at FROM issue_deliveries")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert!(opened_at.is_some());
+    assert_eq!(delivery.opened_at, opened_at);
+    assert_eq!(delivery.clicked_at, None);
+}
+
+#[tokio::test]
+async fn clicking_a_tracked_link_records_the_click_and_redirects() {
+    // Arrange
+    let app = spawn_app().await;
+    let html = deliver_issue(&app).await;
+    let start = html.find(&format!("{}track/click/", app.base_url)).unwrap();
+    let end = start + html[start..].find('"').unwrap();
+    let path = html[start..end].trim_start_matches(&app.base_url.to_string());
+
+    // Act
+    let response = no_redirects()
+        .get(format!("{}/{}", app.address, path))
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(302, response.status().as_u16());
+    assert_eq!(
+        response.headers()["location"],
+        "https://example.com/post?id=1&ref=mail"
+    );
+    let delivery = sqlx::query!("SELECT opened_at, clicked_at FROM issue_deliveries")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert!(delivery.opened_at.is_some());
+    assert!(delivery.clicked_at.is_some());
+    let clicks = sqlx::query_scalar!(r#"SELECT COUNT(*) AS "count!" FROM link_clicks"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(clicks, 1);
+    let export: serde_json::Value = app
+        .get_subscriber_export(EMAIL)
+        .await
+        .error_for_status()
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(
+        export["clicks"][0]["url"],
+        "https://example.com/post?id=1&ref=mail"
+    );
+    assert!(export["deliveries"][0]["clicked_at"].is_string());
+}
+
+#[tokio::test]
+async fn clicking_an_unknown_link_returns_404() {
+    // Arrange
+    let app = spawn_app().await;
+    deliver_issue(&app).await;
+    let token = tracking_token(&app).await;
+
+    // Act
+    let response = no_redirects()
+        .get(format!(
+            "{}/track/click/{}/{}",
+            app.address,
+            token,
+            Uuid::new_v4()
+        ))
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(404, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn the_issue_report_counts_opens_and_clicks() {
+    // Arrange
+    let app = spawn_app().await;
+    deliver_issue(&app).await;
+    let token = tracking_token(&app).await;
+    let issue_id = sqlx::query_scalar!("SELECT id FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+
+    // Act
+    reqwest::get(format!("{}/track/open/{}", app.address, token))
+        .await
+        .unwrap();
+
+    // Assert
+    let report: serde_json::Value = app
+        .get_issue_report(&issue_id.to_string())
+        .await
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(report["engagement"]["opened"], 1);
+    assert_eq!(report["engagement"]["clicked"], 0);
+    assert!(report["ab_test"].is_null());
+}
@@ -55,6 +55,18 @@ impl AwsRequestsWrapper {
         request
     }
 
+    pub fn expect_requests_and_remove(&self, count: usize) -> Vec<SendEmailInput> {
+        let mut requests = self.requests.lock().unwrap();
+        assert_eq!(
+            requests.len(),
+            count,
+            "Expected {} requests from AWS client, but got: {:?}",
+            count,
+            requests.len()
+        );
+        requests.drain(..).collect()
+    }
+
     pub fn expect_one_request(&self) -> SendEmailInput {
         let requests = self.requests.lock().unwrap();
         assert_eq!(